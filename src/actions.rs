@@ -24,10 +24,10 @@ impl<'a> ActionCompiler<'a> {
                 self.do_atom_term(at);
                 self.locals.insert(v.clone());
             }
-            GenericCoreAction::Extract(_ann, e, b) => {
+            GenericCoreAction::Extract(_ann, e, b, mode) => {
                 let sort = self.do_atom_term(e);
                 self.do_atom_term(b);
-                self.instructions.push(Instruction::Extract(2, sort));
+                self.instructions.push(Instruction::Extract(2, sort, *mode));
             }
             GenericCoreAction::Set(_ann, f, args, e) => {
                 let ResolvedCall::Func(func) = f else {
@@ -139,7 +139,7 @@ enum Instruction {
     /// Extract the best expression. `n` is always 2.
     /// The first value on the stack is the expression to extract,
     /// and the second value is the number of variants to extract.
-    Extract(usize, ArcSort),
+    Extract(usize, ArcSort, ExtractMode),
     /// Panic with the given message.
     Panic(String),
 }
@@ -147,6 +147,14 @@ enum Instruction {
 #[derive(Clone, Debug)]
 pub struct Program(Vec<Instruction>);
 
+/// The unions and sets performed by rules in one iteration, when they are
+/// applied in a batch. See [`EGraph::batch_updates`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PendingUpdates {
+    unions: Vec<(Value, Value, Symbol)>,
+    sets: Vec<(Symbol, ValueVec, Value)>,
+}
+
 impl EGraph {
     /// Takes `binding`, which is a set of variables bound during matching
     /// whose positions are captured by indices of the `IndexSet``, and a list of core actions.
@@ -204,7 +212,37 @@ impl EGraph {
         Ok(Program(compiler.instructions))
     }
 
-    fn perform_set(
+    /// Applies the updates collected while running rules with
+    /// [`EGraph::batch_updates`] set. Duplicates are dropped, unions are
+    /// applied first, and the values set for the same row are merged in
+    /// increasing order, so the result does not depend on the order the
+    /// rules produced them in.
+    pub(crate) fn apply_pending_updates(&mut self, pending: PendingUpdates) -> Result<(), Error> {
+        let PendingUpdates {
+            mut unions,
+            mut sets,
+        } = pending;
+        unions.sort_unstable_by_key(|(a, b, sort)| (sort.as_str(), *a, *b));
+        unions.dedup();
+        for (a, b, sort) in unions {
+            let (a, b) = (self.unionfind.find(a.bits), self.unionfind.find(b.bits));
+            self.unionfind.union(a, b, sort);
+        }
+
+        sets.sort_unstable_by(|(f1, args1, v1), (f2, args2, v2)| {
+            (f1.as_str(), args1, v1).cmp(&(f2.as_str(), args2, v2))
+        });
+        sets.dedup();
+        let mut stack = vec![];
+        for (table, args, value) in sets {
+            stack.clear();
+            stack.extend_from_slice(&args);
+            self.perform_set(table, value, &mut stack)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn perform_set(
         &mut self,
         table: Symbol,
         new_value: Value,
@@ -251,6 +289,11 @@ impl EGraph {
                 }
             }
         } else {
+            if let Some(explanations) = self.unionfind.explanations_mut() {
+                if function.schema.output.is_eq_sort() {
+                    explanations.add_row(table, args, new_value);
+                }
+            }
             function.insert(args, new_value, self.timestamp);
         }
         Ok(())
@@ -297,6 +340,9 @@ impl EGraph {
                                     tag: out.name(),
                                     bits: self.unionfind.make_set(),
                                 };
+                                if let Some(explanations) = self.unionfind.explanations_mut() {
+                                    explanations.add_node(*f, values, value);
+                                }
                                 function.insert(values, value, ts);
                                 value
                             }
@@ -347,20 +393,29 @@ impl EGraph {
                     let new_value = stack.pop().unwrap();
                     let new_len = stack.len() - function.schema.input.len();
 
-                    self.perform_set(*f, new_value, stack)?;
+                    if let Some(pending) = &mut self.pending_updates {
+                        pending.sets.push((*f, stack[new_len..].into(), new_value));
+                    } else {
+                        self.perform_set(*f, new_value, stack)?;
+                    }
                     stack.truncate(new_len)
                 }
                 Instruction::Union(arity, sort) => {
                     let new_len = stack.len() - arity;
                     let values = &stack[new_len..];
-                    let first = self.unionfind.find(values[0].bits);
-                    values[1..].iter().fold(first, |a, b| {
-                        let b = self.unionfind.find(b.bits);
-                        self.unionfind.union(a, b, sort.name())
-                    });
+                    if let Some(pending) = &mut self.pending_updates {
+                        let pairs = values[1..].iter().map(|b| (values[0], *b, sort.name()));
+                        pending.unions.extend(pairs);
+                    } else {
+                        let first = self.unionfind.find(values[0].bits);
+                        values[1..].iter().fold(first, |a, b| {
+                            let b = self.unionfind.find(b.bits);
+                            self.unionfind.union(a, b, sort.name())
+                        });
+                    }
                     stack.truncate(new_len);
                 }
-                Instruction::Extract(arity, sort) => {
+                Instruction::Extract(arity, sort, mode) => {
                     let new_len = stack.len() - arity;
                     let values = &stack[new_len..];
                     let new_len = stack.len() - arity;
@@ -368,10 +423,20 @@ impl EGraph {
 
                     let variants = values[1].bits as i64;
                     if variants == 0 {
-                        let (cost, term) = self.extract(values[0], &mut termdag, sort);
+                        let (cost, term) = match mode {
+                            ExtractMode::Tree => {
+                                self.extract_incremental(values[0], &mut termdag, sort)
+                            }
+                            _ => self.extract_with_mode(values[0], &mut termdag, sort, *mode),
+                        };
                         let extracted = termdag.to_string(&term);
                         log::info!("extracted with cost {cost}: {extracted}");
                         self.print_msg(extracted);
+                        if self.unionfind.explanations().is_some() {
+                            let expr = termdag.term_to_expr(&term);
+                            let explanation = self.explain_extracted(sort, values[0], &expr)?;
+                            self.print_msg(explanation);
+                        }
                         self.extract_report = Some(ExtractReport::Best {
                             termdag,
                             cost,
@@ -381,8 +446,10 @@ impl EGraph {
                         if variants < 0 {
                             panic!("Cannot extract negative number of variants");
                         }
-                        let terms =
-                            self.extract_variants(sort, values[0], variants as usize, &mut termdag);
+                        let (costs, terms): (Vec<_>, Vec<_>) = self
+                            .extract_top_n(values[0], sort, variants as usize, &mut termdag)
+                            .into_iter()
+                            .unzip();
                         log::info!("extracted variants:");
                         let mut msg = String::default();
                         msg += "(\n";
@@ -394,31 +461,55 @@ impl EGraph {
                         }
                         msg += ")";
                         self.print_msg(msg);
-                        self.extract_report = Some(ExtractReport::Variants { termdag, terms });
+                        self.extract_report = Some(ExtractReport::Variants {
+                            termdag,
+                            costs,
+                            terms,
+                        });
                     }
 
                     stack.truncate(new_len);
                 }
                 Instruction::Panic(msg) => panic!("Panic: {msg}"),
-                Instruction::Literal(lit) => match lit {
-                    Literal::Int(i) => stack.push(Value::from(*i)),
-                    Literal::F64(f) => stack.push(Value::from(*f)),
-                    Literal::String(s) => stack.push(Value::from(*s)),
-                    Literal::Bool(b) => stack.push(Value::from(*b)),
-                    Literal::Unit => stack.push(Value::unit()),
-                },
+                Instruction::Literal(lit) => stack.push(self.eval_lit(lit)),
                 Instruction::Change(change, f) => {
                     let function = self.functions.get_mut(f).unwrap();
                     let new_len = stack.len() - function.schema.input.len();
                     let args = &stack[new_len..];
                     match change {
                         Change::Delete => {
+                            self.extraction_cache = None;
                             function.remove(args, self.timestamp);
                         }
                         Change::Subsume => {
                             if function.decl.merge.is_some() {
                                 return Err(Error::SubsumeMergeError(*f));
                             }
+                            // Subsuming a term that is not in the e-graph yet
+                            // adds it, so that it can't be added again later
+                            if function.nodes.get(args).is_none() {
+                                let out = &function.schema.output;
+                                let value = if out.name() == UnitSort.name() {
+                                    Value::unit()
+                                } else if out.is_eq_sort() {
+                                    let value = Value {
+                                        #[cfg(debug_assertions)]
+                                        tag: out.name(),
+                                        bits: self.unionfind.make_set(),
+                                    };
+                                    if let Some(explanations) = self.unionfind.explanations_mut() {
+                                        explanations.add_node(*f, args, value);
+                                    }
+                                    value
+                                } else {
+                                    return Err(Error::NotFoundError(NotFoundError(format!(
+                                        "No value found for {f} {:?}",
+                                        args
+                                    ))));
+                                };
+                                function.insert(args, value, self.timestamp);
+                            }
+                            self.extraction_cache = None;
                             function.subsume(args);
                         }
                     }
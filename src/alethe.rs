@@ -0,0 +1,281 @@
+//! Export of [`Explanation`]s as Alethe proofs, which proof checkers such as
+//! Carcara can check independently of egglog.
+//!
+//! An explanation becomes an SMT-LIB problem that asserts the unions and rule
+//! applications the explanation relies on, together with the negation of the
+//! equality it explains, and an Alethe proof that the problem is
+//! unsatisfiable. Congruences and chains of equalities are proven with the
+//! `cong` and `trans` rules, so the checker only has to trust the asserted
+//! equalities. Rules are not translated, so each rule application is an
+//! assumption, with the rule's name in a comment.
+
+use crate::*;
+
+/// An [`Explanation`] as an SMT-LIB problem and an Alethe proof that the
+/// problem is unsatisfiable. See [`EGraph::export_alethe`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AletheProof {
+    pub problem: String,
+    pub proof: String,
+}
+
+impl EGraph {
+    /// Exports an explanation from [`EGraph::explain`] as an Alethe proof.
+    ///
+    /// Sorts and functions of the e-graph are declared in the problem, with
+    /// `i64`, `f64`, `String`, and `bool` as SMT-LIB's `Int`, `Real`,
+    /// `String`, and `Bool`. Other primitives are not declared, so proofs
+    /// about terms that contain them need the declarations added by hand.
+    ///
+    /// ```
+    /// # use egglog::{ast::Expr, EGraph};
+    /// let mut egraph = EGraph::default();
+    /// egraph.enable_explanations();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Neg Math))
+    ///          (Neg (Num 1))
+    ///          (Neg (Num -1))
+    ///          (union (Num 1) (Num -1))
+    ///          (run 1)",
+    ///     )
+    ///     .unwrap();
+    /// let neg = |n: i64| Expr::call_no_span("Neg", [Expr::call_no_span("Num", [Expr::lit_no_span(n)])]);
+    /// let (termdag, explanation) = egraph.explain(&neg(1), &neg(-1)).unwrap();
+    /// let alethe = egraph.export_alethe(&termdag, &explanation);
+    /// assert_eq!(
+    ///     alethe.problem,
+    ///     "(set-logic ALL)
+    /// (declare-sort Math 0)
+    /// (declare-fun Neg (Math) Math)
+    /// (declare-fun Num (Int) Math)
+    /// (assert (= (Num 1) (Num (- 1))))
+    /// (assert (not (= (Neg (Num 1)) (Neg (Num (- 1))))))
+    /// (check-sat)
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     alethe.proof,
+    ///     "(assume goal (not (= (Neg (Num 1)) (Neg (Num (- 1))))))
+    /// ; given
+    /// (assume h1 (= (Num 1) (Num (- 1))))
+    /// (step t1 (cl (= (Neg (Num 1)) (Neg (Num (- 1))))) :rule cong :premises (h1))
+    /// (step t2 (cl) :rule resolution :premises (goal t1))
+    /// "
+    /// );
+    /// ```
+    pub fn export_alethe(&self, termdag: &TermDag, explanation: &Explanation) -> AletheProof {
+        let mut writer = AletheWriter {
+            egraph: self,
+            termdag,
+            functions: Default::default(),
+            assumptions: vec![],
+            proof: String::new(),
+            steps: 0,
+        };
+        let last = explanation
+            .steps
+            .last()
+            .map_or(&explanation.start, |step| &step.term);
+        let goal = writer.equality(&explanation.start, last);
+        writer.proof += &format!("(assume goal (not {goal}))\n");
+        let equality = match writer.chain(explanation) {
+            Some(equality) => equality,
+            None => writer.step(&goal, "refl", &[]),
+        };
+        writer.step("", "resolution", &["goal".into(), equality]);
+
+        let mut problem = String::from("(set-logic ALL)\n");
+        problem += &writer.declarations();
+        for assumption in &writer.assumptions {
+            problem += &format!("(assert {assumption})\n");
+        }
+        problem += &format!("(assert (not {goal}))\n(check-sat)\n");
+        AletheProof {
+            problem,
+            proof: writer.proof,
+        }
+    }
+}
+
+struct AletheWriter<'a> {
+    egraph: &'a EGraph,
+    termdag: &'a TermDag,
+    /// The functions applied in the proof, in the order they first appear.
+    functions: IndexSet<Symbol>,
+    /// The equalities the proof assumes.
+    assumptions: Vec<String>,
+    proof: String,
+    steps: usize,
+}
+
+impl AletheWriter<'_> {
+    /// Proves the equality between the first and last terms of a chain,
+    /// returning the name of the step that concludes it, or `None` if the
+    /// chain is empty.
+    fn chain(&mut self, explanation: &Explanation) -> Option<String> {
+        let mut premises = vec![];
+        let mut prev = &explanation.start;
+        for step in &explanation.steps {
+            let equality = self.equality(prev, &step.term);
+            let premise = match &step.justification {
+                Justification::Given => self.assume(equality, "given".into()),
+                Justification::Batched => self.assume(equality, "by a batched rule".into()),
+                Justification::Rule { name, .. } => {
+                    let name = name.as_str().split_whitespace().collect::<Vec<_>>();
+                    self.assume(equality, format!("by rule {}", name.join(" ")))
+                }
+                // the inputs were not recorded
+                Justification::Congruence(inputs) if inputs.is_empty() => {
+                    self.assume(equality, "by congruence".into())
+                }
+                Justification::Congruence(inputs) => {
+                    // inputs that are the same term need no premise
+                    let inputs: Vec<String> = inputs.iter().filter_map(|i| self.chain(i)).collect();
+                    self.step(&equality, "cong", &inputs)
+                }
+            };
+            premises.push(premise);
+            prev = &step.term;
+        }
+        match premises.len() {
+            0 => None,
+            1 => premises.pop(),
+            _ => {
+                let equality = self.equality(&explanation.start, prev);
+                Some(self.step(&equality, "trans", &premises))
+            }
+        }
+    }
+
+    fn assume(&mut self, equality: String, note: String) -> String {
+        let name = format!("h{}", self.assumptions.len() + 1);
+        self.proof += &format!("; {note}\n(assume {name} {equality})\n");
+        self.assumptions.push(equality);
+        name
+    }
+
+    /// Adds a step concluding the clause with the single literal `literal`,
+    /// or the empty clause if it is empty.
+    fn step(&mut self, literal: &str, rule: &str, premises: &[String]) -> String {
+        self.steps += 1;
+        let name = format!("t{}", self.steps);
+        let clause = if literal.is_empty() {
+            "(cl)".to_string()
+        } else {
+            format!("(cl {literal})")
+        };
+        self.proof += &format!("(step {name} {clause} :rule {rule}");
+        if !premises.is_empty() {
+            self.proof += &format!(" :premises ({})", premises.join(" "));
+        }
+        self.proof += ")\n";
+        name
+    }
+
+    fn equality(&mut self, a: &Term, b: &Term) -> String {
+        format!("(= {} {})", self.term(a), self.term(b))
+    }
+
+    fn term(&mut self, term: &Term) -> String {
+        match term {
+            Term::Lit(lit) => literal(lit),
+            Term::Var(var) => symbol(*var),
+            Term::App(function, children) if children.is_empty() => {
+                self.functions.insert(*function);
+                symbol(*function)
+            }
+            Term::App(function, children) => {
+                self.functions.insert(*function);
+                let children: Vec<String> = children
+                    .iter()
+                    .map(|child| self.term(self.termdag.get(*child)))
+                    .collect();
+                format!("({} {})", symbol(*function), children.join(" "))
+            }
+        }
+    }
+
+    /// Declares the sorts and functions of the e-graph used in the proof.
+    fn declarations(&self) -> String {
+        let functions: Vec<&Function> = self
+            .functions
+            .iter()
+            .filter_map(|name| self.egraph.functions.get(name))
+            .collect();
+        let mut sorts = IndexSet::default();
+        for function in &functions {
+            for sort in function
+                .schema
+                .input
+                .iter()
+                .chain([&function.schema.output])
+            {
+                if builtin_sort(sort.name()).is_none() {
+                    sorts.insert(sort.name());
+                }
+            }
+        }
+
+        let mut out = String::new();
+        for sort in sorts {
+            out += &format!("(declare-sort {} 0)\n", symbol(sort));
+        }
+        for function in functions {
+            let inputs: Vec<String> = function
+                .schema
+                .input
+                .iter()
+                .map(|sort| sort_name(sort.name()))
+                .collect();
+            out += &format!(
+                "(declare-fun {} ({}) {})\n",
+                symbol(function.decl.name),
+                inputs.join(" "),
+                sort_name(function.schema.output.name())
+            );
+        }
+        out
+    }
+}
+
+fn builtin_sort(sort: Symbol) -> Option<&'static str> {
+    match sort.as_str() {
+        "i64" => Some("Int"),
+        "f64" => Some("Real"),
+        "String" => Some("String"),
+        "bool" => Some("Bool"),
+        _ => None,
+    }
+}
+
+fn sort_name(sort: Symbol) -> String {
+    builtin_sort(sort).map_or_else(|| symbol(sort), String::from)
+}
+
+fn literal(lit: &Literal) -> String {
+    match lit {
+        Literal::Int(n) if *n < 0 => format!("(- {})", n.unsigned_abs()),
+        Literal::F64(f) if f.is_sign_negative() => format!("(- {:?})", -f.into_inner()),
+        Literal::F64(f) => format!("{:?}", f.into_inner()),
+        // SMT-LIB escapes a quote by doubling it
+        Literal::String(s) => format!("\"{}\"", s.as_str().replace('"', "\"\"")),
+        lit => lit.to_string(),
+    }
+}
+
+/// Quotes a symbol that is not a simple SMT-LIB symbol.
+fn symbol(name: Symbol) -> String {
+    let name = name.as_str();
+    let simple = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(c));
+    if simple {
+        name.to_string()
+    } else {
+        format!("|{name}|")
+    }
+}
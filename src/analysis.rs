@@ -0,0 +1,214 @@
+//! E-class analyses, which keep a lattice value for each e-class up to date
+//! like egg's `Analysis`.
+//!
+//! An analysis is a function from an e-class to a value, whose merge joins
+//! the values of e-classes that are unioned, together with rules in an
+//! `analysis-ruleset` that compute values from e-nodes. Every rebuild runs
+//! the analysis rulesets until saturation, so rules in other rulesets can
+//! use the values in their queries as guards. Analyses can be written in
+//! egglog directly, or registered from Rust with [`EGraph::add_analysis`].
+
+use crate::*;
+
+/// An e-class analysis written in Rust, for [`EGraph::add_analysis`].
+pub trait Analysis: Send + Sync {
+    /// The name of the function that holds the values. Rules can look a
+    /// value up like any other function, as in `(= (name e) v)`.
+    fn name(&self) -> Symbol;
+    /// The e-class sort being analysed.
+    fn sort(&self) -> Symbol;
+    /// The sort of the values.
+    fn value_sort(&self) -> Symbol;
+    /// The value of an e-node made with `constructor`. The children of the
+    /// analysed sort are replaced by their values, and the other children
+    /// are passed as they are. Returns `None` if the e-node says nothing
+    /// about its e-class.
+    fn make(&self, constructor: Symbol, children: &[Value]) -> Option<Value>;
+    /// Joins the values of two e-classes that were unioned.
+    fn join(&self, a: Value, b: Value) -> Value;
+}
+
+impl EGraph {
+    /// Registers an analysis of the e-classes of [`Analysis::sort`]. This
+    /// declares a function [`Analysis::name`] merged with
+    /// [`Analysis::join`], and an analysis ruleset with the same name that
+    /// has a rule calling [`Analysis::make`] for each constructor of the
+    /// sort. Only constructors declared before the analysis are analysed.
+    ///
+    /// ```
+    /// # use egglog::{ast::Symbol, Analysis, EGraph, Value};
+    /// struct ConstantFold;
+    ///
+    /// impl Analysis for ConstantFold {
+    ///     fn name(&self) -> Symbol {
+    ///         "const".into()
+    ///     }
+    ///     fn sort(&self) -> Symbol {
+    ///         "Math".into()
+    ///     }
+    ///     fn value_sort(&self) -> Symbol {
+    ///         "i64".into()
+    ///     }
+    ///     fn make(&self, constructor: Symbol, children: &[Value]) -> Option<Value> {
+    ///         let int = |value: &Value| value.bits as i64;
+    ///         match (constructor.as_str(), children) {
+    ///             ("Num", [n]) => Some(*n),
+    ///             ("Add", [a, b]) => int(a).checked_add(int(b)).map(Value::from),
+    ///             ("Mul", [a, b]) => int(a).checked_mul(int(b)).map(Value::from),
+    ///             _ => None,
+    ///         }
+    ///     }
+    ///     fn join(&self, a: Value, _b: Value) -> Value {
+    ///         a
+    ///     }
+    /// }
+    ///
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(None, "(datatype Math (Num i64) (Add Math Math) (Mul Math Math))")
+    ///     .unwrap();
+    /// egraph.add_analysis(ConstantFold).unwrap();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(rule ((= e (Mul a b)) (= (const e) 0)) ((union e (Num 0))))
+    ///          (let x (Mul (Add (Num 1) (Num -1)) (Num 5)))
+    ///          (run 1)
+    ///          (check (= x (Num 0)))",
+    ///     )
+    ///     .unwrap();
+    /// ```
+    pub fn add_analysis(&mut self, analysis: impl Analysis + 'static) -> Result<(), Error> {
+        let analysis: Arc<dyn Analysis> = Arc::new(analysis);
+        let (name, sort, value_sort) = (analysis.name(), analysis.sort(), analysis.value_sort());
+        let lookup = |sort| {
+            self.type_info
+                .sorts
+                .get(&sort)
+                .cloned()
+                .ok_or_else(|| TypeError::UndefinedSort(sort, DUMMY_SPAN.clone()))
+        };
+        if !lookup(sort)?.is_eq_sort() {
+            return Err(TypeError::DisallowedSort(
+                sort,
+                "only e-classes can be analysed".into(),
+                DUMMY_SPAN.clone(),
+            )
+            .into());
+        }
+        let value_arcsort = lookup(value_sort)?;
+
+        let join = Symbol::from(format!("{name}-join"));
+        self.add_primitive(AnalysisPrimitive {
+            name: join,
+            analysis: analysis.clone(),
+            constructor: None,
+            sorts: vec![value_arcsort.clone(); 3],
+        });
+        let mut program = format!(
+            "(analysis-ruleset {name})
+             (function {name} ({sort}) {value_sort} :merge ({join} old new))"
+        );
+
+        let constructors: Vec<(Symbol, Vec<ArcSort>)> = self
+            .functions
+            .values()
+            .filter(|function| {
+                function.schema.output.name() == sort
+                    && function.decl.merge.is_none()
+                    && !function.decl.ignore_viz
+                    && !self.symbol_gen.is_fresh(function.decl.name)
+            })
+            .map(|function| (function.decl.name, function.schema.input.clone()))
+            .collect();
+        for (constructor, inputs) in constructors {
+            let make = Symbol::from(format!("{name}-make-{constructor}"));
+            let (mut children, mut lookups, mut args) = (vec![], vec![], vec![]);
+            let mut sorts = vec![];
+            for (i, input) in inputs.iter().enumerate() {
+                let child = format!("{name}-child{i}");
+                if input.name() == sort {
+                    let value = format!("{name}-value{i}");
+                    lookups.push(format!("(= {value} ({name} {child}))"));
+                    args.push(value);
+                    sorts.push(value_arcsort.clone());
+                } else {
+                    args.push(child.clone());
+                    sorts.push(input.clone());
+                }
+                children.push(child);
+            }
+            sorts.push(value_arcsort.clone());
+            self.add_primitive(AnalysisPrimitive {
+                name: make,
+                analysis: analysis.clone(),
+                constructor: Some(constructor),
+                sorts,
+            });
+            program += &format!(
+                "(rule ((= {name}-eclass ({constructor} {}))
+                        {}
+                        (= {name}-value ({make} {})))
+                       ((set ({name} {name}-eclass) {name}-value))
+                       :ruleset {name})",
+                children.join(" "),
+                lookups.join(" "),
+                args.join(" "),
+            );
+        }
+
+        self.parse_and_run_program(None, &program)?;
+        Ok(())
+    }
+
+    /// Runs one iteration of each analysis ruleset, returning whether it
+    /// changed the e-graph.
+    pub(crate) fn update_analyses(&mut self) -> bool {
+        if self.analysis_rulesets.is_empty() {
+            return false;
+        }
+        // Analyses are not part of the schedule that is running, so the
+        // schedule's backoff does not apply to them
+        let backoff = self.backoff.take();
+        self.timestamp += 1;
+        let mut updated = false;
+        for ruleset in self.analysis_rulesets.clone() {
+            updated |= self.step_rules(ruleset).updated;
+        }
+        self.timestamp += 1;
+        self.backoff = backoff;
+        updated
+    }
+}
+
+/// The primitives that call an [`Analysis`] from its rules and merge.
+struct AnalysisPrimitive {
+    name: Symbol,
+    analysis: Arc<dyn Analysis>,
+    /// The constructor to make values for, or `None` for the join.
+    constructor: Option<Symbol>,
+    /// The input sorts followed by the output sort.
+    sorts: Vec<ArcSort>,
+}
+
+impl PrimitiveLike for AnalysisPrimitive {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(self.name, self.sorts.clone(), span.clone()).into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        match self.constructor {
+            Some(constructor) => self.analysis.make(constructor, values),
+            None => Some(self.analysis.join(values[0], values[1])),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::{Rewrite, Rule};
 use crate::*;
+use std::path::Path;
 
 /// Desugars a list of commands into the normalized form.
 /// Gets rid of a bunch of syntactic sugar, but also
@@ -17,6 +18,32 @@ pub(crate) fn desugar_program(
     Ok(res)
 }
 
+/// The path of a file included at `span`, which is relative to the directory
+/// of the file with the `include` in it, or to the working directory when
+/// that file has no name. Including a file that is already being included
+/// is an error.
+fn include_path(span: &Span, file: &str) -> Result<PathBuf, Error> {
+    let path = match span.file() {
+        Some(including) => Path::new(including)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(file),
+        None => PathBuf::from(file),
+    };
+    let same_file = |other: &str| match (path.canonicalize(), Path::new(other).canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => path == Path::new(other),
+    };
+    let mut including = Some(span);
+    while let Some(from) = including {
+        if from.file().is_some_and(same_file) {
+            return Err(Error::IncludeCycle(path, span.clone()));
+        }
+        including = from.included_from();
+    }
+    Ok(path)
+}
+
 /// Desugars a single command into the normalized form.
 /// Gets rid of a bunch of syntactic sugar, but also
 /// makes rules into a SSA-like format (see [`NormFact`]).
@@ -99,10 +126,12 @@ pub(crate) fn desugar_command(
             desugar_birewrite(ruleset, rewrite_name(&rewrite).into(), &rewrite)
         }
         Command::Include(span, file) => {
-            let s = std::fs::read_to_string(&file)
-                .unwrap_or_else(|_| panic!("{span} Failed to read file {file}"));
+            let path = include_path(&span, &file)?;
+            let s = std::fs::read_to_string(&path)
+                .map_err(|e| Error::IoError(path.clone(), e, span.clone()))?;
+            let name = path.to_string_lossy().into_owned();
             return desugar_program(
-                parse_program(Some(file), &s)?,
+                parse_included_program(name, &s, span)?,
                 symbol_gen,
                 seminaive_transform,
             );
@@ -136,6 +165,7 @@ pub(crate) fn desugar_command(
         }
         Command::Sort(span, sort, option) => vec![NCommand::Sort(span, sort, option)],
         Command::AddRuleset(name) => vec![NCommand::AddRuleset(name)],
+        Command::AddAnalysisRuleset(name) => vec![NCommand::AddAnalysisRuleset(name)],
         Command::UnstableCombinedRuleset(name, subrulesets) => {
             vec![NCommand::UnstableCombinedRuleset(name, subrulesets)]
         }
@@ -148,6 +178,32 @@ pub(crate) fn desugar_command(
         Command::RunSchedule(sched) => {
             vec![NCommand::RunSchedule(sched.clone())]
         }
+        Command::RunPortfolio {
+            span,
+            schedules,
+            cost_of,
+        } => vec![NCommand::RunPortfolio {
+            span,
+            schedules,
+            cost_of,
+        }],
+        Command::Module { span, name, body } => vec![NCommand::Module {
+            span,
+            name,
+            body: desugar_program(body, symbol_gen, seminaive_transform)?,
+        }],
+        Command::Import(span, name) => vec![NCommand::Import(span, name)],
+        Command::DefMacro {
+            span,
+            name,
+            params,
+            body,
+        } => vec![NCommand::DefMacro {
+            span,
+            name,
+            params,
+            body: desugar_program(body, symbol_gen, seminaive_transform)?,
+        }],
         Command::PrintOverallStatistics => {
             vec![NCommand::PrintOverallStatistics]
         }
@@ -163,6 +219,7 @@ pub(crate) fn desugar_command(
                     span.clone(),
                     expr,
                     variants,
+                    ExtractMode::Tree,
                 ))]
             } else {
                 // (check {expr})
@@ -184,7 +241,10 @@ pub(crate) fn desugar_command(
                         span.clone(),
                         Expr::Var(span.clone(), fresh),
                         variants,
+                        ExtractMode::Tree,
                     )),
+                    priority: 0,
+                    match_limit: None,
                 };
                 vec![
                     NCommand::Check(span.clone(), vec![Fact::Fact(expr.clone())]),
@@ -198,6 +258,7 @@ pub(crate) fn desugar_command(
                         span.clone(),
                         RunConfig {
                             ruleset: fresh_ruleset,
+                            node_limit: None,
                             until: None,
                         },
                     )),
@@ -223,9 +284,48 @@ pub(crate) fn desugar_command(
             desugared.push(NCommand::Fail(span, Box::new(last)));
             return Ok(desugared);
         }
-        Command::Input { span, name, file } => {
-            vec![NCommand::Input { span, name, file }]
+        Command::Input {
+            span,
+            name,
+            file,
+            format,
+        } => {
+            vec![NCommand::Input {
+                span,
+                name,
+                file,
+                format,
+            }]
+        }
+        Command::OutputFunction {
+            span,
+            name,
+            file,
+            format,
+        } => {
+            vec![NCommand::OutputFunction {
+                span,
+                name,
+                file,
+                format,
+            }]
         }
+        Command::Save { span, file } => vec![NCommand::Save { span, file }],
+        Command::Load { span, file } => vec![NCommand::Load { span, file }],
+        Command::ImportEGraph { span, file } => vec![NCommand::ImportEGraph { span, file }],
+        Command::CollectGarbage(span) => vec![NCommand::CollectGarbage(span)],
+        Command::Explain(span, a, b) => vec![NCommand::Explain(span, a, b)],
+        Command::SetCost {
+            span,
+            constructor,
+            args,
+            cost,
+        } => vec![NCommand::SetCost {
+            span,
+            constructor,
+            args,
+            cost,
+        }],
     };
 
     Ok(res)
@@ -297,6 +397,8 @@ fn desugar_rewrite(
             .chain(rewrite.conditions.clone())
             .collect(),
             head,
+            priority: rewrite.priority,
+            match_limit: rewrite.match_limit,
         },
     }]
 }
@@ -308,6 +410,8 @@ fn desugar_birewrite(ruleset: Symbol, name: Symbol, rewrite: &Rewrite) -> Vec<NC
         lhs: rewrite.rhs.clone(),
         rhs: rewrite.lhs.clone(),
         conditions: rewrite.conditions.clone(),
+        priority: rewrite.priority,
+        match_limit: rewrite.match_limit,
     };
     desugar_rewrite(ruleset, format!("{}=>", name).into(), rewrite, false)
         .into_iter()
@@ -320,8 +424,25 @@ fn desugar_birewrite(ruleset: Symbol, name: Symbol, rewrite: &Rewrite) -> Vec<NC
         .collect()
 }
 
+/// Primitives that need the e-graph, so they cannot be moved into a rule body.
+const ACTION_ONLY_PRIMITIVES: &[&str] = &["fresh-symbol", "quote", "unquote", "unstable-app"];
+
+fn calls_action_only_primitive(actions: &Actions) -> bool {
+    let mut found = false;
+    actions.clone().visit_exprs(&mut |expr| {
+        if let Expr::Call(_, head, _) = &expr {
+            found |= ACTION_ONLY_PRIMITIVES.contains(&head.as_str());
+        }
+        expr
+    });
+    found
+}
+
 // TODO(yz): we can delete this code once we enforce that all rule bodies cannot read the database (except EqSort).
 fn add_semi_naive_rule(symbol_gen: &mut SymbolGen, rule: Rule) -> Option<Rule> {
+    if calls_action_only_primitive(&rule.head) {
+        return None;
+    }
     let mut new_rule = rule;
     // Whenever an Let(_, expr@Call(...)) or Set(_, expr@Call(...)) is present in action,
     // an additional seminaive rule should be created.
@@ -7,8 +7,10 @@ use std::{fmt::Display, hash::Hasher};
 pub enum Literal {
     Int(i64),
     F64(OrderedFloat<f64>),
+    F32(OrderedFloat<f32>),
     String(Symbol),
     Bool(bool),
+    Bytes(Vec<u8>),
     Unit,
 }
 
@@ -34,6 +36,7 @@ macro_rules! impl_from {
 
 impl_from!(Int(i64));
 impl_from!(F64(OrderedFloat<f64>));
+impl_from!(F32(OrderedFloat<f32>));
 impl_from!(String(Symbol));
 
 impl Display for Literal {
@@ -49,8 +52,10 @@ impl Display for Literal {
                     write!(f, "{}", str)
                 }
             }
+            Literal::F32(n) => write!(f, "{n}f32"),
             Literal::Bool(b) => Display::fmt(b, f),
             Literal::String(s) => write!(f, "\"{}\"", s),
+            Literal::Bytes(b) => write!(f, "#x{}", encode_hex(b)),
             Literal::Unit => write!(f, "()"),
         }
     }
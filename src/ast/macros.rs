@@ -0,0 +1,131 @@
+//! Expand the macros defined with `defmacro`.
+//!
+//! A call of a macro is a command that looks like a call of a function with
+//! the macro's name. It is replaced by the body of the macro, where the
+//! parameters that got a name as their argument are renamed to it, and the
+//! others are replaced by their argument where they are used as expressions.
+//! The rules in the body are named after the call as well, so that calling a
+//! macro twice doesn't give two rules the same name.
+//!
+//! Like resolving modules, this is done after desugaring and before
+//! typechecking, so a macro is only checked where it is called.
+
+use super::modules::rename_command;
+use crate::*;
+
+#[derive(Clone)]
+struct Macro {
+    params: Vec<Symbol>,
+    body: Vec<NCommand>,
+}
+
+/// The macros defined so far.
+#[derive(Clone, Default)]
+pub(crate) struct Macros {
+    macros: HashMap<Symbol, Macro>,
+    /// The macros being expanded, innermost last.
+    expanding: Vec<Symbol>,
+}
+
+impl Macros {
+    /// Removes the macro definitions from a desugared program, and replaces
+    /// the calls of macros with their bodies.
+    pub(crate) fn expand(&mut self, program: Vec<NCommand>) -> Result<Vec<NCommand>, Error> {
+        let mut res = vec![];
+        self.expand_into(program, &mut res)?;
+        Ok(res)
+    }
+
+    fn expand_into(
+        &mut self,
+        program: Vec<NCommand>,
+        res: &mut Vec<NCommand>,
+    ) -> Result<(), Error> {
+        for command in program {
+            match command {
+                NCommand::DefMacro {
+                    span: _,
+                    name,
+                    params,
+                    body,
+                } => {
+                    self.macros.insert(name, Macro { params, body });
+                }
+                NCommand::CoreAction(Action::Expr(span, Expr::Call(_, head, args)))
+                    if self.macros.contains_key(&head) =>
+                {
+                    self.expand_call(span, head, args, res)?;
+                }
+                NCommand::Module { span, name, body } => {
+                    let body = self.expand(body)?;
+                    res.push(NCommand::Module { span, name, body });
+                }
+                NCommand::Fail(span, command) => {
+                    // expand it on the side, since it may not work
+                    let mut macros = self.clone();
+                    match macros.expand(vec![*command]) {
+                        Ok(mut commands) => {
+                            let Some(last) = commands.pop() else {
+                                return Err(Error::ExpectFail(span));
+                            };
+                            res.extend(commands);
+                            res.push(NCommand::Fail(span, Box::new(last)));
+                        }
+                        Err(e) => log::info!("Command failed as expected: {e}"),
+                    }
+                }
+                command => res.push(command),
+            }
+        }
+        Ok(())
+    }
+
+    fn expand_call(
+        &mut self,
+        span: Span,
+        name: Symbol,
+        args: Vec<Expr>,
+        res: &mut Vec<NCommand>,
+    ) -> Result<(), Error> {
+        let Macro { params, body } = self.macros[&name].clone();
+        if args.len() != params.len() {
+            return Err(Error::MacroArityError(name, params.len(), args.len(), span));
+        }
+        if self.expanding.contains(&name) {
+            return Err(Error::RecursiveMacro(name, span));
+        }
+
+        let call = format!("({name} {})", ListDisplay(&args, " ")).replace('"', "'");
+        let mut names = HashMap::default();
+        let mut exprs = HashMap::default();
+        for (param, arg) in params.into_iter().zip(args) {
+            match arg {
+                Expr::Var(_, arg) => {
+                    names.insert(param, arg);
+                }
+                arg => {
+                    exprs.insert(param, arg);
+                }
+            }
+        }
+        let body = body
+            .into_iter()
+            .map(|command| {
+                let mut command =
+                    rename_command(command, &names).visit_exprs(&mut |expr| match expr {
+                        Expr::Var(_, var) if exprs.contains_key(&var) => exprs[&var].clone(),
+                        expr => expr,
+                    });
+                if let NCommand::NormRule { name, .. } = &mut command {
+                    *name = format!("{call} {name}").into();
+                }
+                command
+            })
+            .collect();
+
+        self.expanding.push(name);
+        let result = self.expand_into(body, res);
+        self.expanding.pop();
+        result
+    }
+}
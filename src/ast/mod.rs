@@ -1,5 +1,7 @@
 pub mod desugar;
 mod expr;
+pub(crate) mod macros;
+pub(crate) mod modules;
 pub mod parse;
 pub(crate) mod remove_globals;
 
@@ -58,6 +60,7 @@ where
     ),
     Function(GenericFunctionDecl<Head, Leaf>),
     AddRuleset(Symbol),
+    AddAnalysisRuleset(Symbol),
     UnstableCombinedRuleset(Symbol, Vec<Symbol>),
     NormRule {
         name: Symbol,
@@ -66,6 +69,11 @@ where
     },
     CoreAction(GenericAction<Head, Leaf>),
     RunSchedule(GenericSchedule<Head, Leaf>),
+    RunPortfolio {
+        span: Span,
+        schedules: Vec<GenericSchedule<Head, Leaf>>,
+        cost_of: GenericExpr<Head, Leaf>,
+    },
     PrintOverallStatistics,
     Check(Span, Vec<GenericFact<Head, Leaf>>),
     PrintTable(Span, Symbol, usize),
@@ -82,6 +90,45 @@ where
         span: Span,
         name: Symbol,
         file: String,
+        format: TableFormat,
+    },
+    OutputFunction {
+        span: Span,
+        name: Symbol,
+        file: String,
+        format: TableFormat,
+    },
+    Save {
+        span: Span,
+        file: String,
+    },
+    Load {
+        span: Span,
+        file: String,
+    },
+    ImportEGraph {
+        span: Span,
+        file: String,
+    },
+    CollectGarbage(Span),
+    Explain(Span, GenericExpr<Head, Leaf>, GenericExpr<Head, Leaf>),
+    SetCost {
+        span: Span,
+        constructor: Symbol,
+        args: Vec<Leaf>,
+        cost: GenericExpr<Head, Leaf>,
+    },
+    Module {
+        span: Span,
+        name: Symbol,
+        body: Vec<GenericNCommand<Head, Leaf>>,
+    },
+    Import(Span, Symbol),
+    DefMacro {
+        span: Span,
+        name: Symbol,
+        params: Vec<Symbol>,
+        body: Vec<GenericNCommand<Head, Leaf>>,
     },
 }
 
@@ -101,6 +148,7 @@ where
             }
             GenericNCommand::Function(f) => GenericCommand::Function(f.clone()),
             GenericNCommand::AddRuleset(name) => GenericCommand::AddRuleset(*name),
+            GenericNCommand::AddAnalysisRuleset(name) => GenericCommand::AddAnalysisRuleset(*name),
             GenericNCommand::UnstableCombinedRuleset(name, others) => {
                 GenericCommand::UnstableCombinedRuleset(*name, others.clone())
             }
@@ -114,6 +162,15 @@ where
                 rule: rule.clone(),
             },
             GenericNCommand::RunSchedule(schedule) => GenericCommand::RunSchedule(schedule.clone()),
+            GenericNCommand::RunPortfolio {
+                span,
+                schedules,
+                cost_of,
+            } => GenericCommand::RunPortfolio {
+                span: span.clone(),
+                schedules: schedules.clone(),
+                cost_of: cost_of.clone(),
+            },
             GenericNCommand::PrintOverallStatistics => GenericCommand::PrintOverallStatistics,
             GenericNCommand::CoreAction(action) => GenericCommand::Action(action.clone()),
             GenericNCommand::Check(span, facts) => {
@@ -135,10 +192,71 @@ where
             GenericNCommand::Fail(span, cmd) => {
                 GenericCommand::Fail(span.clone(), Box::new(cmd.to_command()))
             }
-            GenericNCommand::Input { span, name, file } => GenericCommand::Input {
+            GenericNCommand::Input {
+                span,
+                name,
+                file,
+                format,
+            } => GenericCommand::Input {
+                span: span.clone(),
+                name: *name,
+                file: file.clone(),
+                format: format.clone(),
+            },
+            GenericNCommand::OutputFunction {
+                span,
+                name,
+                file,
+                format,
+            } => GenericCommand::OutputFunction {
                 span: span.clone(),
                 name: *name,
                 file: file.clone(),
+                format: format.clone(),
+            },
+            GenericNCommand::Save { span, file } => GenericCommand::Save {
+                span: span.clone(),
+                file: file.clone(),
+            },
+            GenericNCommand::Load { span, file } => GenericCommand::Load {
+                span: span.clone(),
+                file: file.clone(),
+            },
+            GenericNCommand::ImportEGraph { span, file } => GenericCommand::ImportEGraph {
+                span: span.clone(),
+                file: file.clone(),
+            },
+            GenericNCommand::CollectGarbage(span) => GenericCommand::CollectGarbage(span.clone()),
+            GenericNCommand::Explain(span, a, b) => {
+                GenericCommand::Explain(span.clone(), a.clone(), b.clone())
+            }
+            GenericNCommand::SetCost {
+                span,
+                constructor,
+                args,
+                cost,
+            } => GenericCommand::SetCost {
+                span: span.clone(),
+                constructor: *constructor,
+                args: args.clone(),
+                cost: cost.clone(),
+            },
+            GenericNCommand::Module { span, name, body } => GenericCommand::Module {
+                span: span.clone(),
+                name: *name,
+                body: body.iter().map(|command| command.to_command()).collect(),
+            },
+            GenericNCommand::Import(span, name) => GenericCommand::Import(span.clone(), *name),
+            GenericNCommand::DefMacro {
+                span,
+                name,
+                params,
+                body,
+            } => GenericCommand::DefMacro {
+                span: span.clone(),
+                name: *name,
+                params: params.clone(),
+                body: body.iter().map(|command| command.to_command()).collect(),
             },
         }
     }
@@ -155,6 +273,7 @@ where
             GenericNCommand::Sort(span, name, params) => GenericNCommand::Sort(span, name, params),
             GenericNCommand::Function(func) => GenericNCommand::Function(func.visit_exprs(f)),
             GenericNCommand::AddRuleset(name) => GenericNCommand::AddRuleset(name),
+            GenericNCommand::AddAnalysisRuleset(name) => GenericNCommand::AddAnalysisRuleset(name),
             GenericNCommand::UnstableCombinedRuleset(name, rulesets) => {
                 GenericNCommand::UnstableCombinedRuleset(name, rulesets)
             }
@@ -170,6 +289,18 @@ where
             GenericNCommand::RunSchedule(schedule) => {
                 GenericNCommand::RunSchedule(schedule.visit_exprs(f))
             }
+            GenericNCommand::RunPortfolio {
+                span,
+                schedules,
+                cost_of,
+            } => GenericNCommand::RunPortfolio {
+                span,
+                schedules: schedules
+                    .into_iter()
+                    .map(|schedule| schedule.visit_exprs(f))
+                    .collect(),
+                cost_of: cost_of.visit_exprs(f),
+            },
             GenericNCommand::PrintOverallStatistics => GenericNCommand::PrintOverallStatistics,
             GenericNCommand::CoreAction(action) => {
                 GenericNCommand::CoreAction(action.visit_exprs(f))
@@ -192,9 +323,69 @@ where
             GenericNCommand::Fail(span, cmd) => {
                 GenericNCommand::Fail(span, Box::new(cmd.visit_exprs(f)))
             }
-            GenericNCommand::Input { span, name, file } => {
-                GenericNCommand::Input { span, name, file }
+            GenericNCommand::Input {
+                span,
+                name,
+                file,
+                format,
+            } => GenericNCommand::Input {
+                span,
+                name,
+                file,
+                format,
+            },
+            GenericNCommand::OutputFunction {
+                span,
+                name,
+                file,
+                format,
+            } => GenericNCommand::OutputFunction {
+                span,
+                name,
+                file,
+                format,
+            },
+            GenericNCommand::Save { span, file } => GenericNCommand::Save { span, file },
+            GenericNCommand::Load { span, file } => GenericNCommand::Load { span, file },
+            GenericNCommand::ImportEGraph { span, file } => {
+                GenericNCommand::ImportEGraph { span, file }
             }
+            GenericNCommand::CollectGarbage(span) => GenericNCommand::CollectGarbage(span),
+            GenericNCommand::Explain(span, a, b) => GenericNCommand::Explain(span, f(a), f(b)),
+            GenericNCommand::SetCost {
+                span,
+                constructor,
+                args,
+                cost,
+            } => GenericNCommand::SetCost {
+                span,
+                constructor,
+                args,
+                cost: f(cost),
+            },
+            GenericNCommand::Module { span, name, body } => GenericNCommand::Module {
+                span,
+                name,
+                body: body
+                    .into_iter()
+                    .map(|command| command.visit_exprs(f))
+                    .collect(),
+            },
+            GenericNCommand::Import(span, name) => GenericNCommand::Import(span, name),
+            GenericNCommand::DefMacro {
+                span,
+                name,
+                params,
+                body,
+            } => GenericNCommand::DefMacro {
+                span,
+                name,
+                params,
+                body: body
+                    .into_iter()
+                    .map(|command| command.visit_exprs(f))
+                    .collect(),
+            },
         }
     }
 }
@@ -208,6 +399,33 @@ pub enum GenericSchedule<Head, Leaf> {
     Repeat(Span, usize, Box<GenericSchedule<Head, Leaf>>),
     Run(Span, GenericRunConfig<Head, Leaf>),
     Sequence(Span, Vec<GenericSchedule<Head, Leaf>>),
+    /// Runs a schedule while temporarily banning rules that match too often,
+    /// written `(backoff :match-limit 1000 :ban-length 5 schedules...)`.
+    Backoff(Span, BackoffConfig, Box<GenericSchedule<Head, Leaf>>),
+    /// Runs a schedule until it finishes or the time limit passes, written
+    /// `(time-limit seconds schedules...)`. The limit is only checked
+    /// between iterations.
+    TimeLimit(Span, Duration, Box<GenericSchedule<Head, Leaf>>),
+}
+
+/// The limits of a [`GenericSchedule::Backoff`] schedule.
+///
+/// A rule that has been banned `n` times is banned again when it finds more
+/// than `match_limit << n` matches in one iteration, this time for
+/// `ban_length << n` iterations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BackoffConfig {
+    pub match_limit: usize,
+    pub ban_length: usize,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        BackoffConfig {
+            match_limit: 1000,
+            ban_length: 5,
+        }
+    }
 }
 
 pub trait ToSexp {
@@ -232,6 +450,12 @@ impl ToSexp for usize {
     }
 }
 
+impl ToSexp for ExtractMode {
+    fn to_sexp(&self) -> Sexp {
+        Sexp::Symbol(self.to_string())
+    }
+}
+
 impl ToSexp for Sexp {
     fn to_sexp(&self) -> Sexp {
         self.clone()
@@ -271,6 +495,12 @@ where
                 span,
                 scheds.into_iter().map(|s| s.visit_exprs(f)).collect(),
             ),
+            GenericSchedule::Backoff(span, config, sched) => {
+                GenericSchedule::Backoff(span, config, Box::new(sched.visit_exprs(f)))
+            }
+            GenericSchedule::TimeLimit(span, limit, sched) => {
+                GenericSchedule::TimeLimit(span, limit, Box::new(sched.visit_exprs(f)))
+            }
         }
     }
 }
@@ -282,6 +512,19 @@ impl<Head: Display, Leaf: Display> ToSexp for GenericSchedule<Head, Leaf> {
             GenericSchedule::Repeat(_ann, size, sched) => list!("repeat", size, sched),
             GenericSchedule::Run(_ann, config) => config.to_sexp(),
             GenericSchedule::Sequence(_ann, scheds) => list!("seq", ++ scheds),
+            GenericSchedule::Backoff(_ann, config, sched) => list!(
+                "backoff",
+                ":match-limit",
+                config.match_limit,
+                ":ban-length",
+                config.ban_length,
+                sched
+            ),
+            GenericSchedule::TimeLimit(_ann, limit, sched) => list!(
+                "time-limit",
+                Sexp::Symbol(limit.as_secs_f64().to_string()),
+                sched
+            ),
         }
     }
 }
@@ -451,6 +694,27 @@ where
     /// (run myrules 2)
     /// ```
     AddRuleset(Symbol),
+    /// Using the `analysis-ruleset` command, defines a ruleset whose rules
+    /// are run until saturation at the end of every rebuild, instead of
+    /// in a [`Schedule`]. This is how e-class analyses are kept up to date:
+    /// a function from an e-class to a lattice value, whose merge is the
+    /// join, together with rules that compute values from e-nodes.
+    /// Rules in other rulesets, `check`, and `extract` then always see
+    /// the analysis of the current e-graph.
+    /// See also [`EGraph::add_analysis`](crate::EGraph::add_analysis).
+    ///
+    /// Example:
+    /// ```text
+    /// (datatype Math (Num i64) (Add Math Math))
+    /// (analysis-ruleset bounds)
+    /// (function hi (Math) i64 :merge (min old new))
+    /// (rule ((= e (Num n))) ((set (hi e) n)) :ruleset bounds)
+    /// (rule ((= e (Add a b)) (= x (hi a)) (= y (hi b)))
+    ///       ((set (hi e) (+ x y)))
+    ///       :ruleset bounds)
+    /// (check (= (hi (Add (Num 1) (Num 2))) 3))
+    /// ```
+    AddAnalysisRuleset(Symbol),
     /// Using the `combined-ruleset` command, construct another ruleset
     /// which runs all the rules in the given rulesets.
     /// This is useful for running multiple rulesets together.
@@ -533,6 +797,9 @@ where
     ///       ((union lhs (bitshift-left a 1))
     ///        (subsume (Mul a 2))))
     /// ```
+    ///
+    /// A `:priority` or `:match-limit` is passed on to the rule, see
+    /// [`GenericRule::priority`] and [`GenericRule::match_limit`].
     Rewrite(Symbol, GenericRewrite<Head, Leaf>, Subsume),
     /// Similar to [`Command::Rewrite`], but
     /// generates two rules, one for each direction.
@@ -573,6 +840,25 @@ where
     ///
     /// See [`Schedule`] for more details.
     RunSchedule(GenericSchedule<Head, Leaf>),
+    /// Runs each schedule on its own copy of the e-graph, in parallel, and
+    /// keeps the copy where the expression has the cheapest extraction.
+    ///
+    /// Example:
+    /// ```text
+    /// (run-portfolio
+    ///     ((saturate expand) (run simplify 10))
+    ///     :select (best-cost prog))
+    /// ```
+    ///
+    /// Runs `expand` until saturation on one copy and `simplify` ten times
+    /// on another, then continues with whichever copy extracts `prog` with
+    /// the lower cost, or the first one when they tie. Each copy runs on a
+    /// thread of its own, so the schedules take as long as the slowest one.
+    RunPortfolio {
+        span: Span,
+        schedules: Vec<GenericSchedule<Head, Leaf>>,
+        cost_of: GenericExpr<Head, Leaf>,
+    },
     /// Print runtime statistics about rules
     /// and rulesets so far.
     PrintOverallStatistics,
@@ -605,6 +891,9 @@ where
     ///     (Num 1))
     /// ```
     ///
+    /// With `:variants n`, it extracts the `n` cheapest distinct
+    /// terms for each match instead, cheapest first.
+    ///
     /// Under the hood, this command is implemented with the [`EGraph::extract`]
     /// function.
     QueryExtract {
@@ -643,11 +932,35 @@ where
     PrintFunction(Span, Symbol, usize),
     /// Print out the number of rows in a function or all functions.
     PrintSize(Span, Option<Symbol>),
-    /// Input a CSV file directly into a function.
+    /// Input a CSV file directly into a function, with a row of the function
+    /// on each line: its inputs, followed by its output unless the function
+    /// is a constructor or a relation. Fields are read as the sorts of the
+    /// function's columns, which must be `i64`, `f64`, `f32`, `bool`, or
+    /// `String`.
+    /// Example:
+    /// ```text
+    /// (input edge "edges.csv" :delimiter "," :header :columns (1 0))
+    /// ```
+    /// reads the second and first fields of each line after the first as
+    /// the two inputs of `edge`. See [`TableFormat`] for the options.
     Input {
         span: Span,
         name: Symbol,
         file: String,
+        format: TableFormat,
+    },
+    /// Output the rows of a function to a CSV file in the format `input`
+    /// reads, replacing the file. E-classes are written as their cheapest
+    /// terms.
+    /// Example:
+    /// ```text
+    /// (output edge "edges.csv" :delimiter ",")
+    /// ```
+    OutputFunction {
+        span: Span,
+        name: Symbol,
+        file: String,
+        format: TableFormat,
     },
     /// Extract and output a set of expressions to a file.
     Output {
@@ -655,6 +968,89 @@ where
         file: String,
         exprs: Vec<GenericExpr<Head, Leaf>>,
     },
+    /// Save the e-graph to a file, along with everything declared so far,
+    /// so that `load` can restore it later.
+    /// Example:
+    /// ```text
+    /// (save "math.egraph")
+    /// ```
+    /// See [`EGraph::save`](crate::EGraph::save).
+    Save { span: Span, file: String },
+    /// Load an e-graph saved with `save`. Nothing may be declared before it.
+    /// Example:
+    /// ```text
+    /// (load "math.egraph")
+    /// (run 10)
+    /// ```
+    Load { span: Span, file: String },
+    /// Add the e-graph saved with `save` in a file to this one. Sorts,
+    /// functions, rulesets, rules and globals it declares that are not
+    /// declared here yet are declared first. Then its e-classes get fresh
+    /// ids, and its rows are added like `set`s, so an e-node that is in both
+    /// e-graphs ends up in one e-class, and merges resolve conflicting
+    /// values.
+    /// Example:
+    /// ```text
+    /// (datatype Math (Num i64) (Add Math Math))
+    /// (import-egraph "lemmas.egraph")
+    /// (run 10)
+    /// ```
+    ImportEGraph { span: Span, file: String },
+    /// Remove the e-nodes of e-classes that are unreachable from the rows of
+    /// relations, functions to primitives, and globals, then renumber the
+    /// remaining e-classes.
+    /// Example:
+    /// ```text
+    /// (datatype Math (Num i64) (Add Math Math))
+    /// (let x (Add (Num 1) (Num 2)))
+    /// (Num 3)
+    /// (gc)
+    /// ```
+    /// removes `(Num 3)`. See [`EGraph::collect_garbage`](crate::EGraph::collect_garbage).
+    CollectGarbage(Span),
+    /// Print why two terms are equal, as a chain of equalities between
+    /// them with the rule, union, or congruence that justifies each step.
+    /// Fails if the terms are not equal. Needs `(set-option explanations 1)`
+    /// before anything is added to the e-graph.
+    /// Example:
+    /// ```text
+    /// (set-option explanations 1)
+    /// (datatype Math (Num i64) (Add Math Math))
+    /// (rule ((= e (Add a b))) ((union e (Add b a))))
+    /// (Add (Num 1) (Num 2))
+    /// (run 1)
+    /// (explain (Add (Num 1) (Num 2)) (Add (Num 2) (Num 1)))
+    /// ```
+    /// prints
+    /// ```text
+    /// (Add (Num 1) (Num 2))
+    /// = (Add (Num 2) (Num 1)) by rule (rule ((= e (Add a b))) ((union e (Add b a))) ) on a = (Num 1), b = (Num 2)
+    /// ```
+    /// When explanations are enabled, `extract` also explains why the term it
+    /// prints is equal to the one it was given.
+    /// See [`EGraph::explain`](crate::EGraph::explain).
+    Explain(Span, GenericExpr<Head, Leaf>, GenericExpr<Head, Leaf>),
+    /// Set the cost of the e-nodes of a constructor to an `i64` expression
+    /// over its children, in which `(cost a)` is the cost of the cheapest
+    /// term of the child `a`. This replaces the `:cost` of the constructor,
+    /// which adds the costs of the children to a constant.
+    /// Example:
+    /// ```text
+    /// (datatype Math (Num i64) (Add Math Math) (Mul Math Math))
+    /// (set-cost (Mul a b) (+ 2 (+ (cost a) (cost b))))
+    /// (set-cost (Num n) (max 1 (min n 10)))
+    /// ```
+    /// makes multiplications cost twice as much as additions, and numbers
+    /// cost as much as they are worth, from 1 up to 10.
+    /// Negative costs count as 0. When a cost cannot be computed, such as
+    /// when it looks up a function that has no value for the children, the
+    /// e-node is as expensive as can be.
+    SetCost {
+        span: Span,
+        constructor: Symbol,
+        args: Vec<Leaf>,
+        cost: GenericExpr<Head, Leaf>,
+    },
     /// `push` the current egraph `n` times so that it is saved.
     /// Later, the current database and rules can be restored using `pop`.
     Push(usize),
@@ -664,7 +1060,64 @@ where
     /// Assert that a command fails with an error.
     Fail(Span, Box<GenericCommand<Head, Leaf>>),
     /// Include another egglog file directly as text and run it.
+    /// The path is relative to the directory of the file with the `include`
+    /// in it, and a file can't include itself, directly or through other
+    /// files. Errors in an included file say where it was included from.
     Include(Span, String),
+    /// Declares the sorts, functions, rulesets, rules, and globals in the
+    /// body with the module's name in front of their names, so that
+    /// modules can use the same names without clashing.
+    ///
+    /// Example:
+    /// ```text
+    /// (module Int
+    ///     (datatype Expr (Num i64) (Add Expr Expr))
+    ///     (rewrite (Add x y) (Add y x)))
+    /// (module Bool
+    ///     (datatype Expr (Lit bool) (Add Expr Expr)))
+    /// (Int.Add (Int.Num 1) (Int.Num 2))
+    /// ```
+    ///
+    /// Inside the module, its names can be used without the prefix from
+    /// their declaration on. See [`GenericCommand::Import`] for using them
+    /// without the prefix elsewhere.
+    Module {
+        span: Span,
+        name: Symbol,
+        body: Vec<GenericCommand<Head, Leaf>>,
+    },
+    /// Lets the commands that follow, up to the end of the enclosing module,
+    /// use the names a module declared without its prefix. Later imports
+    /// and declarations of the same names shadow earlier ones.
+    ///
+    /// Example:
+    /// ```text
+    /// (import Int)
+    /// (Add (Num 1) (Num 2))
+    /// ```
+    Import(Span, Symbol),
+    /// Defines a macro: a list of commands with parameters, which runs
+    /// where the macro is called like a function, with the arguments in
+    /// place of the parameters. An argument that is a name can stand for a
+    /// function, sort, or ruleset, while any other argument can only be used
+    /// as an expression.
+    ///
+    /// Example:
+    /// ```text
+    /// (defmacro comm (op)
+    ///     (rewrite (op a b) (op b a)))
+    /// (comm Add)
+    /// (comm Mul)
+    /// ```
+    ///
+    /// Macros are expanded before anything in them is checked, and their
+    /// names are not qualified by modules.
+    DefMacro {
+        span: Span,
+        name: Symbol,
+        params: Vec<Symbol>,
+        body: Vec<GenericCommand<Head, Leaf>>,
+    },
 }
 
 impl<Head, Leaf> ToSexp for GenericCommand<Head, Leaf>
@@ -696,6 +1149,7 @@ where
                 inputs,
             } => list!("relation", constructor, list!(++ inputs)),
             GenericCommand::AddRuleset(name) => list!("ruleset", name),
+            GenericCommand::AddAnalysisRuleset(name) => list!("analysis-ruleset", name),
             GenericCommand::UnstableCombinedRuleset(name, others) => {
                 list!("unstable-combined-ruleset", name, ++ others)
             }
@@ -705,6 +1159,16 @@ where
                 rule,
             } => rule.to_sexp(*ruleset, *name),
             GenericCommand::RunSchedule(sched) => list!("run-schedule", sched),
+            GenericCommand::RunPortfolio {
+                span: _,
+                schedules,
+                cost_of,
+            } => list!(
+                "run-portfolio",
+                list!(++ schedules),
+                ":select",
+                list!("best-cost", cost_of)
+            ),
             GenericCommand::PrintOverallStatistics => list!("print-stats"),
             GenericCommand::QueryExtract {
                 span: _,
@@ -722,8 +1186,17 @@ where
                 span: _,
                 name,
                 file,
+                format,
             } => {
-                list!("input", name, format!("\"{}\"", file))
+                list!("input", name, format!("\"{}\"", file), ++ format.to_sexps())
+            }
+            GenericCommand::OutputFunction {
+                span: _,
+                name,
+                file,
+                format,
+            } => {
+                list!("output", name, format!("\"{}\"", file), ++ format.to_sexps())
             }
             GenericCommand::Output {
                 span: _,
@@ -732,8 +1205,33 @@ where
             } => {
                 list!("output", format!("\"{}\"", file), ++ exprs)
             }
+            GenericCommand::Save { span: _, file } => list!("save", format!("\"{}\"", file)),
+            GenericCommand::Load { span: _, file } => list!("load", format!("\"{}\"", file)),
+            GenericCommand::ImportEGraph { span: _, file } => {
+                list!("import-egraph", format!("\"{}\"", file))
+            }
+            GenericCommand::CollectGarbage(_span) => list!("gc"),
+            GenericCommand::Explain(_span, a, b) => list!("explain", a, b),
+            GenericCommand::SetCost {
+                span: _,
+                constructor,
+                args,
+                cost,
+            } => list!("set-cost", list!(constructor, ++ args), cost),
             GenericCommand::Fail(_span, cmd) => list!("fail", cmd),
             GenericCommand::Include(_span, file) => list!("include", format!("\"{}\"", file)),
+            GenericCommand::Module {
+                span: _,
+                name,
+                body,
+            } => list!("module", name, ++ body),
+            GenericCommand::Import(_span, name) => list!("import", name),
+            GenericCommand::DefMacro {
+                span: _,
+                name,
+                params,
+                body,
+            } => list!("defmacro", name, list!(++ params), ++ body),
             GenericCommand::Simplify {
                 span: _,
                 expr,
@@ -803,12 +1301,59 @@ impl Display for IdentSort {
     }
 }
 
+/// How `input` and `output` lay out the rows of a function in a file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TableFormat {
+    /// The character between fields, a tab by default. Fields holding it,
+    /// a double quote, or a line break are quoted with double quotes, with
+    /// double quotes inside doubled.
+    pub delimiter: char,
+    /// Whether the file starts with a line naming its columns, which `input`
+    /// skips and `output` writes with the sort of each column.
+    pub header: bool,
+    /// The field of each line that holds each column of the function, its
+    /// inputs first. By default the fields are the columns in order.
+    pub columns: Option<Vec<usize>>,
+}
+
+impl Default for TableFormat {
+    fn default() -> Self {
+        TableFormat {
+            delimiter: '\t',
+            header: false,
+            columns: None,
+        }
+    }
+}
+
+impl TableFormat {
+    /// The options that give this format, for printing a command.
+    fn to_sexps(&self) -> Vec<Sexp> {
+        let mut res = vec![];
+        if self.delimiter != '\t' {
+            res.push(Sexp::Symbol(":delimiter".into()));
+            res.push(Sexp::Symbol(format!("{:?}", self.delimiter.to_string())));
+        }
+        if self.header {
+            res.push(Sexp::Symbol(":header".into()));
+        }
+        if let Some(columns) = &self.columns {
+            res.push(Sexp::Symbol(":columns".into()));
+            res.push(Sexp::List(columns.iter().map(|c| c.to_sexp()).collect()));
+        }
+        res
+    }
+}
+
 pub type RunConfig = GenericRunConfig<Symbol, Symbol>;
 pub(crate) type ResolvedRunConfig = GenericRunConfig<ResolvedCall, ResolvedVar>;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct GenericRunConfig<Head, Leaf> {
     pub ruleset: Symbol,
+    /// Stops the run before an iteration once the database holds at least
+    /// this many tuples.
+    pub node_limit: Option<usize>,
     pub until: Option<Vec<GenericFact<Head, Leaf>>>,
 }
 
@@ -823,6 +1368,7 @@ where
     ) -> Self {
         Self {
             ruleset: self.ruleset,
+            node_limit: self.node_limit,
             until: self
                 .until
                 .map(|until| until.into_iter().map(|fact| fact.visit_exprs(f)).collect()),
@@ -840,6 +1386,10 @@ where
         if self.ruleset != "".into() {
             res.push(Sexp::Symbol(self.ruleset.to_string()));
         }
+        if let Some(limit) = self.node_limit {
+            res.push(Sexp::Symbol(":node-limit".into()));
+            res.push(Sexp::Symbol(limit.to_string()));
+        }
         if let Some(until) = &self.until {
             res.push(Sexp::Symbol(":until".into()));
             res.extend(until.iter().map(|fact| fact.to_sexp()));
@@ -1199,6 +1749,34 @@ pub enum Change {
     Subsume,
 }
 
+/// How `extract` measures the cost of a term.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ExtractMode {
+    /// The cost of a term is the cost of its root plus the costs of its
+    /// children, so a subterm that occurs twice is paid for twice.
+    #[default]
+    Tree,
+    /// Each distinct subterm is paid for once, as if the term were printed
+    /// with `let`s for its common subexpressions.
+    Dag,
+    /// The cost of a term is its depth, ignoring the costs of constructors.
+    Depth,
+    /// The cost of a term is its number of nodes, ignoring the costs of
+    /// constructors.
+    Size,
+}
+
+impl Display for ExtractMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractMode::Tree => write!(f, "tree"),
+            ExtractMode::Dag => write!(f, "dag"),
+            ExtractMode::Depth => write!(f, "depth"),
+            ExtractMode::Size => write!(f, "size"),
+        }
+    }
+}
+
 pub type Action = GenericAction<Symbol, Symbol>;
 pub(crate) type MappedAction = GenericAction<CorrespondingVar<Symbol, Symbol>, Symbol>;
 pub(crate) type ResolvedAction = GenericAction<ResolvedCall, ResolvedVar>;
@@ -1243,9 +1821,19 @@ where
     /// (common subexpressions are not shared in the cost
     /// model).
     /// The second argument is the number of variants to
-    /// extract, picking different terms in the
-    /// same equivalence class.
-    Extract(Span, GenericExpr<Head, Leaf>, GenericExpr<Head, Leaf>),
+    /// extract, picking the cheapest distinct terms in the
+    /// same equivalence class, cheapest first.
+    /// With `:mode dag`, the best term is instead the one
+    /// with the smallest cost when common subexpressions
+    /// are counted once, with `:mode depth` the shallowest
+    /// one, and with `:mode size` the one with the fewest
+    /// nodes (see [`ExtractMode`]).
+    Extract(
+        Span,
+        GenericExpr<Head, Leaf>,
+        GenericExpr<Head, Leaf>,
+        ExtractMode,
+    ),
     Panic(Span, String),
     Expr(Span, GenericExpr<Head, Leaf>),
     // If(Expr, Action, Action),
@@ -1314,7 +1902,12 @@ where
                     list!(lhs, ++ args)
                 )
             }
-            GenericAction::Extract(_ann, expr, variants) => list!("extract", expr, variants),
+            GenericAction::Extract(_ann, expr, variants, ExtractMode::Tree) => {
+                list!("extract", expr, variants)
+            }
+            GenericAction::Extract(_ann, expr, variants, mode) => {
+                list!("extract", expr, variants, ":mode", mode)
+            }
             GenericAction::Panic(_ann, msg) => list!("panic", format!("\"{}\"", msg.clone())),
             GenericAction::Expr(_ann, e) => e.to_sexp(),
         }
@@ -1353,8 +1946,8 @@ where
             GenericAction::Union(span, lhs, rhs) => {
                 GenericAction::Union(span.clone(), f(lhs), f(rhs))
             }
-            GenericAction::Extract(span, expr, variants) => {
-                GenericAction::Extract(span.clone(), f(expr), f(variants))
+            GenericAction::Extract(span, expr, variants, mode) => {
+                GenericAction::Extract(span.clone(), f(expr), f(variants), *mode)
             }
             GenericAction::Panic(span, msg) => GenericAction::Panic(span.clone(), msg.clone()),
             GenericAction::Expr(span, e) => GenericAction::Expr(span.clone(), f(e)),
@@ -1385,8 +1978,8 @@ where
             GenericAction::Union(span, lhs, rhs) => {
                 GenericAction::Union(span, lhs.visit_exprs(f), rhs.visit_exprs(f))
             }
-            GenericAction::Extract(span, expr, variants) => {
-                GenericAction::Extract(span, expr.visit_exprs(f), variants.visit_exprs(f))
+            GenericAction::Extract(span, expr, variants, mode) => {
+                GenericAction::Extract(span, expr.visit_exprs(f), variants.visit_exprs(f), mode)
             }
             GenericAction::Panic(span, msg) => GenericAction::Panic(span, msg.clone()),
             GenericAction::Expr(span, e) => GenericAction::Expr(span, e.visit_exprs(f)),
@@ -1429,10 +2022,10 @@ where
                 let rhs = rhs.subst_leaf(&mut fvar_expr!());
                 GenericAction::Union(span, lhs, rhs)
             }
-            GenericAction::Extract(span, expr, variants) => {
+            GenericAction::Extract(span, expr, variants, mode) => {
                 let expr = expr.subst_leaf(&mut fvar_expr!());
                 let variants = variants.subst_leaf(&mut fvar_expr!());
-                GenericAction::Extract(span, expr, variants)
+                GenericAction::Extract(span, expr, variants, mode)
             }
             GenericAction::Panic(span, msg) => GenericAction::Panic(span, msg.clone()),
             GenericAction::Expr(span, e) => {
@@ -1456,6 +2049,10 @@ where
 pub(crate) struct CompiledRule {
     pub(crate) query: CompiledQuery,
     pub(crate) program: Program,
+    pub(crate) priority: i64,
+    pub(crate) match_limit: Option<usize>,
+    /// The variables bound by each match, in the order of its values.
+    pub(crate) vars: Vec<ResolvedVar>,
 }
 
 pub type Rule = GenericRule<Symbol, Symbol>;
@@ -1470,6 +2067,14 @@ where
     pub span: Span,
     pub head: GenericActions<Head, Leaf>,
     pub body: Vec<GenericFact<Head, Leaf>>,
+    /// Within a ruleset, rules with a higher priority are searched and
+    /// applied before rules with a lower one. Rules with the same priority
+    /// run in declaration order. Defaults to 0.
+    pub priority: i64,
+    /// Caps how many of the rule's matches are applied in one iteration.
+    /// The rest are applied in later iterations, and a run does not
+    /// saturate while any are left.
+    pub match_limit: Option<usize>,
 }
 
 impl<Head, Leaf> GenericRule<Head, Leaf>
@@ -1489,6 +2094,8 @@ where
                 .into_iter()
                 .map(|bexpr| bexpr.visit_exprs(f))
                 .collect(),
+            priority: self.priority,
+            match_limit: self.match_limit,
         }
     }
 }
@@ -1538,7 +2145,21 @@ where
         } else {
             "".into()
         };
-        write!(f, ")\n{} {} {})", indent, ruleset, name)
+        let priority = if self.priority != 0 {
+            format!(" :priority {}", self.priority)
+        } else {
+            "".into()
+        };
+        let match_limit = if let Some(limit) = self.match_limit {
+            format!(" :match-limit {}", limit)
+        } else {
+            "".into()
+        };
+        write!(
+            f,
+            ")\n{} {} {}{}{})",
+            indent, ruleset, name, priority, match_limit
+        )
     }
 }
 
@@ -1562,6 +2183,14 @@ where
             res.push(Sexp::Symbol(":name".into()));
             res.push(Sexp::Symbol(format!("\"{}\"", name)));
         }
+        if self.priority != 0 {
+            res.push(Sexp::Symbol(":priority".into()));
+            res.push(Sexp::Symbol(self.priority.to_string()));
+        }
+        if let Some(limit) = self.match_limit {
+            res.push(Sexp::Symbol(":match-limit".into()));
+            res.push(Sexp::Symbol(limit.to_string()));
+        }
         Sexp::List(res)
     }
 }
@@ -1584,6 +2213,12 @@ pub struct GenericRewrite<Head, Leaf> {
     pub lhs: GenericExpr<Head, Leaf>,
     pub rhs: GenericExpr<Head, Leaf>,
     pub conditions: Vec<GenericFact<Head, Leaf>>,
+    /// The priority of the rules the rewrite becomes, see
+    /// [`GenericRule::priority`].
+    pub priority: i64,
+    /// The match limit of the rules the rewrite becomes, see
+    /// [`GenericRule::match_limit`].
+    pub match_limit: Option<usize>,
 }
 
 impl<Head: Display, Leaf: Display> GenericRewrite<Head, Leaf> {
@@ -1613,6 +2248,14 @@ impl<Head: Display, Leaf: Display> GenericRewrite<Head, Leaf> {
             res.push(Sexp::Symbol(":ruleset".into()));
             res.push(Sexp::Symbol(ruleset.to_string()));
         }
+        if self.priority != 0 {
+            res.push(Sexp::Symbol(":priority".into()));
+            res.push(Sexp::Symbol(self.priority.to_string()));
+        }
+        if let Some(limit) = self.match_limit {
+            res.push(Sexp::Symbol(":match-limit".into()));
+            res.push(Sexp::Symbol(limit.to_string()));
+        }
         Sexp::List(res)
     }
 }
@@ -0,0 +1,359 @@
+//! Resolve `module` and `import` commands.
+//!
+//! The sorts, functions, rulesets, and globals declared inside
+//! `(module M ...)` are renamed to `M.name`, and so are the references to them
+//! in the module after their declaration. Rules declared in a module get the
+//! module's name as a prefix as well, so that two modules can each have a rule
+//! that is written the same way. A module nested in `M` as `N` is `M.N`, and
+//! its names can be used as `N.name` in the rest of `M`.
+//!
+//! `(import M)` makes the names of `M` usable without the prefix in the
+//! commands that follow, up to the end of the module the import is in.
+//! Later imports and declarations shadow earlier ones. Qualified names
+//! can always be used.
+//!
+//! This is done after desugaring and before typechecking, so the rest of
+//! egglog only ever sees the qualified names.
+
+use crate::*;
+
+/// The names of the modules declared so far, and the names imported at the
+/// top level.
+#[derive(Clone, Default)]
+pub(crate) struct Modules {
+    /// The names each module declared, by qualified module name, from their
+    /// names inside the module to their qualified names.
+    exports: HashMap<Symbol, HashMap<Symbol, Symbol>>,
+    /// The names imported at the top level, from the names they are used as
+    /// to their qualified names.
+    imported: HashMap<Symbol, Symbol>,
+}
+
+impl Modules {
+    /// Removes the modules and imports from a desugared program, renaming the
+    /// names they declare and use.
+    pub(crate) fn resolve(&mut self, program: Vec<NCommand>) -> Result<Vec<NCommand>, Error> {
+        let mut res = vec![];
+        let mut names = std::mem::take(&mut self.imported);
+        let result = self.resolve_in(program, None, &mut names, &mut res);
+        self.imported = names;
+        result?;
+        Ok(res)
+    }
+
+    fn resolve_in(
+        &mut self,
+        program: Vec<NCommand>,
+        module: Option<Symbol>,
+        names: &mut HashMap<Symbol, Symbol>,
+        res: &mut Vec<NCommand>,
+    ) -> Result<(), Error> {
+        for command in program {
+            match command {
+                NCommand::Module {
+                    span: _,
+                    name,
+                    body,
+                } => {
+                    let qualified = qualify(module, name);
+                    self.exports.insert(qualified, Default::default());
+                    let mut inner = names.clone();
+                    self.resolve_in(body, Some(qualified), &mut inner, res)?;
+                    // the names of the nested module, as seen from this one
+                    let nested: Vec<(Symbol, Symbol)> = self.exports[&qualified]
+                        .iter()
+                        .map(|(inner_name, full)| (qualify(Some(name), *inner_name), *full))
+                        .collect();
+                    for (local, full) in nested {
+                        self.declare(module, names, local, full);
+                    }
+                }
+                NCommand::Import(span, name) => {
+                    let exports = module
+                        .and_then(|module| self.exports.get(&qualify(Some(module), name)))
+                        .or_else(|| self.exports.get(&name))
+                        .ok_or(Error::NoSuchModule(name, span))?;
+                    names.extend(exports.iter().map(|(local, full)| (*local, *full)));
+                }
+                NCommand::Fail(span, command)
+                    if matches!(*command, NCommand::Module { .. } | NCommand::Import(..)) =>
+                {
+                    // resolve it on the side, since it is not supposed to work
+                    let mut modules = self.clone();
+                    let mut names = names.clone();
+                    match modules.resolve_in(vec![*command], module, &mut names, &mut vec![]) {
+                        Ok(()) => return Err(Error::ExpectFail(span)),
+                        Err(e) => log::info!("Command failed as expected: {e}"),
+                    }
+                }
+                command => {
+                    for declared in declared_names(&command) {
+                        self.declare(module, names, declared, qualify(module, declared));
+                    }
+                    let mut command = rename_command(command, names);
+                    if let (Some(module), NCommand::NormRule { name, .. }) = (module, &mut command)
+                    {
+                        *name = qualify(Some(module), *name);
+                    }
+                    res.push(command);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes `local` refer to `full` in the rest of `module`, and exports it
+    /// from the module.
+    fn declare(
+        &mut self,
+        module: Option<Symbol>,
+        names: &mut HashMap<Symbol, Symbol>,
+        local: Symbol,
+        full: Symbol,
+    ) {
+        match module {
+            Some(module) => {
+                names.insert(local, full);
+                self.exports.get_mut(&module).unwrap().insert(local, full);
+            }
+            // a declaration at the top level shadows any imported name
+            None => {
+                names.swap_remove(&local);
+            }
+        }
+    }
+}
+
+fn qualify(module: Option<Symbol>, name: Symbol) -> Symbol {
+    match module {
+        Some(module) => format!("{module}.{name}").into(),
+        None => name,
+    }
+}
+
+/// The names of the sorts, functions, rulesets, and globals a command
+/// declares.
+fn declared_names(command: &NCommand) -> Vec<Symbol> {
+    match command {
+        NCommand::Sort(_, name, _)
+        | NCommand::AddRuleset(name)
+        | NCommand::AddAnalysisRuleset(name)
+        | NCommand::UnstableCombinedRuleset(name, _)
+        | NCommand::CoreAction(Action::Let(_, name, _)) => vec![*name],
+        NCommand::Function(decl) => vec![decl.name],
+        _ => vec![],
+    }
+}
+
+fn rename(names: &HashMap<Symbol, Symbol>, name: Symbol) -> Symbol {
+    names.get(&name).copied().unwrap_or(name)
+}
+
+fn rename_expr(expr: Expr, names: &HashMap<Symbol, Symbol>) -> Expr {
+    expr.visit_exprs(&mut |expr| match expr {
+        Expr::Var(span, var) => Expr::Var(span, rename(names, var)),
+        Expr::Call(span, head, args) => Expr::Call(span, rename(names, head), args),
+        Expr::Lit(..) => expr,
+    })
+}
+
+fn rename_exprs(exprs: Vec<Expr>, names: &HashMap<Symbol, Symbol>) -> Vec<Expr> {
+    exprs.into_iter().map(|e| rename_expr(e, names)).collect()
+}
+
+fn rename_fact(fact: Fact, names: &HashMap<Symbol, Symbol>) -> Fact {
+    match fact {
+        Fact::Eq(span, exprs) => Fact::Eq(span, rename_exprs(exprs, names)),
+        Fact::Fact(expr) => Fact::Fact(rename_expr(expr, names)),
+    }
+}
+
+fn rename_facts(facts: Vec<Fact>, names: &HashMap<Symbol, Symbol>) -> Vec<Fact> {
+    facts.into_iter().map(|f| rename_fact(f, names)).collect()
+}
+
+fn rename_action(action: Action, names: &HashMap<Symbol, Symbol>) -> Action {
+    match action {
+        Action::Let(span, var, expr) => {
+            Action::Let(span, rename(names, var), rename_expr(expr, names))
+        }
+        Action::Set(span, f, args, expr) => Action::Set(
+            span,
+            rename(names, f),
+            rename_exprs(args, names),
+            rename_expr(expr, names),
+        ),
+        Action::Change(span, change, f, args) => {
+            Action::Change(span, change, rename(names, f), rename_exprs(args, names))
+        }
+        Action::Union(span, a, b) => {
+            Action::Union(span, rename_expr(a, names), rename_expr(b, names))
+        }
+        Action::Extract(span, expr, variants, mode) => Action::Extract(
+            span,
+            rename_expr(expr, names),
+            rename_expr(variants, names),
+            mode,
+        ),
+        Action::Panic(span, msg) => Action::Panic(span, msg),
+        Action::Expr(span, expr) => Action::Expr(span, rename_expr(expr, names)),
+    }
+}
+
+fn rename_actions(actions: Actions, names: &HashMap<Symbol, Symbol>) -> Actions {
+    Actions::new(
+        actions
+            .0
+            .into_iter()
+            .map(|a| rename_action(a, names))
+            .collect(),
+    )
+}
+
+fn rename_schedule(schedule: Schedule, names: &HashMap<Symbol, Symbol>) -> Schedule {
+    let boxed = |schedule: Box<Schedule>| Box::new(rename_schedule(*schedule, names));
+    match schedule {
+        Schedule::Saturate(span, s) => Schedule::Saturate(span, boxed(s)),
+        Schedule::Repeat(span, limit, s) => Schedule::Repeat(span, limit, boxed(s)),
+        Schedule::Run(span, config) => Schedule::Run(
+            span,
+            RunConfig {
+                ruleset: rename(names, config.ruleset),
+                node_limit: config.node_limit,
+                until: config.until.map(|facts| rename_facts(facts, names)),
+            },
+        ),
+        Schedule::Sequence(span, scheds) => Schedule::Sequence(
+            span,
+            scheds
+                .into_iter()
+                .map(|s| rename_schedule(s, names))
+                .collect(),
+        ),
+        Schedule::Backoff(span, config, s) => Schedule::Backoff(span, config, boxed(s)),
+        Schedule::TimeLimit(span, limit, s) => Schedule::TimeLimit(span, limit, boxed(s)),
+    }
+}
+
+pub(super) fn rename_command(command: NCommand, names: &HashMap<Symbol, Symbol>) -> NCommand {
+    let rename = |name| rename(names, name);
+    match command {
+        NCommand::SetOption { name, value } => NCommand::SetOption {
+            name,
+            value: rename_expr(value, names),
+        },
+        NCommand::Sort(span, name, params) => NCommand::Sort(
+            span,
+            rename(name),
+            params.map(|(sort, args)| (sort, rename_exprs(args, names))),
+        ),
+        NCommand::Function(decl) => NCommand::Function(FunctionDecl {
+            name: rename(decl.name),
+            schema: Schema {
+                input: decl.schema.input.into_iter().map(rename).collect(),
+                output: rename(decl.schema.output),
+            },
+            default: decl.default.map(|e| rename_expr(e, names)),
+            merge: decl.merge.map(|e| rename_expr(e, names)),
+            merge_action: rename_actions(decl.merge_action, names),
+            ..decl
+        }),
+        NCommand::AddRuleset(name) => NCommand::AddRuleset(rename(name)),
+        NCommand::AddAnalysisRuleset(name) => NCommand::AddAnalysisRuleset(rename(name)),
+        NCommand::UnstableCombinedRuleset(name, rulesets) => NCommand::UnstableCombinedRuleset(
+            rename(name),
+            rulesets.into_iter().map(rename).collect(),
+        ),
+        NCommand::NormRule {
+            name,
+            ruleset,
+            rule,
+        } => NCommand::NormRule {
+            name,
+            ruleset: rename(ruleset),
+            rule: Rule {
+                head: rename_actions(rule.head, names),
+                body: rename_facts(rule.body, names),
+                ..rule
+            },
+        },
+        NCommand::CoreAction(action) => NCommand::CoreAction(rename_action(action, names)),
+        NCommand::RunSchedule(schedule) => NCommand::RunSchedule(rename_schedule(schedule, names)),
+        NCommand::RunPortfolio {
+            span,
+            schedules,
+            cost_of,
+        } => NCommand::RunPortfolio {
+            span,
+            schedules: schedules
+                .into_iter()
+                .map(|s| rename_schedule(s, names))
+                .collect(),
+            cost_of: rename_expr(cost_of, names),
+        },
+        NCommand::Check(span, facts) => NCommand::Check(span, rename_facts(facts, names)),
+        NCommand::PrintTable(span, name, n) => NCommand::PrintTable(span, rename(name), n),
+        NCommand::PrintSize(span, name) => NCommand::PrintSize(span, name.map(rename)),
+        NCommand::Output { span, file, exprs } => NCommand::Output {
+            span,
+            file,
+            exprs: rename_exprs(exprs, names),
+        },
+        NCommand::Fail(span, command) => {
+            NCommand::Fail(span, Box::new(rename_command(*command, names)))
+        }
+        NCommand::Input {
+            span,
+            name,
+            file,
+            format,
+        } => NCommand::Input {
+            span,
+            name: rename(name),
+            file,
+            format,
+        },
+        NCommand::OutputFunction {
+            span,
+            name,
+            file,
+            format,
+        } => NCommand::OutputFunction {
+            span,
+            name: rename(name),
+            file,
+            format,
+        },
+        NCommand::Explain(span, a, b) => {
+            NCommand::Explain(span, rename_expr(a, names), rename_expr(b, names))
+        }
+        NCommand::SetCost {
+            span,
+            constructor,
+            args,
+            cost,
+        } => NCommand::SetCost {
+            span,
+            constructor: rename(constructor),
+            args: args.into_iter().map(rename).collect(),
+            cost: rename_expr(cost, names),
+        },
+        NCommand::Module { span, name, body } => NCommand::Module {
+            span,
+            name,
+            body: body
+                .into_iter()
+                .map(|command| rename_command(command, names))
+                .collect(),
+        },
+        command @ (NCommand::PrintOverallStatistics
+        | NCommand::Push(_)
+        | NCommand::Pop(..)
+        | NCommand::Save { .. }
+        | NCommand::Load { .. }
+        | NCommand::ImportEGraph { .. }
+        | NCommand::CollectGarbage(_)
+        | NCommand::Import(..)
+        | NCommand::DefMacro { .. }) => command,
+    }
+}
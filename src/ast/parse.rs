@@ -9,6 +9,121 @@ pub fn parse_program(filename: Option<String>, input: &str) -> Result<Vec<Comman
     Ok(out)
 }
 
+/// Parses a file included by the `include` command at `included_from`, so
+/// that the spans in it know where it was included.
+pub(crate) fn parse_included_program(
+    filename: String,
+    input: &str,
+    included_from: Span,
+) -> Result<Vec<Command>, ParseError> {
+    let ctx = Context::with_source(SrcFile {
+        name: Some(filename),
+        contents: input.to_string(),
+        included_from: Some(included_from),
+    });
+    let (out, _span, rest) = program(&ctx)?;
+    assert!(rest.is_at_end(), "did not parse entire program");
+    Ok(out)
+}
+
+/// Parses a program like [`parse_program`], with its top-level commands
+/// split into chunks that the threads of `pool` parse at the same time. The
+/// commands come back in program order, and a program that does not parse
+/// gets the error [`parse_program`] would report.
+pub fn parse_program_parallel(
+    filename: Option<String>,
+    input: &str,
+    pool: &rayon::ThreadPool,
+) -> Result<Vec<Command>, ParseError> {
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+    let ctx = Context::new(filename, input);
+    let Some(starts) = command_starts(&ctx) else {
+        return program(&ctx).map(|(out, _, _)| out);
+    };
+    // enough chunks to keep the threads busy when commands vary in size,
+    // each big enough to be worth handing to a thread
+    let chunk_len = (input.len() / (4 * pool.current_num_threads())).max(1 << 16);
+    let mut chunks = vec![];
+    let mut start = 0;
+    for (i, &offset) in starts.iter().enumerate().skip(1) {
+        if offset - starts[start] >= chunk_len {
+            chunks.push(starts[start]..offset);
+            start = i;
+        }
+    }
+    if let Some(&offset) = starts.get(start) {
+        chunks.push(offset..input.len());
+    }
+    if chunks.len() <= 1 {
+        return program(&ctx).map(|(out, _, _)| out);
+    }
+
+    let parsed: Vec<Result<Vec<Command>, ParseError>> = pool.install(|| {
+        chunks
+            .into_par_iter()
+            .map(|range| {
+                let mut next = Context {
+                    index: range.start,
+                    ..ctx.clone()
+                };
+                let mut out = vec![];
+                while next.index < range.end {
+                    let (command, _, rest) = command(&next)?;
+                    out.push(command);
+                    next = rest;
+                }
+                Ok(out)
+            })
+            .collect()
+    });
+    let mut out = vec![];
+    for chunk in parsed {
+        out.extend(chunk?);
+    }
+    Ok(out)
+}
+
+/// The offsets of the top-level commands of a program, found by matching
+/// brackets outside of strings and comments, or `None` if the program is
+/// not a sequence of balanced lists.
+fn command_starts(ctx: &Context) -> Option<Vec<usize>> {
+    let mut starts = vec![];
+    let mut depth = 0usize;
+    let mut chars = ctx.source.contents[ctx.index..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '(' | '[' => {
+                if depth == 0 {
+                    starts.push(ctx.index + i);
+                }
+                depth += 1;
+            }
+            ')' | ']' => depth = depth.checked_sub(1)?,
+            '"' => {
+                let mut in_escape = false;
+                loop {
+                    match chars.next()?.1 {
+                        '"' if !in_escape => break,
+                        '\\' if !in_escape => in_escape = true,
+                        _ => in_escape = false,
+                    }
+                }
+            }
+            ';' => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if depth == 0 && !c.is_whitespace() => return None,
+            _ => {}
+        }
+    }
+    (depth == 0).then_some(starts)
+}
+
 // currently only used for testing, but no reason it couldn't be used elsewhere later
 pub fn parse_expr(filename: Option<String>, input: &str) -> Result<Expr, ParseError> {
     let (out, _span, rest) = expr(&Context::new(filename, input))?;
@@ -21,19 +136,31 @@ pub fn parse_expr(filename: Option<String>, input: &str) -> Result<Expr, ParseEr
 pub struct Span(Arc<SrcFile>, usize, usize);
 
 lazy_static::lazy_static! {
-    pub static ref DUMMY_SPAN: Span = Span(Arc::new(SrcFile {name: None, contents: String::new()}), 0, 0);
+    pub static ref DUMMY_SPAN: Span = Span(Arc::new(SrcFile {name: None, contents: String::new(), included_from: None}), 0, 0);
 }
 
 impl Span {
     pub fn string(&self) -> &str {
         &self.0.contents[self.1..self.2]
     }
+
+    /// The name of the file the span is in, if it has one.
+    pub fn file(&self) -> Option<&str> {
+        self.0.name.as_deref()
+    }
+
+    /// The span of the `include` command that included the file the span is
+    /// in, if it was included.
+    pub fn included_from(&self) -> Option<&Span> {
+        self.0.included_from.as_ref()
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 struct SrcFile {
     name: Option<String>,
     contents: String,
+    included_from: Option<Span>,
 }
 
 struct Location {
@@ -90,6 +217,10 @@ impl Display for Span {
                 start.line, start.col, end.line, end.col
             ),
             (None, true) => write!(f, "In {}:{}-{}: {quote}", start.line, start.col, end.col),
+        }?;
+        match &self.0.included_from {
+            Some(from) => write!(f, "\nIncluded from: {from}"),
+            None => Ok(()),
         }
     }
 }
@@ -102,11 +233,16 @@ struct Context {
 
 impl Context {
     fn new(name: Option<String>, contents: &str) -> Context {
+        Context::with_source(SrcFile {
+            name,
+            contents: contents.to_string(),
+            included_from: None,
+        })
+    }
+
+    fn with_source(source: SrcFile) -> Context {
         let mut next = Context {
-            source: Arc::new(SrcFile {
-                name,
-                contents: contents.to_string(),
-            }),
+            source: Arc::new(source),
             index: 0,
         };
         next.advance_past_whitespace();
@@ -191,8 +327,13 @@ fn ident(ctx: &Context) -> Res<Symbol> {
 
 fn text(s: &'static str) -> impl Parser<()> {
     move |ctx| {
+        let contents = &ctx.source.contents;
         let mut span = ctx.span();
-        span.2 = (span.1 + s.len()).min(ctx.source.contents.len());
+        span.2 = (span.1 + s.len()).min(contents.len());
+        // `s` may end in the middle of a multi-byte character of the input
+        while !contents.is_char_boundary(span.2) {
+            span.2 += 1;
+        }
 
         if span.string() == s {
             let mut next = ctx.clone();
@@ -333,13 +474,23 @@ fn command(ctx: &Context) -> Res<Command> {
             name,
             variants,
         })(ctx),
-        "sort" => choice(
+        "sort" => choices!(
             parens(sequence3(
                 text("sort"),
                 ident,
                 parens(sequence(ident, repeat_until_end_paren(expr))),
             ))
-            .map(|((), name, (head, tail)), span| Command::Sort(span, name, Some((head, tail)))),
+            .map(|((), name, (head, tail)), span| Command::Sort(
+                span,
+                name,
+                Some((head, tail))
+            )),
+            // `(sort Addr i64 :newtype)` is shorthand for `(sort Addr (Newtype i64))`
+            parens(sequence4(text("sort"), ident, expr, text(":newtype"))).map(
+                |((), name, base, ()), span| {
+                    Command::Sort(span, name, Some(("Newtype".into(), vec![base])))
+                }
+            ),
             parens(sequence(text("sort"), ident))
                 .map(|((), name), span| Command::Sort(span, name, None)),
         )(ctx),
@@ -385,6 +536,10 @@ fn command(ctx: &Context) -> Res<Command> {
         )(ctx),
         "ruleset" => parens(sequence(text("ruleset"), ident))
             .map(|((), name), _| Command::AddRuleset(name))(ctx),
+        "analysis-ruleset" => {
+            parens(sequence(text("analysis-ruleset"), ident))
+                .map(|((), name), _| Command::AddAnalysisRuleset(name))(ctx)
+        }
         "unstable-combined-ruleset" => parens(sequence3(
             text("unstable-combined-ruleset"),
             ident,
@@ -399,12 +554,22 @@ fn command(ctx: &Context) -> Res<Command> {
             list(action).map(|x, _| Actions::new(x)),
             option(sequence(text(":ruleset"), ident)).map(snd),
             option(sequence(text(":name"), string)).map(snd),
+            option(sequence(text(":priority"), num)).map(snd),
+            option(sequence(text(":match-limit"), unum)).map(snd),
         ))
         .map(
-            |((), (body, (head, (ruleset, name)))), span| Command::Rule {
-                ruleset: ruleset.unwrap_or("".into()),
-                name: name.unwrap_or("".to_string()).into(),
-                rule: Rule { span, head, body },
+            |((), (body, (head, (ruleset, (name, (priority, match_limit)))))), span| {
+                Command::Rule {
+                    ruleset: ruleset.unwrap_or("".into()),
+                    name: name.unwrap_or("".to_string()).into(),
+                    rule: Rule {
+                        span,
+                        head,
+                        body,
+                        priority: priority.unwrap_or(0),
+                        match_limit,
+                    },
+                }
             },
         )(ctx),
         "rewrite" => parens(sequences!(
@@ -414,9 +579,12 @@ fn command(ctx: &Context) -> Res<Command> {
             option(text(":subsume")).map(|x, _| x.is_some()),
             option(sequence(text(":when"), list(fact))).map(snd),
             option(sequence(text(":ruleset"), ident)).map(snd),
+            option(sequence(text(":priority"), num)).map(snd),
+            option(sequence(text(":match-limit"), unum)).map(snd),
         ))
         .map(
-            |((), (lhs, (rhs, (subsume, (conditions, ruleset))))), span| {
+            |((), (lhs, (rhs, (subsume, (conditions, (ruleset, (priority, match_limit))))))),
+             span| {
                 Command::Rewrite(
                     ruleset.unwrap_or("".into()),
                     Rewrite {
@@ -424,6 +592,8 @@ fn command(ctx: &Context) -> Res<Command> {
                         lhs,
                         rhs,
                         conditions: conditions.unwrap_or_default(),
+                        priority: priority.unwrap_or(0),
+                        match_limit,
                     },
                     subsume,
                 )
@@ -435,54 +605,63 @@ fn command(ctx: &Context) -> Res<Command> {
             expr,
             option(sequence(text(":when"), list(fact))).map(snd),
             option(sequence(text(":ruleset"), ident)).map(snd),
+            option(sequence(text(":priority"), num)).map(snd),
+            option(sequence(text(":match-limit"), unum)).map(snd),
         ))
-        .map(|((), (lhs, (rhs, (conditions, ruleset)))), span| {
-            Command::BiRewrite(
-                ruleset.unwrap_or("".into()),
-                Rewrite {
-                    span,
-                    lhs,
-                    rhs,
-                    conditions: conditions.unwrap_or_default(),
-                },
-            )
-        })(ctx),
+        .map(
+            |((), (lhs, (rhs, (conditions, (ruleset, (priority, match_limit)))))), span| {
+                Command::BiRewrite(
+                    ruleset.unwrap_or("".into()),
+                    Rewrite {
+                        span,
+                        lhs,
+                        rhs,
+                        conditions: conditions.unwrap_or_default(),
+                        priority: priority.unwrap_or(0),
+                        match_limit,
+                    },
+                )
+            },
+        )(ctx),
         "let" => parens(sequence3(text("let"), ident, expr))
             .map(|((), name, expr), span| Command::Action(Action::Let(span, name, expr)))(
             ctx
         ),
         "run" => choice(
-            parens(sequence3(
-                text("run"),
-                unum,
-                option(sequence(text(":until"), repeat_until_end_paren(fact))).map(snd),
-            ))
-            .map(|((), limit, until), span| {
-                Command::RunSchedule(Schedule::Repeat(
-                    span.clone(),
-                    limit,
-                    Box::new(Schedule::Run(
-                        span,
-                        RunConfig {
-                            ruleset: "".into(),
-                            until,
-                        },
-                    )),
-                ))
-            }),
-            parens(sequence4(
-                text("run"),
-                ident,
-                unum,
-                option(sequence(text(":until"), repeat_until_end_paren(fact))).map(snd),
-            ))
-            .map(|((), ruleset, limit, until), span| {
-                Command::RunSchedule(Schedule::Repeat(
-                    span.clone(),
-                    limit,
-                    Box::new(Schedule::Run(span, RunConfig { ruleset, until })),
-                ))
-            }),
+            parens(sequence3(text("run"), unum, run_options)).map(
+                |((), limit, (node_limit, (time_limit, until))), span| {
+                    let sched = Schedule::Repeat(
+                        span.clone(),
+                        limit,
+                        Box::new(Schedule::Run(
+                            span.clone(),
+                            RunConfig {
+                                ruleset: "".into(),
+                                node_limit,
+                                until,
+                            },
+                        )),
+                    );
+                    Command::RunSchedule(with_time_limit(span, time_limit, sched))
+                },
+            ),
+            parens(sequence4(text("run"), ident, unum, run_options)).map(
+                |((), ruleset, limit, (node_limit, (time_limit, until))), span| {
+                    let sched = Schedule::Repeat(
+                        span.clone(),
+                        limit,
+                        Box::new(Schedule::Run(
+                            span.clone(),
+                            RunConfig {
+                                ruleset,
+                                node_limit,
+                                until,
+                            },
+                        )),
+                    );
+                    Command::RunSchedule(with_time_limit(span, time_limit, sched))
+                },
+            ),
         )(ctx),
         "simplify" => {
             parens(sequence3(text("simplify"), schedule, expr)).map(|((), schedule, expr), span| {
@@ -505,13 +684,28 @@ fn command(ctx: &Context) -> Res<Command> {
         })(ctx),
         "check" => parens(sequence(text("check"), repeat_until_end_paren(fact)))
             .map(|((), facts), span| Command::Check(span, facts))(ctx),
-        "run-schedule" => parens(sequence(
+        "run-schedule" => parens(sequence3(
             text("run-schedule"),
+            option(sequence(text(":time-limit"), duration)).map(snd),
             repeat_until_end_paren(schedule),
         ))
-        .map(|((), scheds), span| Command::RunSchedule(Schedule::Sequence(span, scheds)))(
-            ctx
-        ),
+        .map(|((), time_limit, scheds), span| {
+            let sched = Schedule::Sequence(span.clone(), scheds);
+            Command::RunSchedule(with_time_limit(span, time_limit, sched))
+        })(ctx),
+        "run-portfolio" => parens(sequence4(
+            text("run-portfolio"),
+            list(schedule),
+            text(":select"),
+            parens(sequence(text("best-cost"), expr)),
+        ))
+        .map(
+            |((), schedules, (), ((), cost_of)), span| Command::RunPortfolio {
+                span,
+                schedules,
+                cost_of,
+            },
+        )(ctx),
         "print-stats" => {
             parens(text("print-stats")).map(|(), _| Command::PrintOverallStatistics)(ctx)
         }
@@ -525,24 +719,88 @@ fn command(ctx: &Context) -> Res<Command> {
         }
         "print-size" => parens(sequence(text("print-size"), option(ident)))
             .map(|((), sym), span| Command::PrintSize(span, sym))(ctx),
-        "input" => {
-            parens(sequence3(text("input"), ident, string))
-                .map(|((), name, file), span| Command::Input { span, name, file })(ctx)
-        }
-        "output" => parens(sequence4(
-            text("output"),
-            string,
+        "input" => parens(sequence4(text("input"), ident, string, table_format)).map(
+            |((), name, file, format), span| Command::Input {
+                span,
+                name,
+                file,
+                format,
+            },
+        )(ctx),
+        "gc" => parens(text("gc")).map(|(), span| Command::CollectGarbage(span))(ctx),
+        "explain" => parens(sequence3(text("explain"), expr, expr))
+            .map(|((), a, b), span| Command::Explain(span, a, b))(ctx),
+        "set-cost" => parens(sequence3(
+            text("set-cost"),
+            parens(sequence(ident, repeat_until_end_paren(ident))),
             expr,
-            repeat_until_end_paren(expr),
         ))
-        .map(|((), file, e, mut exprs), span| {
-            exprs.insert(0, e);
-            Command::Output { span, file, exprs }
+        .map(|((), (constructor, args), cost), span| Command::SetCost {
+            span,
+            constructor,
+            args,
+            cost,
         })(ctx),
+        // `save` and `load` are also common names for functions, so they
+        // are only commands when given a file name
+        "save" => choice(
+            parens(sequence(text("save"), string))
+                .map(|((), file), span| Command::Save { span, file }),
+            non_let_action.map(|action, _| Command::Action(action)),
+        )(ctx),
+        "load" => choice(
+            parens(sequence(text("load"), string))
+                .map(|((), file), span| Command::Load { span, file }),
+            non_let_action.map(|action, _| Command::Action(action)),
+        )(ctx),
+        "import-egraph" => {
+            parens(sequence(text("import-egraph"), string))
+                .map(|((), file), span| Command::ImportEGraph { span, file })(ctx)
+        }
+        "output" => choice(
+            parens(sequence4(
+                text("output"),
+                string,
+                expr,
+                repeat_until_end_paren(expr),
+            ))
+            .map(|((), file, e, mut exprs), span| {
+                exprs.insert(0, e);
+                Command::Output { span, file, exprs }
+            }),
+            parens(sequence4(text("output"), ident, string, table_format)).map(
+                |((), name, file, format), span| Command::OutputFunction {
+                    span,
+                    name,
+                    file,
+                    format,
+                },
+            ),
+        )(ctx),
         "fail" => parens(sequence(text("fail"), command))
             .map(|((), c), span| Command::Fail(span, Box::new(c)))(ctx),
         "include" => parens(sequence(text("include"), string))
             .map(|((), file), span| Command::Include(span, file))(ctx),
+        "module" => parens(sequence3(
+            text("module"),
+            ident,
+            repeat_until_end_paren(command),
+        ))
+        .map(|((), name, body), span| Command::Module { span, name, body })(ctx),
+        "import" => parens(sequence(text("import"), ident))
+            .map(|((), name), span| Command::Import(span, name))(ctx),
+        "defmacro" => parens(sequence4(
+            text("defmacro"),
+            ident,
+            list(ident),
+            repeat_until_end_paren(command),
+        ))
+        .map(|((), name, params, body), span| Command::DefMacro {
+            span,
+            name,
+            params,
+            body,
+        })(ctx),
         _ => non_let_action.map(|action, _| Command::Action(action))(ctx),
     }
 }
@@ -568,32 +826,70 @@ fn schedule(ctx: &Context) -> Res<Schedule> {
                 Box::new(Schedule::Sequence(span, scheds)),
             )
         })(ctx),
+        "backoff" => parens(sequence4(
+            text("backoff"),
+            option(sequence(text(":match-limit"), unum)).map(snd),
+            option(sequence(text(":ban-length"), unum)).map(snd),
+            repeat_until_end_paren(schedule),
+        ))
+        .map(|((), match_limit, ban_length, scheds), span| {
+            let default = BackoffConfig::default();
+            let config = BackoffConfig {
+                match_limit: match_limit.unwrap_or(default.match_limit),
+                ban_length: ban_length.unwrap_or(default.ban_length),
+            };
+            Schedule::Backoff(
+                span.clone(),
+                config,
+                Box::new(Schedule::Sequence(span, scheds)),
+            )
+        })(ctx),
+        "time-limit" => parens(sequence3(
+            text("time-limit"),
+            duration,
+            repeat_until_end_paren(schedule),
+        ))
+        .map(|((), limit, scheds), span| {
+            Schedule::TimeLimit(
+                span.clone(),
+                limit,
+                Box::new(Schedule::Sequence(span, scheds)),
+            )
+        })(ctx),
         "run" => choice(
-            parens(sequence(
-                text("run"),
-                option(sequence(text(":until"), repeat_until_end_paren(fact))).map(snd),
-            ))
-            .map(|((), until), span| {
-                Schedule::Run(
-                    span,
-                    RunConfig {
-                        ruleset: "".into(),
-                        until,
-                    },
-                )
-            }),
-            parens(sequence3(
-                text("run"),
-                ident,
-                option(sequence(text(":until"), repeat_until_end_paren(fact))).map(snd),
-            ))
-            .map(|((), ruleset, until), span| Schedule::Run(span, RunConfig { ruleset, until })),
+            parens(sequence(text("run"), run_options)).map(
+                |((), (node_limit, (time_limit, until))), span| {
+                    let sched = Schedule::Run(
+                        span.clone(),
+                        RunConfig {
+                            ruleset: "".into(),
+                            node_limit,
+                            until,
+                        },
+                    );
+                    with_time_limit(span, time_limit, sched)
+                },
+            ),
+            parens(sequence3(text("run"), ident, run_options)).map(
+                |((), ruleset, (node_limit, (time_limit, until))), span| {
+                    let sched = Schedule::Run(
+                        span.clone(),
+                        RunConfig {
+                            ruleset,
+                            node_limit,
+                            until,
+                        },
+                    );
+                    with_time_limit(span, time_limit, sched)
+                },
+            ),
         )(ctx),
         _ => ident.map(|ruleset, span| {
             Schedule::Run(
                 span.clone(),
                 RunConfig {
                     ruleset,
+                    node_limit: None,
                     until: None,
                 },
             )
@@ -601,6 +897,68 @@ fn schedule(ctx: &Context) -> Res<Schedule> {
     }
 }
 
+/// The `[:node-limit n] [:time-limit seconds] [:until facts...]` options
+/// that end a `run`.
+#[allow(clippy::type_complexity)]
+fn run_options(ctx: &Context) -> Res<(Option<usize>, (Option<Duration>, Option<Vec<Fact>>))> {
+    sequence3(
+        option(sequence(text(":node-limit"), unum)).map(snd),
+        option(sequence(text(":time-limit"), duration)).map(snd),
+        option(sequence(text(":until"), repeat_until_end_paren(fact))).map(snd),
+    )
+    .map(|(node_limit, time_limit, until), _| (node_limit, (time_limit, until)))(ctx)
+}
+
+/// The `[:delimiter "c"] [:header] [:columns (i ...)]` options of `input`
+/// and `output`.
+fn table_format(ctx: &Context) -> Res<TableFormat> {
+    sequence3(
+        option(sequence(text(":delimiter"), delimiter)).map(snd),
+        option(text(":header")).map(|x, _| x.is_some()),
+        option(sequence(text(":columns"), list(unum))).map(snd),
+    )
+    .map(|(delimiter, header, columns), _| TableFormat {
+        delimiter: delimiter.unwrap_or('\t'),
+        header,
+        columns,
+    })(ctx)
+}
+
+/// A string of one character, where `\t` is a tab and a backslash before
+/// a quote or another backslash escapes it.
+fn delimiter(ctx: &Context) -> Res<char> {
+    let (s, span, next) = string(ctx)?;
+    let c = match s.as_str() {
+        "\\t" => Some('\t'),
+        "\\\"" => Some('"'),
+        "\\\\" => Some('\\'),
+        s => {
+            let mut chars = s.chars();
+            chars.next().filter(|_| chars.next().is_none())
+        }
+    };
+    match c {
+        Some(c) => Ok((c, span, next)),
+        None => Err(ParseError::Delimiter(span)),
+    }
+}
+
+fn with_time_limit(span: Span, time_limit: Option<Duration>, sched: Schedule) -> Schedule {
+    match time_limit {
+        Some(limit) => Schedule::TimeLimit(span, limit, Box::new(sched)),
+        None => sched,
+    }
+}
+
+/// A non-negative number of seconds.
+fn duration(ctx: &Context) -> Res<Duration> {
+    let (secs, span, next) = r#f64(ctx)?;
+    match Duration::try_from_secs_f64(secs.0) {
+        Ok(duration) => Ok((duration, span, next)),
+        Err(_) => Err(ParseError::Float(span)),
+    }
+}
+
 fn cost(ctx: &Context) -> Res<Option<usize>> {
     option(sequence(text(":cost"), unum)).map(snd)(ctx)
 }
@@ -639,17 +997,33 @@ fn non_let_action(ctx: &Context) -> Res<Action> {
             .map(|((), e1, e2), span| Action::Union(span, e1, e2))(ctx),
         "panic" => parens(sequence(text("panic"), string))
             .map(|(_, msg), span| Action::Panic(span, msg))(ctx),
-        "extract" => choice(
-            parens(sequence(text("extract"), expr)).map(|((), expr), span| {
-                Action::Extract(span.clone(), expr, Expr::Lit(span, Literal::Int(0)))
-            }),
-            parens(sequence3(text("extract"), expr, expr))
-                .map(|((), expr, variants), span| Action::Extract(span, expr, variants)),
-        )(ctx),
+        "extract" => parens(sequence4(
+            text("extract"),
+            expr,
+            option(expr),
+            option(sequence(text(":mode"), extract_mode)).map(snd),
+        ))
+        .map(|((), expr, variants, mode), span| {
+            let variants = variants.unwrap_or_else(|| Expr::Lit(span.clone(), Literal::Int(0)));
+            Action::Extract(span, expr, variants, mode.unwrap_or_default())
+        })(ctx),
         _ => call_expr.map(|e, span| Action::Expr(span, e))(ctx),
     }
 }
 
+fn extract_mode(ctx: &Context) -> Res<ExtractMode> {
+    choice(
+        choice(
+            text("tree").map(|(), _| ExtractMode::Tree),
+            text("dag").map(|(), _| ExtractMode::Dag),
+        ),
+        choice(
+            text("depth").map(|(), _| ExtractMode::Depth),
+            text("size").map(|(), _| ExtractMode::Size),
+        ),
+    )(ctx)
+}
+
 fn fact(ctx: &Context) -> Res<Fact> {
     let (call_expr, span, next) = call_expr(ctx)?;
     match call_expr {
@@ -671,7 +1045,10 @@ fn schema(ctx: &Context) -> Res<Schema> {
 
 fn expr(ctx: &Context) -> Res<Expr> {
     choices!(
+        // before `call_expr`, which also accepts square brackets
+        container_expr,
         call_expr,
+        rational_expr,
         literal.map(|literal, span| Expr::Lit(span, literal)),
         ident.map(|ident, span| Expr::Var(span, ident)),
     )(ctx)
@@ -681,9 +1058,11 @@ fn literal(ctx: &Context) -> Res<Literal> {
     choices!(
         sequence(text("("), text(")")).map(|((), ()), _| Literal::Unit),
         num.map(|x, _| Literal::Int(x)),
+        r#f32.map(|x, _| Literal::F32(x)),
         r#f64.map(|x, _| Literal::F64(x)),
         r#bool.map(|x, _| Literal::Bool(x)),
         string.map(|x, _| Literal::String(x.into())),
+        bytes.map(|x, _| Literal::Bytes(x)),
     )(ctx)
 }
 
@@ -696,6 +1075,67 @@ fn r#bool(ctx: &Context) -> Res<bool> {
     }
 }
 
+/// Container literals: `[x ...]` for a `Vec`, `#{x ...}` for a `Set`, and
+/// `{k v, ...}` for a `Map`, where the commas are optional. They parse into
+/// calls of the containers' primitives.
+fn container_expr(ctx: &Context) -> Res<Expr> {
+    let end_brace = |ctx: &Context| text("}")(ctx).is_ok();
+    let container = |span: Span, of: &str, empty: &str, elements: Vec<Expr>| {
+        let head = if elements.is_empty() { empty } else { of };
+        Expr::Call(span, head.into(), elements)
+    };
+    choices!(
+        sequence3(
+            text("["),
+            repeat_until(expr, |ctx| text("]")(ctx).is_ok()),
+            text("]"),
+        )
+        .map(move |((), elements, ()), span| container(
+            span,
+            "vec-of",
+            "vec-empty",
+            elements
+        )),
+        sequence3(text("#{"), repeat_until(expr, end_brace), text("}"))
+            .map(move |((), elements, ()), span| container(span, "set-of", "set-empty", elements)),
+        sequence3(
+            text("{"),
+            repeat_until(sequence3(expr, expr, option(text(","))), end_brace),
+            text("}"),
+        )
+        .map(|((), entries, ()), span| {
+            let empty = Expr::Call(span.clone(), "map-empty".into(), vec![]);
+            entries.into_iter().fold(empty, |map, (key, value, _)| {
+                Expr::Call(span.clone(), "map-insert".into(), vec![map, key, value])
+            })
+        }),
+    )(ctx)
+}
+
+/// Rationals can be written as `3/4` or `-3/4`, which is shorthand for
+/// `(rational 3 4)`. The denominator must be positive.
+fn rational_expr(ctx: &Context) -> Res<Expr> {
+    let (_, span, next) = ident(ctx)?;
+    let parts = span.string().split_once('/').and_then(|(numer, denom)| {
+        if !denom.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some((numer.parse::<i64>().ok()?, denom.parse::<i64>().ok()?))
+    });
+    match parts {
+        Some((numer, denom)) if denom > 0 => {
+            let int = |i| Expr::Lit(span.clone(), Literal::Int(i));
+            let expr = Expr::Call(
+                span.clone(),
+                "rational".into(),
+                vec![int(numer), int(denom)],
+            );
+            Ok((expr, span, next))
+        }
+        _ => Err(ParseError::Rational(span)),
+    }
+}
+
 fn call_expr(ctx: &Context) -> Res<Expr> {
     parens(sequence(ident, repeat_until_end_paren(expr)))
         .map(|(head, tail), span| Expr::Call(span, head, tail))(ctx)
@@ -719,11 +1159,44 @@ fn r#type(ctx: &Context) -> Res<Symbol> {
     ident(ctx)
 }
 
+/// Integers can also be written in hexadecimal (`0xff`) or binary (`0b1010`),
+/// and with underscores between digits (`1_000_000`). Hexadecimal and binary
+/// literals may set all 64 bits, so `0xffff_ffff_ffff_ffff` is `-1`.
 fn num(ctx: &Context) -> Res<i64> {
     let (_, span, next) = ident(ctx)?;
-    match span.string().parse::<i64>() {
-        Ok(x) => Ok((x, span, next)),
-        Err(_) => Err(ParseError::Int(span)),
+    match parse_int(span.string()) {
+        Some(x) => Ok((x, span, next)),
+        None => Err(ParseError::Int(span)),
+    }
+}
+
+fn parse_int(s: &str) -> Option<i64> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (radix, digits) = if let Some(digits) = unsigned.strip_prefix("0x") {
+        (16, digits)
+    } else if let Some(digits) = unsigned.strip_prefix("0b") {
+        (2, digits)
+    } else {
+        (10, unsigned)
+    };
+    if !digits.starts_with(|c: char| c.is_digit(radix)) {
+        return None;
+    }
+    if radix == 10 {
+        return s.replace('_', "").parse::<i64>().ok();
+    }
+    let digits = digits.replace('_', "");
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let bits = u64::from_str_radix(&digits, radix).ok()? as i64;
+    if negative {
+        bits.checked_neg()
+    } else {
+        Some(bits)
     }
 }
 
@@ -754,6 +1227,53 @@ fn r#f64(ctx: &Context) -> Res<OrderedFloat<f64>> {
     }
 }
 
+/// Single-precision floats are written with an `f32` suffix, e.g. `1.5f32`.
+fn r#f32(ctx: &Context) -> Res<OrderedFloat<f32>> {
+    use std::num::FpCategory::*;
+    let (_, span, next) = ident(ctx)?;
+    let Some(s) = span.string().strip_suffix("f32") else {
+        return Err(ParseError::Float(span));
+    };
+    match s {
+        "NaN" => Ok((OrderedFloat(f32::NAN), span, next)),
+        "inf" => Ok((OrderedFloat(f32::INFINITY), span, next)),
+        "-inf" => Ok((OrderedFloat(f32::NEG_INFINITY), span, next)),
+        _ => match s.parse::<f32>() {
+            Err(_) => Err(ParseError::Float(span)),
+            Ok(x) => match x.classify() {
+                Nan | Infinite => Err(ParseError::Float(span)),
+                Zero | Subnormal | Normal => Ok((OrderedFloat(x), span, next)),
+            },
+        },
+    }
+}
+
+/// Byte strings are written as `#x` followed by pairs of hex digits.
+fn bytes(ctx: &Context) -> Res<Vec<u8>> {
+    let mut span = ctx.span();
+    if !ctx.source.contents[ctx.index..].starts_with("#x") {
+        return Err(ParseError::Bytes(span));
+    }
+
+    let mut next = ctx.clone();
+    next.advance_char();
+    next.advance_char();
+    while next
+        .current_char()
+        .is_some_and(|c| c.is_ascii_alphanumeric())
+    {
+        next.advance_char();
+    }
+    span.2 = next.index;
+
+    next.advance_past_whitespace();
+
+    match decode_hex(&span.string()[2..]) {
+        Some(bytes) => Ok((bytes, span, next)),
+        None => Err(ParseError::Bytes(span)),
+    }
+}
+
 fn string(ctx: &Context) -> Res<String> {
     let mut span = Span(ctx.source.clone(), ctx.index, ctx.index);
     if ctx.current_char() != Some('"') {
@@ -794,6 +1314,8 @@ pub enum ParseError {
     Text(Span, &'static str),
     #[error("{0}\nexpected string")]
     String(Span),
+    #[error("{0}\nexpected a delimiter of one character")]
+    Delimiter(Span),
     #[error("{0}\nmissing end quote for string")]
     MissingEndQuote(Span),
     #[error("{0}\nunexpected end of file")]
@@ -808,6 +1330,10 @@ pub enum ParseError {
     Float(Span),
     #[error("{0}\nexpected boolean literal")]
     Bool(Span),
+    #[error("{0}\nexpected rational literal")]
+    Rational(Span),
+    #[error("{0}\nexpected byte string literal of the form #x followed by pairs of hex digits")]
+    Bytes(Span),
     #[error("{0}\nusing = with less than two arguments is not allowed")]
     EqFactLt2(Span),
 }
@@ -820,4 +1346,113 @@ mod tests {
         let e = crate::ast::parse_expr(None, s).unwrap();
         assert_eq!(format!("{}", e), s);
     }
+
+    #[test]
+    fn test_parse_container_literals() {
+        let parse = |s| crate::ast::parse_expr(None, s).unwrap().to_string();
+        assert_eq!(parse("[1 2 (f x)]"), "(vec-of 1 2 (f x))");
+        assert_eq!(parse("[ ]"), "(vec-empty)");
+        assert_eq!(parse("#{1 [2]}"), "(set-of 1 (vec-of 2))");
+        assert_eq!(
+            parse(r#"{"a" 1, "b" 2}"#),
+            r#"(map-insert (map-insert (map-empty) "a" 1) "b" 2)"#
+        );
+        assert_eq!(parse("{}"), "(map-empty)");
+        assert_eq!(parse(r#"["é" #{}]"#), r#"(vec-of "é" (set-empty))"#);
+
+        assert!(crate::ast::parse_expr(None, "{1 2 3}").is_err());
+        assert!(crate::ast::parse_expr(None, "[1 2").is_err());
+    }
+
+    #[test]
+    fn test_parse_rational_literal() {
+        let parse = |s| crate::ast::parse_expr(None, s).unwrap().to_string();
+        assert_eq!(parse("(f 3/4 -1/2)"), "(f (rational 3 4) (rational -1 2))");
+        // these are identifiers
+        assert_eq!(parse("(f 3/-4 x/y 1/0 /)"), "(f 3/-4 x/y 1/0 /)");
+    }
+
+    #[test]
+    fn test_parse_int_literal() {
+        use super::parse_int;
+        assert_eq!(parse_int("42"), Some(42));
+        assert_eq!(parse_int("-42"), Some(-42));
+        assert_eq!(parse_int("+42"), Some(42));
+        assert_eq!(parse_int("1_000_000"), Some(1_000_000));
+        assert_eq!(parse_int("0xFF"), Some(255));
+        assert_eq!(parse_int("-0x10"), Some(-16));
+        assert_eq!(parse_int("0b1010"), Some(10));
+        assert_eq!(parse_int("0xffff_ffff_ffff_ffff"), Some(-1));
+        assert_eq!(parse_int("-9223372036854775808"), Some(i64::MIN));
+
+        for s in [
+            "_1",
+            "0x",
+            "0b102",
+            "0x_f",
+            "1.5",
+            "x",
+            "9223372036854775808",
+            "0x1_0000_0000_0000_0000",
+        ] {
+            assert_eq!(parse_int(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_parse_special_float_literals() {
+        use crate::ast::GenericExpr;
+        let s = "(f inf -inf NaN inff32 -inff32 NaNf32)";
+        let e = crate::ast::parse_expr(None, s).unwrap();
+        assert_eq!(e.to_string(), s);
+        let GenericExpr::Call(_, _, args) = e else {
+            panic!("expected a call")
+        };
+        assert!(args.iter().all(|arg| matches!(arg, GenericExpr::Lit(..))));
+
+        // other spellings Rust accepts are identifiers
+        for s in ["infinity", "nan", "-NaN"] {
+            let e = crate::ast::parse_expr(None, s).unwrap();
+            assert!(matches!(e, GenericExpr::Var(..)), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_parse_bytes_literal() {
+        let s = r#"(f #x00ff1a #x)"#;
+        let e = crate::ast::parse_expr(None, s).unwrap();
+        assert_eq!(format!("{}", e), s);
+
+        assert!(crate::ast::parse_expr(None, "#xabc").is_err());
+        assert!(crate::ast::parse_expr(None, "#xzz").is_err());
+    }
+
+    #[test]
+    fn test_parse_program_parallel() {
+        use crate::ast::{parse_program, parse_program_parallel};
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let mut program = String::from("(datatype Math (Num i64) (Var String))\n");
+        for i in 0..20_000 {
+            program.push_str(&format!(
+                "; (a comment with a \" and a (\n(let x{i} (Var \"(x\\\" {i}\")) [let y{i} (Num {i})]\n"
+            ));
+        }
+        let serial = parse_program(None, &program).unwrap();
+        let parallel = parse_program_parallel(None, &program, &pool).unwrap();
+        assert_eq!(parallel.len(), 40_001);
+        // commands debug-print their spans with line numbers, which is slow
+        // on a program this long
+        let print = |commands: Vec<crate::ast::Command>| {
+            commands.iter().map(|c| c.to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(print(parallel), print(serial));
+
+        program.push_str("(let y (Num 1) 2)\n(let z (Num 2))");
+        let serial = parse_program(None, &program).unwrap_err();
+        let parallel = parse_program_parallel(None, &program, &pool).unwrap_err();
+        assert_eq!(parallel.to_string(), serial.to_string());
+    }
 }
@@ -51,6 +51,24 @@ pub(crate) fn remove_globals(
         .collect()
 }
 
+/// The function that stores the value of the global `name` of sort `ty`.
+pub(crate) fn global_function_decl(span: Span, name: Symbol, ty: &ArcSort) -> ResolvedFunctionDecl {
+    ResolvedFunctionDecl {
+        name,
+        schema: Schema {
+            input: vec![],
+            output: ty.name(),
+        },
+        default: None,
+        merge: None,
+        merge_action: GenericActions(vec![]),
+        cost: None,
+        unextractable: true,
+        ignore_viz: true,
+        span,
+    }
+}
+
 fn resolved_var_to_call(var: &ResolvedVar) -> ResolvedCall {
     assert!(
         var.is_global_ref,
@@ -78,7 +96,7 @@ fn replace_global_vars(expr: ResolvedExpr) -> ResolvedExpr {
     }
 }
 
-fn remove_globals_expr(expr: ResolvedExpr) -> ResolvedExpr {
+pub(crate) fn remove_globals_expr(expr: ResolvedExpr) -> ResolvedExpr {
     expr.visit_exprs(&mut replace_global_vars)
 }
 
@@ -93,20 +111,7 @@ impl<'a> GlobalRemover<'a> {
                 GenericAction::Let(span, name, expr) => {
                     let ty = expr.output_type();
 
-                    let func_decl = ResolvedFunctionDecl {
-                        name: name.name,
-                        schema: Schema {
-                            input: vec![],
-                            output: ty.name(),
-                        },
-                        default: None,
-                        merge: None,
-                        merge_action: GenericActions(vec![]),
-                        cost: None,
-                        unextractable: true,
-                        ignore_viz: true,
-                        span: span.clone(),
-                    };
+                    let func_decl = global_function_decl(span.clone(), name.name, &ty);
                     let resolved_call = ResolvedCall::Func(FuncType {
                         name: name.name,
                         input: vec![],
@@ -190,6 +195,8 @@ impl<'a> GlobalRemover<'a> {
                             expr
                         }
                     }),
+                    priority: rule.priority,
+                    match_limit: rule.match_limit,
                 };
                 vec![GenericNCommand::NormRule {
                     name,
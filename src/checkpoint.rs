@@ -0,0 +1,671 @@
+//! Saving an e-graph to a file and loading it back later, with
+//! [`EGraph::save`] and [`EGraph::load`] or the `save` and `load` commands.
+//!
+//! A checkpoint records the sorts, functions, rulesets, rules, costs and
+//! globals that were declared, in order, followed by the union-find and the
+//! rows of every table. Loading it into an empty e-graph replays the declarations and then
+//! restores the rows, so the e-graph can keep running from where it was saved.
+//! Options, rule timestamps and the push/pop stack are not saved.
+//!
+//! A checkpoint can also be imported into an e-graph that is not empty, with
+//! [`EGraph::import_egraph`] or the `import-egraph` command. Declarations it
+//! shares with the e-graph are skipped, its e-classes get fresh ids, and its
+//! rows are merged into the tables like `set`s. [`EGraph::absorb`] imports
+//! another e-graph the same way, through a checkpoint kept in memory.
+//!
+//! The format is little-endian binary:
+//! - the magic bytes `EGGLOGCK` and a `u32` version,
+//! - the `u32` timestamp,
+//! - a `u32` count of declarations, each a `u8` kind followed by either the
+//!   source text of a command or the name and sort of a global,
+//! - a `u64` count of ids, followed by the `u64` root of each id,
+//! - a `u32` count of tables, each a name, a `u64` count of rows, and for each
+//!   row its inputs, its output and a `u8` that is 1 if it is subsumed.
+//!
+//! Strings are a `u32` length followed by UTF-8 bytes. E-class ids and values
+//! that fit in their bits, such as `i64`s, are stored as `u64`s, and strings as
+//! strings. Any other value is stored as the expression that builds it, with
+//! the e-classes inside it as variables, followed by a `u32` count of those
+//! e-classes and the sort and id of each.
+
+use std::path::Path;
+
+use crate::ast::remove_globals::global_function_decl;
+use crate::*;
+
+const MAGIC: &[u8; 8] = b"EGGLOGCK";
+const VERSION: u32 = 1;
+
+/// Sorts whose values mean the same thing in any e-graph, so their bits can be
+/// saved as they are.
+pub(crate) const RAW_SORTS: &[&str] = &["i64", "f64", "f32", "bool", "Unit"];
+
+/// Something a checkpoint declares before restoring the rows that use it.
+#[derive(Clone, Debug)]
+pub(crate) enum Declaration {
+    /// A sort, function, ruleset, rule or `set-cost`, as source text.
+    Command(String),
+    /// A global bound by `let`, with the name of its sort. Its value is
+    /// restored with the row of its function.
+    Global(Symbol, Symbol),
+}
+
+impl EGraph {
+    /// Saves the e-graph to `path`, so that [`EGraph::load`] can restore it.
+    /// The e-graph is rebuilt first.
+    ///
+    /// ```
+    /// # use egglog::EGraph;
+    /// let path = std::env::temp_dir().join("egglog-save-doctest.egraph");
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math))
+    ///          (rewrite (Add a b) (Add b a))
+    ///          (let x (Add (Num 1) (Num 2)))",
+    ///     )
+    ///     .unwrap();
+    /// egraph.save(&path).unwrap();
+    ///
+    /// let mut loaded = EGraph::default();
+    /// loaded.load(&path).unwrap();
+    /// loaded
+    ///     .parse_and_run_program(None, "(run 1) (check (= x (Add (Num 2) (Num 1))))")
+    ///     .unwrap();
+    /// ```
+    pub fn save(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.save_checkpoint(path.as_ref(), &DUMMY_SPAN)
+    }
+
+    /// Loads a checkpoint written by [`EGraph::save`]. The e-graph must not
+    /// have declared any functions yet.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.load_checkpoint(path.as_ref(), &DUMMY_SPAN)
+    }
+
+    /// Adds the e-graph saved by [`EGraph::save`] at `path` to this one,
+    /// declaring what it declares that is not declared here yet. Its
+    /// e-classes get fresh ids, and its rows are added like `set`s, so that
+    /// e-nodes in both e-graphs end up in the same e-classes. The e-graph is
+    /// rebuilt afterwards. Rules are declared from their source, so a global
+    /// here named like a variable of an imported rule changes what the rule
+    /// matches.
+    ///
+    /// ```
+    /// # use egglog::EGraph;
+    /// let path = std::env::temp_dir().join("egglog-import-doctest.egraph");
+    /// let mut lemmas = EGraph::default();
+    /// lemmas
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math))
+    ///          (union (Add (Num 1) (Num 2)) (Num 3))",
+    ///     )
+    ///     .unwrap();
+    /// lemmas.save(&path).unwrap();
+    ///
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math))
+    ///          (let x (Add (Num 1) (Num 2)))",
+    ///     )
+    ///     .unwrap();
+    /// egraph.import_egraph(&path).unwrap();
+    /// egraph
+    ///     .parse_and_run_program(None, "(check (= x (Num 3)))")
+    ///     .unwrap();
+    /// ```
+    pub fn import_egraph(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.import_checkpoint(path.as_ref(), &DUMMY_SPAN)
+    }
+
+    /// Adds another e-graph to this one, like [`EGraph::import_egraph`] does
+    /// with a saved one. Strings and other primitives are interned again
+    /// here, and the e-classes of `other` get fresh ids, so the two e-graphs
+    /// can come from different runs, such as runs of different rulesets on
+    /// the same program.
+    ///
+    /// ```
+    /// # use egglog::EGraph;
+    /// let program = "(datatype Math (Num i64) (Add Math Math) (Mul Math Math))
+    ///                (let x (Add (Num 1) (Num 1)))";
+    /// let mut add = EGraph::default();
+    /// add.parse_and_run_program(None, program).unwrap();
+    /// add.parse_and_run_program(None, "(union x (Num 2))").unwrap();
+    /// let mut mul = EGraph::default();
+    /// mul.parse_and_run_program(None, program).unwrap();
+    /// mul.parse_and_run_program(None, "(union x (Mul (Num 2) (Num 1)))")
+    ///     .unwrap();
+    ///
+    /// add.absorb(mul).unwrap();
+    /// add.parse_and_run_program(None, "(check (= (Num 2) (Mul (Num 2) (Num 1))))")
+    ///     .unwrap();
+    /// ```
+    pub fn absorb(&mut self, mut other: EGraph) -> Result<(), Error> {
+        let path = Path::new("<absorbed e-graph>");
+        other.rebuild()?;
+        let bytes = other.encode_checkpoint().map_err(|message| {
+            Error::CheckpointError(path.to_path_buf(), message, DUMMY_SPAN.clone())
+        })?;
+        let mut reader = Reader {
+            bytes: &bytes,
+            path,
+            span: &DUMMY_SPAN,
+        };
+        self.decode_checkpoint(&mut reader, true)?;
+        self.rebuild()?;
+        Ok(())
+    }
+
+    /// Records the declarations in a typechecked program, before globals are
+    /// removed from it. Names from the [`SymbolGen`] only belong to
+    /// temporaries, and usually cannot be parsed back, so they are left out.
+    pub(crate) fn record_declarations(&mut self, program: &[ResolvedNCommand]) {
+        for command in program {
+            let declaration = match command {
+                ResolvedNCommand::Sort(..)
+                | ResolvedNCommand::AddRuleset(..)
+                | ResolvedNCommand::AddAnalysisRuleset(..)
+                | ResolvedNCommand::UnstableCombinedRuleset(..)
+                | ResolvedNCommand::NormRule { .. }
+                | ResolvedNCommand::SetCost { .. } => {
+                    Declaration::Command(command.to_command().to_string())
+                }
+                ResolvedNCommand::Function(fdecl) if !self.symbol_gen.is_fresh(fdecl.name) => {
+                    Declaration::Command(command.to_command().to_string())
+                }
+                ResolvedNCommand::CoreAction(ResolvedAction::Let(_, var, expr))
+                    if !self.symbol_gen.is_fresh(var.name) =>
+                {
+                    Declaration::Global(var.name, expr.output_type().name())
+                }
+                _ => continue,
+            };
+            self.declarations.push(declaration);
+        }
+    }
+
+    pub(crate) fn save_checkpoint(&mut self, path: &Path, span: &Span) -> Result<(), Error> {
+        self.rebuild()?;
+        let bytes = self
+            .encode_checkpoint()
+            .map_err(|message| Error::CheckpointError(path.to_path_buf(), message, span.clone()))?;
+        std::fs::write(path, bytes)
+            .map_err(|e| Error::IoError(path.to_path_buf(), e, span.clone()))?;
+        log::info!("Saved checkpoint to {path:?}.");
+        Ok(())
+    }
+
+    pub(crate) fn load_checkpoint(&mut self, path: &Path, span: &Span) -> Result<(), Error> {
+        let bytes =
+            std::fs::read(path).map_err(|e| Error::IoError(path.to_path_buf(), e, span.clone()))?;
+        let mut reader = Reader {
+            bytes: &bytes,
+            path,
+            span,
+        };
+        if !self.functions.is_empty() || self.unionfind.num_ids() > 0 {
+            return Err(reader.error("checkpoints can only be loaded into an empty e-graph"));
+        }
+        self.decode_checkpoint(&mut reader, false)?;
+        self.rebuild()?;
+        log::info!("Loaded checkpoint from {path:?}.");
+        Ok(())
+    }
+
+    pub(crate) fn import_checkpoint(&mut self, path: &Path, span: &Span) -> Result<(), Error> {
+        let bytes =
+            std::fs::read(path).map_err(|e| Error::IoError(path.to_path_buf(), e, span.clone()))?;
+        let mut reader = Reader {
+            bytes: &bytes,
+            path,
+            span,
+        };
+        self.decode_checkpoint(&mut reader, true)?;
+        self.rebuild()?;
+        log::info!("Imported checkpoint from {path:?}.");
+        Ok(())
+    }
+
+    fn encode_checkpoint(&self) -> Result<Vec<u8>, String> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u32(VERSION);
+        writer.u32(self.timestamp);
+
+        writer.u32(self.declarations.len() as u32);
+        for declaration in &self.declarations {
+            match declaration {
+                Declaration::Command(text) => {
+                    writer.u8(0);
+                    writer.str(text);
+                }
+                Declaration::Global(name, sort) => {
+                    writer.u8(1);
+                    writer.str(name.as_str());
+                    writer.str(sort.as_str());
+                }
+            }
+        }
+
+        let num_ids = self.unionfind.num_ids() as u64;
+        writer.u64(num_ids);
+        for id in 0..num_ids {
+            writer.u64(self.unionfind.find(id));
+        }
+
+        let mut termdag = TermDag::default();
+        let mut extractor = Extractor::with_costs(self, Default::default());
+        let functions: Vec<&Function> = self
+            .functions
+            .values()
+            .filter(|function| !self.symbol_gen.is_fresh(function.decl.name))
+            .collect();
+        writer.u32(functions.len() as u32);
+        for function in functions {
+            writer.str(function.decl.name.as_str());
+            let rows: Vec<_> = function.nodes.iter(true).collect();
+            writer.u64(rows.len() as u64);
+            for (inputs, output) in rows {
+                for (sort, value) in function.schema.input.iter().zip(inputs) {
+                    self.write_value(&mut writer, &mut extractor, &mut termdag, sort, *value)?;
+                }
+                let sort = &function.schema.output;
+                self.write_value(
+                    &mut writer,
+                    &mut extractor,
+                    &mut termdag,
+                    sort,
+                    output.value,
+                )?;
+                writer.u8(output.subsumed as u8);
+            }
+        }
+        Ok(writer.bytes)
+    }
+
+    fn write_value(
+        &self,
+        writer: &mut Writer,
+        extractor: &mut Extractor,
+        termdag: &mut TermDag,
+        sort: &ArcSort,
+        value: Value,
+    ) -> Result<(), String> {
+        if sort.is_eq_sort() {
+            writer.u64(self.find(sort, value).bits);
+        } else if RAW_SORTS.contains(&sort.name().as_str()) {
+            writer.u64(value.bits);
+        } else if sort.name() == StringSort.name() {
+            writer.str(Symbol::load(&StringSort, &value).as_str());
+        } else {
+            let mut eclasses = IndexMap::default();
+            self.for_each_eclass(sort, value, &mut |id, sort| {
+                eclasses.insert(id, sort.clone());
+            });
+            for id in eclasses.keys() {
+                extractor
+                    .costs
+                    .entry(*id)
+                    .or_insert_with(|| (1, termdag.var(saved_var(*id))));
+            }
+            let (_, expr) = sort
+                .extract_expr(self, value, extractor, termdag)
+                .ok_or_else(|| format!("cannot save a value of sort {}", sort.name()))?;
+            writer.str(&expr.to_string());
+            writer.u32(eclasses.len() as u32);
+            for (id, sort) in eclasses {
+                writer.str(sort.name().as_str());
+                writer.u64(id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes a checkpoint into the e-graph, which must be empty unless
+    /// `import` is set. An import skips what is already declared, gives the
+    /// e-classes fresh ids, and merges the rows instead of restoring them.
+    fn decode_checkpoint(&mut self, reader: &mut Reader, import: bool) -> Result<(), Error> {
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(reader.error("not an egglog checkpoint"));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(reader.error(format!("unsupported checkpoint version {version}")));
+        }
+        let timestamp = reader.u32()?;
+
+        for _ in 0..reader.u32()? {
+            match reader.u8()? {
+                0 => {
+                    let text = reader.str()?;
+                    for command in parse_program(None, &text)? {
+                        if import && self.is_declared(&command).map_err(|e| reader.error(e))? {
+                            continue;
+                        }
+                        for processed in self.process_command(command)? {
+                            self.run_command(processed)?;
+                        }
+                    }
+                }
+                1 => {
+                    let name = Symbol::from(reader.str()?);
+                    let sort = self.read_sort(reader)?;
+                    if import {
+                        if let Some(existing) = self.type_info.global_types.get(&name) {
+                            if existing.name() != sort.name() {
+                                return Err(reader.error(format!(
+                                    "global {name} has sort {} here",
+                                    existing.name()
+                                )));
+                            }
+                            continue;
+                        }
+                    }
+                    self.declare_global(name, &sort)?;
+                }
+                kind => return Err(reader.error(format!("unknown declaration kind {kind}"))),
+            }
+        }
+
+        // The id in this e-graph of each saved id
+        let num_ids = reader.u64()?;
+        let mut ids = vec![];
+        if import {
+            let mut fresh: HashMap<Id, Id> = HashMap::default();
+            for id in 0..num_ids {
+                let root = reader.u64()?;
+                if root >= num_ids {
+                    return Err(reader.error(format!("id {id} has a root {root} out of range")));
+                }
+                ids.push(
+                    *fresh
+                        .entry(root)
+                        .or_insert_with(|| self.unionfind.make_set()),
+                );
+            }
+        } else {
+            for _ in 0..num_ids {
+                ids.push(self.unionfind.make_set());
+            }
+            for id in 0..num_ids {
+                let root = reader.u64()?;
+                if root >= num_ids {
+                    return Err(reader.error(format!("id {id} has a root {root} out of range")));
+                }
+                if root != id {
+                    self.unionfind.set_parent(id, root);
+                }
+            }
+            self.timestamp = timestamp;
+        }
+
+        self.extraction_cache = None;
+        for _ in 0..reader.u32()? {
+            let name = Symbol::from(reader.str()?);
+            let Some(function) = self.functions.get(&name) else {
+                return Err(reader.error(format!("unknown function {name}")));
+            };
+            let schema = function.schema.clone();
+            for _ in 0..reader.u64()? {
+                let mut inputs = schema
+                    .input
+                    .iter()
+                    .map(|sort| self.read_value(reader, sort, &ids))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let output = self.read_value(reader, &schema.output, &ids)?;
+                let subsumed = reader.u8()? != 0;
+                if import {
+                    for (sort, value) in schema.input.iter().zip(&mut inputs) {
+                        *value = self.find(sort, *value);
+                    }
+                    self.perform_set(name, output, &mut inputs)?;
+                } else {
+                    let function = self.functions.get_mut(&name).unwrap();
+                    function.insert(&inputs, output, self.timestamp);
+                }
+                let function = self.functions.get_mut(&name).unwrap();
+                if subsumed {
+                    function.subsume(&inputs);
+                }
+            }
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(reader.error("unexpected data after the end of the checkpoint"));
+        }
+        Ok(())
+    }
+
+    /// Declares again something declared in another e-graph.
+    pub(crate) fn redeclare(&mut self, declaration: &Declaration) -> Result<(), Error> {
+        match declaration {
+            Declaration::Command(text) => {
+                for command in parse_program(None, text)? {
+                    for processed in self.process_command(command)? {
+                        self.run_command(processed)?;
+                    }
+                }
+            }
+            Declaration::Global(name, sort) => {
+                let sort = self.type_info.sorts[sort].clone();
+                self.declare_global(*name, &sort)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn declare_global(&mut self, name: Symbol, sort: &ArcSort) -> Result<(), Error> {
+        self.declare_function(&global_function_decl(DUMMY_SPAN.clone(), name, sort))?;
+        self.type_info.global_types.insert(name, sort.clone());
+        self.declarations
+            .push(Declaration::Global(name, sort.name()));
+        Ok(())
+    }
+
+    /// Whether the e-graph already declares what a saved declaration
+    /// declares, in which case an import skips it. A function must have the
+    /// same schema here.
+    fn is_declared(&self, command: &Command) -> Result<bool, String> {
+        Ok(match command {
+            Command::Sort(_, name, _) => self.type_info.sorts.contains_key(name),
+            Command::Function(decl) => match self.functions.get(&decl.name) {
+                Some(function) if function.decl.schema != decl.schema => {
+                    return Err(format!(
+                        "function {} has a different schema here",
+                        decl.name
+                    ))
+                }
+                function => function.is_some(),
+            },
+            Command::AddRuleset(name)
+            | Command::AddAnalysisRuleset(name)
+            | Command::UnstableCombinedRuleset(name, _) => self.rulesets.contains_key(name),
+            Command::Rule { name, ruleset, .. } => matches!(
+                self.rulesets.get(ruleset),
+                Some(Ruleset::Rules(_, rules)) if rules.contains_key(name)
+            ),
+            Command::SetCost { constructor, .. } => self
+                .functions
+                .get(constructor)
+                .is_some_and(|function| function.cost.is_some()),
+            _ => false,
+        })
+    }
+
+    fn read_sort(&self, reader: &mut Reader) -> Result<ArcSort, Error> {
+        let name = Symbol::from(reader.str()?);
+        self.type_info
+            .sorts
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| reader.error(format!("unknown sort {name}")))
+    }
+
+    /// Reads a value of `sort`, where `ids` has the id in this e-graph of
+    /// each saved id.
+    fn read_value(
+        &mut self,
+        reader: &mut Reader,
+        sort: &ArcSort,
+        ids: &[Id],
+    ) -> Result<Value, Error> {
+        let id = |reader: &Reader, saved: u64| {
+            ids.get(saved as usize)
+                .copied()
+                .ok_or_else(|| reader.error(format!("id {saved} out of range")))
+        };
+        if sort.is_eq_sort() {
+            let saved = reader.u64()?;
+            Ok(tagged(sort, id(reader, saved)?))
+        } else if RAW_SORTS.contains(&sort.name().as_str()) {
+            Ok(tagged(sort, reader.u64()?))
+        } else if sort.name() == StringSort.name() {
+            Ok(Value::from(Symbol::from(reader.str()?)))
+        } else {
+            let text = reader.str()?;
+            let mut eclasses = vec![];
+            for _ in 0..reader.u32()? {
+                let sort = self.read_sort(reader)?;
+                let saved = reader.u64()?;
+                eclasses.push((sort, saved, id(reader, saved)?));
+            }
+            self.eval_saved_expr(sort, &text, &eclasses)
+        }
+    }
+
+    /// Evaluates the expression saved for a value of `sort`, where the
+    /// variables from [`saved_var`] stand for `eclasses`, given by their
+    /// saved ids and their ids here.
+    fn eval_saved_expr(
+        &mut self,
+        sort: &ArcSort,
+        text: &str,
+        eclasses: &[(ArcSort, Id, Id)],
+    ) -> Result<Value, Error> {
+        let expr = parse_expr(None, text)?;
+        let vars: IndexSet<ResolvedVar> = eclasses
+            .iter()
+            .map(|(sort, saved, _)| ResolvedVar {
+                name: saved_var(*saved),
+                sort: sort.clone(),
+                is_global_ref: false,
+            })
+            .collect();
+
+        // Typechecking the expression as the right side of a union with a
+        // variable of the expected sort resolves containers that are empty.
+        let output = Symbol::from("%saved-output");
+        let mut binding = IndexMap::default();
+        binding.insert(output, (DUMMY_SPAN.clone(), sort.clone()));
+        for var in &vars {
+            binding.insert(var.name, (DUMMY_SPAN.clone(), var.sort.clone()));
+        }
+        let action = Action::Union(DUMMY_SPAN.clone(), Expr::var_no_span(output), expr);
+        let ResolvedAction::Union(_, _, expr) =
+            self.type_info
+                .typecheck_action(&mut self.symbol_gen, &action, &binding)?
+        else {
+            unreachable!("typechecking keeps the kind of action")
+        };
+
+        let (actions, mapped_expr) =
+            expr.to_core_actions(&self.type_info, &mut vars.clone(), &mut self.symbol_gen)?;
+        let target = mapped_expr.get_corresponding_var_or_lit(&self.type_info);
+        let program = self
+            .compile_expr(&vars, &actions, &target)
+            .map_err(Error::TypeErrors)?;
+        let subst: Vec<Value> = eclasses
+            .iter()
+            .map(|(sort, _, id)| tagged(sort, *id))
+            .collect();
+        let mut stack = vec![];
+        self.run_actions(&mut stack, &subst, &program)?;
+        Ok(stack.pop().unwrap())
+    }
+}
+
+/// The variable that stands for the e-class `id` in a saved expression.
+fn saved_var(id: Id) -> Symbol {
+    format!("%saved-{id}").into()
+}
+
+pub(crate) fn tagged(sort: &ArcSort, bits: u64) -> Value {
+    #[cfg(not(debug_assertions))]
+    let _ = sort;
+    Value {
+        #[cfg(debug_assertions)]
+        tag: sort.name(),
+        bits,
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, x: u8) {
+        self.bytes.push(x);
+    }
+
+    fn u32(&mut self, x: u32) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn u64(&mut self, x: u64) {
+        self.bytes.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    path: &'a Path,
+    span: &'a Span,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, message: impl Display) -> Error {
+        Error::CheckpointError(
+            self.path.to_path_buf(),
+            message.to_string(),
+            self.span.clone(),
+        )
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < n {
+            return Err(self.error("the checkpoint ends too early"));
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn str(&mut self) -> Result<String, Error> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("invalid UTF-8 in a string"))
+    }
+}
@@ -395,10 +395,11 @@ impl Assignment<AtomTerm, ArcSort> {
                 self.annotate_expr(lhs, typeinfo),
                 self.annotate_expr(rhs, typeinfo),
             )),
-            GenericAction::Extract(span, lhs, rhs) => Ok(ResolvedAction::Extract(
+            GenericAction::Extract(span, lhs, rhs, mode) => Ok(ResolvedAction::Extract(
                 span.clone(),
                 self.annotate_expr(lhs, typeinfo),
                 self.annotate_expr(rhs, typeinfo),
+                *mode,
             )),
             GenericAction::Panic(span, msg) => Ok(ResolvedAction::Panic(span.clone(), msg.clone())),
             GenericAction::Expr(span, expr) => Ok(ResolvedAction::Expr(
@@ -559,7 +560,7 @@ impl CoreAction {
             )
             .chain(once(Constraint::Eq(lhs.clone(), rhs.clone())))
             .collect()),
-            CoreAction::Extract(_ann, e, n) => {
+            CoreAction::Extract(_ann, e, n, _mode) => {
                 // e can be anything
                 Ok(
                     get_literal_and_global_constraints(&[e.clone(), n.clone()], typeinfo)
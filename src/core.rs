@@ -377,7 +377,12 @@ impl<Leaf: Clone> Query<ResolvedCall, Leaf> {
 pub enum GenericCoreAction<Head, Leaf> {
     Let(Span, Leaf, Head, Vec<GenericAtomTerm<Leaf>>),
     LetAtomTerm(Span, Leaf, GenericAtomTerm<Leaf>),
-    Extract(Span, GenericAtomTerm<Leaf>, GenericAtomTerm<Leaf>),
+    Extract(
+        Span,
+        GenericAtomTerm<Leaf>,
+        GenericAtomTerm<Leaf>,
+        ExtractMode,
+    ),
     Set(
         Span,
         Head,
@@ -531,7 +536,7 @@ where
                         .0
                         .push(GenericAction::Union(span.clone(), mapped_e1, mapped_e2));
                 }
-                GenericAction::Extract(span, e, n) => {
+                GenericAction::Extract(span, e, n, mode) => {
                     let (actions, mapped_e) = e.to_core_actions(typeinfo, binding, fresh_gen)?;
                     norm_actions.extend(actions.0);
                     let (actions, mapped_n) = n.to_core_actions(typeinfo, binding, fresh_gen)?;
@@ -540,10 +545,14 @@ where
                         span.clone(),
                         mapped_e.get_corresponding_var_or_lit(typeinfo),
                         mapped_n.get_corresponding_var_or_lit(typeinfo),
+                        *mode,
+                    ));
+                    mapped_actions.0.push(GenericAction::Extract(
+                        span.clone(),
+                        mapped_e,
+                        mapped_n,
+                        *mode,
                     ));
-                    mapped_actions
-                        .0
-                        .push(GenericAction::Extract(span.clone(), mapped_e, mapped_n));
                 }
                 GenericAction::Panic(span, string) => {
                     norm_actions.push(GenericCoreAction::Panic(span.clone(), string.clone()));
@@ -807,6 +816,8 @@ where
             span: _,
             head,
             body,
+            priority: _,
+            match_limit: _,
         } = self;
 
         let (body, _correspondence) = Facts(body.clone()).to_query(typeinfo, fresh_gen);
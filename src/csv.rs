@@ -0,0 +1,221 @@
+//! Reading and writing the rows of a function as delimited text, for the
+//! `input` and `output` commands.
+
+use std::io::{self, Write};
+
+use crate::*;
+
+impl EGraph {
+    /// The path of a file named in `input` or `output`, which is relative to
+    /// the fact directory.
+    fn fact_path(&self, file: &str) -> PathBuf {
+        let mut path = self.fact_directory.clone().unwrap_or_default();
+        path.push(file);
+        path
+    }
+
+    /// The sorts of the columns of a function in a file: its inputs, and its
+    /// output unless it is a constructor or a relation.
+    fn file_columns(&self, name: Symbol) -> Vec<ArcSort> {
+        let function = &self.functions[&name];
+        let mut columns = function.schema.input.clone();
+        let output = &function.schema.output;
+        let is_datatype = self
+            .type_info
+            .lookup_user_func(name)
+            .is_some_and(|f| f.is_datatype);
+        if !is_datatype && output.name() != UnitSort.name() {
+            columns.push(output.clone());
+        }
+        columns
+    }
+
+    pub(crate) fn input_file(
+        &mut self,
+        span: &Span,
+        func_name: Symbol,
+        file: &str,
+        format: &TableFormat,
+    ) -> Result<(), Error> {
+        let filename = self.fact_path(file);
+        let invalid = |line: usize, message: String| {
+            let message = format!("line {}: {message}", line + 1);
+            let error = io::Error::new(io::ErrorKind::InvalidData, message);
+            Error::IoError(filename.clone(), error, span.clone())
+        };
+        let columns = self.file_columns(func_name);
+        let n_inputs = self.functions[&func_name].schema.input.len();
+        let fields: Vec<usize> = match &format.columns {
+            Some(fields) if fields.len() != columns.len() => {
+                return Err(invalid(
+                    0,
+                    format!("expected {} columns, got {}", columns.len(), fields.len()),
+                ));
+            }
+            Some(fields) => fields.clone(),
+            None => (0..columns.len()).collect(),
+        };
+
+        log::info!("Opening file '{:?}'...", filename);
+        let contents = std::fs::read_to_string(&filename)
+            .map_err(|e| Error::IoError(filename.clone(), e, span.clone()))?;
+
+        let mut facts = vec![];
+        let lines = split_records(&contents, format.delimiter);
+        for (line, record) in lines.into_iter().enumerate().skip(format.header as usize) {
+            if record.len() == 1 && record[0].trim().is_empty() {
+                continue;
+            }
+            let mut values = vec![];
+            for (sort, &field) in columns.iter().zip(&fields) {
+                let Some(text) = record.get(field) else {
+                    return Err(invalid(line, format!("no field {field}")));
+                };
+                let literal = parse_field(sort, text).ok_or_else(|| {
+                    invalid(line, format!("expected {} in field {field}", sort.name()))
+                })?;
+                values.push(self.eval_lit(&literal));
+            }
+            let output = values.get(n_inputs).copied();
+            values.truncate(n_inputs);
+            facts.push((func_name, values, output));
+        }
+        let num_facts = facts.len();
+        self.insert_facts(facts)?;
+        log::info!("Read {num_facts} facts into {func_name} from '{file}'.");
+        Ok(())
+    }
+
+    pub(crate) fn output_function(
+        &mut self,
+        span: &Span,
+        func_name: Symbol,
+        file: &str,
+        format: &TableFormat,
+    ) -> Result<(), Error> {
+        let filename = self.fact_path(file);
+        let columns = self.file_columns(func_name);
+        let fields = format
+            .columns
+            .clone()
+            .unwrap_or_else(|| (0..columns.len()).collect());
+        let width = fields.iter().map(|f| f + 1).max().unwrap_or(0);
+
+        let mut out = String::new();
+        let mut record = vec![String::new(); width];
+        let write_record = |out: &mut String, record: &mut Vec<String>| {
+            let quoted: Vec<String> = record
+                .iter_mut()
+                .map(|field| quote_field(&std::mem::take(field), format.delimiter))
+                .collect();
+            out.push_str(&quoted.join(&format.delimiter.to_string()));
+            out.push('\n');
+        };
+        if format.header {
+            for (sort, &field) in columns.iter().zip(&fields) {
+                record[field] = sort.name().to_string();
+            }
+            write_record(&mut out, &mut record);
+        }
+        let mut termdag = TermDag::default();
+        let function = &self.functions[&func_name];
+        for (inputs, output) in function.nodes.iter(false) {
+            let values = inputs.iter().chain([&output.value]);
+            for ((sort, value), &field) in columns.iter().zip(values).zip(&fields) {
+                let (_, term) = self.extract(*value, &mut termdag, sort);
+                record[field] = match &term {
+                    Term::Lit(Literal::String(s)) => s.to_string(),
+                    _ => termdag.to_string(&term),
+                };
+            }
+            write_record(&mut out, &mut record);
+        }
+
+        let mut f = File::create(&filename)
+            .map_err(|e| Error::IoError(filename.clone(), e, span.clone()))?;
+        f.write_all(out.as_bytes())
+            .map_err(|e| Error::IoError(filename.clone(), e, span.clone()))?;
+        log::info!("Output {func_name} to '{filename:?}'.");
+        Ok(())
+    }
+}
+
+/// Splits delimited text into records of fields. Fields may be quoted with
+/// double quotes, with double quotes inside doubled, and then hold the
+/// delimiter and line breaks. Unquoted fields are trimmed.
+fn split_records(contents: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = Field::default();
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.text.push('"');
+            }
+            '"' if field.in_quotes => field.in_quotes = false,
+            '"' if !field.quoted && field.text.trim().is_empty() => {
+                field.text.clear();
+                field.quoted = true;
+                field.in_quotes = true;
+            }
+            c if field.in_quotes => field.text.push(c),
+            c if c == delimiter => record.push(field.take()),
+            '\n' => {
+                record.push(field.take());
+                records.push(std::mem::take(&mut record));
+            }
+            // text after the closing quote is left out
+            _ if field.quoted => {}
+            c => field.text.push(c),
+        }
+    }
+    if !field.text.is_empty() || field.quoted || !record.is_empty() {
+        record.push(field.take());
+        records.push(record);
+    }
+    records
+}
+
+#[derive(Default)]
+struct Field {
+    text: String,
+    /// Whether the field started with a quote.
+    quoted: bool,
+    /// Whether the quote has not been closed yet.
+    in_quotes: bool,
+}
+
+impl Field {
+    fn take(&mut self) -> String {
+        let field = std::mem::take(self);
+        if field.quoted {
+            field.text
+        } else {
+            field.text.trim().to_string()
+        }
+    }
+}
+
+/// Quotes a field if it would not be read back as itself.
+fn quote_field(field: &str, delimiter: char) -> String {
+    let special = |c: char| c == delimiter || c == '"' || c == '\n' || c == '\r';
+    if field.contains(special) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Reads a field as a literal of a sort.
+fn parse_field(sort: &ArcSort, text: &str) -> Option<Literal> {
+    Some(match sort.name().as_str() {
+        "i64" => Literal::Int(text.parse().ok()?),
+        "f64" => Literal::F64(text.parse::<f64>().ok()?.into()),
+        "f32" => Literal::F32(text.parse::<f32>().ok()?.into()),
+        "bool" => Literal::Bool(text.parse().ok()?),
+        "String" => Literal::String(text.into()),
+        _ => return None,
+    })
+}
@@ -0,0 +1,266 @@
+//! The differences between two e-graphs, with [`EGraph::diff`].
+//!
+//! The e-graphs are matched by terms, so they can come from separate runs:
+//! each e-class of the first e-graph is named by its cheapest term, which is
+//! looked up in the second. E-classes of the first whose terms are in the same
+//! e-class of the second were merged, and rows of the second that no row of
+//! the first maps to appeared. When the second e-graph has explanations
+//! enabled, the explanation of each merge says which rules caused it.
+
+use crate::explain::Pattern;
+use crate::intersect::comparable;
+use crate::*;
+
+/// How an e-graph differs from an earlier one, from [`EGraph::diff`].
+#[derive(Clone, Debug)]
+pub struct EGraphDiff {
+    pub termdag: TermDag,
+    /// Terms in different e-classes of the earlier e-graph that are in the
+    /// same e-class of the later one.
+    pub merged: Vec<MergedTerms>,
+    /// The rows of the later e-graph that the earlier one has no row for,
+    /// as the term of the row's e-node or call and the term of its output.
+    pub new_rows: Vec<(Term, Term)>,
+}
+
+/// Two terms that an [`EGraphDiff`] found merged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergedTerms {
+    pub a: Term,
+    pub b: Term,
+    /// The rules that explain why `a` and `b` are equal in the later
+    /// e-graph, in the order they first come up. It is empty if the later
+    /// e-graph does not have explanations enabled.
+    pub rules: Vec<Symbol>,
+}
+
+impl EGraph {
+    /// How `after` differs from this e-graph: which of this e-graph's
+    /// e-classes it merged, which rows it has that this e-graph does not,
+    /// and, if it has explanations enabled, which rules merged the
+    /// e-classes. The e-graphs are compared by terms, so they need not share
+    /// ids, but rows with primitives other than `i64`, `f64`, `f32`, `bool`,
+    /// `Unit` and `String` are left out.
+    ///
+    /// ```
+    /// # use egglog::EGraph;
+    /// let mut before = EGraph::default();
+    /// before.enable_explanations();
+    /// before
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math))
+    ///          (rewrite (Add a b) (Add b a))
+    ///          (Add (Num 1) (Num 2))
+    ///          (Add (Num 2) (Num 1))",
+    ///     )
+    ///     .unwrap();
+    /// let mut after = before.clone();
+    /// after.parse_and_run_program(None, "(run 1)").unwrap();
+    ///
+    /// let diff = before.diff(&after);
+    /// assert_eq!(diff.merged.len(), 1);
+    /// assert_eq!(diff.merged[0].rules.len(), 1);
+    /// assert!(diff.new_rows.is_empty());
+    /// // rules are named by their desugared source
+    /// assert!(diff
+    ///     .to_string()
+    ///     .starts_with("merged (Add (Num 1) (Num 2)) = (Add (Num 2) (Num 1)) by (rule"));
+    /// ```
+    pub fn diff(&self, after: &EGraph) -> EGraphDiff {
+        let mut termdag = TermDag::default();
+        let before_extractor = Extractor::new(self, &mut termdag);
+        let after_extractor = Extractor::new(after, &mut termdag);
+        // The term of each e-class of this e-graph, and its e-class in
+        // `after` if the term is there
+        let mut classes: IndexMap<Id, (ArcSort, Term, Option<Value>)> = IndexMap::default();
+        for function in functions(self) {
+            let sort = &function.schema.output;
+            if !sort.is_eq_sort() {
+                continue;
+            }
+            for (_, output) in function.nodes.iter(false) {
+                let class = self.find(sort, output.value).bits;
+                if classes.contains_key(&class) {
+                    continue;
+                }
+                let Some((_, term)) = before_extractor.find_best(output.value, &mut termdag, sort)
+                else {
+                    continue;
+                };
+                let value = after
+                    .lookup(&termdag, &term, sort)
+                    .map(|(value, _)| after.find(sort, value));
+                classes.insert(class, (sort.clone(), term, value));
+            }
+        }
+
+        let mut by_after_class: IndexMap<Id, Vec<(&ArcSort, &Term)>> = IndexMap::default();
+        for (sort, term, value) in classes.values() {
+            if let Some(value) = value {
+                by_after_class
+                    .entry(value.bits)
+                    .or_default()
+                    .push((sort, term));
+            }
+        }
+        let mut merged = vec![];
+        for terms in by_after_class.values() {
+            let (sort, a) = terms[0];
+            for (_, b) in &terms[1..] {
+                let rules = after.rules_merging(&termdag, a, b, sort);
+                merged.push(MergedTerms {
+                    a: a.clone(),
+                    b: (*b).clone(),
+                    rules,
+                });
+            }
+        }
+
+        // The rows of `after` that the rows of this e-graph map to
+        let mut old_rows: HashSet<(Symbol, Vec<Value>)> = HashSet::default();
+        for function in functions(self) {
+            'rows: for (inputs, _) in function.nodes.iter(false) {
+                let mut after_inputs = vec![];
+                for (sort, value) in function.schema.input.iter().zip(inputs) {
+                    if sort.is_eq_sort() {
+                        let class = self.find(sort, *value).bits;
+                        match classes.get(&class) {
+                            Some((_, _, Some(value))) => after_inputs.push(*value),
+                            _ => continue 'rows,
+                        }
+                    } else {
+                        after_inputs.push(*value);
+                    }
+                }
+                old_rows.insert((function.decl.name, after_inputs));
+            }
+        }
+        let mut new_rows = vec![];
+        for function in functions(after) {
+            for (inputs, output) in function.nodes.iter(false) {
+                let schema = &function.schema;
+                let inputs: Vec<Value> = schema
+                    .input
+                    .iter()
+                    .zip(inputs)
+                    .map(|(sort, value)| after.find(sort, *value))
+                    .collect();
+                if old_rows.contains(&(function.decl.name, inputs.clone())) {
+                    continue;
+                }
+                let mut term = |sort: &ArcSort, value: Value| match after_extractor.find_best(
+                    value,
+                    &mut termdag,
+                    sort,
+                ) {
+                    Some((_, term)) => term,
+                    None => termdag.var(format!("#{}", value.bits).into()),
+                };
+                let children = schema
+                    .input
+                    .iter()
+                    .zip(&inputs)
+                    .map(|(sort, value)| term(sort, *value))
+                    .collect();
+                let output = term(&schema.output, output.value);
+                new_rows.push((termdag.app(function.decl.name, children), output));
+            }
+        }
+
+        EGraphDiff {
+            termdag,
+            merged,
+            new_rows,
+        }
+    }
+
+    /// The value of a term of `sort` in this e-graph, if all of its e-nodes
+    /// are here, with the term as a [`Pattern`] to explain.
+    fn lookup(&self, termdag: &TermDag, term: &Term, sort: &ArcSort) -> Option<(Value, Pattern)> {
+        match term {
+            Term::App(name, children) if sort.is_eq_sort() => {
+                let function = self.functions.get(name)?;
+                let mut inputs = vec![];
+                let mut patterns = vec![];
+                for (child, sort) in children.iter().zip(&function.schema.input) {
+                    let (value, pattern) = self.lookup(termdag, termdag.get(*child), sort)?;
+                    inputs.push(self.find(sort, value));
+                    patterns.push(pattern);
+                }
+                let output = function.nodes.get(&inputs)?.value;
+                Some((output, Pattern::Node(*name, patterns)))
+            }
+            Term::Lit(lit) => {
+                let value = self.eval_lit(lit);
+                Some((value, Pattern::Value(sort.clone(), value)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The rules in the explanation of why the terms `a` and `b` are equal,
+    /// or none if explanations are not enabled.
+    fn rules_merging(&self, termdag: &TermDag, a: &Term, b: &Term, sort: &ArcSort) -> Vec<Symbol> {
+        let mut rules = IndexSet::default();
+        let (Some((_, a)), Some((_, b))) =
+            (self.lookup(termdag, a, sort), self.lookup(termdag, b, sort))
+        else {
+            return vec![];
+        };
+        if let Ok((_, explanation)) = self.explain_patterns(&a, &b, sort) {
+            collect_rules(&explanation, &mut rules);
+        }
+        rules.into_iter().collect()
+    }
+}
+
+/// The functions whose rows are compared: those of the program, with sorts
+/// that can be compared across e-graphs.
+fn functions(egraph: &EGraph) -> impl Iterator<Item = &Function> {
+    egraph.functions.values().filter(|function| {
+        let mut sorts = function
+            .schema
+            .input
+            .iter()
+            .chain([&function.schema.output]);
+        !egraph.symbol_gen.is_fresh(function.decl.name) && sorts.all(comparable)
+    })
+}
+
+fn collect_rules(explanation: &Explanation, rules: &mut IndexSet<Symbol>) {
+    for step in &explanation.steps {
+        match &step.justification {
+            Justification::Rule { name, .. } => {
+                rules.insert(*name);
+            }
+            Justification::Congruence(inputs) => {
+                for input in inputs {
+                    collect_rules(input, rules);
+                }
+            }
+            Justification::Given | Justification::Batched => {}
+        }
+    }
+}
+
+impl Display for EGraphDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for merge in &self.merged {
+            let a = self.termdag.to_string(&merge.a);
+            let b = self.termdag.to_string(&merge.b);
+            write!(f, "merged {a} = {b}")?;
+            if !merge.rules.is_empty() {
+                let rules: Vec<&str> = merge.rules.iter().map(|rule| rule.as_str()).collect();
+                write!(f, " by {}", rules.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        for (row, output) in &self.new_rows {
+            let row = self.termdag.to_string(row);
+            let output = self.termdag.to_string(output);
+            writeln!(f, "new {row} -> {output}")?;
+        }
+        Ok(())
+    }
+}
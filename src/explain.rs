@@ -0,0 +1,686 @@
+//! Explanations of why two terms are equal, with the `explain` command or
+//! [`EGraph::explain`].
+//!
+//! When explanations are enabled, the union-find keeps a proof forest, with
+//! an edge between the two ids passed to each union that merged two classes,
+//! labelled with the reason for the union. Two ids are connected in the
+//! forest exactly when they are in the same class, so the path between them
+//! explains why they are equal. Each id made for an e-node remembers it, and
+//! stands for that e-node's term in explanations.
+//!
+//! Unions made by rebuilding are explained by congruence: the classes on
+//! either side have an e-node of the same function, whose inputs were
+//! already equal because of older edges. Explaining the inputs only uses
+//! edges older than the union, so explanations are well-founded.
+//!
+//! Explanations are kept small in two ways. When several pairs of e-nodes
+//! could explain a congruence, the pair whose inputs and endpoints are
+//! closest in the proof forest is used, as in egg's greedy explanation
+//! optimization. Then every chain of equalities is shortened wherever a term
+//! comes up twice, by dropping the detour between the two.
+//!
+//! Rules match e-classes rather than e-nodes, so the edge for a rule's union
+//! is between the ids of the classes it matched at the time, and the step
+//! shows whichever term those ids stand for.
+
+use crate::*;
+
+/// Why a union happened, as recorded in the proof forest.
+#[derive(Clone, Debug, Default)]
+pub(crate) enum Reason {
+    /// An action at the top level of a program.
+    #[default]
+    Given,
+    /// The actions of a rule, run on a match given as the values of the
+    /// rule's variables.
+    Rule(Symbol, Vec<Value>),
+    /// Rebuilding found two rows of the function with equal inputs.
+    Congruence(Symbol),
+    /// An update from a rule that [`EGraph::batch_updates`] deferred to the
+    /// end of its iteration.
+    Batched,
+}
+
+/// The proof forest, and the e-nodes that ids were made for.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Explanations {
+    /// The e-node each id was made for, as a function and its inputs.
+    origins: HashMap<Id, (Symbol, Vec<Value>)>,
+    /// The first output of each row of a function to e-classes, with the
+    /// reason the row was set, or `None` if the output was made for the row.
+    rows: IndexMap<(Symbol, Vec<Value>), (Value, Option<Reason>)>,
+    /// The edges of the proof forest, oldest first.
+    edges: Vec<(Id, Id, Reason)>,
+    /// The edges at each id, as indices into `edges`.
+    adjacent: HashMap<Id, Vec<usize>>,
+    /// The reason for the unions happening now.
+    reason: Reason,
+}
+
+impl Explanations {
+    /// Records that `output` was made for the e-node `function(inputs)`.
+    pub(crate) fn add_node(&mut self, function: Symbol, inputs: &[Value], output: Value) {
+        self.origins
+            .insert(output.bits, (function, inputs.to_vec()));
+        self.rows
+            .entry((function, inputs.to_vec()))
+            .or_insert((output, None));
+    }
+
+    /// Records that a new row of `function` was set to `output`.
+    pub(crate) fn add_row(&mut self, function: Symbol, inputs: &[Value], output: Value) {
+        let reason = self.reason.clone();
+        self.rows
+            .entry((function, inputs.to_vec()))
+            .or_insert((output, Some(reason)));
+    }
+
+    pub(crate) fn add_edge(&mut self, a: Id, b: Id) {
+        let edge = self.edges.len();
+        self.edges.push((a, b, self.reason.clone()));
+        self.adjacent.entry(a).or_default().push(edge);
+        self.adjacent.entry(b).or_default().push(edge);
+    }
+
+    pub(crate) fn set_reason(&mut self, reason: Reason) -> Reason {
+        std::mem::replace(&mut self.reason, reason)
+    }
+}
+
+/// Why two terms are equal, as a chain of equalities from [`Explanation::start`]
+/// to the term of the last step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    pub start: Term,
+    pub steps: Vec<ExplanationStep>,
+}
+
+/// A term equal to the one before it in an [`Explanation`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplanationStep {
+    pub term: Term,
+    pub justification: Justification,
+}
+
+/// Why a step of an [`Explanation`] is equal to the term before it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Justification {
+    /// A `union` or `set` at the top level of the program.
+    Given,
+    /// A rule fired, on a match that bound its variables to these terms.
+    Rule {
+        name: Symbol,
+        bindings: Vec<(Symbol, Term)>,
+    },
+    /// Both terms apply the same function, to inputs that are equal for the
+    /// reasons given, one for each input.
+    Congruence(Vec<Explanation>),
+    /// A rule fired while [`EGraph::batch_updates`] was set, which does not
+    /// record which rule it was.
+    Batched,
+}
+
+impl Explanation {
+    /// The same explanation, from the last term back to the first.
+    pub fn reverse(self) -> Explanation {
+        let mut terms: Vec<Term> = once(self.start)
+            .chain(self.steps.iter().map(|step| step.term.clone()))
+            .collect();
+        let start = terms.pop().unwrap();
+        let steps = self
+            .steps
+            .into_iter()
+            .zip(terms)
+            .rev()
+            .map(|(step, term)| ExplanationStep {
+                term,
+                justification: step.justification.reverse(),
+            })
+            .collect();
+        Explanation { start, steps }
+    }
+
+    /// The number of steps, including the steps explaining the inputs of
+    /// congruences.
+    pub fn size(&self) -> usize {
+        self.steps
+            .iter()
+            .map(|step| match &step.justification {
+                Justification::Congruence(inputs) => {
+                    1 + inputs.iter().map(Explanation::size).sum::<usize>()
+                }
+                _ => 1,
+            })
+            .sum()
+    }
+
+    /// Removes detours: whenever a term comes up twice in the chain, the
+    /// steps between the two are dropped, in the inputs of congruences too.
+    fn minimize(self) -> Explanation {
+        let mut steps: Vec<ExplanationStep> = vec![];
+        for mut step in self.steps {
+            if let Some(i) = steps.iter().position(|prev| prev.term == step.term) {
+                steps.truncate(i + 1);
+                continue;
+            }
+            if step.term == self.start {
+                steps.clear();
+                continue;
+            }
+            if let Justification::Congruence(inputs) = step.justification {
+                step.justification = Justification::Congruence(
+                    inputs.into_iter().map(Explanation::minimize).collect(),
+                );
+            }
+            steps.push(step);
+        }
+        Explanation {
+            start: self.start,
+            steps,
+        }
+    }
+
+    /// Shows the explanation with one step on each line, and the inputs of
+    /// congruences indented under them.
+    pub fn to_string(&self, termdag: &TermDag) -> String {
+        let mut out = String::new();
+        self.write(termdag, 0, &mut out);
+        out.pop();
+        out
+    }
+
+    fn write(&self, termdag: &TermDag, indent: usize, out: &mut String) {
+        let pad = " ".repeat(indent);
+        out.push_str(&format!("{pad}{}\n", termdag.to_string(&self.start)));
+        for step in &self.steps {
+            let term = termdag.to_string(&step.term);
+            match &step.justification {
+                Justification::Given => out.push_str(&format!("{pad}= {term} given\n")),
+                Justification::Batched => {
+                    out.push_str(&format!("{pad}= {term} by a batched rule\n"))
+                }
+                Justification::Rule { name, bindings } => {
+                    let bindings: Vec<String> = bindings
+                        .iter()
+                        .map(|(var, term)| format!("{var} = {}", termdag.to_string(term)))
+                        .collect();
+                    // rules without a name are named by their text, which can span lines
+                    let name = name.as_str().split_whitespace().collect::<Vec<_>>();
+                    out.push_str(&format!("{pad}= {term} by rule {}", name.join(" ")));
+                    if !bindings.is_empty() {
+                        out.push_str(&format!(" on {}", bindings.join(", ")));
+                    }
+                    out.push('\n');
+                }
+                Justification::Congruence(inputs) => {
+                    out.push_str(&format!("{pad}= {term} by congruence\n"));
+                    for input in inputs.iter().filter(|input| !input.steps.is_empty()) {
+                        input.write(termdag, indent + 4, out);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Justification {
+    fn reverse(self) -> Justification {
+        match self {
+            Justification::Congruence(inputs) => {
+                Justification::Congruence(inputs.into_iter().map(Explanation::reverse).collect())
+            }
+            justification => justification,
+        }
+    }
+}
+
+/// A term to explain, with the values of the parts that are not e-nodes
+/// already evaluated.
+pub(crate) enum Pattern {
+    Node(Symbol, Vec<Pattern>),
+    Value(ArcSort, Value),
+}
+
+impl EGraph {
+    /// Starts recording why e-classes are merged, so that they can be
+    /// explained with [`EGraph::explain`], the `explain` command, and
+    /// `extract`. Terms added before this are shown as extracted terms,
+    /// and their unions cannot be explained. Collecting garbage forgets
+    /// everything recorded so far.
+    pub fn enable_explanations(&mut self) {
+        self.unionfind.enable_explanations();
+    }
+
+    /// Explains why `a` and `b` are equal, as [`Command::Explain`] does.
+    ///
+    /// ```
+    /// # use egglog::{ast::Expr, EGraph};
+    /// let mut egraph = EGraph::default();
+    /// egraph.enable_explanations();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math))
+    ///          (rule ((= e (Add a b))) ((union e (Add b a))))
+    ///          (Add (Num 1) (Num 2))
+    ///          (run 1)",
+    ///     )
+    ///     .unwrap();
+    /// let num = |n| Expr::call_no_span("Num", [Expr::lit_no_span(n)]);
+    /// let (termdag, explanation) = egraph
+    ///     .explain(
+    ///         &Expr::call_no_span("Add", [num(1), num(2)]),
+    ///         &Expr::call_no_span("Add", [num(2), num(1)]),
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     explanation.to_string(&termdag),
+    ///     "(Add (Num 1) (Num 2))
+    /// = (Add (Num 2) (Num 1)) by rule (rule ((= e (Add a b))) ((union e (Add b a))) ) on a = (Num 1), b = (Num 2)"
+    /// );
+    /// ```
+    pub fn explain(&mut self, a: &Expr, b: &Expr) -> Result<(TermDag, Explanation), Error> {
+        self.run_program(vec![Command::Explain(
+            DUMMY_SPAN.clone(),
+            a.clone(),
+            b.clone(),
+        )])?;
+        Ok(self.recent_explanation.take().unwrap())
+    }
+
+    /// Evaluates the parts of `expr` that are not e-nodes.
+    pub(crate) fn explain_pattern(&mut self, expr: &ResolvedExpr) -> Result<Pattern, Error> {
+        match expr {
+            GenericExpr::Call(_, ResolvedCall::Func(func), args) if func.output.is_eq_sort() => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.explain_pattern(arg))
+                    .collect::<Result<_, _>>()?;
+                Ok(Pattern::Node(func.name, args))
+            }
+            _ => Ok(Pattern::Value(
+                expr.output_type(),
+                self.eval_resolved_expr(expr)?,
+            )),
+        }
+    }
+
+    /// Explains why the terms of `a` and `b`, of sort `sort`, are equal.
+    pub(crate) fn explain_patterns(
+        &self,
+        a: &Pattern,
+        b: &Pattern,
+        sort: &ArcSort,
+    ) -> Result<(TermDag, Explanation), String> {
+        let mut explainer = Explainer::new(self).ok_or("explanations are not enabled")?;
+        let (a_value, a_term, mut steps) = explainer.resolve(a)?;
+        let (b_value, b_term, b_steps) = explainer.resolve(b)?;
+        if self.find(sort, a_value) != self.find(sort, b_value) {
+            let termdag = &explainer.termdag;
+            return Err(format!(
+                "{} and {} are not equal",
+                termdag.to_string(&a_term),
+                termdag.to_string(&b_term)
+            ));
+        }
+        if sort.is_eq_sort() {
+            steps.extend(explainer.path(a_value.bits, b_value.bits, usize::MAX, sort)?);
+        }
+        let b_explanation = Explanation {
+            start: b_term,
+            steps: b_steps,
+        };
+        steps.extend(b_explanation.reverse().steps);
+        let explanation = Explanation {
+            start: a_term,
+            steps,
+        };
+        Ok((explainer.termdag, explanation.minimize()))
+    }
+
+    /// Explains why `value` is equal to the term `expr`, which was extracted
+    /// from it.
+    pub(crate) fn explain_extracted(
+        &mut self,
+        sort: &ArcSort,
+        value: Value,
+        expr: &Expr,
+    ) -> Result<String, Error> {
+        let expr =
+            self.type_info
+                .typecheck_expr(&mut self.symbol_gen, expr, &Default::default())?;
+        let pattern = self.explain_pattern(&expr)?;
+        let (termdag, explanation) = self
+            .explain_patterns(&Pattern::Value(sort.clone(), value), &pattern, sort)
+            .map_err(|message| Error::ExplainError(message, DUMMY_SPAN.clone()))?;
+        Ok(explanation.to_string(&termdag))
+    }
+}
+
+/// Builds [`Explanation`]s from the proof forest.
+struct Explainer<'a> {
+    egraph: &'a EGraph,
+    explanations: &'a Explanations,
+    extractor: Extractor<'a>,
+    termdag: TermDag,
+    /// The term each id stands for.
+    terms: HashMap<Id, Term>,
+}
+
+impl<'a> Explainer<'a> {
+    fn new(egraph: &'a EGraph) -> Option<Self> {
+        let explanations = egraph.unionfind.explanations()?;
+        let mut termdag = TermDag::default();
+        let extractor = Extractor::new(egraph, &mut termdag);
+        Some(Explainer {
+            egraph,
+            explanations,
+            extractor,
+            termdag,
+            terms: Default::default(),
+        })
+    }
+
+    /// The term that an id stands for: the e-node it was made for, or else
+    /// the term extracted from its e-class.
+    fn term(&mut self, id: Id, sort: &ArcSort) -> Term {
+        if let Some(term) = self.terms.get(&id) {
+            return term.clone();
+        }
+        let egraph = self.egraph;
+        let term = match self.explanations.origins.get(&id) {
+            Some((function, inputs)) => {
+                let schema = &egraph.functions[function].schema;
+                let children = schema
+                    .input
+                    .iter()
+                    .zip(inputs)
+                    .map(|(sort, value)| self.value_term(sort, *value))
+                    .collect();
+                self.termdag.app(*function, children)
+            }
+            None => self.extract(sort, id_value(sort, id)),
+        };
+        self.terms.insert(id, term.clone());
+        term
+    }
+
+    fn value_term(&mut self, sort: &ArcSort, value: Value) -> Term {
+        if sort.is_eq_sort() {
+            self.term(value.bits, sort)
+        } else {
+            self.extract(sort, value)
+        }
+    }
+
+    fn extract(&mut self, sort: &ArcSort, value: Value) -> Term {
+        match self.extractor.find_best(value, &mut self.termdag, sort) {
+            Some((_cost, term)) => term,
+            None => self.termdag.var(format!("#{}", value.bits).into()),
+        }
+    }
+
+    /// Finds the value of the term `pattern`, returning it with the term
+    /// and the steps from the term to the one its value stands for.
+    fn resolve(
+        &mut self,
+        pattern: &Pattern,
+    ) -> Result<(Value, Term, Vec<ExplanationStep>), String> {
+        let (function, args) = match pattern {
+            Pattern::Node(function, args) => (*function, args),
+            Pattern::Value(sort, value) => {
+                let term = self.value_term(sort, *value);
+                return Ok((*value, term, vec![]));
+            }
+        };
+        let (egraph, explanations) = (self.egraph, self.explanations);
+        let schema = &egraph.functions[&function].schema;
+        let (mut inputs, mut written, mut input_steps) = (vec![], vec![], vec![]);
+        for arg in args {
+            let (value, term, steps) = self.resolve(arg)?;
+            inputs.push(value);
+            written.push(term);
+            input_steps.push(steps);
+        }
+        let term = self.termdag.app(function, written.clone());
+
+        // the row for these inputs, or else one with equal inputs
+        let key = (function, inputs);
+        let row =
+            explanations.rows.get_key_value(&key).or_else(|| {
+                explanations
+                    .rows
+                    .iter()
+                    .find(|((row_function, row_inputs), _)| {
+                        *row_function == function
+                            && schema.input.iter().zip(row_inputs.iter().zip(&key.1)).all(
+                                |(sort, (a, b))| egraph.find(sort, *a) == egraph.find(sort, *b),
+                            )
+                    })
+            });
+        let Some(((_, row_inputs), (output, reason))) = row else {
+            return Err(format!(
+                "{} is not in the e-graph",
+                self.termdag.to_string(&term)
+            ));
+        };
+
+        let mut steps = vec![];
+        if *row_inputs != key.1 || input_steps.iter().any(|steps| !steps.is_empty()) {
+            let (mut explanations, mut row_terms) = (vec![], vec![]);
+            for (((sort, input), row_input), (start, mut steps)) in schema
+                .input
+                .iter()
+                .zip(&key.1)
+                .zip(row_inputs)
+                .zip(written.into_iter().zip(input_steps))
+            {
+                if sort.is_eq_sort() {
+                    steps.extend(self.path(input.bits, row_input.bits, usize::MAX, sort)?);
+                }
+                explanations.push(Explanation { start, steps });
+                row_terms.push(self.value_term(sort, *row_input));
+            }
+            steps.push(ExplanationStep {
+                term: self.termdag.app(function, row_terms),
+                justification: Justification::Congruence(explanations),
+            });
+        }
+        if let Some(reason) = reason {
+            steps.push(ExplanationStep {
+                term: self.value_term(&schema.output, *output),
+                justification: self.justify(reason),
+            });
+        }
+        Ok((*output, term, steps))
+    }
+
+    /// The steps along the path from `a` to `b` in the proof forest, using
+    /// only the edges older than `before`.
+    fn path(
+        &mut self,
+        a: Id,
+        b: Id,
+        before: usize,
+        sort: &ArcSort,
+    ) -> Result<Vec<ExplanationStep>, String> {
+        let Some(path) = self.path_edges(a, b, before) else {
+            return Err(format!(
+                "the union of {} and {} was not recorded",
+                self.egraph.extract_value_to_string(sort, id_value(sort, a)),
+                self.egraph.extract_value_to_string(sort, id_value(sort, b)),
+            ));
+        };
+        let mut steps = vec![];
+        for (edge, from, to) in path {
+            match &self.explanations.edges[edge].2 {
+                Reason::Congruence(function) => {
+                    steps.extend(self.congruence(edge, *function, from, to, sort)?)
+                }
+                reason => steps.push(ExplanationStep {
+                    term: self.term(to, sort),
+                    justification: self.justify(reason),
+                }),
+            }
+        }
+        Ok(steps)
+    }
+
+    /// The edges along the path from `a` to `b` in the proof forest, as
+    /// indices into the edges with the ids they go from and to, using only
+    /// the edges older than `before`.
+    fn path_edges(&self, a: Id, b: Id, before: usize) -> Option<Vec<(usize, Id, Id)>> {
+        let explanations = self.explanations;
+        let mut parents: HashMap<Id, (usize, Id)> = HashMap::default();
+        let mut todo = vec![a];
+        while let Some(id) = todo.pop() {
+            if id == b {
+                break;
+            }
+            for &edge in explanations.adjacent.get(&id).into_iter().flatten() {
+                let (x, y, _) = &explanations.edges[edge];
+                let next = if *x == id { *y } else { *x };
+                if edge < before && next != a && !parents.contains_key(&next) {
+                    parents.insert(next, (edge, id));
+                    todo.push(next);
+                }
+            }
+        }
+
+        let mut path = vec![];
+        let mut id = b;
+        while id != a {
+            let &(edge, from) = parents.get(&id)?;
+            path.push((edge, from, id));
+            id = from;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The number of edges between `a` and `b` in the proof forest, using
+    /// only the edges older than `before`.
+    fn distance(&self, a: Id, b: Id, before: usize) -> usize {
+        self.path_edges(a, b, before)
+            .map_or(usize::MAX, |path| path.len())
+    }
+
+    /// Explains the union of `from` and `to` that rebuilding `function`
+    /// made, by finding e-nodes of `function` on either side whose inputs
+    /// were equal before the union. Of those, the pair with the fewest edges
+    /// to explain at the top level is used.
+    fn congruence(
+        &mut self,
+        edge: usize,
+        function: Symbol,
+        from: Id,
+        to: Id,
+        sort: &ArcSort,
+    ) -> Result<Vec<ExplanationStep>, String> {
+        let (egraph, explanations) = (self.egraph, self.explanations);
+        let mut classes = UnionFind::default();
+        for _ in 0..egraph.unionfind.num_ids() {
+            classes.make_set();
+        }
+        for (a, b, _) in &explanations.edges[..edge] {
+            classes.union_raw(*a, *b);
+        }
+        let classes = &classes;
+
+        let nodes = move |class: Id| {
+            explanations
+                .origins
+                .iter()
+                .filter(move |(id, (f, _))| *f == function && classes.find(**id) == class)
+        };
+        let input_sorts = &egraph.functions[&function].schema.input;
+        let equal = |a: &[Value], b: &[Value]| {
+            input_sorts
+                .iter()
+                .zip(a.iter().zip(b))
+                .all(|(sort, (a, b))| {
+                    if sort.is_eq_sort() {
+                        classes.find(a.bits) == classes.find(b.bits)
+                    } else {
+                        a == b
+                    }
+                })
+        };
+        let cost = |u: Id, u_inputs: &[Value], v: Id, v_inputs: &[Value]| {
+            let inputs = input_sorts
+                .iter()
+                .zip(u_inputs.iter().zip(v_inputs))
+                .filter(|(sort, _)| sort.is_eq_sort())
+                .map(|(_, (a, b))| self.distance(a.bits, b.bits, edge));
+            once(self.distance(from, u, edge))
+                .chain(once(self.distance(v, to, edge)))
+                .chain(inputs)
+                .fold(0, usize::saturating_add)
+        };
+        let pair = nodes(classes.find(from))
+            .flat_map(|(u, (_, u_inputs))| {
+                nodes(classes.find(to))
+                    .filter(|(_, (_, v_inputs))| equal(u_inputs, v_inputs))
+                    .map(move |(v, (_, v_inputs))| (*u, u_inputs, *v, v_inputs))
+            })
+            .min_by_key(|(u, u_inputs, v, v_inputs)| cost(*u, u_inputs, *v, v_inputs));
+        let Some((u, u_inputs, v, v_inputs)) = pair else {
+            // the rows were not made for e-nodes, so their inputs are unknown
+            return Ok(vec![ExplanationStep {
+                term: self.term(to, sort),
+                justification: Justification::Congruence(vec![]),
+            }]);
+        };
+
+        let mut steps = self.path(from, u, edge, sort)?;
+        let mut inputs = vec![];
+        for (input_sort, (a, b)) in input_sorts.iter().zip(u_inputs.iter().zip(v_inputs)) {
+            let start = self.value_term(input_sort, *a);
+            let steps = if input_sort.is_eq_sort() {
+                self.path(a.bits, b.bits, edge, input_sort)?
+            } else {
+                vec![]
+            };
+            inputs.push(Explanation { start, steps });
+        }
+        steps.push(ExplanationStep {
+            term: self.term(v, sort),
+            justification: Justification::Congruence(inputs),
+        });
+        steps.extend(self.path(v, to, edge, sort)?);
+        Ok(steps)
+    }
+
+    fn justify(&mut self, reason: &Reason) -> Justification {
+        match reason {
+            Reason::Given => Justification::Given,
+            Reason::Batched => Justification::Batched,
+            Reason::Congruence(_) => Justification::Congruence(vec![]),
+            Reason::Rule(name, values) => {
+                let egraph = self.egraph;
+                let rule = egraph.rulesets.values().find_map(|ruleset| match ruleset {
+                    Ruleset::Rules(_, rules) => rules.get(name),
+                    Ruleset::Combined(..) => None,
+                });
+                let bindings = rule
+                    .into_iter()
+                    .flat_map(|rule| rule.vars.iter().zip(values))
+                    .filter(|(var, _)| !egraph.symbol_gen.is_fresh(var.name))
+                    .map(|(var, value)| (var.name, self.value_term(&var.sort, *value)))
+                    .collect();
+                Justification::Rule {
+                    name: *name,
+                    bindings,
+                }
+            }
+        }
+    }
+}
+
+fn id_value(sort: &ArcSort, id: Id) -> Value {
+    Value {
+        #[cfg(debug_assertions)]
+        tag: sort.name(),
+        bits: id,
+    }
+}
@@ -0,0 +1,89 @@
+//! Costs of e-nodes written in egglog with `set-cost`.
+
+use super::*;
+use crate::ast::ResolvedExpr;
+use crate::core::ResolvedCall;
+use crate::ResolvedVar;
+
+/// The variable that stands for `(cost arg)` in a typechecked `set-cost`.
+/// Its name is not an identifier, so it cannot clash with a variable of the
+/// program, and it prints as the call it replaces.
+pub(crate) fn cost_var(arg: Symbol) -> Symbol {
+    format!("(cost {arg})").into()
+}
+
+/// The cost of the e-nodes of a constructor, as set by `set-cost`.
+#[derive(Clone, Debug)]
+pub(crate) struct CostExpr {
+    /// The variables bound to the children of an e-node.
+    args: Vec<Symbol>,
+    /// The variables bound to the costs of the children.
+    cost_vars: Vec<Symbol>,
+    expr: ResolvedExpr,
+}
+
+impl CostExpr {
+    pub(crate) fn new(args: &[ResolvedVar], expr: ResolvedExpr) -> Self {
+        CostExpr {
+            args: args.iter().map(|arg| arg.name).collect(),
+            cost_vars: args.iter().map(|arg| cost_var(arg.name)).collect(),
+            expr,
+        }
+    }
+
+    /// Evaluates the cost of an e-node with `children`, whose cheapest terms
+    /// cost `child_costs`.
+    pub(crate) fn eval(&self, egraph: &EGraph, children: &[Value], child_costs: &[Cost]) -> Cost {
+        let mut bindings = HashMap::default();
+        for (i, (child, cost)) in children.iter().zip(child_costs).enumerate() {
+            let cost = i64::try_from(*cost).unwrap_or(i64::MAX);
+            bindings.insert(self.args[i], *child);
+            bindings.insert(self.cost_vars[i], Value::from(cost));
+        }
+        match eval(egraph, &self.expr, &bindings) {
+            Some(cost) => (cost.bits as i64).max(0) as Cost,
+            None => Cost::MAX,
+        }
+    }
+
+    /// Whether the cost looks up functions, so that it can change without
+    /// the children of the e-node changing.
+    pub(crate) fn reads_functions(&self) -> bool {
+        reads_functions(&self.expr)
+    }
+}
+
+fn reads_functions(expr: &ResolvedExpr) -> bool {
+    match expr {
+        ResolvedExpr::Lit(..) | ResolvedExpr::Var(..) => false,
+        ResolvedExpr::Call(_, call, args) => {
+            matches!(call, ResolvedCall::Func(_)) || args.iter().any(reads_functions)
+        }
+    }
+}
+
+fn eval(egraph: &EGraph, expr: &ResolvedExpr, bindings: &HashMap<Symbol, Value>) -> Option<Value> {
+    match expr {
+        ResolvedExpr::Lit(_, lit) => Some(egraph.eval_lit(lit)),
+        ResolvedExpr::Var(_, var) => bindings.get(&var.name).copied(),
+        ResolvedExpr::Call(_, call, args) => {
+            let values = args
+                .iter()
+                .map(|arg| eval(egraph, arg, bindings))
+                .collect::<Option<Vec<_>>>()?;
+            match call {
+                ResolvedCall::Primitive(p) => {
+                    p.primitive.apply(&values, (&p.input, &p.output), None)
+                }
+                ResolvedCall::Func(func) => {
+                    let values: Vec<Value> = values
+                        .iter()
+                        .zip(&func.input)
+                        .map(|(value, sort)| egraph.find(sort, *value))
+                        .collect();
+                    egraph.functions.get(&func.name)?.get(&values)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Extraction of the term with the cheapest DAG, in which each distinct
+//! subterm is paid for once, for `(extract e :mode dag)`.
+//!
+//! Finding the cheapest DAG is NP-hard, so [`DagExtractor`] starts from the
+//! e-nodes the tree-cost [`Extractor`] would pick and improves on them with a
+//! branch-and-bound search over the e-nodes of the e-classes below the root.
+//! The search tries the e-nodes of each e-class from the cheapest tree down
+//! and prunes a partial choice once its cost plus the cheapest e-node of each
+//! e-class it still needs is no better than the best choice found. The result
+//! is exact when the search finishes within [`SEARCH_BUDGET`] steps, and is
+//! the best choice found so far otherwise.
+
+use super::*;
+use std::collections::BTreeMap;
+
+/// The number of partial choices the search visits before it settles for
+/// the best choice found so far.
+const SEARCH_BUDGET: usize = 100_000;
+/// The number of e-classes a partial choice may have before the search
+/// stops extending it, which bounds the depth of the recursion.
+const MAX_DEPTH: usize = 1_000;
+
+struct DagNode<'a> {
+    sym: Symbol,
+    func: &'a Function,
+    inputs: &'a [Value],
+    /// The cost of the e-node and of its children that are not e-classes,
+    /// which are not shared, as if the children that are e-classes cost
+    /// nothing.
+    cost: Cost,
+    /// The e-classes of the children that are e-classes, in order.
+    children: Vec<Id>,
+}
+
+/// The index of the e-node chosen for each e-class the root reaches.
+type Choice = BTreeMap<Id, usize>;
+
+pub(crate) struct DagExtractor<'a> {
+    tree: Extractor<'a>,
+    /// The e-nodes of each e-class, cheapest tree first.
+    classes: HashMap<Id, Vec<DagNode<'a>>>,
+}
+
+impl<'a> DagExtractor<'a> {
+    pub(crate) fn new(egraph: &'a EGraph, termdag: &mut TermDag) -> Self {
+        let tree = Extractor::new(egraph, termdag);
+        let mut classes: HashMap<Id, Vec<DagNode>> = HashMap::default();
+        let mut scratch = TermDag::default();
+        for &sym in &tree.ctors {
+            let func = &egraph.functions[&sym];
+            if !func.schema.output.is_eq_sort() {
+                continue;
+            }
+            'nodes: for (inputs, output) in func.nodes.iter(false) {
+                let mut children = vec![];
+                let mut child_costs = vec![];
+                for (value, sort) in inputs.iter().zip(&func.schema.input) {
+                    if sort.is_eq_sort() {
+                        children.push(egraph.find(sort, *value).bits);
+                        child_costs.push(0);
+                    } else if let Some((child_cost, _)) = tree.find_best(*value, &mut scratch, sort)
+                    {
+                        child_costs.push(child_cost);
+                    } else {
+                        continue 'nodes;
+                    }
+                }
+                let cost = DefaultCost.cost(egraph, func, inputs, &child_costs);
+                let class = egraph.find(&func.schema.output, output.value).bits;
+                classes.entry(class).or_default().push(DagNode {
+                    sym,
+                    func,
+                    inputs,
+                    cost,
+                    children,
+                });
+            }
+        }
+
+        let tree_cost = |node: &DagNode| {
+            node.children.iter().fold(node.cost, |cost, child| {
+                let child_cost = tree.costs.get(child).map_or(Cost::MAX, |(c, _)| *c);
+                cost.saturating_add(child_cost)
+            })
+        };
+        for nodes in classes.values_mut() {
+            nodes.sort_by_cached_key(tree_cost);
+        }
+        DagExtractor { tree, classes }
+    }
+
+    pub(crate) fn find_best(
+        &self,
+        value: Value,
+        termdag: &mut TermDag,
+        sort: &ArcSort,
+    ) -> Option<(Cost, Term)> {
+        if !sort.is_eq_sort() {
+            return self.tree.find_best(value, termdag, sort);
+        }
+        let root = self.tree.egraph.find(sort, value).bits;
+        let mut search = Search {
+            extractor: self,
+            choice: Choice::default(),
+            cost: 0,
+            best: self.cheapest_trees(root),
+            budget: SEARCH_BUDGET,
+        };
+        search.branch(vec![root], 0);
+        let (cost, choice) = search.best?;
+        let term = self.build(root, &choice, termdag, &mut HashMap::default());
+        Some((cost, term))
+    }
+
+    /// The choice of the cheapest tree for each e-class, if it is acyclic,
+    /// which it is unless some e-nodes cost nothing.
+    fn cheapest_trees(&self, root: Id) -> Option<(Cost, Choice)> {
+        let mut choice = Choice::default();
+        let mut cost: Cost = 0;
+        let mut todo = vec![root];
+        while let Some(class) = todo.pop() {
+            if choice.contains_key(&class) {
+                continue;
+            }
+            let node = self.classes.get(&class)?.first()?;
+            choice.insert(class, 0);
+            cost = cost.saturating_add(node.cost);
+            todo.extend(&node.children);
+        }
+        self.is_acyclic(&choice).then_some((cost, choice))
+    }
+
+    fn is_acyclic(&self, choice: &Choice) -> bool {
+        let children = |class: &Id| &self.classes[class][choice[class]].children;
+        let mut parents: HashMap<Id, usize> = choice.keys().map(|class| (*class, 0)).collect();
+        for class in choice.keys() {
+            for child in children(class) {
+                *parents.get_mut(child).unwrap() += 1;
+            }
+        }
+        let mut todo: Vec<Id> = parents
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .map(|(class, _)| *class)
+            .collect();
+        let mut seen = 0;
+        while let Some(class) = todo.pop() {
+            seen += 1;
+            for child in children(&class) {
+                let n = parents.get_mut(child).unwrap();
+                *n -= 1;
+                if *n == 0 {
+                    todo.push(*child);
+                }
+            }
+        }
+        seen == choice.len()
+    }
+
+    fn build(
+        &self,
+        class: Id,
+        choice: &Choice,
+        termdag: &mut TermDag,
+        built: &mut HashMap<Id, Term>,
+    ) -> Term {
+        if let Some(term) = built.get(&class) {
+            return term.clone();
+        }
+        let node = &self.classes[&class][choice[&class]];
+        let mut eq_children = node.children.iter();
+        let mut children = vec![];
+        for (value, sort) in node.inputs.iter().zip(&node.func.schema.input) {
+            let child = if sort.is_eq_sort() {
+                let child = *eq_children.next().unwrap();
+                self.build(child, choice, termdag, built)
+            } else {
+                self.tree.find_best(*value, termdag, sort).unwrap().1
+            };
+            children.push(child);
+        }
+        let term = termdag.app(node.sym, children);
+        built.insert(class, term.clone());
+        term
+    }
+
+    /// The cheapest an e-class can be, which is what the search expects to
+    /// pay for each e-class it still needs.
+    fn min_cost(&self, class: &Id) -> Cost {
+        self.classes
+            .get(class)
+            .and_then(|nodes| nodes.iter().map(|node| node.cost).min())
+            .unwrap_or(Cost::MAX)
+    }
+}
+
+struct Search<'b, 'a> {
+    extractor: &'b DagExtractor<'a>,
+    /// The e-nodes chosen so far.
+    choice: Choice,
+    /// The total cost of the e-nodes chosen so far.
+    cost: Cost,
+    best: Option<(Cost, Choice)>,
+    budget: usize,
+}
+
+impl Search<'_, '_> {
+    /// Extends the partial choice with an e-node for each e-class in
+    /// `pending`, which holds the distinct e-classes the choice needs but
+    /// has no e-node for.
+    fn branch(&mut self, mut pending: Vec<Id>, depth: usize) {
+        if self.budget == 0 || depth > MAX_DEPTH {
+            return;
+        }
+        self.budget -= 1;
+        let bound = pending.iter().fold(self.cost, |bound, class| {
+            bound.saturating_add(self.extractor.min_cost(class))
+        });
+        if matches!(&self.best, Some((best, _)) if bound >= *best) {
+            return;
+        }
+        let Some(class) = pending.pop() else {
+            self.best = Some((self.cost, self.choice.clone()));
+            return;
+        };
+        let Some(nodes) = self.extractor.classes.get(&class) else {
+            return;
+        };
+        for (i, node) in nodes.iter().enumerate() {
+            if node
+                .children
+                .iter()
+                .any(|child| self.reaches(*child, class))
+            {
+                continue;
+            }
+            let mut next = pending.clone();
+            for child in &node.children {
+                if !self.choice.contains_key(child) && !next.contains(child) {
+                    next.push(*child);
+                }
+            }
+            let cost = self.cost;
+            self.choice.insert(class, i);
+            self.cost = cost.saturating_add(node.cost);
+            self.branch(next, depth + 1);
+            self.choice.remove(&class);
+            self.cost = cost;
+        }
+    }
+
+    /// Whether the e-nodes chosen so far lead from `from` to `to`, in which
+    /// case choosing an e-node of `to` with child `from` makes a cycle.
+    fn reaches(&self, from: Id, to: Id) -> bool {
+        let mut seen = HashSet::default();
+        let mut todo = vec![from];
+        while let Some(class) = todo.pop() {
+            if class == to {
+                return true;
+            }
+            if let Some(&i) = self.choice.get(&class) {
+                if seen.insert(class) {
+                    todo.extend(&self.extractor.classes[&class][i].children);
+                }
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,187 @@
+//! Extraction that reuses the costs found by the previous `extract` command,
+//! for e-graphs that have only grown since.
+//!
+//! [`ExtractionCache`] keeps the cheapest term of each e-class, and the
+//! e-nodes that have each e-class as a child. The next extraction merges the
+//! entries of the e-classes unioned since, computes the costs of the e-nodes
+//! added since, and recomputes the parents of the e-classes that got cheaper
+//! until none do. Unions and new e-nodes only make e-classes cheaper, so this
+//! finds the same costs as extracting from scratch. Deleting or subsuming
+//! e-nodes, collecting garbage, and `set-cost` can make e-classes more
+//! expensive, so they drop the cache.
+
+use super::*;
+
+#[derive(Clone, Default)]
+pub(crate) struct ExtractionCache {
+    /// The e-nodes from this timestamp on may be new since the costs were
+    /// computed.
+    timestamp: u32,
+    costs: HashMap<Id, (Cost, Term)>,
+    /// The e-nodes of each e-class's parents, by function and inputs.
+    parents: HashMap<Id, HashSet<(Symbol, Vec<Value>)>>,
+    /// Holds the terms in `costs`.
+    termdag: TermDag,
+}
+
+impl EGraph {
+    /// Extracts the cheapest term of an e-class like [`EGraph::extract`],
+    /// reusing the costs computed by the previous call when the e-graph has
+    /// only grown since. This is what the `extract` command does.
+    /// ```
+    /// use egglog::{ast::Expr, EGraph, TermDag};
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math))
+    ///          (let x (Add (Num 1) (Add (Num 2) (Num 3))))",
+    ///     )
+    ///     .unwrap();
+    /// let (sort, value) = egraph.eval_expr(&Expr::var_no_span("x")).unwrap();
+    /// let mut termdag = TermDag::default();
+    /// let (cost, _) = egraph.extract_incremental(value, &mut termdag, &sort);
+    /// assert_eq!(cost, 8);
+    /// egraph
+    ///     .parse_and_run_program(None, "(union (Add (Num 2) (Num 3)) (Num 5))")
+    ///     .unwrap();
+    /// let (cost, term) = egraph.extract_incremental(value, &mut termdag, &sort);
+    /// assert_eq!(cost, 5);
+    /// assert_eq!(termdag.to_string(&term), "(Add (Num 1) (Num 5))");
+    /// ```
+    pub fn extract_incremental(
+        &mut self,
+        value: Value,
+        termdag: &mut TermDag,
+        arcsort: &ArcSort,
+    ) -> (Cost, Term) {
+        if !self.extracts_incrementally() {
+            self.extraction_cache = None;
+            return self.extract(value, termdag, arcsort);
+        }
+        let mut cache = self.extraction_cache.take().unwrap_or_default();
+        cache.update(self);
+        let extractor = Extractor::with_costs(self, std::mem::take(&mut cache.costs));
+        let best = extractor.find_best(value, &mut cache.termdag, arcsort);
+        cache.costs = extractor.costs;
+        let (cost, term) = best.unwrap_or_else(|| panic!("No cost for {:?}", value));
+        let term = termdag.expr_to_term(&cache.termdag.term_to_expr(&term));
+        self.extraction_cache = Some(cache);
+        (cost, term)
+    }
+
+    /// Whether the cost of every e-node only depends on the e-classes of its
+    /// children, which is what [`ExtractionCache`] tracks. Children in
+    /// containers and `set-cost`s that look up functions depend on more.
+    fn extracts_incrementally(&self) -> bool {
+        self.functions
+            .values()
+            .filter(|func| !func.decl.unextractable && func.schema.output.is_eq_sort())
+            .all(|func| {
+                !func
+                    .schema
+                    .input
+                    .iter()
+                    .any(|sort| sort.is_eq_container_sort())
+                    && !func
+                        .cost
+                        .as_ref()
+                        .is_some_and(|cost| cost.reads_functions())
+            })
+    }
+}
+
+impl ExtractionCache {
+    /// Brings the costs up to date with the unions and e-nodes added since
+    /// they were computed.
+    fn update(&mut self, egraph: &EGraph) {
+        let mut dirty = vec![];
+        let mut costs: HashMap<Id, (Cost, Term)> = HashMap::default();
+        for (id, (cost, term)) in std::mem::take(&mut self.costs) {
+            let class = egraph.unionfind.find(id);
+            if class != id {
+                dirty.push(class);
+            }
+            match costs.entry(class) {
+                HEntry::Vacant(e) => {
+                    e.insert((cost, term));
+                }
+                HEntry::Occupied(mut e) => {
+                    if cost < e.get().0 {
+                        e.insert((cost, term));
+                    }
+                }
+            }
+        }
+        let mut parents: HashMap<Id, HashSet<(Symbol, Vec<Value>)>> = HashMap::default();
+        for (id, nodes) in std::mem::take(&mut self.parents) {
+            parents
+                .entry(egraph.unionfind.find(id))
+                .or_default()
+                .extend(nodes);
+        }
+
+        let mut extractor = Extractor::with_costs(egraph, costs);
+        let range = self.timestamp..u32::MAX;
+        for (&sym, func) in &egraph.functions {
+            if func.decl.unextractable || !func.schema.output.is_eq_sort() {
+                continue;
+            }
+            for (_, inputs, output) in func.nodes.iter_timestamp_range(&range, false) {
+                for (value, sort) in inputs.iter().zip(&func.schema.input) {
+                    if sort.is_eq_sort() {
+                        parents
+                            .entry(egraph.find(sort, *value).bits)
+                            .or_default()
+                            .insert((sym, inputs.to_vec()));
+                    }
+                }
+                let node = (sym, func, inputs, output.value);
+                self.visit(&mut extractor, node, &mut dirty);
+            }
+        }
+
+        while let Some(class) = dirty.pop() {
+            let Some(nodes) = parents.get(&class) else {
+                continue;
+            };
+            for (sym, inputs) in nodes {
+                let func = &egraph.functions[sym];
+                // rows canonicalized since are in the set under their new
+                // inputs
+                if let Some(output) = func.nodes.get(inputs).filter(|o| !o.subsumed) {
+                    let node = (*sym, func, &inputs[..], output.value);
+                    self.visit(&mut extractor, node, &mut dirty);
+                }
+            }
+        }
+
+        self.costs = extractor.costs;
+        self.parents = parents;
+        self.timestamp = egraph.timestamp;
+    }
+
+    /// Gives an e-node's e-class the e-node's term if it is cheaper, and
+    /// marks the e-class dirty if so.
+    fn visit(
+        &mut self,
+        extractor: &mut Extractor,
+        (sym, func, inputs, output): (Symbol, &Function, &[Value], Value),
+        dirty: &mut Vec<Id>,
+    ) {
+        let Some((children, cost)) = extractor.node_total_cost(func, inputs, &mut self.termdag)
+        else {
+            return;
+        };
+        let class = extractor.egraph.find(&func.schema.output, output).bits;
+        if extractor
+            .costs
+            .get(&class)
+            .map_or(true, |(old, _)| cost < *old)
+        {
+            let term = self.termdag.app(sym, children);
+            extractor.costs.insert(class, (cost, term));
+            dirty.push(class);
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use crate::ast::{ExtractMode, Symbol};
+use crate::termdag::{Term, TermDag};
+use crate::util::{HashMap, HashSet};
+use crate::{ArcSort, EGraph, Function, HEntry, Id, Value};
+use dag::DagExtractor;
+use rayon::prelude::*;
+
+mod cost;
+mod dag;
+mod incremental;
+mod variants;
+
+pub(crate) use cost::{cost_var, CostExpr};
+pub(crate) use incremental::ExtractionCache;
+
+pub type Cost = usize;
+
+/// A cost model for extraction, for [`EGraph::extract_with_cost_function`].
+pub trait CostFunction: Send + Sync {
+    /// The cost of an e-node of `function` with children `children`, whose
+    /// cheapest terms cost `child_costs`. The e-graph has the values of the
+    /// children in other functions, such as analyses, with
+    /// [`Function::get`]. Extraction finds the cheapest terms when the cost
+    /// of an e-node does not go down as the costs of its children go up.
+    fn cost(
+        &self,
+        egraph: &EGraph,
+        function: &Function,
+        children: &[Value],
+        child_costs: &[Cost],
+    ) -> Cost;
+}
+
+/// The cost model of [`EGraph::extract`], where an e-node costs the `:cost`
+/// of its function, 1 by default, plus the costs of its children, unless
+/// the cost of the function was set with `set-cost`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultCost;
+
+impl CostFunction for DefaultCost {
+    fn cost(
+        &self,
+        egraph: &EGraph,
+        function: &Function,
+        children: &[Value],
+        child_costs: &[Cost],
+    ) -> Cost {
+        if let Some(cost) = &function.cost {
+            return cost.eval(egraph, children, child_costs);
+        }
+        child_costs
+            .iter()
+            .fold(function.decl.cost.unwrap_or(1), |cost, child| {
+                cost.saturating_add(*child)
+            })
+    }
+}
+
+/// The cost model of `(extract e :mode size)`, where a term costs its number
+/// of nodes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AstSize;
+
+impl CostFunction for AstSize {
+    fn cost(&self, _: &EGraph, _: &Function, _: &[Value], child_costs: &[Cost]) -> Cost {
+        child_costs
+            .iter()
+            .fold(1, |cost: Cost, child| cost.saturating_add(*child))
+    }
+}
+
+/// The cost model of `(extract e :mode depth)`, where a term costs its depth.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AstDepth;
+
+impl CostFunction for AstDepth {
+    fn cost(&self, _: &EGraph, _: &Function, _: &[Value], child_costs: &[Cost]) -> Cost {
+        child_costs
+            .iter()
+            .max()
+            .map_or(1, |child| child.saturating_add(1))
+    }
+}
+
+pub struct Extractor<'a> {
+    pub costs: HashMap<Id, (Cost, Term)>,
+    ctors: Vec<Symbol>,
+    egraph: &'a EGraph,
+    cost_function: &'a dyn CostFunction,
+}
+
+impl EGraph {
+    /// This example uses [`EGraph::extract`] to extract a term. The example is
+    /// trivial, as there is only a single variant of the expression in the
+    /// egraph.
+    /// ```
+    /// use egglog::{EGraph, TermDag};
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Op (Add i64 i64))
+    ///          (let expr (Add 1 1))",
+    ///     )
+    ///     .unwrap();
+    /// let mut termdag = TermDag::default();
+    /// let (sort, value) = egraph
+    ///     .eval_expr(&egglog::ast::Expr::var_no_span("expr"))
+    ///     .unwrap();
+    /// let (_, extracted) = egraph.extract(value, &mut termdag, &sort);
+    /// assert_eq!(termdag.to_string(&extracted), "(Add 1 1)");
+    /// ```
+    pub fn extract(&self, value: Value, termdag: &mut TermDag, arcsort: &ArcSort) -> (Cost, Term) {
+        let extractor = Extractor::new(self, termdag);
+        extractor
+            .find_best(value, termdag, arcsort)
+            .unwrap_or_else(|| {
+                log::error!("No cost for {:?}", value);
+                for func in self.functions.values() {
+                    for (inputs, output) in func.nodes.iter(false) {
+                        if output.value == value {
+                            log::error!("Found unextractable function: {:?}", func.decl.name);
+                            log::error!("Inputs: {:?}", inputs);
+
+                            assert_eq!(inputs.len(), func.schema.input.len());
+                            log::error!(
+                                "{:?}",
+                                inputs
+                                    .iter()
+                                    .zip(&func.schema.input)
+                                    .map(|(input, sort)| extractor
+                                        .costs
+                                        .get(&extractor.egraph.find(sort, *input).bits))
+                                    .collect::<Vec<_>>()
+                            );
+                        }
+                    }
+                }
+
+                panic!("No cost for {:?}", value)
+            })
+    }
+
+    /// Extracts the cheapest term of an e-class like [`EGraph::extract`], with
+    /// the costs of e-nodes given by `cost_function` instead of the `:cost`s
+    /// of their functions.
+    /// ```
+    /// use egglog::{ast::{Expr, Symbol}, Cost, CostFunction, EGraph, Function, TermDag, Value};
+    /// /// Shifting is cheap, but only by small amounts
+    /// struct ShiftCost;
+    ///
+    /// impl CostFunction for ShiftCost {
+    ///     fn cost(
+    ///         &self,
+    ///         egraph: &EGraph,
+    ///         function: &Function,
+    ///         children: &[Value],
+    ///         child_costs: &[Cost],
+    ///     ) -> Cost {
+    ///         let small = &egraph.functions[&Symbol::from("small")];
+    ///         let own = match function.name().as_str() {
+    ///             "Shl" if small.get(&children[1..]).is_some() => 1,
+    ///             "Shl" | "Mul" => 10,
+    ///             _ => 1,
+    ///         };
+    ///         own + child_costs.iter().sum::<Cost>()
+    ///     }
+    /// }
+    ///
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Var String) (Mul Math Math) (Shl Math Math))
+    ///          (relation small (Math))
+    ///          (small (Num 1))
+    ///          (let x (Mul (Var \"x\") (Num 2)))
+    ///          (union x (Shl (Var \"x\") (Num 1)))
+    ///          (let y (Mul (Var \"y\") (Num 1024)))
+    ///          (union y (Shl (Var \"y\") (Num 10)))",
+    ///     )
+    ///     .unwrap();
+    /// let mut termdag = TermDag::default();
+    /// let mut extract = |var| {
+    ///     let (sort, value) = egraph.eval_expr(&Expr::var_no_span(var)).unwrap();
+    ///     let (_, term) = egraph.extract_with_cost_function(value, &mut termdag, &sort, &ShiftCost);
+    ///     termdag.to_string(&term)
+    /// };
+    /// assert_eq!(extract("x"), "(Shl (Var \"x\") (Num 1))");
+    /// assert_eq!(extract("y"), "(Mul (Var \"y\") (Num 1024))");
+    /// ```
+    pub fn extract_with_cost_function(
+        &self,
+        value: Value,
+        termdag: &mut TermDag,
+        arcsort: &ArcSort,
+        cost_function: &dyn CostFunction,
+    ) -> (Cost, Term) {
+        Extractor::with_cost_function(self, termdag, cost_function)
+            .find_best(value, termdag, arcsort)
+            .unwrap_or_else(|| panic!("No cost for {:?}", value))
+    }
+
+    /// Extracts the cheapest term of an e-class like [`EGraph::extract`],
+    /// measuring costs as `mode` says. With [`ExtractMode::Dag`], a subterm
+    /// that occurs several times in the term is paid for once, so the term
+    /// may cost more as a tree than the one [`EGraph::extract`] finds. With
+    /// [`ExtractMode::Depth`] and [`ExtractMode::Size`], the costs are those
+    /// of [`AstDepth`] and [`AstSize`].
+    /// ```
+    /// use egglog::{ast::{Expr, ExtractMode}, EGraph, TermDag};
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math) (Mul Math Math))
+    ///          (let y (Add (Num 1) (Num 2)))
+    ///          (let x (Add y y))
+    ///          (union x (Mul (Num 3) (Add (Num 4) (Num 5))))",
+    ///     )
+    ///     .unwrap();
+    /// let (sort, value) = egraph.eval_expr(&Expr::var_no_span("x")).unwrap();
+    /// let mut termdag = TermDag::default();
+    /// let (cost, tree) = egraph.extract_with_mode(value, &mut termdag, &sort, ExtractMode::Tree);
+    /// assert_eq!(cost, 8);
+    /// assert_eq!(termdag.to_string(&tree), "(Mul (Num 3) (Add (Num 4) (Num 5)))");
+    /// let (cost, dag) = egraph.extract_with_mode(value, &mut termdag, &sort, ExtractMode::Dag);
+    /// assert_eq!(cost, 6);
+    /// assert_eq!(
+    ///     termdag.to_string(&dag),
+    ///     "(Add (Add (Num 1) (Num 2)) (Add (Num 1) (Num 2)))"
+    /// );
+    /// let (depth, _) = egraph.extract_with_mode(value, &mut termdag, &sort, ExtractMode::Depth);
+    /// assert_eq!(depth, 4);
+    /// ```
+    pub fn extract_with_mode(
+        &self,
+        value: Value,
+        termdag: &mut TermDag,
+        arcsort: &ArcSort,
+        mode: ExtractMode,
+    ) -> (Cost, Term) {
+        match mode {
+            ExtractMode::Tree => self.extract(value, termdag, arcsort),
+            ExtractMode::Dag => DagExtractor::new(self, termdag)
+                .find_best(value, termdag, arcsort)
+                .unwrap_or_else(|| panic!("No cost for {:?}", value)),
+            ExtractMode::Depth => {
+                self.extract_with_cost_function(value, termdag, arcsort, &AstDepth)
+            }
+            ExtractMode::Size => self.extract_with_cost_function(value, termdag, arcsort, &AstSize),
+        }
+    }
+
+    /// Extracts the cheapest term for each of `roots`, computing the costs of
+    /// the e-classes once for all of them.
+    /// ```
+    /// use egglog::{ast::Expr, EGraph, TermDag};
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math))
+    ///          (let x (Add (Num 1) (Num 2)))
+    ///          (let y (Num 3))
+    ///          (union y (Add (Num 1) (Num 2)))",
+    ///     )
+    ///     .unwrap();
+    /// let roots = ["x", "y"].map(|var| egraph.eval_expr(&Expr::var_no_span(var)).unwrap());
+    /// let mut termdag = TermDag::default();
+    /// let extracted = egraph.extract_many(&roots, &mut termdag);
+    /// assert_eq!(termdag.to_string(&extracted[0].1), "(Num 3)");
+    /// assert_eq!(extracted[0], extracted[1]);
+    /// ```
+    pub fn extract_many(
+        &self,
+        roots: &[(ArcSort, Value)],
+        termdag: &mut TermDag,
+    ) -> Vec<(Cost, Term)> {
+        let extractor = Extractor::new(self, termdag);
+        roots
+            .iter()
+            .map(|(sort, value)| {
+                extractor
+                    .find_best(*value, termdag, sort)
+                    .unwrap_or_else(|| panic!("No cost for {:?}", value))
+            })
+            .collect()
+    }
+
+    /// Extracts up to `limit` distinct terms of an e-class, cheapest first,
+    /// like [`EGraph::extract_top_n`] without the costs.
+    pub fn extract_variants(
+        &mut self,
+        sort: &ArcSort,
+        value: Value,
+        limit: usize,
+        termdag: &mut TermDag,
+    ) -> Vec<Term> {
+        self.extract_top_n(value, sort, limit, termdag)
+            .into_iter()
+            .map(|(_, term)| term)
+            .collect()
+    }
+}
+
+impl<'a> Extractor<'a> {
+    pub fn new(egraph: &'a EGraph, termdag: &mut TermDag) -> Self {
+        Self::with_cost_function(egraph, termdag, &DefaultCost)
+    }
+
+    pub fn with_cost_function(
+        egraph: &'a EGraph,
+        termdag: &mut TermDag,
+        cost_function: &'a dyn CostFunction,
+    ) -> Self {
+        let mut extractor = Extractor {
+            costs: HashMap::default(),
+            egraph,
+            ctors: vec![],
+            cost_function,
+        };
+
+        // only consider "extractable" functions
+        extractor.ctors.extend(
+            egraph
+                .functions
+                .keys()
+                .filter(|func| !egraph.functions.get(*func).unwrap().decl.unextractable)
+                .cloned(),
+        );
+
+        log::debug!("Extracting from ctors: {:?}", extractor.ctors);
+        extractor.find_costs(termdag);
+        extractor
+    }
+
+    /// An extractor that only knows the terms in `costs`, without looking at
+    /// any e-nodes, so only e-classes with a term there can be extracted.
+    pub(crate) fn with_costs(egraph: &'a EGraph, costs: HashMap<Id, (Cost, Term)>) -> Self {
+        Extractor {
+            costs,
+            egraph,
+            ctors: vec![],
+            cost_function: &DefaultCost,
+        }
+    }
+
+    pub fn find_best(
+        &self,
+        value: Value,
+        termdag: &mut TermDag,
+        sort: &ArcSort,
+    ) -> Option<(Cost, Term)> {
+        if sort.is_eq_sort() {
+            let id = self.egraph.find(sort, value).bits;
+            let (cost, node) = self.costs.get(&id)?.clone();
+            Some((cost, node))
+        } else {
+            let (cost, node) = sort.extract_expr(self.egraph, value, self, termdag)?;
+            Some((cost, termdag.expr_to_term(&node)))
+        }
+    }
+
+    fn node_total_cost(
+        &mut self,
+        function: &Function,
+        children: &[Value],
+        termdag: &mut TermDag,
+    ) -> Option<(Vec<Term>, Cost)> {
+        let types = &function.schema.input;
+        let mut terms: Vec<Term> = vec![];
+        let mut costs: Vec<Cost> = vec![];
+        for (ty, value) in types.iter().zip(children) {
+            let (term_cost, term) = self.find_best(*value, termdag, ty)?;
+            terms.push(term.clone());
+            costs.push(term_cost);
+        }
+        let cost = self
+            .cost_function
+            .cost(self.egraph, function, children, &costs);
+        Some((terms, cost))
+    }
+
+    /// Computes the cheapest term of every e-class, in parallel rounds when
+    /// the e-graph has a thread pool and is not deterministic.
+    fn find_costs(&mut self, termdag: &mut TermDag) {
+        match self.egraph.thread_pool.clone() {
+            Some(pool) if !self.egraph.deterministic => {
+                pool.install(|| self.find_costs_parallel(termdag))
+            }
+            _ => self.find_costs_serial(termdag),
+        }
+    }
+
+    fn find_costs_serial(&mut self, termdag: &mut TermDag) {
+        let mut did_something = true;
+        while did_something {
+            did_something = false;
+
+            for sym in self.ctors.clone() {
+                let func = &self.egraph.functions[&sym];
+                if func.schema.output.is_eq_sort() {
+                    for (inputs, output) in func.nodes.iter(false) {
+                        if let Some((term_inputs, new_cost)) =
+                            self.node_total_cost(func, inputs, termdag)
+                        {
+                            let make_new_pair = || (new_cost, termdag.app(sym, term_inputs));
+
+                            let id = self.egraph.find(&func.schema.output, output.value).bits;
+                            match self.costs.entry(id) {
+                                HEntry::Vacant(e) => {
+                                    did_something = true;
+                                    e.insert(make_new_pair());
+                                }
+                                HEntry::Occupied(mut e) => {
+                                    if new_cost < e.get().0 {
+                                        did_something = true;
+                                        e.insert(make_new_pair());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Each round computes the cost of every e-node from the costs of the
+    /// previous round in parallel, then builds terms for the e-classes that
+    /// got cheaper. Only the terms are built serially, as they go in the
+    /// shared [`TermDag`].
+    fn find_costs_parallel(&mut self, termdag: &mut TermDag) {
+        loop {
+            let this = &*self;
+            let candidates: Vec<Vec<(Id, Cost, usize)>> = self
+                .ctors
+                .par_iter()
+                .map(|sym| {
+                    let func = &this.egraph.functions[sym];
+                    if !func.schema.output.is_eq_sort() {
+                        return vec![];
+                    }
+                    (0..func.nodes.num_offsets())
+                        .into_par_iter()
+                        .map_init(TermDag::default, |scratch, offset| {
+                            let (inputs, output) = func.nodes.get_index(offset, false)?;
+                            let cost = this.node_cost(func, inputs, scratch)?;
+                            let id = this.egraph.find(&func.schema.output, output.value).bits;
+                            match this.costs.get(&id) {
+                                Some((old, _)) if *old <= cost => None,
+                                _ => Some((id, cost, offset)),
+                            }
+                        })
+                        .flatten()
+                        .collect()
+                })
+                .collect();
+
+            // the cheapest e-node for each e-class, the first one on ties
+            let mut cheapest: HashMap<Id, (Cost, Symbol, usize)> = HashMap::default();
+            for (sym, candidates) in self.ctors.iter().zip(candidates) {
+                for (id, cost, offset) in candidates {
+                    if cheapest.get(&id).map_or(true, |(old, _, _)| cost < *old) {
+                        cheapest.insert(id, (cost, *sym, offset));
+                    }
+                }
+            }
+            let mut improved = false;
+            for (id, (_, sym, offset)) in cheapest {
+                let func = &self.egraph.functions[&sym];
+                let (inputs, _) = func.nodes.get_index(offset, false).unwrap();
+                if let Some((children, cost)) = self.node_total_cost(func, inputs, termdag) {
+                    if self.costs.get(&id).map_or(true, |(old, _)| cost < *old) {
+                        self.costs.insert(id, (cost, termdag.app(sym, children)));
+                        improved = true;
+                    }
+                }
+            }
+            if !improved {
+                return;
+            }
+        }
+    }
+
+    /// The cost of an e-node, like [`Extractor::node_total_cost`] but
+    /// without building terms in a shared [`TermDag`]. Terms of primitive
+    /// children are built in `scratch`.
+    fn node_cost(
+        &self,
+        function: &Function,
+        children: &[Value],
+        scratch: &mut TermDag,
+    ) -> Option<Cost> {
+        let mut costs: Vec<Cost> = vec![];
+        for (ty, value) in function.schema.input.iter().zip(children) {
+            let child_cost = if ty.is_eq_sort() {
+                self.costs.get(&self.egraph.find(ty, *value).bits)?.0
+            } else {
+                ty.extract_expr(self.egraph, *value, self, scratch)?.0
+            };
+            costs.push(child_cost);
+        }
+        Some(
+            self.cost_function
+                .cost(self.egraph, function, children, &costs),
+        )
+    }
+}
@@ -0,0 +1,186 @@
+//! Extraction of the cheapest distinct terms of an e-class, for
+//! `(extract e n)` and `(query-extract :variants n e)`.
+//!
+//! Each e-class below the root keeps its `n` cheapest distinct terms found so
+//! far. Rounds combine the terms of the children of every e-node, cheapest
+//! combinations first, until no e-class gets a new term. Terms are distinct
+//! when they differ anywhere, not just in their root e-node.
+
+use super::*;
+use crate::util::IndexMap;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// The e-nodes of the e-classes reachable from a root.
+type Classes<'a> = IndexMap<Id, Vec<(Symbol, &'a Function, &'a [Value])>>;
+
+impl EGraph {
+    /// Extracts up to `n` distinct terms of an e-class, cheapest first, with
+    /// their costs.
+    /// ```
+    /// use egglog::{ast::Expr, EGraph, TermDag};
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math) (Mul Math Math))
+    ///          (let two (Num 2))
+    ///          (union two (Add (Num 1) (Num 1)))
+    ///          (let x (Mul two (Num 3)))
+    ///          (union x (Num 6))",
+    ///     )
+    ///     .unwrap();
+    /// let (sort, value) = egraph.eval_expr(&Expr::var_no_span("x")).unwrap();
+    /// let mut termdag = TermDag::default();
+    /// let variants: Vec<(usize, String)> = egraph
+    ///     .extract_top_n(value, &sort, 3, &mut termdag)
+    ///     .into_iter()
+    ///     .map(|(cost, term)| (cost, termdag.to_string(&term)))
+    ///     .collect();
+    /// assert_eq!(
+    ///     variants,
+    ///     [
+    ///         (2, "(Num 6)".into()),
+    ///         (5, "(Mul (Num 2) (Num 3))".into()),
+    ///         (8, "(Mul (Add (Num 1) (Num 1)) (Num 3))".into()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn extract_top_n(
+        &self,
+        value: Value,
+        arcsort: &ArcSort,
+        n: usize,
+        termdag: &mut TermDag,
+    ) -> Vec<(Cost, Term)> {
+        let extractor = Extractor::new(self, termdag);
+        if !arcsort.is_eq_sort() {
+            return extractor
+                .find_best(value, termdag, arcsort)
+                .into_iter()
+                .take(n)
+                .collect();
+        }
+        let root = self.find(arcsort, value).bits;
+        let classes = self.reachable_classes(&extractor, root);
+
+        let mut variants: IndexMap<Id, Vec<(Cost, Term)>> = IndexMap::default();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (class, nodes) in &classes {
+                for (sym, func, inputs) in nodes {
+                    let candidates =
+                        node_variants(&extractor, &variants, *sym, func, inputs, n, termdag);
+                    let class_variants = variants.entry(*class).or_default();
+                    for candidate in candidates {
+                        changed |= insert_variant(class_variants, candidate, n);
+                    }
+                }
+            }
+        }
+        variants.swap_remove(&root).unwrap_or_default()
+    }
+
+    /// The extractable e-nodes of the e-classes that `root` reaches through
+    /// them.
+    fn reachable_classes<'a>(&'a self, extractor: &Extractor<'a>, root: Id) -> Classes<'a> {
+        let mut all: Classes = IndexMap::default();
+        for &sym in &extractor.ctors {
+            let func = &self.functions[&sym];
+            if func.schema.output.is_eq_sort() {
+                for (inputs, output) in func.nodes.iter(false) {
+                    let class = self.find(&func.schema.output, output.value).bits;
+                    all.entry(class).or_default().push((sym, func, inputs));
+                }
+            }
+        }
+        let mut reachable: Classes = IndexMap::default();
+        let mut todo = vec![root];
+        while let Some(class) = todo.pop() {
+            if reachable.contains_key(&class) {
+                continue;
+            }
+            let nodes = all.swap_remove(&class).unwrap_or_default();
+            for (_, func, inputs) in &nodes {
+                for (value, sort) in inputs.iter().zip(&func.schema.input) {
+                    if sort.is_eq_sort() {
+                        todo.push(self.find(sort, *value).bits);
+                    }
+                }
+            }
+            reachable.insert(class, nodes);
+        }
+        reachable
+    }
+}
+
+/// Up to `n` of the cheapest terms of an e-node, from the terms of its
+/// children found so far, enumerated from the cheapest combination up.
+fn node_variants(
+    extractor: &Extractor,
+    variants: &IndexMap<Id, Vec<(Cost, Term)>>,
+    sym: Symbol,
+    func: &Function,
+    inputs: &[Value],
+    n: usize,
+    termdag: &mut TermDag,
+) -> Vec<(Cost, Term)> {
+    let mut children: Vec<Vec<(Cost, Term)>> = vec![];
+    for (value, sort) in inputs.iter().zip(&func.schema.input) {
+        let child = if sort.is_eq_sort() {
+            let class = extractor.egraph.find(sort, *value).bits;
+            variants.get(&class).cloned().unwrap_or_default()
+        } else {
+            extractor
+                .find_best(*value, termdag, sort)
+                .into_iter()
+                .collect()
+        };
+        if child.is_empty() {
+            return vec![];
+        }
+        children.push(child);
+    }
+
+    let cost = |choice: &[usize]| {
+        let costs: Vec<Cost> = choice.iter().zip(&children).map(|(i, c)| c[*i].0).collect();
+        DefaultCost.cost(extractor.egraph, func, inputs, &costs)
+    };
+    let first = vec![0; children.len()];
+    let mut heap = BinaryHeap::from([(Reverse(cost(&first)), first.clone())]);
+    let mut seen = HashSet::from_iter([first]);
+    let mut out = vec![];
+    while let Some((Reverse(node_cost), choice)) = heap.pop() {
+        let terms = choice.iter().zip(&children).map(|(i, c)| c[*i].1.clone());
+        out.push((node_cost, termdag.app(sym, terms.collect())));
+        if out.len() == n {
+            break;
+        }
+        for i in 0..choice.len() {
+            if choice[i] + 1 < children[i].len() {
+                let mut next = choice.clone();
+                next[i] += 1;
+                if seen.insert(next.clone()) {
+                    heap.push((Reverse(cost(&next)), next));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Adds a term to the cheapest `n` terms of an e-class, sorted by cost,
+/// returning whether it is one of them and was not already.
+fn insert_variant(variants: &mut Vec<(Cost, Term)>, variant: (Cost, Term), n: usize) -> bool {
+    if variants.iter().any(|(_, term)| *term == variant.1) {
+        return false;
+    }
+    if variants.len() == n && variants.last().map_or(true, |(cost, _)| variant.0 >= *cost) {
+        return false;
+    }
+    let at = variants.partition_point(|(cost, _)| *cost <= variant.0);
+    variants.insert(at, variant);
+    variants.truncate(n);
+    true
+}
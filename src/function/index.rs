@@ -38,6 +38,10 @@ impl ColumnIndex {
         self.ids.len()
     }
 
+    pub(crate) fn approximate_memory_usage(&self) -> usize {
+        self.ids.capacity() * std::mem::size_of::<(u64, SmallVec<[Offset; 8]>)>()
+    }
+
     pub(crate) fn get(&self, v: &Value) -> Option<&[Offset]> {
         self.get_indexes_for_bits(v.bits)
     }
@@ -80,6 +84,13 @@ impl CompositeColumnIndex {
         CompositeColumnIndex(SmallVec::new())
     }
 
+    pub(crate) fn approximate_memory_usage(&self) -> usize {
+        self.0
+            .iter()
+            .map(ColumnIndex::approximate_memory_usage)
+            .sum()
+    }
+
     pub(crate) fn add(&mut self, s: Symbol, v: Value, i: usize) {
         if let Some(index) = self.0.iter().position(|index| index.sort() == s) {
             (self.0)[index].add(v, i);
@@ -15,10 +15,21 @@ pub struct Function {
     pub(crate) decl: ResolvedFunctionDecl,
     pub schema: ResolvedSchema,
     pub merge: MergeAction,
+    /// The cost of the e-nodes set by `set-cost`, which replaces the
+    /// `:cost` of the declaration.
+    pub(crate) cost: Option<Arc<CostExpr>>,
     pub(crate) nodes: table::Table,
     sorts: HashSet<Symbol>,
-    pub(crate) indexes: Vec<Rc<ColumnIndex>>,
+    /// An index on every column, the output included, from values to the
+    /// rows that hold them. Queries use them to look rows up by whichever
+    /// column the join binds first, and rebuilding uses them to find the
+    /// rows holding ids that are no longer canonical. Rows are added to them
+    /// at the start of each rebuild.
+    pub(crate) indexes: Vec<Arc<ColumnIndex>>,
+    /// Indexes from the ids inside the containers in each column, for
+    /// columns of container sorts that hold e-classes.
     pub(crate) rebuild_indexes: Vec<Option<CompositeColumnIndex>>,
+    /// The rows before this offset are in the indexes.
     index_updated_through: usize,
     updates: usize,
     scratch: IndexSet<usize>,
@@ -26,7 +37,7 @@ pub struct Function {
 
 #[derive(Clone)]
 pub struct MergeAction {
-    pub on_merge: Option<Rc<Program>>,
+    pub on_merge: Option<Arc<Program>>,
     pub merge_vals: MergeFn,
 }
 
@@ -36,7 +47,7 @@ pub enum MergeFn {
     Union,
     // the rc is make sure it's cheaply clonable, since calling the merge fn
     // requires a clone
-    Expr(Rc<Program>),
+    Expr(Arc<Program>),
 }
 
 /// All information we know determined by the input.
@@ -68,6 +79,7 @@ impl Debug for Function {
         f.debug_struct("Function")
             .field("decl", &self.decl)
             .field("schema", &self.schema)
+            .field("cost", &self.cost)
             .field("nodes", &self.nodes)
             .field("indexes", &self.indexes)
             .field("rebuild_indexes", &self.rebuild_indexes)
@@ -82,7 +94,32 @@ impl Debug for Function {
 /// resuming execution.
 pub(crate) type DeferredMerge = (ValueVec, Value, Value);
 
+/// The canonical forms of the rows a rebuild pass found holding ids that are
+/// no longer canonical, as the offset of each row with its canonical output
+/// and whether it is subsumed, and their inputs one after the other.
+#[derive(Default)]
+struct CanonicalRows {
+    rows: Vec<(usize, Value, bool)>,
+    inputs: Vec<Value>,
+}
+
 impl Function {
+    /// Roughly how many bytes the function's table and indexes use.
+    pub(crate) fn approximate_memory_usage(&self) -> usize {
+        let indexes: usize = self
+            .indexes
+            .iter()
+            .map(|index| index.approximate_memory_usage())
+            .sum();
+        let rebuild_indexes: usize = self
+            .rebuild_indexes
+            .iter()
+            .flatten()
+            .map(CompositeColumnIndex::approximate_memory_usage)
+            .sum();
+        self.nodes.approximate_memory_usage() + indexes + rebuild_indexes
+    }
+
     pub(crate) fn new(egraph: &mut EGraph, decl: &ResolvedFunctionDecl) -> Result<Self, Error> {
         let mut input = Vec::with_capacity(decl.schema.input.len());
         for s in &decl.schema.input {
@@ -131,7 +168,7 @@ impl Function {
             let program = egraph
                 .compile_expr(&binding, &actions, &target)
                 .map_err(Error::TypeErrors)?;
-            MergeFn::Expr(Rc::new(program))
+            MergeFn::Expr(Arc::new(program))
         } else if output.is_eq_sort() {
             MergeFn::Union
         } else {
@@ -149,14 +186,14 @@ impl Function {
             let program = egraph
                 .compile_actions(&binding, &merge_action)
                 .map_err(Error::TypeErrors)?;
-            Some(Rc::new(program))
+            Some(Arc::new(program))
         };
 
         let indexes = Vec::from_iter(
             input
                 .iter()
                 .chain(once(&output))
-                .map(|x| Rc::new(ColumnIndex::new(x.name()))),
+                .map(|x| Arc::new(ColumnIndex::new(x.name()))),
         );
 
         let rebuild_indexes = Vec::from_iter(input.iter().chain(once(&output)).map(|x| {
@@ -176,6 +213,7 @@ impl Function {
         Ok(Function {
             decl: decl.clone(),
             schema: ResolvedSchema { input, output },
+            cost: None,
             nodes: Default::default(),
             scratch: Default::default(),
             sorts,
@@ -202,7 +240,7 @@ impl Function {
         self.nodes.clear();
         self.indexes
             .iter_mut()
-            .for_each(|x| Rc::make_mut(x).clear());
+            .for_each(|x| Arc::make_mut(x).clear());
         self.rebuild_indexes.iter_mut().for_each(|x| {
             if let Some(x) = x {
                 x.clear()
@@ -240,6 +278,12 @@ impl Function {
         self.nodes.get_mut(inputs).unwrap().subsumed = true;
     }
 
+    /// Keeps the rows that are removed or replaced at or after `timestamp`,
+    /// so that the table can still be read as of then, or stops keeping them.
+    pub(crate) fn keep_rows_since(&mut self, timestamp: Option<u32>) {
+        self.nodes.keep_stale_since(timestamp);
+    }
+
     /// Return a column index that contains (a superset of) the offsets for the
     /// given column. This method can return nothing if the indexes available
     /// contain too many irrelevant offsets.
@@ -247,7 +291,7 @@ impl Function {
         &self,
         col: usize,
         timestamps: &Range<u32>,
-    ) -> Option<Rc<ColumnIndex>> {
+    ) -> Option<Arc<ColumnIndex>> {
         let range = self.nodes.transform_range(timestamps);
         if range.end > self.index_updated_through {
             return None;
@@ -261,6 +305,13 @@ impl Function {
         Some(target.clone())
     }
 
+    /// The number of distinct values in a column, with the output as the
+    /// last column. Values of removed rows are counted until the table is
+    /// rehashed, and rows added since the last rebuild are not counted.
+    pub(crate) fn column_distinct(&self, col: usize) -> usize {
+        self.indexes[col].len()
+    }
+
     pub(crate) fn remove(&mut self, ks: &[Value], ts: u32) -> bool {
         let res = self.nodes.remove(ks, ts);
         self.maybe_rehash();
@@ -278,7 +329,7 @@ impl Function {
             .zip(self.rebuild_indexes.iter_mut())
             .enumerate()
         {
-            let as_mut = Rc::make_mut(index);
+            let as_mut = Arc::make_mut(index);
             if col == self.schema.input.len() {
                 for (slot, _, out) in self.nodes.iter_range(offsets.clone(), true) {
                     as_mut.add(out.value, slot)
@@ -323,7 +374,7 @@ impl Function {
         for index in &mut self.indexes {
             // Everything works if we don't have a unique copy of the indexes,
             // but we ought to be able to avoid this copy.
-            Rc::make_mut(index).clear();
+            Arc::make_mut(index).clear();
         }
         for rebuild_index in self.rebuild_indexes.iter_mut().flatten() {
             rebuild_index.clear();
@@ -345,10 +396,16 @@ impl Function {
             .iter_timestamp_range(timestamps, include_subsumed)
     }
 
-    pub fn rebuild(
+    /// Canonicalizes the rows holding ids that stopped being canonical, in
+    /// two stages: the canonical form of each of those rows is computed
+    /// first, and then they are reinserted, which is where rows that became
+    /// congruent are merged. Unions made while reinserting can leave rows
+    /// that are no longer canonical for the next call.
+    pub(crate) fn rebuild(
         &mut self,
-        uf: &mut UnionFind,
+        uf: &mut UnionRef,
         timestamp: u32,
+        profile: &mut RebuildProfile,
     ) -> Result<(usize, Vec<DeferredMerge>), Error> {
         // Make sure indexes are up to date.
         self.update_indexes(self.nodes.num_offsets());
@@ -362,15 +419,23 @@ impl Function {
         {
             return Ok((std::mem::take(&mut self.updates), Default::default()));
         }
+        // Only rows holding ids that stopped being canonical need to be
+        // canonicalized, so tables without any are left alone. The ids in
+        // containers are of other sorts, so those tables are always checked.
+        let dirty = uf.get().new_ids(|sort| self.sorts.contains(&sort));
+        if dirty == 0 && self.rebuild_indexes.iter().all(Option::is_none) {
+            return Ok((std::mem::take(&mut self.updates), Default::default()));
+        }
+        let start = Instant::now();
         let mut deferred_merges = Vec::new();
-        let mut scratch = ValueVec::new();
         let n_unions = uf.n_unions();
+        let mut rows = CanonicalRows::default();
 
-        if uf.new_ids(|sort| self.sorts.contains(&sort)) > (self.nodes.num_offsets() / 2) {
+        if dirty > (self.nodes.num_offsets() / 2) {
             // basic heuristic: if we displaced a large number of ids relative
             // to the size of the table, then just rebuild everything.
             for i in 0..self.nodes.num_offsets() {
-                self.rebuild_at(i, timestamp, uf, &mut scratch, &mut deferred_merges)?;
+                self.canonicalize_at(i, uf, &mut rows);
             }
         } else {
             let mut to_canon = mem::take(&mut self.scratch);
@@ -392,69 +457,94 @@ impl Function {
                 // attempt to use the rebuilding index if it exists
                 if let Some(ridx) = ridx {
                     debug_assert!(sort.is_eq_container_sort());
-                    to_canon.extend(ridx.iter().flat_map(|idx| idx.to_canonicalize(uf)))
+                    to_canon.extend(ridx.iter().flat_map(|idx| idx.to_canonicalize(uf.get())))
                 } else {
                     debug_assert!(sort.is_eq_sort());
-                    to_canon.extend(idx.to_canonicalize(uf))
+                    to_canon.extend(idx.to_canonicalize(uf.get()))
                 }
             }
 
             for i in to_canon.iter().copied() {
-                self.rebuild_at(i, timestamp, uf, &mut scratch, &mut deferred_merges)?;
+                self.canonicalize_at(i, uf, &mut rows);
             }
             self.scratch = to_canon;
         }
+        let canonicalized = Instant::now();
+        profile.canonicalize += canonicalized - start;
+
+        let arity = self.schema.input.len();
+        for (row, &(i, out_val, subsumed)) in rows.rows.iter().enumerate() {
+            let inputs = &rows.inputs[row * arity..(row + 1) * arity];
+            self.reinsert_at(
+                i,
+                inputs,
+                out_val,
+                subsumed,
+                timestamp,
+                uf,
+                &mut deferred_merges,
+            )?;
+        }
         self.maybe_rehash();
+        profile.congruence += canonicalized.elapsed();
         Ok((
             uf.n_unions() - n_unions + std::mem::take(&mut self.updates),
             deferred_merges,
         ))
     }
 
-    fn rebuild_at(
-        &mut self,
-        i: usize,
-        timestamp: u32,
-        uf: &mut UnionFind,
-        scratch: &mut ValueVec,
-        deferred_merges: &mut Vec<(ValueVec, Value, Value)>,
-    ) -> Result<(), Error> {
-        let mut result: Result<(), Error> = Ok(());
-        let mut modified = false;
-        let (args, out) = if let Some(x) = self.nodes.get_index(i, true) {
-            x
-        } else {
+    /// Adds the canonical form of the row at `i` to `rows` if the row is not
+    /// canonical.
+    fn canonicalize_at(&self, i: usize, uf: &UnionRef, rows: &mut CanonicalRows) {
+        let Some((args, out)) = self.nodes.get_index(i, true) else {
             // Entry is stale
-            return result;
+            return;
         };
-
+        let start = rows.inputs.len();
+        rows.inputs.extend_from_slice(args);
+        let mut modified = false;
+        for (val, ty) in rows.inputs[start..].iter_mut().zip(&self.schema.input) {
+            modified |= ty.canonicalize(val, uf.get());
+        }
         let mut out_val = out.value;
-        scratch.clear();
-        scratch.extend(args.iter().copied());
+        modified |= self.schema.output.canonicalize(&mut out_val, uf.get());
 
-        for (val, ty) in scratch.iter_mut().zip(&self.schema.input) {
-            modified |= ty.canonicalize(val, uf);
+        if modified {
+            rows.rows.push((i, out_val, out.subsumed));
+        } else {
+            rows.inputs.truncate(start);
         }
+    }
 
-        modified |= self.schema.output.canonicalize(&mut out_val, uf);
-
-        if !modified {
-            return result;
-        }
+    /// Replaces the row at `i` with its canonical form, merging it with the
+    /// row already there if there is one.
+    #[allow(clippy::too_many_arguments)]
+    fn reinsert_at(
+        &mut self,
+        i: usize,
+        inputs: &[Value],
+        out_val: Value,
+        subsumed: bool,
+        timestamp: u32,
+        uf: &mut UnionRef,
+        deferred_merges: &mut Vec<DeferredMerge>,
+    ) -> Result<(), Error> {
+        let mut result: Result<(), Error> = Ok(());
         let out_ty = &self.schema.output;
         self.nodes
-            .insert_and_merge(scratch, timestamp, out.subsumed, |prev| {
+            .insert_and_merge(inputs, timestamp, subsumed, |prev| {
                 if let Some(mut prev) = prev {
-                    out_ty.canonicalize(&mut prev, uf);
+                    out_ty.canonicalize(&mut prev, uf.get());
                     let mut appended = false;
                     if self.merge.on_merge.is_some() && prev != out_val {
-                        deferred_merges.push((scratch.clone(), prev, out_val));
+                        deferred_merges.push((inputs.into(), prev, out_val));
                         appended = true;
                     }
                     match &self.merge.merge_vals {
                         MergeFn::Union => {
                             debug_assert!(self.schema.output.is_eq_sort());
-                            uf.union_values(prev, out_val, self.schema.output.name())
+                            let sort = self.schema.output.name();
+                            uf.union_congruent(prev, out_val, sort, self.decl.name)
                         }
                         MergeFn::AssertEq => {
                             if prev != out_val {
@@ -464,7 +554,7 @@ impl Function {
                         }
                         MergeFn::Expr(_) => {
                             if !appended && prev != out_val {
-                                deferred_merges.push((scratch.clone(), prev, out_val));
+                                deferred_merges.push((inputs.into(), prev, out_val));
                             }
                             prev
                         }
@@ -473,12 +563,10 @@ impl Function {
                     out_val
                 }
             });
-        if let Some((inputs, _)) = self.nodes.get_index(i, true) {
-            if inputs != &scratch[..] {
-                scratch.clear();
-                scratch.extend_from_slice(inputs);
-                self.nodes.remove(scratch, timestamp);
-                scratch.clear();
+        if let Some((old, _)) = self.nodes.get_index(i, true) {
+            if old != inputs {
+                let old = ValueVec::from(old);
+                self.nodes.remove(&old, timestamp);
             }
         }
         result
@@ -491,4 +579,8 @@ impl Function {
     pub fn is_extractable(&self) -> bool {
         !self.decl.unextractable
     }
+
+    pub fn name(&self) -> Symbol {
+        self.decl.name
+    }
 }
@@ -19,6 +19,10 @@
 //! making it efficient to iterate over subsets of a table matching a given
 //! timestamp range.
 //!
+//! While [`Table::keep_stale_since`] is set, stale entries keep their inputs,
+//! so the table as of an earlier timestamp can be read back with
+//! [`Table::iter_at`], and rehashing drops only the ones older than it.
+//!
 //! Note on rehashing: We will eventually want to keep old/stale entries around
 //! to facilitate proofs/provenance. Early testing found that removing this in
 //! the "obvious" way (keeping 'vals' around, avoiding `mem::take()`s for stale
@@ -51,6 +55,11 @@ struct TableOffset {
 pub(crate) struct Table {
     max_ts: u32,
     n_stale: usize,
+    /// The number of stale entries the last rehash kept.
+    n_kept: usize,
+    /// Stale entries that became stale at or after this timestamp survive
+    /// rehashes.
+    keep_since: Option<u32>,
     table: HashTable<TableOffset>,
     pub(crate) vals: Vec<(Input, TupleOutput)>,
 }
@@ -76,6 +85,7 @@ impl Debug for Table {
         f.debug_struct("Table")
             .field("max_ts", &self.max_ts)
             .field("n_stale", &self.n_stale)
+            .field("n_kept", &self.n_kept)
             .field("vals", &self.vals)
             .finish()
     }
@@ -86,18 +96,42 @@ impl Table {
     pub(crate) fn clear(&mut self) {
         self.max_ts = 0;
         self.n_stale = 0;
+        self.n_kept = 0;
         self.table.clear();
         self.vals.clear();
     }
 
+    /// Roughly how many bytes the table uses, including spare capacity.
+    pub(crate) fn approximate_memory_usage(&self) -> usize {
+        // all inputs have the same length, so either all of them or none of
+        // them spill onto the heap
+        let spilled = match self.vals.first() {
+            Some((input, _)) if input.data.spilled() => {
+                self.vals.len() * input.data.capacity() * std::mem::size_of::<Value>()
+            }
+            _ => 0,
+        };
+        self.vals.capacity() * std::mem::size_of::<(Input, TupleOutput)>()
+            + self.table.capacity() * std::mem::size_of::<TableOffset>()
+            + spilled
+    }
+
     /// Indicates whether or not the table should be rehashed.
     pub(crate) fn too_stale(&self) -> bool {
-        self.n_stale > (self.vals.len() / 2)
+        self.n_stale - self.n_kept > (self.vals.len() / 2)
+    }
+
+    /// Keeps entries that become stale at or after `ts` through rehashes, or
+    /// none of them.
+    pub(crate) fn keep_stale_since(&mut self, ts: Option<u32>) {
+        self.keep_since = ts;
     }
 
     /// Rehashes the table, invalidating any offsets stored into the table.
     pub(crate) fn rehash(&mut self) {
         let mut dst = 0usize;
+        let mut kept = 0;
+        let keep_since = self.keep_since.unwrap_or(u32::MAX);
         self.table.clear();
         self.vals.retain(|(inp, _)| {
             if inp.live() {
@@ -106,13 +140,16 @@ impl Table {
                 self.table
                     .entry(hash, |to2| to2 == &to, |to2| to2.hash)
                     .insert(to);
-                dst += 1;
-                true
+            } else if inp.stale_at >= keep_since {
+                kept += 1;
             } else {
-                false
+                return false;
             }
+            dst += 1;
+            true
         });
-        self.n_stale = 0;
+        self.n_stale = kept;
+        self.n_kept = kept;
     }
 
     /// Get the entry in the table for the given values, if they are in the
@@ -170,7 +207,12 @@ impl Table {
             }
             inp.stale_at = ts;
             self.n_stale += 1;
-            let k = mem::take(&mut inp.data);
+            // the stale entry only needs its inputs while an epoch is pinned
+            let k = if self.keep_since.is_some() {
+                inp.data.clone()
+            } else {
+                mem::take(&mut inp.data)
+            };
             let new_offset = self.vals.len();
             self.vals.push((
                 Input::new(k),
@@ -293,6 +335,20 @@ impl Table {
             .all(|xs| xs[0].1.timestamp <= xs[1].1.timestamp))
     }
 
+    /// Iterate over the entries as they were at the start of timestamp `ts`:
+    /// those inserted before it that were not yet stale.
+    pub(crate) fn iter_at(
+        &self,
+        ts: u32,
+        include_subsumed: bool,
+    ) -> impl Iterator<Item = (&[Value], &TupleOutput)> + '_ {
+        let end = self.transform_range(&(0..ts)).end;
+        self.vals[..end]
+            .iter()
+            .filter(move |(inp, out)| inp.stale_at >= ts && (include_subsumed || !out.subsumed))
+            .map(|(inp, out)| (inp.data(), out))
+    }
+
     /// Iterate over the live entries in the timestamp range, passing back their
     /// offset into the table.
     pub(crate) fn iter_timestamp_range(
@@ -0,0 +1,167 @@
+//! Garbage collection, with [`EGraph::collect_garbage`] or the `gc` command.
+//!
+//! The roots are the e-classes that rows of functions without e-class outputs
+//! (relations and functions to primitives) mention, along with anything stored
+//! in a function without inputs, such as a global. An e-class is reachable if
+//! it is a root or a child of an e-node in a reachable e-class, counting the
+//! e-classes inside containers. Collection removes the e-nodes in unreachable
+//! e-classes, then renumbers the remaining e-classes from 0. Subsumed rows
+//! count like any other, so that they stay subsumed when a rule adds them
+//! again.
+
+use crate::*;
+use std::mem;
+
+impl EGraph {
+    /// Removes the e-nodes of e-classes that cannot be reached from any root,
+    /// then compacts the e-class ids, returning the number of rows removed.
+    /// Ids held outside the e-graph, such as the [`Value`]s returned by
+    /// [`EGraph::eval_expr`], are invalid afterwards.
+    ///
+    /// ```
+    /// # use egglog::EGraph;
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(datatype Math (Num i64) (Add Math Math))
+    ///          (let x (Add (Num 1) (Num 2)))
+    ///          (Add (Num 3) (Num 4))",
+    ///     )
+    ///     .unwrap();
+    /// // `(Add (Num 3) (Num 4))`, `(Num 3)` and `(Num 4)` are unreachable
+    /// assert_eq!(egraph.collect_garbage().unwrap(), 3);
+    /// egraph
+    ///     .parse_and_run_program(None, "(check (= x (Add (Num 1) (Num 2))))")
+    ///     .unwrap();
+    /// ```
+    pub fn collect_garbage(&mut self) -> Result<usize, Error> {
+        self.rebuild()?;
+        self.extraction_cache = None;
+        let reachable = self.reachable_eclasses();
+
+        // Keep the rows in their order, so their timestamps stay sorted
+        let mut tables = vec![];
+        let mut removed = 0;
+        for function in self.functions.values() {
+            let rows: Vec<(Vec<Value>, TupleOutput)> = function
+                .nodes
+                .iter(true)
+                .filter(|(inputs, output)| {
+                    !is_enode(function, inputs) || reachable.contains(&output.value.bits)
+                })
+                .map(|(inputs, output)| (inputs.to_vec(), output.clone()))
+                .collect();
+            removed += function.nodes.len() - rows.len();
+            tables.push(rows);
+        }
+
+        // Ids are renumbered in two steps, first to distinct ids after all of
+        // the ones in use and then down from there, since container sorts can
+        // only change ids through a union-find.
+        let mut live: Vec<Id> = reachable.into_iter().collect();
+        live.sort_unstable();
+        let num_ids = self.unionfind.num_ids() as Id;
+        let mut up: Vec<Id> = (0..num_ids + live.len() as Id).collect();
+        let mut down = up.clone();
+        for (new, old) in live.iter().enumerate() {
+            up[*old as usize] = num_ids + new as Id;
+            down[num_ids as usize + new] = new as Id;
+        }
+        let (up, down) = (UnionFind::from_parents(up), UnionFind::from_parents(down));
+        let renumber = |sort: &ArcSort, value: &mut Value| {
+            sort.canonicalize(value, &up);
+            sort.canonicalize(value, &down);
+        };
+
+        for (function, rows) in self.functions.values_mut().zip(tables) {
+            function.clear();
+            let schema = function.schema.clone();
+            for (mut inputs, mut output) in rows {
+                for (sort, value) in schema.input.iter().zip(&mut inputs) {
+                    renumber(sort, value);
+                }
+                renumber(&schema.output, &mut output.value);
+                function.insert(&inputs, output.value, output.timestamp);
+                if output.subsumed {
+                    function.subsume(&inputs);
+                }
+            }
+        }
+
+        // The matches held back by a `:match-limit` are renumbered too, and
+        // the ones with an e-class that is gone can't match again. (Backoff
+        // schedules only count matches per rule, so they hold no ids.)
+        let held_matches = mem::take(&mut self.held_matches);
+        for (rule_name, matches) in held_matches {
+            let rule = self.rulesets.values().find_map(|ruleset| match ruleset {
+                Ruleset::Rules(_, rules) => rules.get(&rule_name),
+                Ruleset::Combined(..) => None,
+            });
+            let Some(rule) = rule else {
+                continue;
+            };
+            let sorts: Vec<ArcSort> = rule.vars.iter().map(|var| var.sort.clone()).collect();
+            let matches = matches
+                .into_iter()
+                .filter_map(|mut values| {
+                    let mut is_live = true;
+                    for (sort, value) in sorts.iter().zip(&mut values) {
+                        self.for_each_eclass(sort, *value, &mut |id, _| {
+                            is_live &= live.binary_search(&id).is_ok()
+                        });
+                        *value = self.find(sort, *value);
+                        renumber(sort, value);
+                    }
+                    is_live.then_some(values)
+                })
+                .collect();
+            self.held_matches.insert(rule_name, matches);
+        }
+        self.unionfind.reset(live.len());
+
+        log::info!(
+            "Collected garbage: removed {removed} rows and {} e-classes",
+            num_ids as usize - live.len()
+        );
+        Ok(removed)
+    }
+
+    /// The canonical ids of the e-classes reachable from a root.
+    fn reachable_eclasses(&self) -> HashSet<Id> {
+        let mut reachable = HashSet::default();
+        let mut todo = vec![];
+        let mut children: HashMap<Id, Vec<Id>> = HashMap::default();
+        for function in self.functions.values() {
+            let schema = &function.schema;
+            for (inputs, output) in function.nodes.iter(true) {
+                let mut eclasses = vec![];
+                for (sort, value) in schema.input.iter().zip(inputs) {
+                    self.for_each_eclass(sort, *value, &mut |id, _| eclasses.push(id));
+                }
+                if is_enode(function, inputs) {
+                    children
+                        .entry(output.value.bits)
+                        .or_default()
+                        .extend(eclasses);
+                } else {
+                    self.for_each_eclass(&schema.output, output.value, &mut |id, _| {
+                        eclasses.push(id)
+                    });
+                    todo.extend(eclasses);
+                }
+            }
+        }
+        while let Some(id) = todo.pop() {
+            if reachable.insert(id) {
+                todo.extend(children.get(&id).into_iter().flatten());
+            }
+        }
+        reachable
+    }
+}
+
+/// Whether a row of `function` is an e-node, as opposed to a root.
+fn is_enode(function: &Function, inputs: &[Value]) -> bool {
+    function.schema.output.is_eq_sort() && !inputs.is_empty()
+}
@@ -1,9 +1,18 @@
+//! Query evaluation by generic join, a worst-case optimal join.
+//!
+//! Every query is compiled to a program that binds one variable at a time,
+//! intersecting the values of that variable in all the atoms it occurs in,
+//! rather than joining the atoms pairwise. Cyclic queries such as triangles
+//! therefore take time bounded by the largest possible output, instead of
+//! the size of an intermediate join of two atoms.
+
 use indexmap::map::Entry;
 use log::log_enabled;
 use smallvec::SmallVec;
 use util::HashMap;
 
 use crate::{core::*, function::index::Offset, *};
+use rayon::prelude::*;
 use std::{
     cell::UnsafeCell,
     fmt::{self, Debug},
@@ -12,6 +21,12 @@ use std::{
 
 type Query = crate::core::Query<ResolvedCall, Symbol>;
 
+/// With a thread pool, a query whose first join variable ranges over at least
+/// this many values is split into chunks of those values, which are searched
+/// in parallel.
+const PARALLEL_SEARCH_MIN_VALUES: usize = 4096;
+const PARALLEL_SEARCH_CHUNK_SIZE: usize = 512;
+
 #[derive(Clone, Debug)]
 enum Instr<'a> {
     Intersect {
@@ -288,6 +303,43 @@ impl<'b> Context<'b> {
     }
 }
 
+impl<'b> Context<'b> {
+    /// Like [`Context::eval`] on the `Intersect` instruction `intersect`
+    /// followed by `program`, but only for one value of the intersected
+    /// variable.
+    fn eval_at<'t, F>(
+        &mut self,
+        tries: &[&'t LazyTrie],
+        intersect: &Instr,
+        value: Value,
+        program: &[Instr],
+        mut stage: InputSizes,
+        f: &mut F,
+    ) -> Result
+    where
+        F: FnMut(&[Value]) -> Result,
+    {
+        let Instr::Intersect {
+            value_idx,
+            trie_accesses,
+            ..
+        } = intersect
+        else {
+            panic!("expected an intersection, got {intersect}")
+        };
+        let mut new_tries = tries.to_vec();
+        for (j, access) in trie_accesses {
+            let trie: &'t LazyTrie = tries[*j];
+            match trie.get(access, value) {
+                Some(t) => new_tries[*j] = t,
+                None => return Ok(()),
+            }
+        }
+        self.tuple[*value_idx] = value;
+        self.eval(&mut new_tries, program, stage.next(), f)
+    }
+}
+
 #[derive(Clone, Debug)]
 enum Constraint {
     Eq(usize, usize),
@@ -481,35 +533,74 @@ impl EGraph {
             return None;
         }
 
-        for (_v, info) in &mut vars {
-            assert!(!info.occurences.is_empty());
-            info.size_guess = info
-                .occurences
+        // The number of distinct values in each column of each atom, within
+        // the atom's timestamp range. Tables change between searches, and
+        // queries are planned again for every search, so the plan follows
+        // the tables as they grow.
+        let distinct: Vec<Vec<f64>> = atoms
+            .iter()
+            .zip(&relation_sizes)
+            .map(|(atom, &rows)| {
+                let function = &self.functions[&atom.head];
+                (0..atom.args.len())
+                    .map(|col| match function.column_distinct(col) {
+                        0 => rows as f64,
+                        n => n.min(rows) as f64,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Estimates how many values `var` can take once the variables in
+        // `bound` are bound, assuming the columns of each atom are
+        // independent: the rows of an atom that match the bound columns, but
+        // no more than the distinct values in the column of `var`.
+        let estimate = |var: Symbol, info: &VarInfo2, bound: &IndexMap<Symbol, VarInfo2>| {
+            info.occurences
                 .iter()
-                .map(|&i| relation_sizes[i])
-                .min()
-                .unwrap();
-            // info.size_guess >>= info.occurences.len() - 1;
-        }
+                .map(|&i| {
+                    let mut rows = relation_sizes[i] as f64;
+                    let mut values = rows;
+                    for (col, arg) in atoms[i].args.iter().enumerate() {
+                        match arg {
+                            AtomTerm::Var(_ann, v) if *v == var => {
+                                values = values.min(distinct[i][col])
+                            }
+                            AtomTerm::Var(_ann, v) if !bound.contains_key(v) => {}
+                            _ => rows /= distinct[i][col],
+                        }
+                    }
+                    values.min(rows.max(1.0))
+                })
+                .fold(f64::INFINITY, f64::min)
+        };
 
-        // here we are picking the variable ordering
+        // here we are picking the variable ordering: the variable with the
+        // fewest estimated values goes next, and ties go to the variable in
+        // the most atoms
         let mut ordered_vars = IndexMap::default();
         while !vars.is_empty() {
             let mut var_cost = vars
                 .iter()
                 .map(|(v, info)| {
-                    let size = info.size_guess as isize;
-                    let cost = (info.occurences.len(), info.intersected_on, -size);
+                    let size = estimate(*v, info, &ordered_vars);
+                    let cost = (size, info.occurences.len(), info.intersected_on);
                     (cost, v)
                 })
                 .collect::<Vec<_>>();
-            var_cost.sort();
-            var_cost.reverse();
+            var_cost.sort_by(
+                |((a, a_occurences, a_intersected), _), ((b, b_occurences, b_intersected), _)| {
+                    a.total_cmp(b)
+                        .then(b_occurences.cmp(a_occurences))
+                        .then(b_intersected.cmp(a_intersected))
+                },
+            );
 
             log::debug!("Variable costs: {:?}", ListDebug(&var_cost, "\n"));
 
-            let var = *var_cost[0].1;
-            let info = vars.swap_remove(&var).unwrap();
+            let ((size, _, _), &var) = var_cost[0];
+            let mut info = vars.swap_remove(&var).unwrap();
+            info.size_guess = size as usize;
             for &i in &info.occurences {
                 for v in atoms[i].vars() {
                     if let Some(info) = vars.get_mut(&v) {
@@ -683,32 +774,38 @@ impl EGraph {
                 order = ListDisplay(&ctx.join_var_ordering, " "),
                 tuple = ListDisplay(cq.vars.keys(), " "),
             );
-            let mut tries = Vec::with_capacity(cq.query.funcs().collect::<Vec<_>>().len());
-            for ((atom, ts), col) in cq
-                .query
-                .funcs()
-                .zip(timestamp_ranges.iter())
-                .zip(cols.iter())
-            {
-                // tries.push(LazyTrie::default());
-                if let Some(target) = col {
-                    if let Some(col) = self.functions[&atom.head].column_index(*target, ts) {
-                        tries.push(LazyTrie::from_column_index(col))
+            let make_tries = || {
+                let mut tries = Vec::with_capacity(cq.query.funcs().collect::<Vec<_>>().len());
+                for ((atom, ts), col) in cq
+                    .query
+                    .funcs()
+                    .zip(timestamp_ranges.iter())
+                    .zip(cols.iter())
+                {
+                    // tries.push(LazyTrie::default());
+                    if let Some(target) = col {
+                        if let Some(col) = self.functions[&atom.head].column_index(*target, ts) {
+                            tries.push(LazyTrie::from_column_index(col))
+                        } else {
+                            tries.push(LazyTrie::default());
+                        }
                     } else {
                         tries.push(LazyTrie::default());
                     }
-                } else {
-                    tries.push(LazyTrie::default());
                 }
-            }
+                tries
+            };
+            let tries = make_tries();
             let mut trie_refs = tries.iter().collect::<Vec<_>>();
             let mut meausrements = HashMap::<usize, Vec<usize>>::default();
             let stages = InputSizes {
                 stage_sizes: &mut meausrements,
                 cur_stage: 0,
             };
-            ctx.eval(&mut trie_refs, &program.0, stages, &mut f)
-                .unwrap_or(());
+            if !self.gj_par(&mut ctx, &trie_refs, &program, make_tries, &mut f) {
+                ctx.eval(&mut trie_refs, &program.0, stages, &mut f)
+                    .unwrap_or(());
+            }
             let mut sums = Vec::from_iter(
                 meausrements
                     .iter()
@@ -728,6 +825,88 @@ impl EGraph {
         }
     }
 
+    /// Searches `program` by splitting the values of its first join variable
+    /// into chunks, which the thread pool searches with work stealing. Each
+    /// chunk collects its matches in its own buffer, and the buffers are
+    /// passed to `f` in the order a serial search would find them.
+    ///
+    /// Returns `false`, without searching, if there is no thread pool or the
+    /// first join variable has too few values to be worth splitting.
+    fn gj_par<F>(
+        &self,
+        ctx: &mut Context,
+        tries: &[&LazyTrie],
+        program: &Program,
+        make_tries: impl Fn() -> Vec<LazyTrie> + Sync,
+        f: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&[Value]) -> Result,
+    {
+        let (Some(pool), Some((first, rest))) = (&self.thread_pool, program.0.split_first()) else {
+            return false;
+        };
+        let Instr::Intersect { trie_accesses, .. } = first else {
+            return false;
+        };
+        // the same trie that `Context::eval` would iterate over
+        let (j_min, access_min) = trie_accesses
+            .iter()
+            .min_by_key(|(j, _a)| tries[*j].len())
+            .unwrap();
+        if tries[*j_min].len() < PARALLEL_SEARCH_MIN_VALUES {
+            return false;
+        }
+        let mut values = vec![];
+        tries[*j_min]
+            .for_each(access_min, |value, _| {
+                values.push(value);
+                Ok(())
+            })
+            .unwrap_or(());
+
+        let n_vars = ctx.tuple.len();
+        let buffers: Vec<Vec<Value>> = pool.install(|| {
+            values
+                .par_chunks(PARALLEL_SEARCH_CHUNK_SIZE)
+                .map(|chunk| {
+                    let tries = make_tries();
+                    let trie_refs = tries.iter().collect::<Vec<_>>();
+                    let mut ctx = Context {
+                        query: ctx.query,
+                        join_var_ordering: vec![],
+                        tuple: vec![Value::fake(); n_vars],
+                        matches: 0,
+                        egraph: self,
+                    };
+                    let mut buffer = vec![];
+                    let mut meausrements = HashMap::<usize, Vec<usize>>::default();
+                    for value in chunk {
+                        let stage = InputSizes {
+                            stage_sizes: &mut meausrements,
+                            cur_stage: 0,
+                        };
+                        ctx.eval_at(&trie_refs, first, *value, rest, stage, &mut |tuple| {
+                            buffer.extend_from_slice(tuple);
+                            Ok(())
+                        })
+                        .unwrap_or(());
+                    }
+                    buffer
+                })
+                .collect()
+        });
+        ctx.matches += buffers.iter().map(Vec::len).sum::<usize>() / n_vars;
+        for buffer in buffers {
+            for tuple in buffer.chunks(n_vars) {
+                if f(tuple).is_err() {
+                    return true;
+                }
+            }
+        }
+        true
+    }
+
     pub(crate) fn run_query<F>(
         &self,
         cq: &CompiledQuery,
@@ -797,7 +976,7 @@ type RowIdx = u32;
 #[derive(Debug)]
 enum LazyTrieInner {
     Borrowed {
-        index: Rc<ColumnIndex>,
+        index: Arc<ColumnIndex>,
         map: HashMap<Value, LazyTrie>,
     },
     Delayed(SmallVec<[RowIdx; 4]>),
@@ -822,7 +1001,7 @@ impl LazyTrie {
             LazyTrieInner::Borrowed { index, .. } => index.len(),
         }
     }
-    fn from_column_index(index: Rc<ColumnIndex>) -> LazyTrie {
+    fn from_column_index(index: Arc<ColumnIndex>) -> LazyTrie {
         LazyTrie(UnsafeCell::new(LazyTrieInner::Borrowed {
             index,
             map: Default::default(),
@@ -0,0 +1,100 @@
+//! Bulk insertion of ground facts from the host, with
+//! [`EGraph::insert_facts`].
+
+use crate::*;
+
+impl EGraph {
+    /// Inserts rows into functions directly, without going through the
+    /// parser, the typechecker, or a command per fact. Each fact is a
+    /// function, its inputs, and its output. An output of `None` adds the row
+    /// like calling the function in an action would, if it is not there
+    /// already: constructors get a new e-class, relations get `()`, and
+    /// other functions their `:default`. An output that differs from the one
+    /// already in the row is merged with it like `set` would.
+    ///
+    /// The facts are sorted by function and inputs and duplicates are dropped
+    /// before anything is inserted, and outputs for the same row are merged
+    /// in increasing order. The values must be of the sorts of the function,
+    /// which is not checked. The e-graph is rebuilt afterwards.
+    ///
+    /// ```
+    /// # use egglog::{EGraph, Value};
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(relation edge (i64 i64))
+    ///          (function weight (i64 i64) i64 :merge (min old new))",
+    ///     )
+    ///     .unwrap();
+    /// let weights = [(1, 2, 10), (2, 3, 5), (1, 2, 7)].map(|(a, b, w)| {
+    ///     ("weight".into(), vec![Value::from(a), Value::from(b)], Some(Value::from(w)))
+    /// });
+    /// let edges = weights.clone().map(|(_, inputs, _)| ("edge".into(), inputs, None));
+    /// egraph.insert_facts(edges.into_iter().chain(weights)).unwrap();
+    /// egraph
+    ///     .parse_and_run_program(None, "(check (edge 2 3) (= (weight 1 2) 7))")
+    ///     .unwrap();
+    /// ```
+    pub fn insert_facts(
+        &mut self,
+        facts: impl IntoIterator<Item = (Symbol, Vec<Value>, Option<Value>)>,
+    ) -> Result<(), Error> {
+        let mut rows = vec![];
+        for (name, mut inputs, output) in facts {
+            let Some((index, _, function)) = self.functions.get_full(&name) else {
+                return Err(TypeError::UnboundFunction(name, DUMMY_SPAN.clone()).into());
+            };
+            let schema = &function.schema;
+            if inputs.len() != schema.input.len() {
+                return Err(Error::FactArityError(
+                    name,
+                    inputs.len(),
+                    schema.input.len(),
+                ));
+            }
+            for (value, sort) in inputs.iter_mut().zip(&schema.input) {
+                *value = self.find(sort, *value);
+            }
+            let output = output.map(|value| self.find(&schema.output, value));
+            rows.push((index, inputs, output));
+        }
+        rows.sort_unstable();
+        rows.dedup();
+
+        for (index, mut inputs, output) in rows {
+            let (&name, function) = self.functions.get_index_mut(index).unwrap();
+            match output {
+                Some(value) => self.perform_set(name, value, &mut inputs)?,
+                None if function.nodes.get(&inputs).is_some() => {}
+                None => {
+                    let out = &function.schema.output;
+                    let value = if out.name() == UnitSort.name() {
+                        Value::unit()
+                    } else if out.is_eq_sort() {
+                        let value = Value {
+                            #[cfg(debug_assertions)]
+                            tag: out.name(),
+                            bits: self.unionfind.make_set(),
+                        };
+                        if let Some(explanations) = self.unionfind.explanations_mut() {
+                            explanations.add_node(name, &inputs, value);
+                        }
+                        value
+                    } else if let Some(default) = function.decl.default.clone() {
+                        self.eval_resolved_expr(&default)?
+                    } else {
+                        return Err(Error::NotFoundError(NotFoundError(format!(
+                            "No value found for {name} {:?}",
+                            inputs
+                        ))));
+                    };
+                    let function = self.functions.get_index_mut(index).unwrap().1;
+                    function.insert(&inputs, value, self.timestamp);
+                }
+            }
+        }
+        self.rebuild()?;
+        Ok(())
+    }
+}
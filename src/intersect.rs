@@ -0,0 +1,159 @@
+//! The intersection of two e-graphs, with [`EGraph::intersect`].
+//!
+//! Each e-class of the intersection is a pair of an e-class of each e-graph
+//! that share a term. The pairs are found bottom up: an e-node of the first
+//! e-graph whose children are in pairs is looked up in the second e-graph with
+//! the other e-class of each pair as its children, and if it is there, the
+//! e-classes of the two e-nodes make a pair. Rounds repeat until no new pair
+//! is found.
+
+use crate::checkpoint::{tagged, RAW_SORTS};
+use crate::*;
+
+/// The rows of the intersection, by function and inputs.
+type Rows = IndexMap<(Symbol, Vec<Value>), Value>;
+
+impl EGraph {
+    /// An e-graph with the terms and equalities that are in both this
+    /// e-graph and `other`: two terms are equal in it when they are equal in
+    /// both. It has the declarations of this e-graph, and the rows of the
+    /// functions declared the same way in both. Rows with primitives other
+    /// than `i64`, `f64`, `f32`, `bool`, `Unit` and `String` are left out, as
+    /// well as subsumed rows, and rows of functions to primitives are kept
+    /// when both e-graphs have the same value.
+    ///
+    /// ```
+    /// # use egglog::EGraph;
+    /// let program = "(datatype Math (Num i64) (Add Math Math))
+    ///                (let x (Add (Num 1) (Num 2)))";
+    /// let mut a = EGraph::default();
+    /// a.parse_and_run_program(None, program).unwrap();
+    /// a.parse_and_run_program(None, "(union x (Num 3)) (union (Num 3) (Num 4))")
+    ///     .unwrap();
+    /// let mut b = EGraph::default();
+    /// b.parse_and_run_program(None, program).unwrap();
+    /// b.parse_and_run_program(None, "(union x (Num 3)) (Num 4)").unwrap();
+    ///
+    /// let mut both = a.intersect(&b).unwrap();
+    /// both.parse_and_run_program(
+    ///     None,
+    ///     "(check (= x (Num 3)))
+    ///      (fail (check (= x (Num 4))))",
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn intersect(&self, other: &EGraph) -> Result<EGraph, Error> {
+        let mut result = EGraph::default();
+        for declaration in &self.declarations {
+            result.redeclare(declaration)?;
+        }
+        let functions: Vec<Symbol> = result
+            .functions
+            .values()
+            .filter(|function| {
+                let name = function.decl.name;
+                let same = |egraph: &EGraph| {
+                    egraph
+                        .functions
+                        .get(&name)
+                        .is_some_and(|f| f.decl.schema == function.decl.schema)
+                };
+                let mut sorts = function
+                    .schema
+                    .input
+                    .iter()
+                    .chain([&function.schema.output]);
+                same(self) && same(other) && sorts.all(comparable)
+            })
+            .map(|function| function.decl.name)
+            .collect();
+
+        // The e-class of the intersection of each pair of e-classes, and the
+        // e-classes of `other` paired with each e-class of this e-graph
+        let mut pairs: IndexMap<(Id, Id), Id> = IndexMap::default();
+        let mut paired: HashMap<Id, Vec<Id>> = HashMap::default();
+        let mut rows = Rows::default();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for name in &functions {
+                let (a, b) = (&self.functions[name], &other.functions[name]);
+                let schema = &a.schema;
+                for (inputs, output) in a.nodes.iter(false) {
+                    // the inputs in `other` and in the intersection for each
+                    // way of pairing the children
+                    let mut choices = vec![(vec![], vec![])];
+                    for (sort, value) in schema.input.iter().zip(inputs) {
+                        let options: Vec<(Value, Value)> = if sort.is_eq_sort() {
+                            let class = self.find(sort, *value).bits;
+                            let Some(classes) = paired.get(&class) else {
+                                choices.clear();
+                                break;
+                            };
+                            classes
+                                .iter()
+                                .map(|b| {
+                                    let id = pairs[&(class, *b)];
+                                    (tagged(sort, *b), tagged(sort, id))
+                                })
+                                .collect()
+                        } else {
+                            vec![(*value, *value)]
+                        };
+                        choices = choices
+                            .into_iter()
+                            .flat_map(|(b_inputs, inputs): (Vec<Value>, Vec<Value>)| {
+                                options.iter().map(move |(b_value, value)| {
+                                    let mut b_inputs = b_inputs.clone();
+                                    let mut inputs = inputs.clone();
+                                    b_inputs.push(*b_value);
+                                    inputs.push(*value);
+                                    (b_inputs, inputs)
+                                })
+                            })
+                            .collect();
+                    }
+
+                    for (b_inputs, inputs) in choices {
+                        let Some(b_output) = b.nodes.get(&b_inputs).filter(|o| !o.subsumed) else {
+                            continue;
+                        };
+                        let sort = &schema.output;
+                        let value = if sort.is_eq_sort() {
+                            let pair = (
+                                self.find(sort, output.value).bits,
+                                other.find(sort, b_output.value).bits,
+                            );
+                            let id = *pairs.entry(pair).or_insert_with(|| {
+                                changed = true;
+                                paired.entry(pair.0).or_default().push(pair.1);
+                                result.unionfind.make_set()
+                            });
+                            tagged(sort, id)
+                        } else if output.value == b_output.value {
+                            output.value
+                        } else {
+                            continue;
+                        };
+                        rows.insert((*name, inputs), value);
+                    }
+                }
+            }
+        }
+
+        for ((name, inputs), output) in rows {
+            let function = result.functions.get_mut(&name).unwrap();
+            function.insert(&inputs, output, result.timestamp);
+        }
+        result.rebuild()?;
+        Ok(result)
+    }
+}
+
+/// Whether values of the sort mean the same thing in any e-graph, or are
+/// e-classes, so that rows with them can be compared across e-graphs.
+pub(crate) fn comparable(sort: &ArcSort) -> bool {
+    sort.is_eq_sort()
+        || RAW_SORTS.contains(&sort.name().as_str())
+        || sort.name() == StringSort.name()
+}
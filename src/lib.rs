@@ -12,13 +12,25 @@
 //! We plan to have a text tutorial here soon, PRs welcome!
 //!
 mod actions;
+mod alethe;
+mod analysis;
 pub mod ast;
+mod checkpoint;
 pub mod constraint;
 mod core;
+mod csv;
+mod diff;
+mod explain;
 mod extract;
 mod function;
+mod gc;
 mod gj;
+mod ingest;
+mod intersect;
+mod portfolio;
+mod scheduler;
 mod serialize;
+mod snapshot;
 pub mod sort;
 mod termdag;
 mod typechecking;
@@ -28,12 +40,21 @@ mod value;
 
 use crate::constraint::Problem;
 use crate::core::{AtomTerm, ResolvedCall};
-use crate::typechecking::TypeError;
-use actions::Program;
-use ast::remove_globals::remove_globals;
+pub use crate::typechecking::TypeError;
+use actions::{PendingUpdates, Program};
+pub use alethe::AletheProof;
+pub use analysis::Analysis;
+use ast::macros::Macros;
+use ast::modules::Modules;
+use ast::remove_globals::{remove_globals, remove_globals_expr};
 use ast::*;
+use checkpoint::Declaration;
 use constraint::{Constraint, SimpleTypeConstraint, TypeConstraint};
-use extract::Extractor;
+pub use diff::{EGraphDiff, MergedTerms};
+use explain::Reason;
+pub use explain::{Explanation, ExplanationStep, Justification};
+use extract::{cost_var, CostExpr, ExtractionCache, Extractor};
+pub use extract::{AstDepth, AstSize, Cost, CostFunction, DefaultCost};
 pub use function::Function;
 use function::*;
 use generic_symbolic_expressions::Sexp;
@@ -41,16 +62,18 @@ use gj::*;
 use index::ColumnIndex;
 use indexmap::map::Entry;
 use instant::{Duration, Instant};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use scheduler::Backoff;
 pub use serialize::{SerializeConfig, SerializedNode};
+pub use snapshot::{Epoch, SnapshotHandle};
 use sort::*;
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::hash::Hash;
-use std::io::Read;
 use std::iter::once;
 use std::ops::{Deref, Range};
 use std::path::PathBuf;
-use std::rc::Rc;
 use std::str::FromStr;
 use std::{fmt::Debug, sync::Arc};
 pub use termdag::{Term, TermDag, TermId};
@@ -64,7 +87,7 @@ pub type ArcSort = Arc<dyn Sort>;
 
 pub type Subst = IndexMap<Symbol, Value>;
 
-pub trait PrimitiveLike {
+pub trait PrimitiveLike: Send + Sync {
     fn name(&self) -> Symbol;
     /// Constructs a type constraint for the primitive that uses the span information
     /// for error localization.
@@ -94,6 +117,64 @@ pub struct RunReport {
     pub num_matches_per_rule: HashMap<Symbol, usize>,
     pub apply_time_per_ruleset: HashMap<Symbol, Duration>,
     pub rebuild_time_per_ruleset: HashMap<Symbol, Duration>,
+    /// Where the rebuilds at the start of each iteration spent their time.
+    pub rebuild_profile: RebuildProfile,
+    /// Set when a limit stopped a run before its schedule finished. A
+    /// schedule that stopped several times reports the first reason.
+    pub stop_reason: Option<StopReason>,
+}
+
+/// The time rebuilding spent in each of its stages, and how many passes over
+/// the tables it made. With more than one thread, the times of the tables
+/// canonicalized in parallel are added up.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RebuildProfile {
+    /// Finding the rows that hold ids that are no longer canonical, and
+    /// computing their canonical forms.
+    pub canonicalize: Duration,
+    /// Reinserting the canonical rows, and unioning the outputs of the ones
+    /// that became congruent.
+    pub congruence: Duration,
+    /// Running the merge functions and `:on_merge` actions of rows that
+    /// became congruent.
+    pub merge: Duration,
+    /// Running the analysis rulesets.
+    pub analyses: Duration,
+    pub passes: usize,
+}
+
+impl RebuildProfile {
+    fn union(&self, other: &Self) -> Self {
+        Self {
+            canonicalize: self.canonicalize + other.canonicalize,
+            congruence: self.congruence + other.congruence,
+            merge: self.merge + other.merge,
+            analyses: self.analyses + other.analyses,
+            passes: self.passes + other.passes,
+        }
+    }
+}
+
+/// Why a run stopped early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The database reached the `:node-limit` of a `run`.
+    NodeLimit,
+    /// The e-graph reached [`EGraph::max_memory`].
+    MemoryLimit,
+    /// The deadline of a `time-limit` schedule or
+    /// [`EGraph::run_with_deadline`] passed.
+    TimeLimit,
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::NodeLimit => write!(f, "node limit reached"),
+            StopReason::MemoryLimit => write!(f, "memory limit reached"),
+            StopReason::TimeLimit => write!(f, "time limit reached"),
+        }
+    }
 }
 
 impl RunReport {
@@ -215,6 +296,28 @@ impl Display for RunReport {
             )?;
         }
 
+        let RebuildProfile {
+            canonicalize,
+            congruence,
+            merge,
+            analyses,
+            passes,
+        } = self.rebuild_profile;
+        if passes > 0 {
+            writeln!(
+                f,
+                "Rebuild: canonicalize {:.3}s, congruence {:.3}s, merge {:.3}s, analyses {:.3}s, {passes} passes",
+                canonicalize.as_secs_f64(),
+                congruence.as_secs_f64(),
+                merge.as_secs_f64(),
+                analyses.as_secs_f64(),
+            )?;
+        }
+
+        if let Some(reason) = self.stop_reason {
+            writeln!(f, "Stopped early: {reason}")?;
+        }
+
         Ok(())
     }
 }
@@ -227,8 +330,10 @@ pub enum ExtractReport {
         cost: usize,
         term: Term,
     },
+    /// The cheapest distinct terms, cheapest first, with their costs.
     Variants {
         termdag: TermDag,
+        costs: Vec<usize>,
         terms: Vec<Term>,
     },
 }
@@ -285,6 +390,8 @@ impl RunReport {
                 &self.rebuild_time_per_ruleset,
                 &other.rebuild_time_per_ruleset,
             ),
+            rebuild_profile: self.rebuild_profile.union(&other.rebuild_profile),
+            stop_reason: self.stop_reason.or(other.stop_reason),
         }
     }
 }
@@ -348,6 +455,8 @@ impl<T: PrimitiveLike + 'static> From<T> for Primitive {
     }
 }
 
+/// A primitive with a fixed signature that operates directly on [`Value`]s.
+/// See [`add_primitives!`] for a typed alternative.
 pub struct SimplePrimitive {
     name: Symbol,
     input: Vec<ArcSort>,
@@ -355,6 +464,22 @@ pub struct SimplePrimitive {
     f: fn(&[Value]) -> Option<Value>,
 }
 
+impl SimplePrimitive {
+    pub fn new(
+        name: impl Into<Symbol>,
+        input: Vec<ArcSort>,
+        output: ArcSort,
+        f: fn(&[Value]) -> Option<Value>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            input,
+            output,
+            f,
+        }
+    }
+}
+
 impl PrimitiveLike for SimplePrimitive {
     fn name(&self) -> Symbol {
         self.name
@@ -431,19 +556,82 @@ pub struct EGraph {
     unionfind: UnionFind,
     pub functions: IndexMap<Symbol, Function>,
     rulesets: IndexMap<Symbol, Ruleset>,
+    /// Rulesets declared with `analysis-ruleset`, which every rebuild runs
+    /// until saturation.
+    analysis_rulesets: Vec<Symbol>,
     rule_last_run_timestamp: HashMap<Symbol, u32>,
+    /// The matches applied so far by rules that found more matches than
+    /// their `:match-limit` since they last ran to the end of their matches,
+    /// with canonical e-classes as of when they were last searched.
+    held_matches: HashMap<Symbol, HashSet<Vec<Value>>>,
     interactive_mode: bool,
     timestamp: u32,
     pub run_mode: RunMode,
     pub fact_directory: Option<PathBuf>,
     pub seminaive: bool,
+    /// Makes runs reproducible regardless of the number of threads: each
+    /// rule's matches are sorted and deduplicated before they are applied,
+    /// and rebuilding stays serial, so a run with any thread pool produces
+    /// the same ids, reports, and extractions as a run without one.
+    pub deterministic: bool,
+    /// Collects the unions and sets rules perform in an iteration and applies
+    /// them together at the end of it, after deduplicating them. Values set
+    /// for the same row are merged in increasing order, so conflicting sets
+    /// resolve the same way no matter which rule ran first. Lookups in rule
+    /// actions see the e-graph as of the start of the iteration.
+    pub batch_updates: bool,
+    /// Stops runs between iterations once
+    /// [`EGraph::approximate_memory_usage`] reaches this many bytes, so a
+    /// run that would exhaust memory ends with [`StopReason::MemoryLimit`]
+    /// instead. The estimate is checked after rebuilding, and an iteration
+    /// can still go over the budget before it is checked again.
+    pub max_memory: Option<usize>,
+    /// The most passes over the tables the rebuild at the start of each
+    /// iteration makes, or `None` to rebuild until the e-graph is closed
+    /// under congruence. Fewer passes leave some congruent rows for later
+    /// iterations to find, and an iteration that leaves some does not count
+    /// as saturating. Commands other than runs still rebuild fully first.
+    pub rebuild_passes: Option<usize>,
+    /// Where rules' unions and sets go while [`EGraph::batch_updates`] is set.
+    pending_updates: Option<PendingUpdates>,
     type_info: TypeInfo,
+    /// Set with `(set-option nan_policy ...)`. The `f64` sort follows it, and
+    /// takes it back from the e-graph on [`EGraph::pop`].
+    nan_policy: NanPolicy,
     extract_report: Option<ExtractReport>,
+    /// The costs found by the last `extract`, for
+    /// [`EGraph::extract_incremental`] to reuse. Anything that can make an
+    /// e-class more expensive drops them.
+    extraction_cache: Option<ExtractionCache>,
     /// The run report for the most recent run of a schedule.
     recent_run_report: Option<RunReport>,
     /// The run report unioned over all runs so far.
     overall_run_report: RunReport,
+    /// The result of the most recent `explain` command.
+    recent_explanation: Option<(TermDag, Explanation)>,
     msgs: Vec<String>,
+    /// See [`EGraph::set_num_threads`]. When there is more than one thread,
+    /// the rules of a ruleset are searched in parallel, rebuilding
+    /// canonicalizes the tables in parallel, extraction computes the
+    /// costs of e-nodes in parallel, and large programs are parsed in
+    /// parallel.
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    /// See [`EGraph::share_snapshots`].
+    snapshots: Option<SnapshotHandle>,
+    /// The timestamps of the epochs readers have pinned with
+    /// [`EGraph::pin_epoch`] and not released yet.
+    pinned_epochs: Vec<u32>,
+    /// The modules declared so far and the names imported from them.
+    modules: Modules,
+    /// The macros defined so far.
+    macros: Macros,
+    /// The innermost `backoff` schedule that is running, if any.
+    backoff: Option<Backoff>,
+    /// When the earliest running time limit ends. Runs stop between
+    /// iterations once it has passed.
+    deadline: Option<Instant>,
+    /// Everything declared so far, for [`EGraph::save`] to replay on load.
+    declarations: Vec<Declaration>,
 }
 
 impl Default for EGraph {
@@ -454,17 +642,35 @@ impl Default for EGraph {
             unionfind: Default::default(),
             functions: Default::default(),
             rulesets: Default::default(),
+            analysis_rulesets: Default::default(),
             rule_last_run_timestamp: Default::default(),
+            held_matches: Default::default(),
             timestamp: 0,
             run_mode: RunMode::Normal,
             interactive_mode: false,
             fact_directory: None,
             seminaive: true,
+            deterministic: false,
+            batch_updates: false,
+            max_memory: None,
+            rebuild_passes: None,
+            pending_updates: None,
             extract_report: None,
+            extraction_cache: None,
             recent_run_report: None,
             overall_run_report: Default::default(),
+            recent_explanation: None,
             msgs: Default::default(),
             type_info: Default::default(),
+            nan_policy: NanPolicy::Preserve,
+            thread_pool: None,
+            snapshots: None,
+            pinned_epochs: vec![],
+            modules: Default::default(),
+            macros: Default::default(),
+            backoff: None,
+            deadline: None,
+            declarations: vec![],
         };
         egraph
             .rulesets
@@ -491,6 +697,10 @@ impl EGraph {
         self.interactive_mode
     }
 
+    /// Save a copy of the egraph on a stack, so that [`EGraph::pop`] can
+    /// later restore its database, union-find, rulesets, and declarations.
+    /// Values that sorts interned after the push stay interned, but nothing
+    /// refers to them once the egraph is popped.
     pub fn push(&mut self) {
         self.egraphs.push(self.clone());
     }
@@ -507,8 +717,16 @@ impl EGraph {
                 let recent_run_report = self.recent_run_report.clone();
                 let overall_run_report = self.overall_run_report.clone();
                 let messages = self.msgs.clone();
+                let snapshots = self.snapshots.take();
+                let deadline = self.deadline;
 
                 *self = e;
+                // the f64 sort is shared with the popped e-graph
+                self.type_info
+                    .get_sort_nofail::<F64Sort>()
+                    .set_nan_policy(self.nan_policy);
+                self.snapshots = snapshots;
+                self.deadline = deadline;
                 self.extract_report = extract_report.or(self.extract_report.clone());
                 // We union the run reports, meaning
                 // that statistics are shared across
@@ -583,6 +801,23 @@ impl EGraph {
         }
     }
 
+    /// Calls `f` with the canonical id and sort of each e-class in `value`,
+    /// which is either an e-class itself or a container.
+    pub(crate) fn for_each_eclass(
+        &self,
+        sort: &ArcSort,
+        value: Value,
+        f: &mut impl FnMut(Id, &ArcSort),
+    ) {
+        if sort.is_eq_sort() {
+            f(self.find(sort, value).bits, sort)
+        } else {
+            for (sort, value) in sort.inner_values(&value) {
+                self.for_each_eclass(&sort, value, f);
+            }
+        }
+    }
+
     /// find the leader value for a particular eclass
     pub fn find(&self, sort: &ArcSort, value: Value) -> Value {
         if sort.is_eq_sort() {
@@ -606,36 +841,109 @@ impl EGraph {
     }
 
     pub fn rebuild(&mut self) -> Result<usize, Error> {
+        let (updates, _, _) = self.rebuild_profiled(None)?;
+        Ok(updates)
+    }
+
+    /// Rebuilds with at most `passes` passes over the tables after each
+    /// round of analyses, timing each stage. Also returns whether the
+    /// e-graph ended up closed under congruence.
+    fn rebuild_profiled(
+        &mut self,
+        passes: Option<usize>,
+    ) -> Result<(usize, bool, RebuildProfile), Error> {
+        let mut profile = RebuildProfile::default();
+        let (mut updates, mut closed) = self.rebuild_congruence(passes, &mut profile)?;
+        loop {
+            let start = Instant::now();
+            let changed = self.update_analyses();
+            profile.analyses += start.elapsed();
+            if !changed {
+                break;
+            }
+            let (new, done) = self.rebuild_congruence(passes, &mut profile)?;
+            updates += new;
+            closed = done;
+        }
+
+        if closed {
+            self.debug_assert_invariants();
+        }
+        Ok((updates, closed, profile))
+    }
+
+    /// Canonicalizes the tables until there are no more unions to make, or
+    /// `passes` passes have run, returning whether they all finished.
+    fn rebuild_congruence(
+        &mut self,
+        passes: Option<usize>,
+        profile: &mut RebuildProfile,
+    ) -> Result<(usize, bool), Error> {
         self.unionfind.clear_recent_ids();
 
         let mut updates = 0;
-        loop {
-            let new = self.rebuild_one()?;
+        for _ in 0..passes.unwrap_or(usize::MAX) {
+            let new = self.rebuild_one(profile)?;
             log::debug!("{new} rebuilds?");
             self.unionfind.clear_recent_ids();
+            profile.passes += 1;
             updates += new;
             if new == 0 {
-                break;
+                return Ok((updates, true));
             }
         }
-
-        self.debug_assert_invariants();
-        Ok(updates)
+        self.unionfind.restage_recent_ids();
+        Ok((updates, false))
     }
 
-    fn rebuild_one(&mut self) -> Result<usize, Error> {
+    fn rebuild_one(&mut self, profile: &mut RebuildProfile) -> Result<usize, Error> {
         let mut new_unions = 0;
         let mut deferred_merges = Vec::new();
-        for function in self.functions.values_mut() {
-            let (unions, merges) = function.rebuild(&mut self.unionfind, self.timestamp)?;
-            if !merges.is_empty() {
-                deferred_merges.push((function.decl.name, merges));
+        // Congruences are only recorded for explanations by a serial rebuild
+        let serial = self.deterministic || self.unionfind.explanations().is_some();
+        if let Some(pool) = self.thread_pool.as_ref().filter(|_| !serial) {
+            // Each table is canonicalized by one thread, with unions going
+            // straight to the shared union-find. Unions that affect other
+            // tables are picked up by the next round.
+            let (unionfind, timestamp) = (&self.unionfind, self.timestamp);
+            let functions: Vec<&mut Function> = self.functions.values_mut().collect();
+            let results: Vec<_> = pool.install(|| {
+                functions
+                    .into_par_iter()
+                    .map(|function| {
+                        let mut uf = UnionRef::Shared(unionfind, vec![]);
+                        let mut profile = RebuildProfile::default();
+                        let result = function.rebuild(&mut uf, timestamp, &mut profile);
+                        (function.decl.name, result, uf.into_dirty(), profile)
+                    })
+                    .collect()
+            });
+            for (name, result, dirty, table_profile) in results {
+                for (id, sort) in dirty {
+                    self.unionfind.mark_dirty(id, sort);
+                }
+                *profile = profile.union(&table_profile);
+                let (unions, merges) = result?;
+                if !merges.is_empty() {
+                    deferred_merges.push((name, merges));
+                }
+                new_unions += unions;
+            }
+        } else {
+            for function in self.functions.values_mut() {
+                let mut uf = UnionRef::Exclusive(&mut self.unionfind);
+                let (unions, merges) = function.rebuild(&mut uf, self.timestamp, profile)?;
+                if !merges.is_empty() {
+                    deferred_merges.push((function.decl.name, merges));
+                }
+                new_unions += unions;
             }
-            new_unions += unions;
         }
+        let start = Instant::now();
         for (func, merges) in deferred_merges {
             new_unions += self.apply_merges(func, &merges);
         }
+        profile.merge += start.elapsed();
 
         Ok(new_unions)
     }
@@ -668,7 +976,8 @@ impl EGraph {
     }
 
     fn declare_function(&mut self, decl: &ResolvedFunctionDecl) -> Result<(), Error> {
-        let function = Function::new(self, decl)?;
+        let mut function = Function::new(self, decl)?;
+        function.keep_rows_since(self.pinned_epochs.iter().min().copied());
         let old = self.functions.insert(decl.name, function);
         if old.is_some() {
             panic!(
@@ -683,10 +992,14 @@ impl EGraph {
     pub fn eval_lit(&self, lit: &Literal) -> Value {
         match lit {
             Literal::Int(i) => i.store(&I64Sort).unwrap(),
-            Literal::F64(f) => f.store(&F64Sort).unwrap(),
+            Literal::F64(f) => f
+                .store(&self.type_info.get_sort_nofail::<F64Sort>())
+                .unwrap(),
+            Literal::F32(f) => f.store(&F32Sort).unwrap(),
             Literal::String(s) => s.store(&StringSort).unwrap(),
             Literal::Unit => ().store(&UnitSort).unwrap(),
             Literal::Bool(b) => b.store(&BoolSort).unwrap(),
+            Literal::Bytes(b) => b.clone().store(&BytesSort).unwrap(),
         }
     }
 
@@ -838,6 +1151,19 @@ impl EGraph {
                 }
                 report
             }
+            ResolvedSchedule::Backoff(_span, config, sched) => {
+                let outer = self.backoff.replace(Backoff::new(*config));
+                let report = self.run_schedule(sched);
+                self.backoff = outer;
+                report
+            }
+            ResolvedSchedule::TimeLimit(_span, limit, sched) => {
+                let outer = self.deadline;
+                self.tighten_deadline(Instant::now() + *limit);
+                let report = self.run_schedule(sched);
+                self.deadline = outer;
+                report
+            }
         }
     }
 
@@ -862,14 +1188,54 @@ impl EGraph {
 
         // first rebuild
         let rebuild_start = Instant::now();
-        let updates = self.rebuild_nofail();
+        let (updates, closed, profile) = self
+            .rebuild_profiled(self.rebuild_passes)
+            .unwrap_or_else(|e| panic!("Unsoundness detected during rebuild. Exiting: {e}"));
+        report.rebuild_profile = profile;
+        // congruences left for later iterations are updates to come
+        report.updated |= !closed;
         log::debug!("database size: {}", self.num_tuples());
         log::debug!("Made {updates} updates");
         // add to the rebuild time for this ruleset
         report.add_ruleset_rebuild_time(config.ruleset, rebuild_start.elapsed());
         self.timestamp += 1;
 
-        let GenericRunConfig { ruleset, until } = config;
+        let GenericRunConfig {
+            ruleset,
+            node_limit,
+            until,
+        } = config;
+
+        if let Some(limit) = node_limit {
+            let num_tuples = self.num_tuples();
+            if num_tuples >= *limit {
+                log::info!(
+                    "Breaking early because the database has {num_tuples} tuples (limit {limit})"
+                );
+                report.stop_reason = Some(StopReason::NodeLimit);
+                return report;
+            }
+        }
+
+        if let Some(limit) = self.max_memory {
+            let memory = self.approximate_memory_usage();
+            if memory >= limit {
+                log::info!(
+                    "Breaking early because the e-graph uses about {memory} bytes (limit {limit})"
+                );
+                report.stop_reason = Some(StopReason::MemoryLimit);
+                return report;
+            }
+        }
+
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            log::info!("Breaking early because the time limit was reached");
+            report.stop_reason = Some(StopReason::TimeLimit);
+            return report;
+        }
 
         if let Some(facts) = until {
             if self.check_facts(span, facts).is_ok() {
@@ -886,6 +1252,7 @@ impl EGraph {
 
         log::debug!("database size: {}", self.num_tuples());
         self.timestamp += 1;
+        self.publish_snapshot();
 
         report
     }
@@ -904,34 +1271,43 @@ impl EGraph {
             .unwrap_or_else(|| panic!("ruleset does not exist: {}", &ruleset));
         match rules {
             Ruleset::Rules(_ruleset_name, rule_names) => {
-                let copy_rules = rule_names.clone();
+                let copy_rules: Vec<_> = rule_names
+                    .iter()
+                    .filter(|(rule_name, _)| {
+                        !self
+                            .backoff
+                            .as_ref()
+                            .is_some_and(|backoff| backoff.is_banned(**rule_name))
+                    })
+                    .collect();
                 let search_start = Instant::now();
 
-                for (rule_name, rule) in copy_rules.iter() {
-                    let mut all_matches = vec![];
-                    let rule_search_start = Instant::now();
-                    let mut did_match = false;
-                    let timestamp = self.rule_last_run_timestamp.get(rule_name).unwrap_or(&0);
-                    self.run_query(&rule.query, *timestamp, false, |values| {
-                        did_match = true;
-                        assert_eq!(values.len(), rule.query.vars.len());
-                        all_matches.extend_from_slice(values);
-                        Ok(())
-                    });
-                    let rule_search_time = rule_search_start.elapsed();
+                // Searching only reads the database, so with a thread pool
+                // each rule is searched on its own thread. The results are
+                // still recorded in rule order.
+                let results: Vec<(Symbol, SearchResult, Duration)> =
+                    if let Some(pool) = &self.thread_pool {
+                        pool.install(|| {
+                            copy_rules
+                                .into_par_iter()
+                                .map(|(rule_name, rule)| self.search_rule(*rule_name, rule))
+                                .collect()
+                        })
+                    } else {
+                        copy_rules
+                            .into_iter()
+                            .map(|(rule_name, rule)| self.search_rule(*rule_name, rule))
+                            .collect()
+                    };
+
+                for (rule_name, search_result, rule_search_time) in results {
                     log::trace!(
                         "Searched for {rule_name} in {:.3}s ({} results)",
                         rule_search_time.as_secs_f64(),
-                        all_matches.len()
-                    );
-                    run_report.add_rule_search_time(*rule_name, rule_search_time);
-                    search_results.insert(
-                        *rule_name,
-                        SearchResult {
-                            all_matches,
-                            did_match,
-                        },
+                        search_result.all_matches.len()
                     );
+                    run_report.add_rule_search_time(rule_name, rule_search_time);
+                    search_results.insert(rule_name, search_result);
                 }
 
                 let search_time = search_start.elapsed();
@@ -948,6 +1324,36 @@ impl EGraph {
         }
     }
 
+    /// Find all matches of a single rule since it last ran.
+    fn search_rule(
+        &self,
+        rule_name: Symbol,
+        rule: &CompiledRule,
+    ) -> (Symbol, SearchResult, Duration) {
+        let mut all_matches = vec![];
+        let rule_search_start = Instant::now();
+        let mut did_match = false;
+        let timestamp = self.rule_last_run_timestamp.get(&rule_name).unwrap_or(&0);
+        self.run_query(&rule.query, *timestamp, false, |values| {
+            did_match = true;
+            assert_eq!(values.len(), rule.query.vars.len());
+            all_matches.extend_from_slice(values);
+            Ok(())
+        });
+        let search_result = SearchResult {
+            all_matches,
+            did_match,
+        };
+        (rule_name, search_result, rule_search_start.elapsed())
+    }
+
+    /// The values of a match with every e-class replaced by its leader.
+    fn canonical_match(&self, rule: &CompiledRule, values: &[Value]) -> Vec<Value> {
+        (rule.vars.iter().zip(values))
+            .map(|(var, value)| self.find(&var.sort, *value))
+            .collect()
+    }
+
     fn apply_rules(
         &mut self,
         ruleset: Symbol,
@@ -961,41 +1367,112 @@ impl EGraph {
                 let apply_start = Instant::now();
                 let rule_names = compiled_rules.keys().cloned().collect::<Vec<_>>();
                 for rule_name in rule_names {
-                    let SearchResult {
+                    // banned rules were not searched
+                    let Some(SearchResult {
                         all_matches,
                         did_match,
-                    } = search_results.get(&rule_name).unwrap();
+                    }) = search_results.get(&rule_name)
+                    else {
+                        continue;
+                    };
                     let rule = compiled_rules.get(&rule_name).unwrap();
                     let num_vars = rule.query.vars.len();
+                    let all_matches: Cow<[Value]> = if self.deterministic && num_vars != 0 {
+                        let mut matches: Vec<&[Value]> = all_matches.chunks(num_vars).collect();
+                        matches.sort_unstable();
+                        matches.dedup();
+                        Cow::Owned(matches.concat())
+                    } else {
+                        Cow::Borrowed(all_matches)
+                    };
 
                     // make sure the query requires matches
                     if num_vars != 0 {
                         run_report.add_rule_num_matches(rule_name, all_matches.len() / num_vars);
                     }
 
-                    self.rule_last_run_timestamp
-                        .insert(rule_name, self.timestamp);
+                    if let Some(backoff) = &mut self.backoff {
+                        let num_matches = if num_vars == 0 {
+                            usize::from(*did_match)
+                        } else {
+                            all_matches.len() / num_vars
+                        };
+                        if backoff.check_matches(rule_name, num_matches) {
+                            continue;
+                        }
+                    }
+                    let mut held_back = false;
+                    let all_matches = match rule.match_limit {
+                        Some(limit) if num_vars != 0 => {
+                            let applied = self.held_matches.swap_remove(&rule_name);
+                            let applied: HashSet<Vec<Value>> = applied
+                                .into_iter()
+                                .flatten()
+                                .map(|m| self.canonical_match(rule, &m))
+                                .collect();
+                            // applied matches that no longer match are
+                            // dropped, so this stays as small as the matches
+                            let mut still_applied = HashSet::default();
+                            let mut matches = vec![];
+                            for m in all_matches.chunks(num_vars) {
+                                let canonical = self.canonical_match(rule, m);
+                                if applied.contains(&canonical) {
+                                    still_applied.insert(canonical);
+                                } else if matches.len() < limit {
+                                    matches.push(m);
+                                    still_applied.insert(canonical);
+                                } else {
+                                    held_back = limit != 0;
+                                }
+                            }
+                            if held_back {
+                                self.held_matches.insert(rule_name, still_applied);
+                            }
+                            Cow::Owned(matches.concat())
+                        }
+                        _ => all_matches,
+                    };
+
+                    if held_back {
+                        // the rule is searched from the same timestamp again
+                        // to apply the rest of its matches, so the rules have
+                        // not saturated yet
+                        run_report.updated = true;
+                    } else {
+                        self.rule_last_run_timestamp
+                            .insert(rule_name, self.timestamp);
+                    }
                     let rule_apply_start = Instant::now();
 
                     let stack = &mut vec![];
+                    let explaining = self.unionfind.explanations().is_some();
 
                     // when there are no variables, a query can still fail to match
                     // here we handle that case
                     if num_vars == 0 {
-                        if *did_match {
+                        if *did_match && rule.match_limit != Some(0) {
                             stack.clear();
+                            let reason = self.unionfind.set_reason(Reason::Rule(rule_name, vec![]));
                             self.run_actions(stack, &[], &rule.program)
                                 .unwrap_or_else(|e| {
                                     panic!("error while running actions for {rule_name}: {e}")
                                 });
+                            self.unionfind.set_reason(reason);
                         }
                     } else {
                         for values in all_matches.chunks(num_vars) {
                             stack.clear();
+                            let reason = explaining.then(|| {
+                                self.unionfind
+                                    .set_reason(Reason::Rule(rule_name, values.to_vec()))
+                            });
                             self.run_actions(stack, values, &rule.program)
                                 .unwrap_or_else(|e| {
                                     panic!("error while running actions for {rule_name}: {e}")
                                 });
+                            if let Some(reason) = reason {
+                                self.unionfind.set_reason(reason);
+                            }
                         }
                     }
 
@@ -1020,9 +1497,26 @@ impl EGraph {
         let mut run_report = Default::default();
         let mut search_results = HashMap::<Symbol, SearchResult>::default();
         self.search_rules(ruleset, &mut run_report, &mut search_results);
+        if self.batch_updates {
+            self.pending_updates = Some(Default::default());
+        }
         self.apply_rules(ruleset, &mut run_report, &search_results);
+        if let Some(pending) = self.pending_updates.take() {
+            let reason = self.unionfind.set_reason(Reason::Batched);
+            self.apply_pending_updates(pending)
+                .unwrap_or_else(|e| panic!("error while applying updates: {e}"));
+            self.unionfind.set_reason(reason);
+        }
         run_report.updated |=
             self.did_change_tables() || n_unions_before != self.unionfind.n_unions();
+        if let Some(backoff) = &mut self.backoff {
+            // Rules that are still banned have matches left to apply, so the
+            // schedule has not saturated yet.
+            if !run_report.updated && backoff.unban_all() {
+                run_report.updated = true;
+            }
+            backoff.next_iteration();
+        }
 
         run_report
     }
@@ -1053,7 +1547,13 @@ impl EGraph {
         let program = self
             .compile_actions(&vars, &actions)
             .map_err(Error::TypeErrors)?;
-        let compiled_rule = CompiledRule { query, program };
+        let compiled_rule = CompiledRule {
+            query,
+            program,
+            priority: rule.priority,
+            match_limit: rule.match_limit,
+            vars: vars.into_iter().collect(),
+        };
         if let Some(rules) = self.rulesets.get_mut(&ruleset) {
             match rules {
                 Ruleset::Rules(_, rules) => {
@@ -1063,6 +1563,8 @@ impl EGraph {
                         }
                         indexmap::map::Entry::Vacant(e) => e.insert(compiled_rule),
                     };
+                    // the sort is stable, so equal priorities keep declaration order
+                    rules.sort_by(|_, a, _, b| b.priority.cmp(&a.priority));
                     Ok(name)
                 }
                 Ruleset::Combined(_, _) => Err(Error::CombinedRulesetError(ruleset, rule.span)),
@@ -1106,8 +1608,51 @@ impl EGraph {
         Ok((sort, value))
     }
 
-    // TODO make a public version of eval_expr that makes a command,
-    // then returns the value at the end.
+    /// Adds a term to the e-graph and returns its e-class, which can be
+    /// passed to [`EGraph::extract`] later. Unlike [`EGraph::eval_expr`], this
+    /// does not declare a global for the term, and the e-graph is not rebuilt
+    /// first.
+    ///
+    /// ```
+    /// # use egglog::{ast::Expr, EGraph, TermDag};
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(None, "(datatype Math (Num i64) (Add Math Math))")
+    ///     .unwrap();
+    /// let one = Expr::call_no_span("Num", [Expr::lit_no_span(1)]);
+    /// let expr = Expr::call_no_span("Add", [one.clone(), one]);
+    /// let (sort, value) = egraph.add_expr(&expr).unwrap();
+    /// let mut termdag = TermDag::default();
+    /// let (_, term) = egraph.extract(value, &mut termdag, &sort);
+    /// assert_eq!(termdag.term_to_expr(&term), expr);
+    /// ```
+    pub fn add_expr(&mut self, expr: &Expr) -> Result<(ArcSort, Value), Error> {
+        Ok(self.add_exprs([expr])?.pop().unwrap())
+    }
+
+    /// Adds terms like [`EGraph::add_expr`], typechecking all of them before
+    /// adding any.
+    pub fn add_exprs<'a>(
+        &mut self,
+        exprs: impl IntoIterator<Item = &'a Expr>,
+    ) -> Result<Vec<(ArcSort, Value)>, Error> {
+        let resolved = exprs
+            .into_iter()
+            .map(|expr| {
+                self.type_info
+                    .typecheck_expr(&mut self.symbol_gen, expr, &Default::default())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        resolved
+            .into_iter()
+            .map(|expr| {
+                let sort = expr.output_type();
+                let value = self.eval_resolved_expr(&remove_globals_expr(expr))?;
+                Ok((sort, value))
+            })
+            .collect()
+    }
+
     fn eval_resolved_expr(&mut self, expr: &ResolvedExpr) -> Result<Value, Error> {
         let (actions, mapped_expr) = expr.to_core_actions(
             &self.type_info,
@@ -1146,6 +1691,47 @@ impl EGraph {
                     panic!("interactive_mode must be an integer");
                 }
             }
+            // Changes the available primitives, so it is applied during typechecking
+            "numeric_coercion" => {}
+            "threads" => {
+                if let ResolvedExpr::Lit(_ann, Literal::Int(n)) = value {
+                    let n = usize::try_from(n).expect("threads must be non-negative");
+                    self.set_num_threads(n);
+                } else {
+                    panic!("threads must be an integer");
+                }
+            }
+            "rebuild_passes" => {
+                if let ResolvedExpr::Lit(_ann, Literal::Int(n)) = value {
+                    let n = usize::try_from(n).expect("rebuild_passes must be non-negative");
+                    self.rebuild_passes = (n > 0).then_some(n);
+                } else {
+                    panic!("rebuild_passes must be an integer");
+                }
+            }
+            "explanations" => {
+                if let ResolvedExpr::Lit(_ann, Literal::Int(i)) = value {
+                    if i != 0 {
+                        self.enable_explanations();
+                    }
+                } else {
+                    panic!("explanations must be an integer");
+                }
+            }
+            "nan_policy" => {
+                self.nan_policy = match value {
+                    ResolvedExpr::Lit(_ann, Literal::String(s)) if s == "preserve".into() => {
+                        NanPolicy::Preserve
+                    }
+                    ResolvedExpr::Lit(_ann, Literal::String(s)) if s == "canonicalize".into() => {
+                        NanPolicy::Canonicalize
+                    }
+                    _ => panic!("nan_policy must be \"preserve\" or \"canonicalize\""),
+                };
+                self.type_info
+                    .get_sort_nofail::<F64Sort>()
+                    .set_nan_policy(self.nan_policy);
+            }
             _ => panic!("Unknown option '{}'", name),
         }
     }
@@ -1155,6 +1741,8 @@ impl EGraph {
             span: span.clone(),
             head: ResolvedActions::default(),
             body: facts.to_vec(),
+            priority: 0,
+            match_limit: None,
         };
         let core_rule = rule.to_canonicalized_core_rule(&self.type_info, &mut self.symbol_gen)?;
         let query = core_rule.body;
@@ -1207,6 +1795,11 @@ impl EGraph {
                 self.add_ruleset(name);
                 log::info!("Declared ruleset {name}.");
             }
+            ResolvedNCommand::AddAnalysisRuleset(name) => {
+                self.add_ruleset(name);
+                self.analysis_rulesets.push(name);
+                log::info!("Declared analysis ruleset {name}.");
+            }
             ResolvedNCommand::UnstableCombinedRuleset(name, others) => {
                 self.add_combined_ruleset(name, others);
                 log::info!("Declared ruleset {name}.");
@@ -1226,6 +1819,19 @@ impl EGraph {
                 self.overall_run_report = self.overall_run_report.union(&report);
                 self.recent_run_report = Some(report);
             }
+            ResolvedNCommand::RunPortfolio {
+                span: _,
+                schedules,
+                cost_of,
+            } => {
+                self.run_portfolio(&schedules, &cost_of)?;
+            }
+            ResolvedNCommand::Module { .. } | ResolvedNCommand::Import(..) => {
+                panic!("Modules should have been resolved before typechecking")
+            }
+            ResolvedNCommand::DefMacro { .. } => {
+                panic!("Macros should have been expanded before typechecking")
+            }
             ResolvedNCommand::PrintOverallStatistics => {
                 log::info!("Overall statistics:\n{}", self.overall_run_report);
                 self.print_msg(format!("Overall statistics:\n{}", self.overall_run_report));
@@ -1285,11 +1891,20 @@ impl EGraph {
                 }
             }
             ResolvedNCommand::Input {
-                span: _,
+                span,
                 name,
                 file,
+                format,
             } => {
-                self.input_file(name, file)?;
+                self.input_file(&span, name, &file, &format)?;
+            }
+            ResolvedNCommand::OutputFunction {
+                span,
+                name,
+                file,
+                format,
+            } => {
+                self.output_function(&span, name, &file, &format)?;
             }
             ResolvedNCommand::Output { span, file, exprs } => {
                 let mut filename = self.fact_directory.clone().unwrap_or_default();
@@ -1312,91 +1927,106 @@ impl EGraph {
 
                 log::info!("Output to '{filename:?}'.")
             }
+            ResolvedNCommand::CollectGarbage(_span) => {
+                self.collect_garbage()?;
+            }
+            ResolvedNCommand::SetCost {
+                constructor,
+                args,
+                cost,
+                ..
+            } => {
+                let cost = CostExpr::new(&args, cost);
+                self.extraction_cache = None;
+                self.functions.get_mut(&constructor).unwrap().cost = Some(Arc::new(cost));
+            }
+            ResolvedNCommand::Explain(span, a, b) => {
+                let sort = a.output_type();
+                let a = self.explain_pattern(&a)?;
+                let b = self.explain_pattern(&b)?;
+                let (termdag, explanation) = self
+                    .explain_patterns(&a, &b, &sort)
+                    .map_err(|message| Error::ExplainError(message, span))?;
+                self.print_msg(explanation.to_string(&termdag));
+                self.recent_explanation = Some((termdag, explanation));
+            }
+            ResolvedNCommand::Save { span, file } => {
+                let mut filename = self.fact_directory.clone().unwrap_or_default();
+                filename.push(file.as_str());
+                self.save_checkpoint(&filename, &span)?;
+            }
+            ResolvedNCommand::Load { span, file } => {
+                let mut filename = self.fact_directory.clone().unwrap_or_default();
+                filename.push(file.as_str());
+                self.load_checkpoint(&filename, &span)?;
+            }
+            ResolvedNCommand::ImportEGraph { span, file } => {
+                let mut filename = self.fact_directory.clone().unwrap_or_default();
+                filename.push(file.as_str());
+                self.import_checkpoint(&filename, &span)?;
+            }
         };
         Ok(())
     }
 
-    fn input_file(&mut self, func_name: Symbol, file: String) -> Result<(), Error> {
-        let function_type = self
-            .type_info
-            .lookup_user_func(func_name)
-            .unwrap_or_else(|| panic!("Unrecognized function name {}", func_name));
-        let func = self.functions.get_mut(&func_name).unwrap();
-
-        let mut filename = self.fact_directory.clone().unwrap_or_default();
-        filename.push(file.as_str());
-
-        // check that the function uses supported types
-
-        for t in &func.schema.input {
-            match t.name().as_str() {
-                "i64" | "f64" | "String" => {}
-                s => panic!("Unsupported type {} for input", s),
-            }
+    pub fn clear(&mut self) {
+        self.extraction_cache = None;
+        for f in self.functions.values_mut() {
+            f.clear();
         }
+    }
 
-        if !function_type.is_datatype {
-            match func.schema.output.name().as_str() {
-                "i64" | "String" | "Unit" => {}
-                s => panic!("Unsupported type {} for input", s),
-            }
+    /// Returns a handle through which other threads can query this e-graph
+    /// while it runs. See [`SnapshotHandle`].
+    pub fn share_snapshots(&mut self) -> SnapshotHandle {
+        let handle = self
+            .snapshots
+            .get_or_insert_with(SnapshotHandle::new)
+            .clone();
+        self.publish_snapshot();
+        handle
+    }
+
+    fn publish_snapshot(&mut self) {
+        if let Some(handle) = self.snapshots.take() {
+            // the stack of pushed e-graphs can't be queried, so don't copy it
+            let egraphs = std::mem::take(&mut self.egraphs);
+            handle.publish(self.clone());
+            self.egraphs = egraphs;
+            self.snapshots = Some(handle);
         }
+    }
 
-        log::info!("Opening file '{:?}'...", filename);
-        let mut f = File::open(filename).unwrap();
-        let mut contents = String::new();
-        f.read_to_string(&mut contents).unwrap();
-
-        let span: Span = DUMMY_SPAN.clone();
-        let mut actions: Vec<Action> = vec![];
-        let mut str_buf: Vec<&str> = vec![];
-        for line in contents.lines() {
-            str_buf.clear();
-            str_buf.extend(line.split('\t').map(|s| s.trim()));
-            if str_buf.is_empty() {
-                continue;
-            }
-
-            let parse = |s: &str| -> Expr {
-                match s.parse::<i64>() {
-                    Ok(i) => Expr::Lit(span.clone(), Literal::Int(i)),
-                    Err(_) => match s.parse::<f64>() {
-                        Ok(f) => Expr::Lit(span.clone(), Literal::F64(f.into())),
-                        Err(_) => Expr::Lit(span.clone(), Literal::String(s.into())),
-                    },
-                }
-            };
-
-            let mut exprs: Vec<Expr> = str_buf.iter().map(|&s| parse(s)).collect();
+    /// Searches rules, rebuilds tables, and extracts on a new pool of `n` threads, as
+    /// `(set-option threads n)` does. With `n` of 0 or 1, everything runs on
+    /// the calling thread, which is the default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the threads cannot be started.
+    pub fn set_num_threads(&mut self, n: usize) {
+        self.thread_pool = match n {
+            0 | 1 => None,
+            n => Some(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(n)
+                    .build()
+                    .expect("failed to start thread pool"),
+            )),
+        };
+    }
 
-            actions.push(
-                if function_type.is_datatype || function_type.output.name() == UnitSort.name() {
-                    Action::Expr(span.clone(), Expr::Call(span.clone(), func_name, exprs))
-                } else {
-                    let out = exprs.pop().unwrap();
-                    Action::Set(span.clone(), func_name, exprs, out)
-                },
-            );
-        }
-        let num_facts = actions.len();
-        let commands = actions
-            .into_iter()
-            .map(NCommand::CoreAction)
-            .collect::<Vec<_>>();
-        let commands: Vec<_> = self
-            .type_info
-            .typecheck_program(&mut self.symbol_gen, &commands)?;
-        for command in commands {
-            self.run_command(command)?;
-        }
-        log::info!("Read {num_facts} facts into {func_name} from '{file}'.");
-        Ok(())
+    /// Searches rules, rebuilds tables, and extracts on an existing thread pool, which
+    /// can be shared with other e-graphs or the rest of the application.
+    pub fn set_thread_pool(&mut self, pool: Arc<rayon::ThreadPool>) {
+        self.thread_pool = Some(pool);
     }
 
-    pub fn clear(&mut self) {
-        for f in self.functions.values_mut() {
-            f.clear();
-        }
+    /// The number of threads used to search rules and rebuild tables.
+    pub fn num_threads(&self) -> usize {
+        self.thread_pool
+            .as_ref()
+            .map_or(1, |pool| pool.current_num_threads())
     }
 
     pub fn set_reserved_symbol(&mut self, sym: Symbol) {
@@ -1410,11 +2040,14 @@ impl EGraph {
     fn process_command(&mut self, command: Command) -> Result<Vec<ResolvedNCommand>, Error> {
         let program =
             desugar::desugar_program(vec![command], &mut self.symbol_gen, self.seminaive)?;
+        let program = self.macros.expand(program)?;
+        let program = self.modules.resolve(program)?;
 
         let program = self
             .type_info
             .typecheck_program(&mut self.symbol_gen, &program)?;
 
+        self.record_declarations(&program);
         let program = remove_globals(program, &mut self.symbol_gen);
 
         Ok(program)
@@ -1452,25 +2085,78 @@ impl EGraph {
     ///
     /// `filename` is an optional argument to indicate the source of
     /// the program for error reporting. If `filename` is `None`,
-    /// a default name will be used.
+    /// a default name will be used. With more than one thread, large
+    /// programs are parsed in parallel with [`parse_program_parallel`].
     pub fn parse_and_run_program(
         &mut self,
         filename: Option<String>,
         input: &str,
     ) -> Result<Vec<String>, Error> {
-        let parsed = parse_program(filename, input)?;
+        let parsed = match &self.thread_pool {
+            Some(pool) => parse_program_parallel(filename, input, pool)?,
+            None => parse_program(filename, input)?,
+        };
         self.run_program(parsed)
     }
 
+    /// Runs `program` like [`EGraph::run_program`], but stops every run that
+    /// is still going at `deadline`, as if it were inside a `time-limit`
+    /// schedule. Runs stop between iterations, right after rebuilding, so
+    /// the e-graph is left consistent, and their reports have
+    /// [`RunReport::stop_reason`] set. Commands other than runs still run
+    /// after the deadline.
+    pub fn run_with_deadline(
+        &mut self,
+        program: Vec<Command>,
+        deadline: Instant,
+    ) -> Result<Vec<String>, Error> {
+        let outer = self.deadline;
+        self.tighten_deadline(deadline);
+        let result = self.run_program(program);
+        self.deadline = outer;
+        result
+    }
+
+    fn tighten_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(match self.deadline {
+            Some(outer) => outer.min(deadline),
+            None => deadline,
+        });
+    }
+
     pub fn num_tuples(&self) -> usize {
         self.functions.values().map(|f| f.nodes.len()).sum()
     }
 
+    /// Roughly how many bytes the e-graph uses for its function tables and
+    /// their indexes, its union-find, and the values its sorts intern.
+    /// Symbols and other values interned for the whole process are not
+    /// counted, nor are states saved by `push`.
+    pub fn approximate_memory_usage(&self) -> usize {
+        let functions: usize = self
+            .functions
+            .values()
+            .map(|f| f.approximate_memory_usage())
+            .sum();
+        let sorts: usize = self
+            .type_info
+            .sorts
+            .values()
+            .map(|sort| sort.approximate_memory_usage())
+            .sum();
+        functions + sorts + self.unionfind.approximate_memory_usage()
+    }
+
     /// Returns a sort based on the type
     pub fn get_sort<S: Sort + Send + Sync>(&self) -> Option<Arc<S>> {
         self.type_info.get_sort_by(|_| true)
     }
 
+    /// Returns a sort based on the type, panicking if it is not registered
+    pub fn get_sort_nofail<S: Sort + Send + Sync>(&self) -> Arc<S> {
+        self.type_info.get_sort_nofail()
+    }
+
     /// Returns the first sort that satisfies the type and predicate if there's one.
     /// Otherwise returns none.
     pub fn get_sort_by<S: Sort + Send + Sync>(
@@ -1533,6 +2219,12 @@ pub enum Error {
     CheckError(Vec<Fact>, Span),
     #[error("{1}\nNo such ruleset: {0}")]
     NoSuchRuleset(Symbol, Span),
+    #[error("{1}\nNo such module: {0}")]
+    NoSuchModule(Symbol, Span),
+    #[error("{3}\nMacro {0} takes {1} arguments, got {2}")]
+    MacroArityError(Symbol, usize, usize, Span),
+    #[error("{1}\nMacro {0} is called in its own expansion")]
+    RecursiveMacro(Symbol, Span),
     #[error("{1}\nAttempted to add a rule to combined ruleset {0}. Combined rulesets may only depend on other rulesets.")]
     CombinedRulesetError(Symbol, Span),
     #[error("Evaluating primitive {0:?} failed. ({0:?} {:?})", ListDebug(.1, " "))]
@@ -1545,8 +2237,16 @@ pub enum Error {
     ExpectFail(Span),
     #[error("{2}\nIO error: {0}: {1}")]
     IoError(PathBuf, std::io::Error, Span),
+    #[error("{1}\nInclude cycle: {0} is already being included")]
+    IncludeCycle(PathBuf, Span),
+    #[error("{2}\nCheckpoint error: {0:?}: {1}")]
+    CheckpointError(PathBuf, String, Span),
     #[error("Cannot subsume function with merge: {0}")]
     SubsumeMergeError(Symbol),
+    #[error("Fact for {0} has {1} inputs, expected {2}")]
+    FactArityError(Symbol, usize, usize),
+    #[error("{1}\nCannot explain: {0}")]
+    ExplainError(String, Span),
 }
 
 #[cfg(test)]
@@ -2,6 +2,7 @@ use clap::Parser;
 use egglog::{EGraph, RunMode, SerializeConfig};
 use std::io::{self, BufRead, BufReader};
 use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Debug, Parser)]
 #[command(version = env!("FULL_VERSION"), about = env!("CARGO_PKG_DESCRIPTION"))]
@@ -10,6 +11,19 @@ struct Args {
     fact_directory: Option<PathBuf>,
     #[clap(long)]
     naive: bool,
+    /// Sort and deduplicate matches, and rebuild serially, so that runs with
+    /// any number of threads produce the same e-graph
+    #[clap(long)]
+    deterministic: bool,
+    /// Apply the unions and sets of each iteration together at its end
+    #[clap(long)]
+    batch_updates: bool,
+    /// Number of threads to search rules and rebuild tables on
+    #[clap(long, default_value_t = 1)]
+    threads: usize,
+    /// Stop runs once the e-graph uses roughly this much memory, e.g. 512M or 8G
+    #[clap(long, value_parser = parse_memory_size)]
+    max_memory: Option<usize>,
     #[clap(long)]
     desugar: bool,
     #[clap(long)]
@@ -39,6 +53,32 @@ struct Args {
     serialize_n_inline_leaves: usize,
 }
 
+/// Parses a number of bytes, optionally followed by `K`, `M`, `G`, or `T`
+/// (powers of 1024) and then `B`.
+fn parse_memory_size(s: &str) -> Result<usize, String> {
+    let upper = s.to_ascii_uppercase();
+    let number = upper.strip_suffix('B').unwrap_or(&upper);
+    let (number, shift) = match number.strip_suffix(['K', 'M', 'G', 'T']) {
+        Some(rest) => {
+            let shift = match number.as_bytes()[number.len() - 1] {
+                b'K' => 10,
+                b'M' => 20,
+                b'G' => 30,
+                _ => 40,
+            };
+            (rest, shift)
+        }
+        None => (number, 0),
+    };
+    let n: usize = number
+        .parse()
+        .map_err(|_| format!("invalid memory size: {s}"))?;
+    1usize
+        .checked_shl(shift)
+        .and_then(|unit| n.checked_mul(unit))
+        .ok_or_else(|| format!("memory size too large: {s}"))
+}
+
 // test if the current command should be evaluated
 fn should_eval(curr_cmd: &str) -> bool {
     let mut count = 0;
@@ -96,12 +136,27 @@ fn main() {
         .init();
 
     let args = Args::parse();
+    // shared by the e-graphs of all inputs
+    let thread_pool = (args.threads > 1).then(|| {
+        Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(args.threads)
+                .build()
+                .expect("failed to start thread pool"),
+        )
+    });
 
     let mk_egraph = || {
         let mut egraph = EGraph::default();
         egraph.set_reserved_symbol(args.reserved_symbol.clone().into());
         egraph.fact_directory.clone_from(&args.fact_directory);
         egraph.seminaive = !args.naive;
+        egraph.deterministic = args.deterministic;
+        egraph.batch_updates = args.batch_updates;
+        egraph.max_memory = args.max_memory;
+        if let Some(pool) = &thread_pool {
+            egraph.set_thread_pool(pool.clone());
+        }
         egraph.run_mode = args.show;
         egraph
     };
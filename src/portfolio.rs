@@ -0,0 +1,63 @@
+//! Running several schedules on copies of an e-graph at once, with the
+//! `run-portfolio` command.
+
+use crate::*;
+
+impl EGraph {
+    /// Runs each schedule on a copy of the e-graph on its own thread, then
+    /// continues with the copy where `cost_of` extracts most cheaply, the
+    /// earliest one on ties.
+    pub(crate) fn run_portfolio(
+        &mut self,
+        schedules: &[ResolvedSchedule],
+        cost_of: &ResolvedExpr,
+    ) -> Result<(), Error> {
+        // the copies don't need the stack of pushed e-graphs or to publish
+        // snapshots of their own
+        let egraphs = std::mem::take(&mut self.egraphs);
+        let snapshots = self.snapshots.take();
+        let cost_of = remove_globals_expr(cost_of.clone());
+        let runs: Vec<Result<(EGraph, RunReport, Cost), Error>> = std::thread::scope(|scope| {
+            let threads: Vec<_> = schedules
+                .iter()
+                .map(|schedule| {
+                    let mut egraph = self.clone();
+                    let cost_of = &cost_of;
+                    scope.spawn(move || {
+                        let report = egraph.run_schedule(schedule);
+                        let value = egraph.eval_resolved_expr(cost_of)?;
+                        let mut termdag = TermDag::default();
+                        let (cost, _) = egraph.extract(value, &mut termdag, &cost_of.output_type());
+                        Ok((egraph, report, cost))
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().expect("portfolio schedule panicked"))
+                .collect()
+        });
+        self.egraphs = egraphs;
+        self.snapshots = snapshots;
+
+        let mut best: Option<(usize, EGraph, RunReport, Cost)> = None;
+        for (i, run) in runs.into_iter().enumerate() {
+            let (egraph, report, cost) = run?;
+            log::info!("Schedule {i} of the portfolio extracts at cost {cost}.");
+            if best.as_ref().map_or(true, |(_, _, _, best)| cost < *best) {
+                best = Some((i, egraph, report, cost));
+            }
+        }
+        let Some((i, mut egraph, report, cost)) = best else {
+            return Ok(());
+        };
+        log::info!("Kept schedule {i} of the portfolio, at cost {cost}.");
+        std::mem::swap(&mut egraph.egraphs, &mut self.egraphs);
+        egraph.snapshots = self.snapshots.take();
+        *self = egraph;
+        self.overall_run_report = self.overall_run_report.union(&report);
+        self.recent_run_report = Some(report);
+        self.publish_snapshot();
+        Ok(())
+    }
+}
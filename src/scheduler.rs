@@ -0,0 +1,79 @@
+use crate::ast::{BackoffConfig, Symbol};
+use crate::util::HashMap;
+
+/// The state of a `backoff` schedule while it runs, like egg's
+/// `BackoffScheduler`.
+///
+/// A rule that finds more than `match_limit << n` matches in an iteration,
+/// where `n` is the number of times it has been banned so far, is not applied
+/// and is banned for the next `ban_length << n` iterations. Its matches are
+/// found again once the ban ends, since it did not record that it ran.
+#[derive(Clone, Debug)]
+pub(crate) struct Backoff {
+    config: BackoffConfig,
+    iteration: usize,
+    stats: HashMap<Symbol, RuleStats>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct RuleStats {
+    times_banned: u32,
+    banned_until: usize,
+}
+
+impl Backoff {
+    pub(crate) fn new(config: BackoffConfig) -> Self {
+        Backoff {
+            config,
+            iteration: 0,
+            stats: Default::default(),
+        }
+    }
+
+    pub(crate) fn is_banned(&self, rule: Symbol) -> bool {
+        self.stats
+            .get(&rule)
+            .is_some_and(|stats| stats.banned_until > self.iteration)
+    }
+
+    /// Bans `rule` and returns true if `num_matches` is over its limit.
+    pub(crate) fn check_matches(&mut self, rule: Symbol, num_matches: usize) -> bool {
+        let stats = self.stats.entry(rule).or_default();
+        let threshold = shl(self.config.match_limit, stats.times_banned);
+        if num_matches <= threshold {
+            return false;
+        }
+        let ban_length = shl(self.config.ban_length, stats.times_banned);
+        stats.banned_until = self.iteration.saturating_add(ban_length);
+        stats.times_banned += 1;
+        log::info!(
+            "Banning {rule} for {ban_length} iterations ({num_matches} matches, limit {threshold})"
+        );
+        true
+    }
+
+    /// Moves on to the next iteration.
+    pub(crate) fn next_iteration(&mut self) {
+        self.iteration += 1;
+    }
+
+    /// Ends every ban, returning whether any rule was banned. Used when
+    /// nothing else changed, so the rules are not waiting on their bans
+    /// before the schedule saturates.
+    pub(crate) fn unban_all(&mut self) -> bool {
+        let mut any_banned = false;
+        for stats in self.stats.values_mut() {
+            if stats.banned_until > self.iteration {
+                stats.banned_until = self.iteration;
+                any_banned = true;
+            }
+        }
+        any_banned
+    }
+}
+
+fn shl(x: usize, n: u32) -> usize {
+    x.checked_shl(n)
+        .filter(|shifted| shifted >> n == x)
+        .unwrap_or(usize::MAX)
+}
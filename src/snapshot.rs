@@ -0,0 +1,129 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{EGraph, Error, Symbol, TypeError, Value, DUMMY_SPAN};
+
+/// A handle through which other threads can query an e-graph while it runs,
+/// created with [`EGraph::share_snapshots`].
+///
+/// The e-graph publishes a copy of itself when the handle is created and after
+/// every iteration of a run. Queries read the most recent copy, so they see the
+/// e-graph as it was between two iterations and never wait for the run to
+/// finish. Publishing clones the whole e-graph, which slows down runs with
+/// many cheap iterations.
+#[derive(Clone)]
+pub struct SnapshotHandle {
+    latest: Arc<Mutex<Option<Arc<EGraph>>>>,
+}
+
+impl SnapshotHandle {
+    pub(crate) fn new() -> Self {
+        SnapshotHandle {
+            latest: Default::default(),
+        }
+    }
+
+    pub(crate) fn publish(&self, egraph: EGraph) {
+        *self.latest.lock().unwrap() = Some(Arc::new(egraph));
+    }
+
+    /// The most recently published copy of the e-graph.
+    pub fn latest(&self) -> Arc<EGraph> {
+        self.latest
+            .lock()
+            .unwrap()
+            .clone()
+            .expect("a snapshot is published when the handle is created")
+    }
+
+    /// Runs commands such as `check`, `extract`, or `print-function` on the
+    /// most recently published copy of the e-graph and returns their output.
+    /// Any changes the commands make are discarded.
+    pub fn query(&self, program: &str) -> Result<Vec<String>, Error> {
+        let mut egraph = EGraph::clone(&self.latest());
+        egraph.parse_and_run_program(None, program)
+    }
+}
+
+/// A version of the tables of an e-graph that stays readable while the
+/// e-graph changes, created with [`EGraph::pin_epoch`].
+///
+/// Tables only ever append rows: a row that is removed or merged is
+/// marked with the timestamp it stopped being current at and a new version of
+/// it is added. An epoch is a timestamp, and the rows as of the epoch are the
+/// ones added before it and not yet replaced then. Rows that are replaced
+/// after the oldest pinned epoch are kept until it is released.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Epoch(u32);
+
+impl EGraph {
+    /// Pins the tables as they are now, so that [`EGraph::rows_at`] can read
+    /// them after later commands change the e-graph. The epoch holds on to
+    /// replaced rows until it is given back to [`EGraph::release_epoch`].
+    /// Garbage collection and [`EGraph::clear`] start the tables over and do
+    /// not keep their history.
+    ///
+    /// ```
+    /// # use egglog::{EGraph, Value};
+    /// let mut egraph = EGraph::default();
+    /// egraph
+    ///     .parse_and_run_program(
+    ///         None,
+    ///         "(function cost (i64) i64 :merge (min old new))
+    ///          (set (cost 1) 10)",
+    ///     )
+    ///     .unwrap();
+    /// let epoch = egraph.pin_epoch();
+    /// egraph
+    ///     .parse_and_run_program(None, "(set (cost 1) 5) (set (cost 2) 7)")
+    ///     .unwrap();
+    /// let rows: Vec<_> = egraph
+    ///     .rows_at("cost".into(), &epoch)
+    ///     .unwrap()
+    ///     .map(|(inputs, output)| (inputs.to_vec(), output))
+    ///     .collect();
+    /// assert_eq!(rows, vec![(vec![Value::from(1)], Value::from(10))]);
+    /// egraph.release_epoch(epoch);
+    /// ```
+    pub fn pin_epoch(&mut self) -> Epoch {
+        // Later changes happen at a later timestamp than any row so far
+        self.timestamp += 1;
+        self.pinned_epochs.push(self.timestamp);
+        self.keep_pinned_rows();
+        Epoch(self.timestamp)
+    }
+
+    /// Releases an epoch, letting the tables drop the rows only it could see.
+    pub fn release_epoch(&mut self, epoch: Epoch) {
+        if let Some(i) = self.pinned_epochs.iter().position(|&ts| ts == epoch.0) {
+            self.pinned_epochs.swap_remove(i);
+        }
+        self.keep_pinned_rows();
+    }
+
+    /// The rows of a function as of an epoch, leaving out subsumed rows.
+    /// Subsuming marks a row in place rather than adding a version of it,
+    /// so rows subsumed after the epoch are left out as well. E-classes in
+    /// them are the ones that were canonical then, which [`EGraph::find`]
+    /// maps to the current ones.
+    pub fn rows_at<'a>(
+        &'a self,
+        name: Symbol,
+        epoch: &Epoch,
+    ) -> Result<impl Iterator<Item = (&'a [Value], Value)> + 'a, Error> {
+        let function = self
+            .functions
+            .get(&name)
+            .ok_or(TypeError::UnboundFunction(name, DUMMY_SPAN.clone()))?;
+        Ok(function
+            .nodes
+            .iter_at(epoch.0, false)
+            .map(|(inputs, output)| (inputs, output.value)))
+    }
+
+    fn keep_pinned_rows(&mut self) {
+        let oldest = self.pinned_epochs.iter().min().copied();
+        for function in self.functions.values_mut() {
+            function.keep_rows_since(oldest);
+        }
+    }
+}
@@ -1,15 +1,16 @@
+use num::integer::Integer;
+use num::traits::{Num, Signed};
 use num::BigInt;
 use std::ops::{Shl, Shr};
-use std::sync::Mutex;
 
 type Z = BigInt;
-use crate::{ast::Literal, util::IndexSet};
+use crate::{ast::Literal, util::Interner};
 
 use super::*;
 
 lazy_static! {
     static ref BIG_INT_SORT_NAME: Symbol = "BigInt".into();
-    static ref INTS: Mutex<IndexSet<Z>> = Default::default();
+    static ref INTS: Interner<Z> = Default::default();
 }
 
 #[derive(Debug)]
@@ -39,11 +40,18 @@ impl Sort for BigIntSort {
         add_primitives!(eg, "&" = |a: Z, b: Z| -> Z { a & b });
         add_primitives!(eg, "|" = |a: Z, b: Z| -> Z { a | b });
         add_primitives!(eg, "^" = |a: Z, b: Z| -> Z { a ^ b });
-        add_primitives!(eg, "<<" = |a: Z, b: i64| -> Z { a.shl(b) });
-        add_primitives!(eg, ">>" = |a: Z, b: i64| -> Z { a.shr(b) });
+        add_primitives!(eg, "<<" = |a: Z, b: i64| -> Opt<Z> { (b >= 0).then(|| a.shl(b)) });
+        add_primitives!(eg, ">>" = |a: Z, b: i64| -> Opt<Z> { (b >= 0).then(|| a.shr(b)) });
         add_primitives!(eg, "not-Z" = |a: Z| -> Z { !a });
 
         add_primitives!(eg, "bits" = |a: Z| -> Z { a.bits().into() });
+        // Negative numbers have infinitely many one bits in two's complement.
+        add_primitives!(eg, "popcount" = |a: Z| -> Opt<Z> { (!a.is_negative()).then(|| a.magnitude().count_ones().into()) });
+
+        add_primitives!(eg, "gcd" = |a: Z, b: Z| -> Z { a.gcd(&b) });
+        add_primitives!(eg, "lcm" = |a: Z, b: Z| -> Z { a.lcm(&b) });
+        // Like `mod_floor`, the result takes the sign of the modulus.
+        add_primitives!(eg, "modpow" = |a: Z, e: Z, m: Z| -> Opt<Z> { (!e.is_negative() && m != BigInt::ZERO).then(|| a.modpow(&e, &m)) });
 
         add_primitives!(eg, "<" = |a: Z, b: Z| -> Opt { (a < b).then_some(()) });
         add_primitives!(eg, ">" = |a: Z, b: Z| -> Opt { (a > b).then_some(()) });
@@ -61,6 +69,8 @@ impl Sort for BigIntSort {
 
         add_primitives!(eg, "to-string" = |a: Z| -> Symbol { a.to_string().into() });
         add_primitives!(eg, "from-string" = |a: Symbol| -> Opt<Z> { a.as_str().parse::<Z>().ok() });
+        add_primitives!(eg, "to-string-radix" = |a: Z, base: i64| -> Opt<Symbol> { Some(a.to_str_radix(radix(base)?).into()) });
+        add_primitives!(eg, "from-string-radix" = |s: Symbol, base: i64| -> Opt<Z> { Z::from_str_radix(s.as_str(), radix(base)?).ok() });
    }
 
     fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
@@ -84,19 +94,18 @@ impl Sort for BigIntSort {
 impl FromSort for Z {
     type Sort = BigIntSort;
     fn load(_sort: &Self::Sort, value: &Value) -> Self {
-        let i = value.bits as usize;
-        INTS.lock().unwrap().get_index(i).unwrap().clone()
+        INTS.get(value.bits)
     }
 }
 
 impl IntoSort for Z {
     type Sort = BigIntSort;
     fn store(self, _sort: &Self::Sort) -> Option<Value> {
-        let (i, _) = INTS.lock().unwrap().insert_full(self);
+        let i = INTS.intern(self);
         Some(Value {
             #[cfg(debug_assertions)]
             tag: BigIntSort.name(),
-            bits: i as u64,
+            bits: i,
         })
     }
 }
@@ -1,16 +1,16 @@
 use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, Signed, ToPrimitive, Zero};
 use num::{rational::BigRational, BigInt};
-use std::sync::Mutex;
 
 type Z = BigInt;
 type Q = BigRational;
-use crate::{ast::Literal, util::IndexSet};
+type R = num::rational::Rational64;
+use crate::{ast::Literal, util::Interner};
 
 use super::*;
 
 lazy_static! {
     static ref BIG_RAT_SORT_NAME: Symbol = "BigRat".into();
-    static ref RATS: Mutex<IndexSet<Q>> = Default::default();
+    static ref RATS: Interner<Q> = Default::default();
 }
 
 #[derive(Debug)]
@@ -46,6 +46,18 @@ impl Sort for BigRatSort {
         add_primitives!(eg, "denom" = |a: Q| -> Z { a.denom().clone() });
 
         add_primitives!(eg, "to-f64" = |a: Q| -> f64 { a.to_f64().unwrap() });
+        add_primitives!(eg, "to-bigrat" = |a: i64| -> Q { Q::from_integer(a.into()) });
+        add_primitives!(eg, "to-bigrat" = |a: R| -> Q { Q::new((*a.numer()).into(), (*a.denom()).into()) });
+        add_primitives!(eg, "to-rational" = |a: Q| -> Opt<R> {
+            Some(R::new(a.numer().to_i64()?, a.denom().to_i64()?))
+        });
+        add_primitives!(eg, "to-i64" = |a: Q| -> Opt<i64> {
+            if a.is_integer() {
+                a.to_integer().to_i64()
+            } else {
+                None
+            }
+        });
 
         add_primitives!(eg, "pow" = |a: Q, b: Q| -> Option<Q> {
             if a.is_zero() {
@@ -56,22 +68,25 @@ impl Sort for BigRatSort {
                 }
             } else if b.is_zero() {
                 Some(Q::one())
+            } else if !b.is_integer() {
+                None
             } else if let Some(b) = b.to_i64() {
-                if let Ok(b) = usize::try_from(b) {
-                    num::traits::checked_pow(a, b)
+                let p = num::traits::checked_pow(a, usize::try_from(b.unsigned_abs()).ok()?)?;
+                if b.is_negative() {
+                    Q::one().checked_div(&p)
                 } else {
-                    // TODO handle negative powers
-                    None
+                    Some(p)
                 }
             } else {
                 None
             }
         });
         add_primitives!(eg, "log" = |a: Q| -> Option<Q> {
+            // The natural log of any rational other than 1 is irrational.
             if a.is_one() {
                 Some(Q::zero())
             } else {
-                todo!()
+                None
             }
         });
         add_primitives!(eg, "sqrt" = |a: Q| -> Option<Q> {
@@ -88,13 +103,16 @@ impl Sort for BigRatSort {
                 None
             }
         });
-        add_primitives!(eg, "cbrt" = |a: Q| -> Option<Q> {
-            if a.is_one() {
-                Some(Q::one())
+        add_primitives!(eg, "cbrt" = |a: Q| -> Option<Q> {{
+            let c1 = a.numer().cbrt();
+            let c2 = a.denom().cbrt();
+            let is_perfect = &(c1.clone() * c1.clone() * c1.clone()) == a.numer() && &(c2.clone() * c2.clone() * c2.clone()) == a.denom();
+            if is_perfect {
+                Some(Q::new(c1, c2))
             } else {
-                todo!()
+                None
             }
-        });
+        }});
 
         add_primitives!(eg, "<" = |a: Q, b: Q| -> Opt { if a < b {Some(())} else {None} });
         add_primitives!(eg, ">" = |a: Q, b: Q| -> Opt { if a > b {Some(())} else {None} });
@@ -137,19 +155,18 @@ impl Sort for BigRatSort {
 impl FromSort for Q {
     type Sort = BigRatSort;
     fn load(_sort: &Self::Sort, value: &Value) -> Self {
-        let i = value.bits as usize;
-        RATS.lock().unwrap().get_index(i).unwrap().clone()
+        RATS.get(value.bits)
     }
 }
 
 impl IntoSort for Q {
     type Sort = BigRatSort;
     fn store(self, _sort: &Self::Sort) -> Option<Value> {
-        let (i, _) = RATS.lock().unwrap().insert_full(self);
+        let i = RATS.intern(self);
         Some(Value {
             #[cfg(debug_assertions)]
             tag: BigRatSort.name(),
-            bits: i as u64,
+            bits: i,
         })
     }
 }
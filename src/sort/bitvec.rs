@@ -0,0 +1,541 @@
+use crate::ast::Literal;
+
+use super::*;
+
+/// Fixed-width bitvectors, declared with `(sort BV8 (BitVec 8))`.
+///
+/// Widths from 1 to 64 are supported. Values are stored inline in
+/// [`Value::bits`], masked to the width of the sort. Arithmetic wraps and
+/// follows SMT-LIB semantics, including for division by zero.
+///
+/// Operations whose output width differs from their inputs (`bvconcat`,
+/// `bvextract`, `bvzext`, `bvsext`) are registered for every
+/// combination of declared bitvector sorts, so the output sort has to be
+/// declared before it can be used and is selected by the surrounding context.
+#[derive(Debug)]
+pub struct BitVecSort {
+    name: Symbol,
+    width: u32,
+}
+
+impl BitVecSort {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.width)
+    }
+
+    fn value(&self, bits: u64) -> Value {
+        Value {
+            #[cfg(debug_assertions)]
+            tag: self.name,
+            bits: bits & self.mask(),
+        }
+    }
+
+    fn to_signed(&self, bits: u64) -> i64 {
+        let shift = 64 - self.width;
+        ((bits << shift) as i64) >> shift
+    }
+}
+
+impl Presort for BitVecSort {
+    fn presort_name() -> Symbol {
+        "BitVec".into()
+    }
+
+    fn reserved_primitives() -> Vec<Symbol> {
+        vec![
+            "bv".into(),
+            "bv-to-nat".into(),
+            "bv-to-int".into(),
+            "bvadd".into(),
+            "bvsub".into(),
+            "bvmul".into(),
+            "bvudiv".into(),
+            "bvurem".into(),
+            "bvand".into(),
+            "bvor".into(),
+            "bvxor".into(),
+            "bvshl".into(),
+            "bvlshr".into(),
+            "bvashr".into(),
+            "bvnot".into(),
+            "bvneg".into(),
+            "bvult".into(),
+            "bvule".into(),
+            "bvugt".into(),
+            "bvuge".into(),
+            "bvslt".into(),
+            "bvsle".into(),
+            "bvsgt".into(),
+            "bvsge".into(),
+            "bvconcat".into(),
+            "bvextract".into(),
+            "bvzext".into(),
+            "bvsext".into(),
+        ]
+    }
+
+    fn make_sort(
+        _typeinfo: &mut TypeInfo,
+        name: Symbol,
+        args: &[Expr],
+    ) -> Result<ArcSort, TypeError> {
+        match args {
+            [Expr::Lit(_, Literal::Int(width))] if (1..=64).contains(width) => {
+                Ok(Arc::new(BitVecSort {
+                    name,
+                    width: *width as u32,
+                }))
+            }
+            [arg] => Err(TypeError::DisallowedSort(
+                name,
+                "BitVec width must be an integer literal between 1 and 64".into(),
+                arg.span(),
+            )),
+            _ => panic!("BitVec sort must have a width as argument. Got {:?}", args),
+        }
+    }
+}
+
+impl Sort for BitVecSort {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn register_primitives(self: Arc<Self>, typeinfo: &mut TypeInfo) {
+        let unit = typeinfo.get_sort_nofail::<UnitSort>();
+        let int = typeinfo.get_sort_nofail::<I64Sort>();
+
+        typeinfo.add_primitive(FromInt {
+            name: "bv".into(),
+            bv: self.clone(),
+            int: int.clone(),
+        });
+        typeinfo.add_primitive(ToInt {
+            name: "bv-to-nat".into(),
+            bv: self.clone(),
+            int: int.clone(),
+            signed: false,
+        });
+        typeinfo.add_primitive(ToInt {
+            name: "bv-to-int".into(),
+            bv: self.clone(),
+            int: int.clone(),
+            signed: true,
+        });
+
+        let binops: [(&str, BinOpFn); 11] = [
+            ("bvadd", |_, a, b| a.wrapping_add(b)),
+            ("bvsub", |_, a, b| a.wrapping_sub(b)),
+            ("bvmul", |_, a, b| a.wrapping_mul(b)),
+            ("bvudiv", |_, a, b| a.checked_div(b).unwrap_or(u64::MAX)),
+            ("bvurem", |_, a, b| a.checked_rem(b).unwrap_or(a)),
+            ("bvand", |_, a, b| a & b),
+            ("bvor", |_, a, b| a | b),
+            ("bvxor", |_, a, b| a ^ b),
+            (
+                "bvshl",
+                |bv, a, b| if b < bv.width as u64 { a << b } else { 0 },
+            ),
+            (
+                "bvlshr",
+                |bv, a, b| if b < bv.width as u64 { a >> b } else { 0 },
+            ),
+            ("bvashr", |bv, a, b| {
+                (bv.to_signed(a) >> b.min(bv.width as u64 - 1)) as u64
+            }),
+        ];
+        for (name, op) in binops {
+            typeinfo.add_primitive(BinOp {
+                name: name.into(),
+                bv: self.clone(),
+                op,
+            });
+        }
+
+        let unops: [(&str, UnOpFn); 2] = [("bvnot", |a| !a), ("bvneg", |a| a.wrapping_neg())];
+        for (name, op) in unops {
+            typeinfo.add_primitive(UnOp {
+                name: name.into(),
+                bv: self.clone(),
+                op,
+            });
+        }
+
+        let cmps: [(&str, CmpFn); 8] = [
+            ("bvult", |_, a, b| a < b),
+            ("bvule", |_, a, b| a <= b),
+            ("bvugt", |_, a, b| a > b),
+            ("bvuge", |_, a, b| a >= b),
+            ("bvslt", |bv, a, b| bv.to_signed(a) < bv.to_signed(b)),
+            ("bvsle", |bv, a, b| bv.to_signed(a) <= bv.to_signed(b)),
+            ("bvsgt", |bv, a, b| bv.to_signed(a) > bv.to_signed(b)),
+            ("bvsge", |bv, a, b| bv.to_signed(a) >= bv.to_signed(b)),
+        ];
+        for (name, op) in cmps {
+            typeinfo.add_primitive(Cmp {
+                name: name.into(),
+                bv: self.clone(),
+                unit: unit.clone(),
+                op,
+            });
+        }
+
+        // Register the width-changing operations between this sort and every
+        // bitvector sort declared so far (including this one).
+        let mut sorts: Vec<Arc<BitVecSort>> = typeinfo
+            .sorts
+            .values()
+            .filter_map(|sort| Arc::downcast(sort.clone().as_arc_any()).ok())
+            .collect();
+        sorts.sort_by_key(|sort| sort.name.as_str());
+        let is_new = |sort: &Arc<BitVecSort>| Arc::ptr_eq(sort, &self);
+
+        for lhs in &sorts {
+            for rhs in &sorts {
+                for out in &sorts {
+                    if lhs.width + rhs.width == out.width
+                        && (is_new(lhs) || is_new(rhs) || is_new(out))
+                    {
+                        typeinfo.add_primitive(Concat {
+                            name: "bvconcat".into(),
+                            lhs: lhs.clone(),
+                            rhs: rhs.clone(),
+                            out: out.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        for src in &sorts {
+            for dst in &sorts {
+                if !is_new(src) && !is_new(dst) {
+                    continue;
+                }
+                if dst.width <= src.width {
+                    typeinfo.add_primitive(Extract {
+                        name: "bvextract".into(),
+                        src: src.clone(),
+                        dst: dst.clone(),
+                        int: int.clone(),
+                    });
+                }
+                if dst.width >= src.width {
+                    typeinfo.add_primitive(Extend {
+                        name: "bvzext".into(),
+                        src: src.clone(),
+                        dst: dst.clone(),
+                        signed: false,
+                    });
+                    typeinfo.add_primitive(Extend {
+                        name: "bvsext".into(),
+                        src: src.clone(),
+                        dst: dst.clone(),
+                        signed: true,
+                    });
+                }
+            }
+        }
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        (
+            1,
+            Expr::call_no_span(
+                "bv",
+                vec![GenericExpr::Lit(
+                    DUMMY_SPAN.clone(),
+                    Literal::Int(value.bits as i64),
+                )],
+            ),
+        )
+    }
+}
+
+type BinOpFn = fn(&BitVecSort, u64, u64) -> u64;
+type UnOpFn = fn(u64) -> u64;
+type CmpFn = fn(&BitVecSort, u64, u64) -> bool;
+
+struct FromInt {
+    name: Symbol,
+    bv: Arc<BitVecSort>,
+    int: Arc<I64Sort>,
+}
+
+impl PrimitiveLike for FromInt {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.int.clone(), self.bv.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        Some(self.bv.value(values[0].bits))
+    }
+}
+
+struct ToInt {
+    name: Symbol,
+    bv: Arc<BitVecSort>,
+    int: Arc<I64Sort>,
+    signed: bool,
+}
+
+impl PrimitiveLike for ToInt {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.bv.clone(), self.int.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let bits = values[0].bits;
+        let int = if self.signed {
+            self.bv.to_signed(bits)
+        } else {
+            i64::try_from(bits).ok()?
+        };
+        int.store(&self.int)
+    }
+}
+
+struct BinOp {
+    name: Symbol,
+    bv: Arc<BitVecSort>,
+    op: BinOpFn,
+}
+
+impl PrimitiveLike for BinOp {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        AllEqualTypeConstraint::new(self.name(), span.clone())
+            .with_all_arguments_sort(self.bv.clone())
+            .with_exact_length(3)
+            .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        Some(
+            self.bv
+                .value((self.op)(&self.bv, values[0].bits, values[1].bits)),
+        )
+    }
+}
+
+struct UnOp {
+    name: Symbol,
+    bv: Arc<BitVecSort>,
+    op: UnOpFn,
+}
+
+impl PrimitiveLike for UnOp {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        AllEqualTypeConstraint::new(self.name(), span.clone())
+            .with_all_arguments_sort(self.bv.clone())
+            .with_exact_length(2)
+            .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        Some(self.bv.value((self.op)(values[0].bits)))
+    }
+}
+
+struct Cmp {
+    name: Symbol,
+    bv: Arc<BitVecSort>,
+    unit: Arc<UnitSort>,
+    op: CmpFn,
+}
+
+impl PrimitiveLike for Cmp {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        AllEqualTypeConstraint::new(self.name(), span.clone())
+            .with_all_arguments_sort(self.bv.clone())
+            .with_exact_length(3)
+            .with_output_sort(self.unit.clone())
+            .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        (self.op)(&self.bv, values[0].bits, values[1].bits).then(Value::unit)
+    }
+}
+
+/// `(bvconcat hi lo)` places the bits of `hi` above the bits of `lo`.
+struct Concat {
+    name: Symbol,
+    lhs: Arc<BitVecSort>,
+    rhs: Arc<BitVecSort>,
+    out: Arc<BitVecSort>,
+}
+
+impl PrimitiveLike for Concat {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.lhs.clone(), self.rhs.clone(), self.out.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        // `checked_shl` handles a 64-bit `lo` with an empty `hi`
+        let hi = values[0].bits.checked_shl(self.rhs.width).unwrap_or(0);
+        Some(self.out.value(hi | values[1].bits))
+    }
+}
+
+/// `(bvextract hi lo x)` returns bits `hi` down to `lo` (inclusive) of `x`. Fails
+/// unless `hi - lo + 1` is the width of the output sort.
+struct Extract {
+    name: Symbol,
+    src: Arc<BitVecSort>,
+    dst: Arc<BitVecSort>,
+    int: Arc<I64Sort>,
+}
+
+impl PrimitiveLike for Extract {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![
+                self.int.clone(),
+                self.int.clone(),
+                self.src.clone(),
+                self.dst.clone(),
+            ],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let hi = i64::load(&self.int, &values[0]);
+        let lo = i64::load(&self.int, &values[1]);
+        if lo < 0 || hi < lo || hi >= self.src.width as i64 {
+            return None;
+        }
+        if hi - lo + 1 != self.dst.width as i64 {
+            return None;
+        }
+        Some(self.dst.value(values[2].bits >> lo))
+    }
+}
+
+struct Extend {
+    name: Symbol,
+    src: Arc<BitVecSort>,
+    dst: Arc<BitVecSort>,
+    signed: bool,
+}
+
+impl PrimitiveLike for Extend {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.src.clone(), self.dst.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let bits = values[0].bits;
+        if self.signed {
+            Some(self.dst.value(self.src.to_signed(bits) as u64))
+        } else {
+            Some(self.dst.value(bits))
+        }
+    }
+}
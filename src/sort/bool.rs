@@ -1,4 +1,5 @@
 use crate::ast::Literal;
+use crate::constraint::AllEqualTypeConstraint;
 
 use super::*;
 
@@ -21,10 +22,11 @@ impl Sort for BoolSort {
     #[rustfmt::skip]
     fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
         add_primitives!(eg, "not" = |a: bool| -> bool { !a });
-        add_primitives!(eg, "and" = |a: bool, b: bool| -> bool { a && b });
-        add_primitives!(eg, "or" = |a: bool, b: bool| -> bool { a || b });
         add_primitives!(eg, "xor" = |a: bool, b: bool| -> bool { a ^ b });
         add_primitives!(eg, "=>" = |a: bool, b: bool| -> bool { !a || b });
+        add_primitives!(eg, "bool-eq" = |a: bool, b: bool| -> bool { a == b });
+        eg.add_primitive(Connective { name: "and".into(), identity: true });
+        eg.add_primitive(Connective { name: "or".into(), identity: false });
     }
 
     fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
@@ -55,3 +57,40 @@ impl FromSort for bool {
         value.bits != 0
     }
 }
+
+/// Variadic `and` (with `identity: true`) and `or` (with `identity: false`).
+///
+/// Like every primitive, these receive already-evaluated arguments, so they
+/// never short-circuit: if any argument fails to evaluate, the whole call
+/// fails, whatever the other arguments are. With no arguments they return
+/// their identity.
+struct Connective {
+    name: Symbol,
+    identity: bool,
+}
+
+impl PrimitiveLike for Connective {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        AllEqualTypeConstraint::new(self.name(), span.clone())
+            .with_all_arguments_sort(Arc::new(BoolSort))
+            .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        // `and` is false as soon as one argument is false, `or` is true as
+        // soon as one is true
+        let absorbing = values
+            .iter()
+            .any(|v| bool::load(&BoolSort, v) != self.identity);
+        (absorbing != self.identity).store(&BoolSort)
+    }
+}
@@ -0,0 +1,94 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use crate::{ast::Literal, util::Interner};
+
+use super::*;
+
+lazy_static! {
+    static ref BYTES_SORT_NAME: Symbol = "Bytes".into();
+    static ref BYTES: Interner<Vec<u8>> = Default::default();
+}
+
+/// Decodes a string of hex digit pairs, as used by `#x...` literals.
+pub(crate) fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 || !s.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes.iter().fold(String::new(), |mut s, b| {
+        write!(s, "{b:02x}").unwrap();
+        s
+    })
+}
+
+/// Immutable byte strings, written `#x48656c6c6f` in programs.
+#[derive(Debug)]
+pub struct BytesSort;
+
+impl Sort for BytesSort {
+    fn name(&self) -> Symbol {
+        *BYTES_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    #[rustfmt::skip]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+        type B = Vec<u8>;
+
+        add_primitives!(eg, "bytes-concat" = |a: B, b: B| -> B { [a, b].concat() });
+        add_primitives!(eg, "bytes-length" = |a: B| -> i64 { a.len() as i64 });
+        add_primitives!(eg, "bytes-get" = |a: B, i: i64| -> Opt<i64> { Some(*a.get(usize::try_from(i).ok()?)? as i64) });
+        // A half-open range, like `substring`.
+        add_primitives!(eg, "bytes-slice" = |a: B, start: i64, end: i64| -> Opt<B> {
+            Some(a.get(usize::try_from(start).ok()?..usize::try_from(end).ok()?)?.to_vec())
+        });
+
+        add_primitives!(eg, "to-hex" = |a: B| -> Symbol { encode_hex(&a).into() });
+        add_primitives!(eg, "from-hex" = |s: Symbol| -> Opt<B> { decode_hex(s.as_str()) });
+        add_primitives!(eg, "to-base64" = |a: B| -> Symbol { BASE64.encode(a).into() });
+        add_primitives!(eg, "from-base64" = |s: Symbol| -> Opt<B> { BASE64.decode(s.as_str()).ok() });
+        add_primitives!(eg, "to-bytes" = |s: Symbol| -> B { s.as_str().as_bytes().to_vec() });
+        add_primitives!(eg, "from-utf8" = |a: B| -> Opt<Symbol> { Some(std::str::from_utf8(&a).ok()?.into()) });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        let bytes = Vec::<u8>::load(self, &value);
+        (
+            1,
+            GenericExpr::Lit(DUMMY_SPAN.clone(), Literal::Bytes(bytes)),
+        )
+    }
+}
+
+impl FromSort for Vec<u8> {
+    type Sort = BytesSort;
+    fn load(_sort: &Self::Sort, value: &Value) -> Self {
+        BYTES.get(value.bits)
+    }
+}
+
+impl IntoSort for Vec<u8> {
+    type Sort = BytesSort;
+    fn store(self, _sort: &Self::Sort) -> Option<Value> {
+        let i = BYTES.intern(self);
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: BytesSort.name(),
+            bits: i,
+        })
+    }
+}
@@ -0,0 +1,78 @@
+use super::*;
+use crate::ast::Literal;
+
+/// Unicode scalar values, stored inline as their code point. The sort is
+/// called `char`, matching scalar sorts such as `i64` and `bool`.
+#[derive(Debug)]
+pub struct CharSort;
+
+lazy_static! {
+    static ref CHAR_SORT_NAME: Symbol = "char".into();
+}
+
+impl Sort for CharSort {
+    fn name(&self) -> Symbol {
+        *CHAR_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    #[rustfmt::skip]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+
+        // `char-at` indexes by character, like `substring` and `index-of`.
+        add_primitives!(eg, "char-at" = |s: Symbol, i: i64| -> Opt<char> { s.as_str().chars().nth(usize::try_from(i).ok()?) });
+        add_primitives!(eg, "to-string" = |c: char| -> Symbol { c.to_string().into() });
+        add_primitives!(eg, "from-code" = |code: i64| -> Opt<char> { char::from_u32(u32::try_from(code).ok()?) });
+        add_primitives!(eg, "to-code" = |c: char| -> i64 { c as i64 });
+
+        add_primitives!(eg, "is-digit" = |c: char| -> Opt { c.is_ascii_digit().then_some(()) });
+        add_primitives!(eg, "is-alpha" = |c: char| -> Opt { c.is_alphabetic().then_some(()) });
+        add_primitives!(eg, "is-alphanumeric" = |c: char| -> Opt { c.is_alphanumeric().then_some(()) });
+        add_primitives!(eg, "is-whitespace" = |c: char| -> Opt { c.is_whitespace().then_some(()) });
+        add_primitives!(eg, "is-upper" = |c: char| -> Opt { c.is_uppercase().then_some(()) });
+        add_primitives!(eg, "is-lower" = |c: char| -> Opt { c.is_lowercase().then_some(()) });
+
+        add_primitives!(eg, "<" = |a: char, b: char| -> Opt { (a < b).then_some(()) });
+        add_primitives!(eg, ">" = |a: char, b: char| -> Opt { (a > b).then_some(()) });
+        add_primitives!(eg, "<=" = |a: char, b: char| -> Opt { (a <= b).then_some(()) });
+        add_primitives!(eg, ">=" = |a: char, b: char| -> Opt { (a >= b).then_some(()) });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        (
+            1,
+            Expr::call_no_span(
+                "from-code",
+                vec![GenericExpr::Lit(
+                    DUMMY_SPAN.clone(),
+                    Literal::Int(value.bits as i64),
+                )],
+            ),
+        )
+    }
+}
+
+impl IntoSort for char {
+    type Sort = CharSort;
+    fn store(self, _sort: &Self::Sort) -> Option<Value> {
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: CharSort.name(),
+            bits: self as u64,
+        })
+    }
+}
+
+impl FromSort for char {
+    type Sort = CharSort;
+    fn load(_sort: &Self::Sort, value: &Value) -> Self {
+        char::from_u32(value.bits as u32).unwrap()
+    }
+}
@@ -0,0 +1,130 @@
+//! Opt-in numeric coercions along the tower `i64 → Rational → f64`.
+//!
+//! Enabled with `(set-option numeric_coercion 1)`. For each arithmetic and
+//! comparison primitive that takes two arguments of the same numeric sort, this
+//! adds overloads taking one argument from lower in the tower, which is
+//! promoted before the original primitive runs. For example,
+//! `(+ 1 (rational 1 2))` resolves to Rational addition.
+//!
+//! Coercions only add overloads, so programs that typechecked before still
+//! resolve the same way, but a call whose argument sorts are not otherwise
+//! determined (such as `(+ x 1)` with an unconstrained `x`) may become
+//! ambiguous.
+
+use num::traits::ToPrimitive;
+
+use crate::constraint::SimpleTypeConstraint;
+
+use super::*;
+
+type R = num::rational::Rational64;
+
+const COERCIBLE_PRIMITIVES: &[&str] = &[
+    "+", "-", "*", "/", "%", "^", "pow", "min", "max", "<", ">", "<=", ">=",
+];
+
+/// Registers the coercing overloads. Called once, when the option is enabled.
+pub(crate) fn register_numeric_coercions(typeinfo: &mut TypeInfo) {
+    let tower: Vec<ArcSort> = vec![
+        Arc::new(I64Sort),
+        typeinfo.get_sort_nofail::<RationalSort>(),
+        typeinfo.get_sort_nofail::<F64Sort>(),
+    ];
+    let rational = typeinfo.get_sort_nofail::<RationalSort>();
+    let f64 = typeinfo.get_sort_nofail::<F64Sort>();
+    let sorts: Vec<ArcSort> = typeinfo.sorts.values().cloned().collect();
+
+    for name in COERCIBLE_PRIMITIVES {
+        let name = Symbol::from(*name);
+        let Some(primitives) = typeinfo.primitives.get(&name).cloned() else {
+            continue;
+        };
+        for (i, target) in tower.iter().enumerate().skip(1) {
+            // find the existing `(target target) -> output` overload
+            let found = primitives.iter().find_map(|p| {
+                sorts
+                    .iter()
+                    .find(|out| {
+                        p.accept(&[target.clone(), target.clone(), (*out).clone()], typeinfo)
+                    })
+                    .map(|out| (p.clone(), out.clone()))
+            });
+            let Some((inner, output)) = found else {
+                continue;
+            };
+            for lower in &tower[..i] {
+                for inputs in [
+                    vec![lower.clone(), target.clone()],
+                    vec![target.clone(), lower.clone()],
+                ] {
+                    typeinfo.add_primitive(Coerced {
+                        name,
+                        inputs,
+                        target: target.clone(),
+                        output: output.clone(),
+                        inner: inner.clone(),
+                        rational: rational.clone(),
+                        f64: f64.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// A binary primitive whose arguments are promoted to `target` before calling
+/// `inner`.
+struct Coerced {
+    name: Symbol,
+    inputs: Vec<ArcSort>,
+    target: ArcSort,
+    output: ArcSort,
+    inner: Primitive,
+    rational: Arc<RationalSort>,
+    f64: Arc<F64Sort>,
+}
+
+impl Coerced {
+    fn promote(&self, value: Value, from: &ArcSort) -> Option<Value> {
+        let (from, to) = (from.name(), self.target.name());
+        if from == to {
+            Some(value)
+        } else if from == I64Sort.name() && to == self.rational.name() {
+            R::from_integer(i64::load(&I64Sort, &value)).store(&self.rational)
+        } else if from == I64Sort.name() && to == self.f64.name() {
+            (i64::load(&I64Sort, &value) as f64).store(&self.f64)
+        } else if from == self.rational.name() && to == self.f64.name() {
+            R::load(&self.rational, &value).to_f64()?.store(&self.f64)
+        } else {
+            unreachable!("no coercion from {from} to {to}")
+        }
+    }
+}
+
+impl PrimitiveLike for Coerced {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        let mut sorts = self.inputs.clone();
+        sorts.push(self.output.clone());
+        SimpleTypeConstraint::new(self.name(), sorts, span.clone()).into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let promoted = values
+            .iter()
+            .zip(&self.inputs)
+            .map(|(value, sort)| self.promote(*value, sort))
+            .collect::<Option<Vec<_>>>()?;
+        let targets = [self.target.clone(), self.target.clone()];
+        self.inner
+            .apply(&promoted, (&targets, &self.output), egraph)
+    }
+}
@@ -0,0 +1,109 @@
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Zero};
+
+type R = num::rational::Rational64;
+type C = num::complex::Complex<R>;
+use crate::util::Interner;
+
+use super::*;
+
+lazy_static! {
+    static ref COMPLEX_SORT_NAME: Symbol = "Complex".into();
+}
+
+/// Complex numbers with exact rational real and imaginary parts.
+#[derive(Debug, Default)]
+pub struct ComplexSort {
+    complexes: Interner<C>,
+}
+
+impl Sort for ComplexSort {
+    fn name(&self) -> Symbol {
+        *COMPLEX_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn approximate_memory_usage(&self) -> usize {
+        self.complexes.approximate_memory_usage()
+    }
+
+    #[rustfmt::skip]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+
+        add_primitives!(eg, "complex" = |re: R, im: R| -> C { C::new(re, im) });
+        add_primitives!(eg, "re" = |a: C| -> R { a.re });
+        add_primitives!(eg, "im" = |a: C| -> R { a.im });
+
+        add_primitives!(eg, "+" = |a: C, b: C| -> Opt<C> { checked_add(&a, &b) });
+        add_primitives!(eg, "-" = |a: C, b: C| -> Opt<C> { checked_sub(&a, &b) });
+        add_primitives!(eg, "*" = |a: C, b: C| -> Opt<C> { checked_mul(&a, &b) });
+        add_primitives!(eg, "/" = |a: C, b: C| -> Opt<C> { checked_div(&a, &b) });
+        add_primitives!(eg, "neg" = |a: C| -> C { -a });
+        add_primitives!(eg, "conj" = |a: C| -> C { a.conj() });
+        add_primitives!(eg, "abs2" = |a: C| -> Opt<R> { checked_norm_sqr(&a) });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        let c = C::load(self, &value);
+        (
+            1,
+            Expr::call_no_span("complex", vec![rational_expr(c.re), rational_expr(c.im)]),
+        )
+    }
+}
+
+fn checked_add(a: &C, b: &C) -> Option<C> {
+    Some(C::new(a.re.checked_add(&b.re)?, a.im.checked_add(&b.im)?))
+}
+
+fn checked_sub(a: &C, b: &C) -> Option<C> {
+    Some(C::new(a.re.checked_sub(&b.re)?, a.im.checked_sub(&b.im)?))
+}
+
+fn checked_mul(a: &C, b: &C) -> Option<C> {
+    let (rr, ii) = (a.re.checked_mul(&b.re)?, a.im.checked_mul(&b.im)?);
+    let (ri, ir) = (a.re.checked_mul(&b.im)?, a.im.checked_mul(&b.re)?);
+    Some(C::new(rr.checked_sub(&ii)?, ri.checked_add(&ir)?))
+}
+
+fn checked_norm_sqr(a: &C) -> Option<R> {
+    let (rr, ii) = (a.re.checked_mul(&a.re)?, a.im.checked_mul(&a.im)?);
+    rr.checked_add(&ii)
+}
+
+fn checked_div(a: &C, b: &C) -> Option<C> {
+    let norm = checked_norm_sqr(b)?;
+    if norm.is_zero() {
+        return None;
+    }
+    let num = checked_mul(a, &b.conj())?;
+    Some(C::new(
+        num.re.checked_div(&norm)?,
+        num.im.checked_div(&norm)?,
+    ))
+}
+
+impl FromSort for C {
+    type Sort = ComplexSort;
+    fn load(sort: &Self::Sort, value: &Value) -> Self {
+        sort.complexes.get(value.bits)
+    }
+}
+
+impl IntoSort for C {
+    type Sort = ComplexSort;
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
+        let i = sort.complexes.intern(self);
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: sort.name(),
+            bits: i,
+        })
+    }
+}
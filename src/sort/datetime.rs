@@ -0,0 +1,178 @@
+use chrono::{DateTime, Datelike, SecondsFormat, TimeDelta, Timelike, Utc};
+
+use crate::{ast::Literal, util::Interner};
+
+use super::*;
+
+type UtcDateTime = DateTime<Utc>;
+
+lazy_static! {
+    static ref DATETIME_SORT_NAME: Symbol = "DateTime".into();
+    static ref DURATION_SORT_NAME: Symbol = "Duration".into();
+}
+
+/// Points in time, normalized to UTC. They are parsed from and printed as
+/// RFC 3339 strings such as `2024-01-15T09:30:00Z`.
+#[derive(Debug, Default)]
+pub struct DateTimeSort {
+    instants: Interner<UtcDateTime>,
+}
+
+impl Sort for DateTimeSort {
+    fn name(&self) -> Symbol {
+        *DATETIME_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn approximate_memory_usage(&self) -> usize {
+        self.instants.approximate_memory_usage()
+    }
+
+    #[rustfmt::skip]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+        type D = TimeDelta;
+
+        add_primitives!(eg, "datetime" = |s: Symbol| -> Opt<UtcDateTime> { Some(DateTime::parse_from_rfc3339(s.as_str()).ok()?.to_utc()) });
+        add_primitives!(eg, "to-string" = |a: UtcDateTime| -> Symbol { a.to_rfc3339_opts(SecondsFormat::AutoSi, true).into() });
+        add_primitives!(eg, "from-unix" = |secs: i64| -> Opt<UtcDateTime> { DateTime::from_timestamp(secs, 0) });
+        add_primitives!(eg, "to-unix" = |a: UtcDateTime| -> i64 { a.timestamp() });
+
+        add_primitives!(eg, "+" = |a: UtcDateTime, d: D| -> Opt<UtcDateTime> { a.checked_add_signed(d) });
+        add_primitives!(eg, "-" = |a: UtcDateTime, d: D| -> Opt<UtcDateTime> { a.checked_sub_signed(d) });
+        add_primitives!(eg, "diff" = |a: UtcDateTime, b: UtcDateTime| -> D { a.signed_duration_since(b) });
+
+        add_primitives!(eg, "year" = |a: UtcDateTime| -> i64 { a.year() as i64 });
+        add_primitives!(eg, "month" = |a: UtcDateTime| -> i64 { a.month() as i64 });
+        add_primitives!(eg, "day" = |a: UtcDateTime| -> i64 { a.day() as i64 });
+        add_primitives!(eg, "hour" = |a: UtcDateTime| -> i64 { a.hour() as i64 });
+        add_primitives!(eg, "minute" = |a: UtcDateTime| -> i64 { a.minute() as i64 });
+        add_primitives!(eg, "second" = |a: UtcDateTime| -> i64 { a.second() as i64 });
+        // ISO 8601 numbering: Monday is 1 and Sunday is 7.
+        add_primitives!(eg, "dow" = |a: UtcDateTime| -> i64 { a.weekday().number_from_monday() as i64 });
+
+        add_primitives!(eg, "<" = |a: UtcDateTime, b: UtcDateTime| -> Opt { (a < b).then_some(()) });
+        add_primitives!(eg, ">" = |a: UtcDateTime, b: UtcDateTime| -> Opt { (a > b).then_some(()) });
+        add_primitives!(eg, "<=" = |a: UtcDateTime, b: UtcDateTime| -> Opt { (a <= b).then_some(()) });
+        add_primitives!(eg, ">=" = |a: UtcDateTime, b: UtcDateTime| -> Opt { (a >= b).then_some(()) });
+        add_primitives!(eg, "min" = |a: UtcDateTime, b: UtcDateTime| -> UtcDateTime { a.min(b) });
+        add_primitives!(eg, "max" = |a: UtcDateTime, b: UtcDateTime| -> UtcDateTime { a.max(b) });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        let instant = UtcDateTime::load(self, &value);
+        (
+            1,
+            Expr::call_no_span(
+                "datetime",
+                vec![GenericExpr::Lit(
+                    DUMMY_SPAN.clone(),
+                    Literal::String(instant.to_rfc3339_opts(SecondsFormat::AutoSi, true).into()),
+                )],
+            ),
+        )
+    }
+}
+
+impl FromSort for UtcDateTime {
+    type Sort = DateTimeSort;
+    fn load(sort: &Self::Sort, value: &Value) -> Self {
+        sort.instants.get(value.bits)
+    }
+}
+
+impl IntoSort for UtcDateTime {
+    type Sort = DateTimeSort;
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
+        let i = sort.instants.intern(self);
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: sort.name(),
+            bits: i,
+        })
+    }
+}
+
+/// Signed spans of time with nanosecond precision, stored inline. Durations
+/// are limited to what fits in an `i64` count of nanoseconds (about 292
+/// years); operations that would exceed this fail.
+#[derive(Debug)]
+pub struct DurationSort;
+
+impl Sort for DurationSort {
+    fn name(&self) -> Symbol {
+        *DURATION_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    #[rustfmt::skip]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+        type D = TimeDelta;
+
+        add_primitives!(eg, "nanoseconds" = |n: i64| -> D { D::nanoseconds(n) });
+        add_primitives!(eg, "milliseconds" = |n: i64| -> Opt<D> { D::try_milliseconds(n) });
+        add_primitives!(eg, "seconds" = |n: i64| -> Opt<D> { D::try_seconds(n) });
+        add_primitives!(eg, "minutes" = |n: i64| -> Opt<D> { D::try_minutes(n) });
+        add_primitives!(eg, "hours" = |n: i64| -> Opt<D> { D::try_hours(n) });
+        add_primitives!(eg, "days" = |n: i64| -> Opt<D> { D::try_days(n) });
+        add_primitives!(eg, "to-nanoseconds" = |d: D| -> i64 { d.num_nanoseconds().unwrap() });
+        add_primitives!(eg, "to-seconds" = |d: D| -> i64 { d.num_seconds() });
+
+        add_primitives!(eg, "+" = |a: D, b: D| -> Opt<D> { a.checked_add(&b) });
+        add_primitives!(eg, "-" = |a: D, b: D| -> Opt<D> { a.checked_sub(&b) });
+        add_primitives!(eg, "*" = |a: D, n: i64| -> Opt<D> { Some(D::nanoseconds(a.num_nanoseconds()?.checked_mul(n)?)) });
+        add_primitives!(eg, "neg" = |a: D| -> D { -a });
+        add_primitives!(eg, "abs" = |a: D| -> D { a.abs() });
+
+        add_primitives!(eg, "<" = |a: D, b: D| -> Opt { (a < b).then_some(()) });
+        add_primitives!(eg, ">" = |a: D, b: D| -> Opt { (a > b).then_some(()) });
+        add_primitives!(eg, "<=" = |a: D, b: D| -> Opt { (a <= b).then_some(()) });
+        add_primitives!(eg, ">=" = |a: D, b: D| -> Opt { (a >= b).then_some(()) });
+        add_primitives!(eg, "min" = |a: D, b: D| -> D { a.min(b) });
+        add_primitives!(eg, "max" = |a: D, b: D| -> D { a.max(b) });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        (
+            1,
+            Expr::call_no_span(
+                "nanoseconds",
+                vec![GenericExpr::Lit(
+                    DUMMY_SPAN.clone(),
+                    Literal::Int(value.bits as i64),
+                )],
+            ),
+        )
+    }
+}
+
+impl IntoSort for TimeDelta {
+    type Sort = DurationSort;
+    fn store(self, _sort: &Self::Sort) -> Option<Value> {
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: DurationSort.name(),
+            bits: self.num_nanoseconds()? as u64,
+        })
+    }
+}
+
+impl FromSort for TimeDelta {
+    type Sort = DurationSort;
+    fn load(_sort: &Self::Sort, value: &Value) -> Self {
+        TimeDelta::nanoseconds(value.bits as i64)
+    }
+}
@@ -0,0 +1,263 @@
+use num::integer::Integer;
+use num::traits::{One, Pow, Signed, ToPrimitive, Zero};
+use num::{rational::BigRational, BigInt};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+type Z = BigInt;
+type Q = BigRational;
+use crate::{ast::Literal, util::Interner};
+
+use super::*;
+
+lazy_static! {
+    static ref DECIMAL_SORT_NAME: Symbol = "Decimal".into();
+}
+
+/// An exact, arbitrary-precision decimal number `mantissa * 10^-scale`.
+///
+/// Values are kept normalized (no trailing zeros in the mantissa), so `1.10`
+/// and `1.1` are the same value.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    mantissa: Z,
+    scale: u32,
+}
+
+fn pow10(n: u32) -> Z {
+    Z::from(10u32).pow(n)
+}
+
+impl Decimal {
+    fn new(mut mantissa: Z, mut scale: u32) -> Self {
+        let ten = Z::from(10u32);
+        if mantissa.is_zero() {
+            scale = 0;
+        }
+        while scale > 0 && mantissa.is_multiple_of(&ten) {
+            mantissa /= &ten;
+            scale -= 1;
+        }
+        Decimal { mantissa, scale }
+    }
+
+    /// Returns the mantissas of `self` and `other` rescaled to a common scale.
+    fn align(&self, other: &Self) -> (Z, Z, u32) {
+        let scale = self.scale.max(other.scale);
+        (
+            &self.mantissa * pow10(scale - self.scale),
+            &other.mantissa * pow10(scale - other.scale),
+            scale,
+        )
+    }
+
+    pub fn to_ratio(&self) -> Q {
+        Q::new(self.mantissa.clone(), pow10(self.scale))
+    }
+
+    /// Converts a rational into a decimal, returning `None` if its decimal
+    /// expansion does not terminate.
+    pub fn from_ratio(q: &Q) -> Option<Self> {
+        let (two, five) = (Z::from(2u32), Z::from(5u32));
+        let mut denom = q.denom().clone();
+        let (mut twos, mut fives) = (0u32, 0u32);
+        while denom.is_multiple_of(&two) {
+            denom /= &two;
+            twos += 1;
+        }
+        while denom.is_multiple_of(&five) {
+            denom /= &five;
+            fives += 1;
+        }
+        if !denom.is_one() {
+            return None;
+        }
+        let scale = twos.max(fives);
+        Some(Decimal::new(q.numer() * (pow10(scale) / q.denom()), scale))
+    }
+
+    /// Rounds to `places` digits after the decimal point, breaking ties
+    /// towards the even neighbor.
+    pub fn round(&self, places: u32) -> Self {
+        if self.scale <= places {
+            return self.clone();
+        }
+        let divisor = pow10(self.scale - places);
+        let (quot, rem) = self.mantissa.div_mod_floor(&divisor);
+        let twice = rem * 2u32;
+        let quot = if twice > divisor || (twice == divisor && quot.is_odd()) {
+            quot + 1u32
+        } else {
+            quot
+        };
+        Decimal::new(quot, places)
+    }
+}
+
+impl std::ops::Add for Decimal {
+    type Output = Decimal;
+    fn add(self, other: Decimal) -> Decimal {
+        let (a, b, scale) = self.align(&other);
+        Decimal::new(a + b, scale)
+    }
+}
+
+impl std::ops::Sub for Decimal {
+    type Output = Decimal;
+    fn sub(self, other: Decimal) -> Decimal {
+        let (a, b, scale) = self.align(&other);
+        Decimal::new(a - b, scale)
+    }
+}
+
+impl std::ops::Mul for Decimal {
+    type Output = Decimal;
+    fn mul(self, other: Decimal) -> Decimal {
+        Decimal::new(self.mantissa * other.mantissa, self.scale + other.scale)
+    }
+}
+
+impl std::ops::Neg for Decimal {
+    type Output = Decimal;
+    fn neg(self) -> Decimal {
+        Decimal::new(-self.mantissa, self.scale)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let (a, b, _) = self.align(other);
+        a.cmp(&b)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ();
+
+    /// Parses strings like `-12.340`, `.5`, or `7`.
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (sign, digits) = match s.strip_prefix('-') {
+            Some(rest) => (-1, rest),
+            None => (1, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if (int.is_empty() && frac.is_empty())
+            || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(());
+        }
+        let mantissa = Z::from_str(&format!("{int}{frac}")).map_err(|_| ())?;
+        Ok(Decimal::new(mantissa * sign, frac.len() as u32))
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.abs().to_string();
+        let sign = if self.mantissa.is_negative() { "-" } else { "" };
+        let scale = self.scale as usize;
+        if scale == 0 {
+            write!(f, "{sign}{digits}")
+        } else if digits.len() > scale {
+            let (int, frac) = digits.split_at(digits.len() - scale);
+            write!(f, "{sign}{int}.{frac}")
+        } else {
+            write!(f, "{sign}0.{digits:0>scale$}")
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DecimalSort {
+    decimals: Interner<Decimal>,
+}
+
+impl Sort for DecimalSort {
+    fn name(&self) -> Symbol {
+        *DECIMAL_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn approximate_memory_usage(&self) -> usize {
+        self.decimals.approximate_memory_usage()
+    }
+
+    #[rustfmt::skip]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+        type D = Decimal;
+
+        add_primitives!(eg, "decimal" = |a: Symbol| -> Opt<D> { a.as_str().parse::<D>().ok() });
+        add_primitives!(eg, "to-string" = |a: D| -> Symbol { a.to_string().into() });
+
+        add_primitives!(eg, "+" = |a: D, b: D| -> D { a + b });
+        add_primitives!(eg, "-" = |a: D, b: D| -> D { a - b });
+        add_primitives!(eg, "*" = |a: D, b: D| -> D { a * b });
+        add_primitives!(eg, "/" = |a: D, b: D| -> Opt<D> {
+            if b.mantissa.is_zero() {
+                None
+            } else {
+                D::from_ratio(&(a.to_ratio() / b.to_ratio()))
+            }
+        });
+        add_primitives!(eg, "neg" = |a: D| -> D { -a });
+        add_primitives!(eg, "abs" = |a: D| -> D { D::new(a.mantissa.abs(), a.scale) });
+        add_primitives!(eg, "min" = |a: D, b: D| -> D { a.min(b) });
+        add_primitives!(eg, "max" = |a: D, b: D| -> D { a.max(b) });
+        add_primitives!(eg, "decimal-round" = |a: D, places: i64| -> Opt<D> { Some(a.round(u32::try_from(places).ok()?)) });
+
+        add_primitives!(eg, "to-f64" = |a: D| -> f64 { a.to_ratio().to_f64().unwrap() });
+        add_primitives!(eg, "to-bigrat" = |a: D| -> Q { a.to_ratio() });
+        add_primitives!(eg, "to-decimal" = |a: Q| -> Opt<D> { D::from_ratio(&a) });
+
+        add_primitives!(eg, "<" = |a: D, b: D| -> Opt { (a < b).then_some(()) });
+        add_primitives!(eg, ">" = |a: D, b: D| -> Opt { (a > b).then_some(()) });
+        add_primitives!(eg, "<=" = |a: D, b: D| -> Opt { (a <= b).then_some(()) });
+        add_primitives!(eg, ">=" = |a: D, b: D| -> Opt { (a >= b).then_some(()) });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        let decimal = Decimal::load(self, &value);
+        (
+            1,
+            Expr::call_no_span(
+                "decimal",
+                vec![GenericExpr::Lit(
+                    DUMMY_SPAN.clone(),
+                    Literal::String(decimal.to_string().into()),
+                )],
+            ),
+        )
+    }
+}
+
+impl FromSort for Decimal {
+    type Sort = DecimalSort;
+    fn load(sort: &Self::Sort, value: &Value) -> Self {
+        sort.decimals.get(value.bits)
+    }
+}
+
+impl IntoSort for Decimal {
+    type Sort = DecimalSort;
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
+        let i = sort.decimals.intern(self);
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: sort.name(),
+            bits: i,
+        })
+    }
+}
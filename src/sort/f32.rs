@@ -0,0 +1,84 @@
+use super::*;
+use crate::ast::Literal;
+use ordered_float::OrderedFloat;
+
+#[derive(Debug)]
+pub struct F32Sort;
+
+lazy_static! {
+    static ref F32_SORT_NAME: Symbol = "f32".into();
+}
+
+impl Sort for F32Sort {
+    fn name(&self) -> Symbol {
+        *F32_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    #[rustfmt::skip]
+    // We need the closure for division and mod operations, as they can panic.
+    // cf https://github.com/rust-lang/rust-clippy/issues/9422
+    #[allow(clippy::unnecessary_lazy_evaluations)]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+
+        add_primitives!(eg, "neg" = |a: f32| -> f32 { -a });
+
+        add_primitives!(eg, "+" = |a: f32, b: f32| -> f32 { a + b });
+        add_primitives!(eg, "-" = |a: f32, b: f32| -> f32 { a - b });
+        add_primitives!(eg, "*" = |a: f32, b: f32| -> f32 { a * b });
+        add_primitives!(eg, "^" = |a: f32, b: f32| -> f32 { a.powf(b) });
+        add_primitives!(eg, "/" = |a: f32, b: f32| -> Opt<f32> { (b != 0.0).then(|| a / b) });
+        add_primitives!(eg, "%" = |a: f32, b: f32| -> Opt<f32> { (b != 0.0).then(|| a % b) });
+
+        add_primitives!(eg, "<" = |a: f32, b: f32| -> Opt { (a < b).then(|| ()) });
+        add_primitives!(eg, ">" = |a: f32, b: f32| -> Opt { (a > b).then(|| ()) });
+        add_primitives!(eg, "<=" = |a: f32, b: f32| -> Opt { (a <= b).then(|| ()) });
+        add_primitives!(eg, ">=" = |a: f32, b: f32| -> Opt { (a >= b).then(|| ()) });
+
+        add_primitives!(eg, "min" = |a: f32, b: f32| -> f32 { a.min(b) });
+        add_primitives!(eg, "max" = |a: f32, b: f32| -> f32 { a.max(b) });
+        add_primitives!(eg, "abs" = |a: f32| -> f32 { a.abs() });
+
+        add_primitives!(eg, "to-f32" = |a: i64| -> f32 { a as f32 });
+        add_primitives!(eg, "to-f32" = |a: f64| -> f32 { a as f32 });
+        add_primitives!(eg, "to-f64" = |a: f32| -> f64 { a as f64 });
+        add_primitives!(eg, "to-i64" = |a: f32| -> i64 { a as i64 });
+        // Use debug instead of to_string so that decimal place is always printed
+        add_primitives!(eg, "to-string" = |a: f32| -> Symbol { format!("{:?}", a).into() });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        (
+            1,
+            GenericExpr::Lit(
+                DUMMY_SPAN.clone(),
+                Literal::F32(OrderedFloat(f32::from_bits(value.bits as u32))),
+            ),
+        )
+    }
+}
+
+impl IntoSort for f32 {
+    type Sort = F32Sort;
+    fn store(self, _sort: &Self::Sort) -> Option<Value> {
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: F32Sort.name(),
+            bits: self.to_bits().into(),
+        })
+    }
+}
+
+impl FromSort for f32 {
+    type Sort = F32Sort;
+    fn load(_sort: &Self::Sort, value: &Value) -> Self {
+        f32::from_bits(value.bits as u32)
+    }
+}
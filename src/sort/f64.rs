@@ -1,14 +1,90 @@
 use super::*;
 use crate::ast::Literal;
 use ordered_float::OrderedFloat;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-#[derive(Debug)]
-pub struct F64Sort;
+#[derive(Debug, Default)]
+pub struct F64Sort {
+    /// Whether NaNs are stored as [`f64::NAN`], see [`NanPolicy`].
+    canonicalize_nan: AtomicBool,
+}
 
 lazy_static! {
     static ref F64_SORT_NAME: Symbol = "f64".into();
 }
 
+/// How NaNs are stored as values.
+///
+/// Values are compared by their bits, so under [`NanPolicy::Preserve`] NaNs
+/// with different signs or payloads (for example `NaN` and `(- inf inf)`) are
+/// different values. [`NanPolicy::Canonicalize`] stores every NaN as
+/// [`f64::NAN`], making them all equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NanPolicy {
+    Preserve,
+    Canonicalize,
+}
+
+impl F64Sort {
+    /// Sets the NaN policy for the values stored through this sort, which
+    /// is shared by an e-graph and its clones. Values that were already
+    /// stored keep their bits. `(set-option nan_policy ...)` sets it from
+    /// the e-graph, so that popping the e-graph restores it.
+    pub fn set_nan_policy(&self, policy: NanPolicy) {
+        self.canonicalize_nan
+            .store(policy == NanPolicy::Canonicalize, Ordering::Relaxed);
+    }
+
+    pub fn nan_policy(&self) -> NanPolicy {
+        if self.canonicalize_nan.load(Ordering::Relaxed) {
+            NanPolicy::Canonicalize
+        } else {
+            NanPolicy::Preserve
+        }
+    }
+
+    fn to_bits(&self, f: f64) -> u64 {
+        if f.is_nan() && self.nan_policy() == NanPolicy::Canonicalize {
+            f64::NAN.to_bits()
+        } else {
+            f.to_bits()
+        }
+    }
+}
+
+/// The next representable number after `a` in the direction of `b`.
+fn next_after(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        b
+    } else if a == 0.0 {
+        f64::from_bits(1).copysign(b)
+    } else if (b > a) == (a > 0.0) {
+        f64::from_bits(a.to_bits() + 1)
+    } else {
+        f64::from_bits(a.to_bits() - 1)
+    }
+}
+
+/// Maps floats to integers such that adjacent floats map to adjacent integers
+/// and both zeros map to 0.
+fn ulp_index(f: f64) -> i64 {
+    let bits = f.to_bits() as i64;
+    if bits < 0 {
+        -(bits & i64::MAX)
+    } else {
+        bits
+    }
+}
+
+/// Whether `a` and `b` are at most `ulps` representable numbers apart.
+fn ulp_eq(a: f64, b: f64, ulps: i64) -> bool {
+    !a.is_nan()
+        && !b.is_nan()
+        && (i128::from(ulp_index(a)) - i128::from(ulp_index(b))).abs() <= i128::from(ulps)
+}
+
 impl Sort for F64Sort {
     fn name(&self) -> Symbol {
         *F64_SORT_NAME
@@ -42,6 +118,12 @@ impl Sort for F64Sort {
         add_primitives!(eg, "min" = |a: f64, b: f64| -> f64 { a.min(b) });
         add_primitives!(eg, "max" = |a: f64, b: f64| -> f64 { a.max(b) });
         add_primitives!(eg, "abs" = |a: f64| -> f64 { a.abs() });
+        add_primitives!(eg, "copysign" = |a: f64, b: f64| -> f64 { a.copysign(b) });
+
+        add_primitives!(eg, "is-nan" = |a: f64| -> Opt { a.is_nan().then(|| ()) });
+        add_primitives!(eg, "is-inf" = |a: f64| -> Opt { a.is_infinite().then(|| ()) });
+        add_primitives!(eg, "next-after" = |a: f64, b: f64| -> f64 { next_after(a, b) });
+        add_primitives!(eg, "ulp-eq" = |a: f64, b: f64, ulps: i64| -> Opt { ulp_eq(a, b, ulps).then(|| ()) });
 
         add_primitives!(eg, "to-f64" = |a: i64| -> f64 { a as f64 });
         add_primitives!(eg, "to-i64" = |a: f64| -> i64 { a as i64 });
@@ -66,11 +148,11 @@ impl Sort for F64Sort {
 
 impl IntoSort for f64 {
     type Sort = F64Sort;
-    fn store(self, _sort: &Self::Sort) -> Option<Value> {
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
         Some(Value {
             #[cfg(debug_assertions)]
-            tag: F64Sort.name(),
-            bits: self.to_bits(),
+            tag: sort.name(),
+            bits: sort.to_bits(self),
         })
     }
 }
@@ -9,6 +9,13 @@ lazy_static! {
     static ref I64_SORT_NAME: Symbol = "i64".into();
 }
 
+/// Checks a base for the `-radix` primitives, which must be between 2 and 36.
+pub(crate) fn radix(base: i64) -> Option<u32> {
+    u32::try_from(base)
+        .ok()
+        .filter(|base| (2..=36).contains(base))
+}
+
 impl Sort for I64Sort {
     fn name(&self) -> Symbol {
         *I64_SORT_NAME
@@ -30,11 +37,25 @@ impl Sort for I64Sort {
 
         type Opt<T=()> = Option<T>;
 
-        add_primitives!(typeinfo, "+" = |a: i64, b: i64| -> i64 { a + b });
-        add_primitives!(typeinfo, "-" = |a: i64, b: i64| -> i64 { a - b });
-        add_primitives!(typeinfo, "*" = |a: i64, b: i64| -> i64 { a * b });
-        add_primitives!(typeinfo, "/" = |a: i64, b: i64| -> Opt<i64> { (b != 0).then(|| a / b) });
-        add_primitives!(typeinfo, "%" = |a: i64, b: i64| -> Opt<i64> { (b != 0).then(|| a % b) });
+        // The plain operators are checked: on overflow they produce no result.
+        // Programs that want a total operation can opt into the wrapping
+        // (two's complement) or saturating variants instead.
+        add_primitives!(typeinfo, "+" = |a: i64, b: i64| -> Opt<i64> { a.checked_add(b) });
+        add_primitives!(typeinfo, "-" = |a: i64, b: i64| -> Opt<i64> { a.checked_sub(b) });
+        add_primitives!(typeinfo, "*" = |a: i64, b: i64| -> Opt<i64> { a.checked_mul(b) });
+        add_primitives!(typeinfo, "/" = |a: i64, b: i64| -> Opt<i64> { a.checked_div(b) });
+        add_primitives!(typeinfo, "%" = |a: i64, b: i64| -> Opt<i64> { a.checked_rem(b) });
+
+        add_primitives!(typeinfo, "+wrap" = |a: i64, b: i64| -> i64 { a.wrapping_add(b) });
+        add_primitives!(typeinfo, "-wrap" = |a: i64, b: i64| -> i64 { a.wrapping_sub(b) });
+        add_primitives!(typeinfo, "*wrap" = |a: i64, b: i64| -> i64 { a.wrapping_mul(b) });
+        add_primitives!(typeinfo, "/wrap" = |a: i64, b: i64| -> Opt<i64> { (b != 0).then(|| a.wrapping_div(b)) });
+        add_primitives!(typeinfo, "%wrap" = |a: i64, b: i64| -> Opt<i64> { (b != 0).then(|| a.wrapping_rem(b)) });
+
+        add_primitives!(typeinfo, "+sat" = |a: i64, b: i64| -> i64 { a.saturating_add(b) });
+        add_primitives!(typeinfo, "-sat" = |a: i64, b: i64| -> i64 { a.saturating_sub(b) });
+        add_primitives!(typeinfo, "*sat" = |a: i64, b: i64| -> i64 { a.saturating_mul(b) });
+        add_primitives!(typeinfo, "/sat" = |a: i64, b: i64| -> Opt<i64> { (b != 0).then(|| a.saturating_div(b)) });
 
         add_primitives!(typeinfo, "&" = |a: i64, b: i64| -> i64 { a & b });
         add_primitives!(typeinfo, "|" = |a: i64, b: i64| -> i64 { a | b });
@@ -60,6 +81,9 @@ impl Sort for I64Sort {
         add_primitives!(typeinfo, "max" = |a: i64, b: i64| -> i64 { a.max(b) });
 
         add_primitives!(typeinfo, "to-string" = |a: i64| -> Symbol { a.to_string().into() });
+        // Digits past 9 are lowercase letters when printing, and either case when parsing.
+        add_primitives!(typeinfo, "to-string-radix" = |a: i64, base: i64| -> Opt<Symbol> { Some(num::BigInt::from(a).to_str_radix(radix(base)?).into()) });
+        add_primitives!(typeinfo, "from-string-radix" = |s: Symbol, base: i64| -> Opt<i64> { i64::from_str_radix(s.as_str(), radix(base)?).ok() });
 
         // Must be in the i64 sort register function because the string sort is registered before the i64 sort.
         typeinfo.add_primitive(CountMatches {
@@ -68,6 +92,18 @@ impl Sort for I64Sort {
             int: self.clone(),
         });
 
+        // String positions are counted in characters, not bytes. `substring`
+        // takes a half-open range and fails if it is out of bounds.
+        add_primitives!(typeinfo, "substring" = |s: Symbol, start: i64, end: i64| -> Opt<Symbol> {{
+            let (start, end) = (usize::try_from(start).ok()?, usize::try_from(end).ok()?);
+            (start <= end && end <= s.as_str().chars().count())
+                .then(|| s.as_str().chars().skip(start).take(end - start).collect::<String>().into())
+        }});
+        add_primitives!(typeinfo, "index-of" = |s: Symbol, needle: Symbol| -> Opt<i64> {{
+            let byte_index = s.as_str().find(needle.as_str())?;
+            Some(s.as_str()[..byte_index].chars().count() as i64)
+        }});
+
     }
 
     fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
@@ -0,0 +1,153 @@
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, Signed};
+
+type R = num::rational::Rational64;
+use crate::util::Interner;
+
+use super::*;
+
+lazy_static! {
+    static ref INTERVAL_SORT_NAME: Symbol = "RatInterval".into();
+}
+
+/// A closed interval `[lo, hi]` with rational bounds, exposed as the
+/// `RatInterval` sort. Constructors guarantee that `lo <= hi`, so there is no
+/// empty interval value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Interval {
+    pub lo: R,
+    pub hi: R,
+}
+
+impl Interval {
+    pub fn new(lo: R, hi: R) -> Option<Self> {
+        (lo <= hi).then_some(Interval { lo, hi })
+    }
+
+    pub fn contains(&self, x: R) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        Interval::new(self.lo.max(other.lo), self.hi.min(other.hi))
+    }
+
+    /// The smallest interval containing both `self` and `other`.
+    pub fn hull(&self, other: &Self) -> Self {
+        Interval {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(Interval {
+            lo: self.lo.checked_add(&other.lo)?,
+            hi: self.hi.checked_add(&other.hi)?,
+        })
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        Some(Interval {
+            lo: self.lo.checked_sub(&other.hi)?,
+            hi: self.hi.checked_sub(&other.lo)?,
+        })
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        let products = [
+            self.lo.checked_mul(&other.lo)?,
+            self.lo.checked_mul(&other.hi)?,
+            self.hi.checked_mul(&other.lo)?,
+            self.hi.checked_mul(&other.hi)?,
+        ];
+        Some(Interval {
+            lo: products.into_iter().min().unwrap(),
+            hi: products.into_iter().max().unwrap(),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct IntervalSort {
+    intervals: Interner<Interval>,
+}
+
+impl Sort for IntervalSort {
+    fn name(&self) -> Symbol {
+        *INTERVAL_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn approximate_memory_usage(&self) -> usize {
+        self.intervals.approximate_memory_usage()
+    }
+
+    #[rustfmt::skip]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+        type I = Interval;
+
+        add_primitives!(eg, "interval" = |lo: R, hi: R| -> Opt<I> { I::new(lo, hi) });
+        add_primitives!(eg, "interval-lo" = |a: I| -> R { a.lo });
+        add_primitives!(eg, "interval-hi" = |a: I| -> R { a.hi });
+        add_primitives!(eg, "interval-width" = |a: I| -> Opt<R> { a.hi.checked_sub(&a.lo) });
+
+        add_primitives!(eg, "+" = |a: I, b: I| -> Opt<I> { a.checked_add(&b) });
+        add_primitives!(eg, "-" = |a: I, b: I| -> Opt<I> { a.checked_sub(&b) });
+        add_primitives!(eg, "*" = |a: I, b: I| -> Opt<I> { a.checked_mul(&b) });
+        add_primitives!(eg, "neg" = |a: I| -> I { I { lo: -a.hi, hi: -a.lo } });
+        add_primitives!(eg, "abs" = |a: I| -> I {
+            if a.lo.is_negative() && a.hi.is_positive() {
+                I { lo: R::from_integer(0), hi: a.hi.max(-a.lo) }
+            } else {
+                I { lo: a.lo.abs().min(a.hi.abs()), hi: a.lo.abs().max(a.hi.abs()) }
+            }
+        });
+
+        // `interval-meet` (intersection) fails on disjoint intervals, so using it
+        // as a `:merge` function reports contradictory bounds as an error.
+        // `interval-join` is the hull of both intervals.
+        add_primitives!(eg, "interval-meet" = |a: I, b: I| -> Opt<I> { a.intersect(&b) });
+        add_primitives!(eg, "interval-join" = |a: I, b: I| -> I { a.hull(&b) });
+
+        add_primitives!(eg, "interval-contains" = |a: I, x: R| -> Opt { a.contains(x).then_some(()) });
+        add_primitives!(eg, "interval-subset" = |a: I, b: I| -> Opt { (b.lo <= a.lo && a.hi <= b.hi).then_some(()) });
+        add_primitives!(eg, "interval-overlaps" = |a: I, b: I| -> Opt { a.intersect(&b).map(|_| ()) });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        let interval = Interval::load(self, &value);
+        (
+            1,
+            Expr::call_no_span(
+                "interval",
+                vec![rational_expr(interval.lo), rational_expr(interval.hi)],
+            ),
+        )
+    }
+}
+
+impl FromSort for Interval {
+    type Sort = IntervalSort;
+    fn load(sort: &Self::Sort, value: &Value) -> Self {
+        sort.intervals.get(value.bits)
+    }
+}
+
+impl IntoSort for Interval {
+    type Sort = IntervalSort;
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
+        let i = sort.intervals.intern(self);
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: sort.name(),
+            bits: i,
+        })
+    }
+}
@@ -1,3 +1,20 @@
+/// Defines a primitive from a typed Rust closure and adds it to an [`EGraph`](crate::EGraph)
+/// or [`TypeInfo`](crate::TypeInfo).
+///
+/// Each parameter and the return type must implement [`FromSort`](crate::sort::FromSort)
+/// and [`IntoSort`](crate::sort::IntoSort) respectively, and their sorts must already
+/// be registered. Returning an `Option` makes the primitive fail on `None`.
+///
+/// ```
+/// use egglog::{add_primitives, EGraph};
+///
+/// let mut egraph = EGraph::default();
+/// add_primitives!(&mut egraph, "double" = |x: i64| -> Option<i64> { x.checked_mul(2) });
+/// egraph
+///     .parse_and_run_program(None, "(check (= (double 21) 42))")
+///     .unwrap();
+/// ```
+#[macro_export]
 macro_rules! add_primitives {
     ($type_info:expr,
         $name:literal = |$($param:ident : $param_t:ty),*| -> $ret:ty { $body:expr }
@@ -5,7 +22,9 @@ macro_rules! add_primitives {
         let type_info: &mut _ = $type_info;
         #[allow(unused_imports, non_snake_case)]
         {
-            use $crate::{*, sort::*, constraint::*};
+            use ::std::sync::Arc;
+            use $crate::sort::{FromSort, IntoSort};
+            use $crate::constraint::TypeConstraint;
 
             struct MyPrim {$(
                 $param: Arc<<$param_t as FromSort>::Sort>,
@@ -14,24 +33,24 @@ macro_rules! add_primitives {
             }
 
             impl $crate::PrimitiveLike for MyPrim {
-                fn name(&self) -> $crate::Symbol {
+                fn name(&self) -> $crate::ast::Symbol {
                     $name.into()
                 }
 
                 fn get_type_constraints(
                     &self,
-                    span: &Span
+                    span: &$crate::ast::Span
                 ) -> Box<dyn TypeConstraint> {
-                    let sorts = vec![$(self.$param.clone() as ArcSort,)* self.__out.clone() as ArcSort];
-                    SimpleTypeConstraint::new(self.name(), sorts, span.clone()).into_box()
+                    let sorts = vec![$(self.$param.clone() as $crate::ArcSort,)* self.__out.clone() as $crate::ArcSort];
+                    $crate::constraint::SimpleTypeConstraint::new(self.name(), sorts, span.clone()).into_box()
                 }
 
                 fn apply(
                     &self,
-                    values: &[Value],
-                    _sorts: (&[ArcSort], &ArcSort),
-                    _egraph: Option<&mut EGraph>,
-                ) -> Option<Value> {
+                    values: &[$crate::Value],
+                    _sorts: (&[$crate::ArcSort], &$crate::ArcSort),
+                    _egraph: Option<&mut $crate::EGraph>,
+                ) -> Option<$crate::Value> {
                     if let [$($param),*] = values {
                         $(let $param: $param_t = <$param_t as FromSort>::load(&self.$param, $param);)*
                         // print!("{}( ", $name);
@@ -40,6 +40,9 @@ impl Presort for MapSort {
             "map-contains".into(),
             "map-remove".into(),
             "map-length".into(),
+            "map-keys".into(),
+            "map-values".into(),
+            "unstable-map-merge-with".into(),
         ]
     }
 
@@ -96,6 +99,12 @@ impl Sort for MapSort {
         self
     }
 
+    fn approximate_memory_usage(&self) -> usize {
+        approximate_set_memory_usage(&self.maps, |map| {
+            map.len() * 2 * std::mem::size_of::<Value>()
+        })
+    }
+
     fn is_container_sort(&self) -> bool {
         true
     }
@@ -164,8 +173,32 @@ impl Sort for MapSort {
         });
         typeinfo.add_primitive(Length {
             name: "map-length".into(),
-            map: self,
+            map: self.clone(),
         });
+
+        let vec_sorts: Vec<Arc<VecSort>> = typeinfo
+            .sorts
+            .values()
+            .filter_map(|sort| sort.clone().as_arc_any().downcast::<VecSort>().ok())
+            .collect();
+        for vec in vec_sorts {
+            register_map_vec_primitives(&self, &vec, typeinfo);
+        }
+
+        let value_name = self.value.name();
+        let fn_sort = typeinfo.get_sort_by(|s: &Arc<FunctionSort>| {
+            s.output.name() == value_name
+                && s.inputs.len() == 2
+                && s.inputs.iter().all(|input| input.name() == value_name)
+        });
+        // Only include merge-with if we already declared a function sort with the correct signature
+        if let Some(fn_sort) = fn_sort {
+            typeinfo.add_primitive(MergeWith {
+                name: "unstable-map-merge-with".into(),
+                map: self,
+                fn_: fn_sort,
+            });
+        }
     }
 
     fn make_expr(&self, egraph: &EGraph, value: Value) -> (Cost, Expr) {
@@ -198,6 +231,44 @@ impl Sort for MapSort {
     }
 }
 
+/// Registers `map-keys` and `map-values` for a map and vector sort whose
+/// element sort matches the map's keys or values. This is called when either
+/// of the two sorts is declared, so the declaration order does not matter.
+/// Both list the entries in the order of their keys, so they are only defined
+/// for key sorts that `vec-sort` can sort.
+pub(super) fn register_map_vec_primitives(
+    map: &Arc<MapSort>,
+    vec: &Arc<VecSort>,
+    typeinfo: &mut TypeInfo,
+) {
+    let Some(order) = element_order(&map.key) else {
+        return;
+    };
+    if vec.element_name() == map.key.name() {
+        typeinfo.add_primitive(Keys {
+            name: "map-keys".into(),
+            map: map.clone(),
+            vec: vec.clone(),
+            order: order.clone(),
+        });
+    }
+    if vec.element_name() == map.value.name() {
+        typeinfo.add_primitive(Values {
+            name: "map-values".into(),
+            map: map.clone(),
+            vec: vec.clone(),
+            order,
+        });
+    }
+}
+
+/// The entries of a map, sorted by their keys.
+fn sorted_entries(map: ValueMap, order: &ElementOrder) -> Vec<(Value, Value)> {
+    let mut entries: Vec<(Value, Value)> = map.into_iter().collect();
+    entries.sort_by(|(a, _), (b, _)| order(a, b));
+    entries
+}
+
 impl IntoSort for ValueMap {
     type Sort = MapSort;
     fn store(self, sort: &Self::Sort) -> Option<Value> {
@@ -540,3 +611,124 @@ impl PrimitiveLike for Length {
         Some(Value::from(map.len() as i64))
     }
 }
+
+struct Keys {
+    name: Symbol,
+    map: Arc<MapSort>,
+    vec: Arc<VecSort>,
+    order: ElementOrder,
+}
+
+impl PrimitiveLike for Keys {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.map.clone(), self.vec.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    /// Returns the keys in sorted order.
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let map = ValueMap::load(&self.map, &values[0]);
+        let keys: Vec<Value> = sorted_entries(map, &self.order)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        keys.store(&self.vec)
+    }
+}
+
+struct Values {
+    name: Symbol,
+    map: Arc<MapSort>,
+    vec: Arc<VecSort>,
+    order: ElementOrder,
+}
+
+impl PrimitiveLike for Values {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.map.clone(), self.vec.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    /// Returns the values in the order of their keys, like `map-keys`.
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let map = ValueMap::load(&self.map, &values[0]);
+        let map_values: Vec<Value> = sorted_entries(map, &self.order)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        map_values.store(&self.vec)
+    }
+}
+
+struct MergeWith {
+    name: Symbol,
+    map: Arc<MapSort>,
+    fn_: Arc<FunctionSort>,
+}
+
+impl PrimitiveLike for MergeWith {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![
+                self.fn_.clone(),
+                self.map.clone(),
+                self.map.clone(),
+                self.map.clone(),
+            ],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    /// Combines two maps, applying the function to the values of keys present
+    /// in both (with the first map's value as the first argument).
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph =
+            egraph.unwrap_or_else(|| panic!("`{}` is not supported yet in facts.", self.name));
+        let mut map = ValueMap::load(&self.map, &values[1]);
+        for (k, v) in ValueMap::load(&self.map, &values[2]) {
+            let merged = match map.get(&k) {
+                Some(old) => self.fn_.apply(&values[0], &[*old, v], egraph),
+                None => v,
+            };
+            map.insert(k, merged);
+        }
+        map.store(&self.map)
+    }
+}
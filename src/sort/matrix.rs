@@ -0,0 +1,181 @@
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, One, Zero};
+
+type R = num::rational::Rational64;
+use crate::{ast::Literal, util::Interner};
+
+use super::*;
+
+lazy_static! {
+    static ref MATRIX_SORT_NAME: Symbol = "Matrix".into();
+}
+
+/// A dense matrix with exact rational entries, stored in row-major order.
+///
+/// Shape mismatches and overflowing entries make the primitives fail rather
+/// than panic.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    entries: Vec<R>,
+}
+
+impl Matrix {
+    pub fn zeros(rows: usize, cols: usize) -> Option<Self> {
+        Some(Matrix {
+            rows,
+            cols,
+            entries: vec![R::zero(); rows.checked_mul(cols)?],
+        })
+    }
+
+    pub fn identity(n: usize) -> Option<Self> {
+        let mut m = Matrix::zeros(n, n)?;
+        for i in 0..n {
+            m.entries[i * n + i] = R::one();
+        }
+        Some(m)
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> Option<R> {
+        (i < self.rows && j < self.cols).then(|| self.entries[i * self.cols + j])
+    }
+
+    fn set(mut self, i: usize, j: usize, x: R) -> Option<Self> {
+        if i >= self.rows || j >= self.cols {
+            return None;
+        }
+        self.entries[i * self.cols + j] = x;
+        Some(self)
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for j in 0..self.cols {
+            for i in 0..self.rows {
+                entries.push(self.entries[i * self.cols + j]);
+            }
+        }
+        Matrix {
+            rows: self.cols,
+            cols: self.rows,
+            entries,
+        }
+    }
+
+    fn zip_with(&self, other: &Self, f: impl Fn(&R, &R) -> Option<R>) -> Option<Self> {
+        if (self.rows, self.cols) != (other.rows, other.cols) {
+            return None;
+        }
+        let entries = self
+            .entries
+            .iter()
+            .zip(&other.entries)
+            .map(|(a, b)| f(a, b))
+            .collect::<Option<_>>()?;
+        Some(Matrix { entries, ..*self })
+    }
+
+    fn checked_scale(&self, k: &R) -> Option<Self> {
+        let entries = self
+            .entries
+            .iter()
+            .map(|x| k.checked_mul(x))
+            .collect::<Option<_>>()?;
+        Some(Matrix { entries, ..*self })
+    }
+
+    fn checked_matmul(&self, other: &Self) -> Option<Self> {
+        if self.cols != other.rows {
+            return None;
+        }
+        let mut res = Matrix::zeros(self.rows, other.cols)?;
+        for i in 0..self.rows {
+            for j in 0..other.cols {
+                let mut acc = R::zero();
+                for k in 0..self.cols {
+                    let prod = self.entries[i * self.cols + k]
+                        .checked_mul(&other.entries[k * other.cols + j])?;
+                    acc = acc.checked_add(&prod)?;
+                }
+                res.entries[i * other.cols + j] = acc;
+            }
+        }
+        Some(res)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MatrixSort {
+    matrices: Interner<Matrix>,
+}
+
+impl Sort for MatrixSort {
+    fn name(&self) -> Symbol {
+        *MATRIX_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn approximate_memory_usage(&self) -> usize {
+        self.matrices.approximate_memory_usage()
+    }
+
+    #[rustfmt::skip]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+        type M = Matrix;
+
+        add_primitives!(eg, "matrix-zeros" = |rows: i64, cols: i64| -> Opt<M> { M::zeros(rows.try_into().ok()?, cols.try_into().ok()?) });
+        add_primitives!(eg, "matrix-identity" = |n: i64| -> Opt<M> { M::identity(n.try_into().ok()?) });
+        add_primitives!(eg, "matrix-get" = |m: M, i: i64, j: i64| -> Opt<R> { m.get(i.try_into().ok()?, j.try_into().ok()?) });
+        add_primitives!(eg, "matrix-set" = |m: M, i: i64, j: i64, x: R| -> Opt<M> { m.set(i.try_into().ok()?, j.try_into().ok()?, x) });
+        add_primitives!(eg, "matrix-rows" = |m: M| -> i64 { m.rows as i64 });
+        add_primitives!(eg, "matrix-cols" = |m: M| -> i64 { m.cols as i64 });
+
+        add_primitives!(eg, "matrix-transpose" = |m: M| -> M { m.transpose() });
+        add_primitives!(eg, "matrix-mul" = |a: M, b: M| -> Opt<M> { a.checked_matmul(&b) });
+        add_primitives!(eg, "matrix-scalar-mul" = |k: R, m: M| -> Opt<M> { m.checked_scale(&k) });
+        add_primitives!(eg, "+" = |a: M, b: M| -> Opt<M> { a.zip_with(&b, |x, y| x.checked_add(y)) });
+        add_primitives!(eg, "-" = |a: M, b: M| -> Opt<M> { a.zip_with(&b, |x, y| x.checked_sub(y)) });
+        add_primitives!(eg, "neg" = |m: M| -> M { M { entries: m.entries.iter().map(|x| -x).collect(), ..m } });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        let m = Matrix::load(self, &value);
+        let int = |n: usize| GenericExpr::Lit(DUMMY_SPAN.clone(), Literal::Int(n as i64));
+        let mut expr = Expr::call_no_span("matrix-zeros", vec![int(m.rows), int(m.cols)]);
+        for (index, x) in m.entries.iter().enumerate() {
+            if !x.is_zero() {
+                let (i, j) = (index / m.cols, index % m.cols);
+                expr =
+                    Expr::call_no_span("matrix-set", vec![expr, int(i), int(j), rational_expr(*x)]);
+            }
+        }
+        (1, expr)
+    }
+}
+
+impl FromSort for Matrix {
+    type Sort = MatrixSort;
+    fn load(sort: &Self::Sort, value: &Value) -> Self {
+        sort.matrices.get(value.bits)
+    }
+}
+
+impl IntoSort for Matrix {
+    type Sort = MatrixSort;
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
+        let i = sort.matrices.intern(self);
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: sort.name(),
+            bits: i,
+        })
+    }
+}
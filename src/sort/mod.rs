@@ -2,34 +2,67 @@
 mod macros;
 use lazy_static::lazy_static;
 use std::fmt::Debug;
-use std::{any::Any, sync::Arc};
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+};
 
 mod bigint;
 pub use bigint::*;
 mod bigrat;
 pub use bigrat::*;
+mod bitvec;
+pub use bitvec::*;
+mod bytes;
+pub use bytes::*;
 mod bool;
 pub use self::bool::*;
+mod char;
+pub use self::char::*;
+mod complex;
+pub use complex::*;
+mod datetime;
+pub use datetime::*;
+mod decimal;
+pub use decimal::*;
+mod interval;
+pub use interval::*;
+mod matrix;
+pub use matrix::*;
+mod newtype;
+pub use newtype::*;
+mod polynomial;
+pub use polynomial::*;
 mod rational;
 pub use rational::*;
 mod string;
 pub use string::*;
+mod term;
+pub use term::*;
 mod unit;
 pub use unit::*;
 mod i64;
 pub use self::i64::*;
 mod f64;
 pub use self::f64::*;
+mod f32;
+pub use self::f32::*;
 mod map;
 pub use map::*;
 mod set;
 pub use set::*;
 mod vec;
 pub use vec::*;
+mod pair;
+pub use pair::*;
+mod option;
+pub use option::*;
 mod r#fn;
 pub use r#fn::*;
 mod multiset;
 pub use multiset::*;
+mod coerce;
+pub(crate) use coerce::*;
 
 use crate::constraint::AllEqualTypeConstraint;
 use crate::extract::{Cost, Extractor};
@@ -99,6 +132,12 @@ pub trait Sort: Any + Send + Sync + Debug {
         let _ = info;
     }
 
+    /// Roughly how many bytes the sort uses to store its values, such as
+    /// interned containers. Sorts whose values fit in a [`Value`] use none.
+    fn approximate_memory_usage(&self) -> usize {
+        0
+    }
+
     /// Extracting an expression (with smallest cost) out of a primitive value
     fn make_expr(&self, egraph: &EGraph, value: Value) -> (Cost, Expr);
 
@@ -118,6 +157,17 @@ pub trait Sort: Any + Send + Sync + Debug {
     }
 }
 
+/// Roughly how many bytes the values interned in `set` use, where
+/// `heap_size` estimates the heap memory owned by each value.
+pub(crate) fn approximate_set_memory_usage<T>(
+    set: &Mutex<IndexSet<T>>,
+    heap_size: impl Fn(&T) -> usize,
+) -> usize {
+    let set = set.lock().unwrap();
+    let entry_size = std::mem::size_of::<T>() + 2 * std::mem::size_of::<usize>();
+    set.capacity() * entry_size + set.iter().map(heap_size).sum::<usize>()
+}
+
 // Note: this trait is currently intended to be implemented on the
 // same struct as `Sort`. If in the future we have dynamic presorts
 // (for example, we want to add partial application) we should revisit
@@ -150,6 +200,10 @@ impl Sort for EqSort {
         true
     }
 
+    fn register_primitives(self: Arc<Self>, info: &mut TypeInfo) {
+        register_term_primitives(self, info);
+    }
+
     fn canonicalize(&self, value: &mut Value, unionfind: &UnionFind) -> bool {
         #[cfg(debug_assertions)]
         debug_assert_eq!(self.name(), value.tag);
@@ -221,9 +275,11 @@ impl PrimitiveLike for ValueEq {
 pub fn literal_sort(lit: &Literal) -> ArcSort {
     match lit {
         Literal::Int(_) => Arc::new(I64Sort) as ArcSort,
-        Literal::F64(_) => Arc::new(F64Sort) as ArcSort,
+        Literal::F64(_) => Arc::new(F64Sort::default()) as ArcSort,
+        Literal::F32(_) => Arc::new(F32Sort) as ArcSort,
         Literal::String(_) => Arc::new(StringSort) as ArcSort,
         Literal::Bool(_) => Arc::new(BoolSort) as ArcSort,
+        Literal::Bytes(_) => Arc::new(BytesSort) as ArcSort,
         Literal::Unit => Arc::new(UnitSort) as ArcSort,
     }
 }
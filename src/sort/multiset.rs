@@ -31,6 +31,11 @@ mod inner {
             self.0.contains_key(value)
         }
 
+        /// Return the number of times a value occurs in the multiset.
+        pub(crate) fn count(&self, value: &T) -> usize {
+            self.0.get(value).copied().unwrap_or(0)
+        }
+
         /// Return the total number of elements in the multiset.
         pub(crate) fn len(&self) -> usize {
             self.1
@@ -103,6 +108,13 @@ mod inner {
                 self.1 + other_count,
             )
         }
+
+        /// Compute the union of two multisets, keeping the larger multiplicity of each element.
+        pub fn union(self, MultiSet(other_map, _): Self) -> Self {
+            let map = self.0.union_with(other_map, std::cmp::max);
+            let count = map.values().sum();
+            Self(map, count)
+        }
     }
 }
 
@@ -139,6 +151,8 @@ impl Presort for MultiSetSort {
             "multiset-remove".into(),
             "multiset-length".into(),
             "multiset-sum".into(),
+            "multiset-union".into(),
+            "multiset-count".into(),
             "unstable-multiset-map".into(),
         ]
     }
@@ -182,6 +196,12 @@ impl Sort for MultiSetSort {
         self
     }
 
+    fn approximate_memory_usage(&self) -> usize {
+        approximate_set_memory_usage(&self.multisets, |multiset| {
+            multiset.len() * std::mem::size_of::<Value>()
+        })
+    }
+
     fn is_container_sort(&self) -> bool {
         true
     }
@@ -246,6 +266,14 @@ impl Sort for MultiSetSort {
             name: "multiset-sum".into(),
             multiset: self.clone(),
         });
+        typeinfo.add_primitive(Union {
+            name: "multiset-union".into(),
+            multiset: self.clone(),
+        });
+        typeinfo.add_primitive(Count {
+            name: "multiset-count".into(),
+            multiset: self.clone(),
+        });
         let inner_name = self.element.name();
         let fn_sort = typeinfo.get_sort_by(|s: &Arc<FunctionSort>| {
             (s.output.name() == inner_name)
@@ -583,6 +611,75 @@ impl PrimitiveLike for Sum {
     }
 }
 
+struct Union {
+    name: Symbol,
+    multiset: Arc<MultiSetSort>,
+}
+
+impl PrimitiveLike for Union {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![
+                self.multiset.clone(),
+                self.multiset.clone(),
+                self.multiset.clone(),
+            ],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let lhs_multiset = ValueMultiSet::load(&self.multiset, &values[0]);
+        let rhs_multiset = ValueMultiSet::load(&self.multiset, &values[1]);
+        lhs_multiset.union(rhs_multiset).store(&self.multiset)
+    }
+}
+
+struct Count {
+    name: Symbol,
+    multiset: Arc<MultiSetSort>,
+}
+
+impl PrimitiveLike for Count {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![
+                self.multiset.clone(),
+                self.multiset.element(),
+                Arc::new(I64Sort),
+            ],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let multiset = ValueMultiSet::load(&self.multiset, &values[0]);
+        Some(Value::from(multiset.count(&values[1]) as i64))
+    }
+}
+
 struct Map {
     name: Symbol,
     multiset: Arc<MultiSetSort>,
@@ -0,0 +1,215 @@
+use crate::constraint::SimpleTypeConstraint;
+
+use super::*;
+
+/// A distinct sort with the same values as a base sort, declared with
+/// `(sort Addr (Newtype i64))` or `(sort Addr i64 :newtype)`.
+///
+/// Values of the two sorts do not unify, so an `Addr` cannot be passed where
+/// an `i64` is expected. Instead, `(Addr x)` converts from the base sort and
+/// `(unwrap a)` converts back. The newtype also gets a copy of each primitive
+/// that, when the newtype is declared, takes one to three arguments all of the
+/// base sort, with the base sort replaced by the newtype throughout. For
+/// example, `(+ a b)` adds two `Addr`s and returns an `Addr`, while `(< a b)`
+/// compares them.
+///
+/// Only sorts without e-class ids inside, such as `i64` or `String`, can be
+/// wrapped.
+#[derive(Debug)]
+pub struct NewtypeSort {
+    name: Symbol,
+    base: ArcSort,
+}
+
+impl NewtypeSort {
+    pub fn base(&self) -> ArcSort {
+        self.base.clone()
+    }
+}
+
+impl Presort for NewtypeSort {
+    fn presort_name() -> Symbol {
+        "Newtype".into()
+    }
+
+    fn reserved_primitives() -> Vec<Symbol> {
+        vec!["unwrap".into()]
+    }
+
+    fn make_sort(
+        typeinfo: &mut TypeInfo,
+        name: Symbol,
+        args: &[Expr],
+    ) -> Result<ArcSort, TypeError> {
+        if let [Expr::Var(span, base)] = args {
+            let base = typeinfo
+                .sorts
+                .get(base)
+                .ok_or(TypeError::UndefinedSort(*base, span.clone()))?;
+            if base.is_eq_sort() || base.is_container_sort() {
+                return Err(TypeError::DisallowedSort(
+                    name,
+                    "Newtypes of datatypes and containers are not allowed".into(),
+                    span.clone(),
+                ));
+            }
+            Ok(Arc::new(Self {
+                name,
+                base: base.clone(),
+            }))
+        } else {
+            panic!("Newtype sort must have a single base sort")
+        }
+    }
+}
+
+/// Gives `value` the tag of `sort`, so debug builds can check newtype values
+/// as values of their own sort.
+fn retag(value: Value, sort: &ArcSort) -> Value {
+    #[cfg(debug_assertions)]
+    return Value {
+        tag: sort.name(),
+        ..value
+    };
+    #[cfg(not(debug_assertions))]
+    {
+        let _ = sort;
+        value
+    }
+}
+
+impl Sort for NewtypeSort {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn register_primitives(self: Arc<Self>, typeinfo: &mut TypeInfo) {
+        let newtype: ArcSort = self.clone();
+        let outputs: Vec<ArcSort> = typeinfo
+            .sorts
+            .values()
+            .filter(|sort| !sort.is_eq_sort() && !sort.is_container_sort())
+            .cloned()
+            .collect();
+        let primitives: Vec<Primitive> = typeinfo.primitives.values().flatten().cloned().collect();
+        for inner in primitives {
+            for arity in 1..=3 {
+                let inputs = vec![self.base.clone(); arity];
+                let output = outputs.iter().find(|output| {
+                    let mut signature = inputs.clone();
+                    signature.push((*output).clone());
+                    inner.accept(&signature, typeinfo)
+                });
+                if let Some(output) = output {
+                    let wrap = |sort: &ArcSort| {
+                        if sort.name() == self.base.name() {
+                            newtype.clone()
+                        } else {
+                            sort.clone()
+                        }
+                    };
+                    typeinfo.add_primitive(Retyped {
+                        new_inputs: inputs.iter().map(wrap).collect(),
+                        new_output: wrap(output),
+                        inputs,
+                        output: output.clone(),
+                        inner: inner.clone(),
+                    });
+                }
+            }
+        }
+
+        typeinfo.add_primitive(Retyped {
+            new_inputs: vec![self.base.clone()],
+            new_output: newtype.clone(),
+            inputs: vec![self.base.clone()],
+            output: self.base.clone(),
+            inner: Primitive::from(Identity { name: self.name }),
+        });
+        typeinfo.add_primitive(Retyped {
+            new_inputs: vec![newtype],
+            new_output: self.base.clone(),
+            inputs: vec![self.base.clone()],
+            output: self.base.clone(),
+            inner: Primitive::from(Identity {
+                name: "unwrap".into(),
+            }),
+        });
+    }
+
+    fn make_expr(&self, egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        let (cost, expr) = self.base.make_expr(egraph, retag(value, &self.base));
+        (cost + 1, Expr::call_no_span(self.name, vec![expr]))
+    }
+}
+
+/// `inner`, with its signature `inputs -> output` replaced by
+/// `new_inputs -> new_output`, where the two only differ in sorts that share
+/// a representation.
+struct Retyped {
+    inputs: Vec<ArcSort>,
+    output: ArcSort,
+    new_inputs: Vec<ArcSort>,
+    new_output: ArcSort,
+    inner: Primitive,
+}
+
+impl PrimitiveLike for Retyped {
+    fn name(&self) -> Symbol {
+        self.inner.name()
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        let mut sorts = self.new_inputs.clone();
+        sorts.push(self.new_output.clone());
+        SimpleTypeConstraint::new(self.name(), sorts, span.clone()).into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let values: Vec<Value> = values
+            .iter()
+            .zip(&self.inputs)
+            .map(|(value, sort)| retag(*value, sort))
+            .collect();
+        let value = self
+            .inner
+            .apply(&values, (&self.inputs, &self.output), egraph)?;
+        Some(retag(value, &self.new_output))
+    }
+}
+
+/// Returns its argument unchanged, for converting to and from newtypes.
+struct Identity {
+    name: Symbol,
+}
+
+impl PrimitiveLike for Identity {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, _span: &Span) -> Box<dyn TypeConstraint> {
+        unreachable!("only called through `Retyped`")
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        Some(values[0])
+    }
+}
@@ -0,0 +1,367 @@
+use std::sync::Mutex;
+
+use super::*;
+
+type ValueOption = Option<Value>;
+
+/// A parametric sort of optional values, declared as `(sort O (Option A))`.
+#[derive(Debug)]
+pub struct OptionSort {
+    name: Symbol,
+    element: ArcSort,
+    options: Mutex<IndexSet<ValueOption>>,
+}
+
+impl OptionSort {
+    pub fn element(&self) -> ArcSort {
+        self.element.clone()
+    }
+}
+
+impl Presort for OptionSort {
+    fn presort_name() -> Symbol {
+        "Option".into()
+    }
+
+    fn reserved_primitives() -> Vec<Symbol> {
+        vec![
+            "some".into(),
+            "none".into(),
+            "is-some".into(),
+            "is-none".into(),
+            "unwrap".into(),
+            "unwrap-or".into(),
+        ]
+    }
+
+    fn make_sort(
+        typeinfo: &mut TypeInfo,
+        name: Symbol,
+        args: &[Expr],
+    ) -> Result<ArcSort, TypeError> {
+        if let [Expr::Var(span, e)] = args {
+            let e = typeinfo
+                .sorts
+                .get(e)
+                .ok_or(TypeError::UndefinedSort(*e, span.clone()))?;
+
+            if e.is_eq_container_sort() {
+                return Err(TypeError::DisallowedSort(
+                    name,
+                    "Options nested with other EqSort containers are not allowed".into(),
+                    span.clone(),
+                ));
+            }
+
+            Ok(Arc::new(Self {
+                name,
+                element: e.clone(),
+                options: Default::default(),
+            }))
+        } else {
+            panic!("Option sort must have sort as argument. Got {:?}", args)
+        }
+    }
+}
+
+impl Sort for OptionSort {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn is_container_sort(&self) -> bool {
+        true
+    }
+
+    fn is_eq_container_sort(&self) -> bool {
+        self.element.is_eq_sort()
+    }
+
+    fn inner_values(&self, value: &Value) -> Vec<(ArcSort, Value)> {
+        ValueOption::load(self, value)
+            .map(|e| (self.element.clone(), e))
+            .into_iter()
+            .collect()
+    }
+
+    fn canonicalize(&self, value: &mut Value, unionfind: &UnionFind) -> bool {
+        let Some(mut e) = ValueOption::load(self, value) else {
+            return false;
+        };
+        let changed = self.element.canonicalize(&mut e, unionfind);
+        *value = Some(e).store(self).unwrap();
+        changed
+    }
+
+    fn register_primitives(self: Arc<Self>, typeinfo: &mut TypeInfo) {
+        typeinfo.add_primitive(OptionRebuild {
+            name: "rebuild".into(),
+            option: self.clone(),
+        });
+        typeinfo.add_primitive(SomeCtor {
+            name: "some".into(),
+            option: self.clone(),
+        });
+        typeinfo.add_primitive(NoneCtor {
+            name: "none".into(),
+            option: self.clone(),
+        });
+        typeinfo.add_primitive(IsSome {
+            name: "is-some".into(),
+            option: self.clone(),
+            expected: true,
+        });
+        typeinfo.add_primitive(IsSome {
+            name: "is-none".into(),
+            option: self.clone(),
+            expected: false,
+        });
+        typeinfo.add_primitive(Unwrap {
+            name: "unwrap".into(),
+            option: self.clone(),
+        });
+        typeinfo.add_primitive(UnwrapOr {
+            name: "unwrap-or".into(),
+            option: self,
+        });
+    }
+
+    fn make_expr(&self, egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        let mut termdag = TermDag::default();
+        let extractor = Extractor::new(egraph, &mut termdag);
+        self.extract_expr(egraph, value, &extractor, &mut termdag)
+            .expect("Extraction should be successful since extractor has been fully initialized")
+    }
+
+    fn extract_expr(
+        &self,
+        _egraph: &EGraph,
+        value: Value,
+        extractor: &Extractor,
+        termdag: &mut TermDag,
+    ) -> Option<(Cost, Expr)> {
+        match ValueOption::load(self, &value) {
+            None => Some((0, Expr::call_no_span("none", []))),
+            Some(e) => {
+                let (cost, term) = extractor.find_best(e, termdag, &self.element)?;
+                Some((
+                    cost,
+                    Expr::call_no_span("some", [termdag.term_to_expr(&term)]),
+                ))
+            }
+        }
+    }
+
+    fn serialized_name(&self, value: &Value) -> Symbol {
+        if ValueOption::load(self, value).is_some() {
+            "some".into()
+        } else {
+            "none".into()
+        }
+    }
+}
+
+impl IntoSort for ValueOption {
+    type Sort = OptionSort;
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
+        let mut options = sort.options.lock().unwrap();
+        let (i, _) = options.insert_full(self);
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: sort.name,
+            bits: i as u64,
+        })
+    }
+}
+
+impl FromSort for ValueOption {
+    type Sort = OptionSort;
+    fn load(sort: &Self::Sort, value: &Value) -> Self {
+        let options = sort.options.lock().unwrap();
+        *options.get_index(value.bits as usize).unwrap()
+    }
+}
+
+struct OptionRebuild {
+    name: Symbol,
+    option: Arc<OptionSort>,
+}
+
+impl PrimitiveLike for OptionRebuild {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.option.clone(), self.option.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph = egraph.unwrap();
+        let option = ValueOption::load(&self.option, &values[0]);
+        option
+            .map(|e| egraph.find(&self.option.element, e))
+            .store(&self.option)
+    }
+}
+
+struct SomeCtor {
+    name: Symbol,
+    option: Arc<OptionSort>,
+}
+
+impl PrimitiveLike for SomeCtor {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.option.element(), self.option.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        Some(values[0]).store(&self.option)
+    }
+}
+
+struct NoneCtor {
+    name: Symbol,
+    option: Arc<OptionSort>,
+}
+
+impl PrimitiveLike for NoneCtor {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(self.name(), vec![self.option.clone()], span.clone()).into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        assert!(values.is_empty());
+        ValueOption::None.store(&self.option)
+    }
+}
+
+struct IsSome {
+    name: Symbol,
+    option: Arc<OptionSort>,
+    expected: bool,
+}
+
+impl PrimitiveLike for IsSome {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.option.clone(), Arc::new(UnitSort)],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let option = ValueOption::load(&self.option, &values[0]);
+        (option.is_some() == self.expected).then(Value::unit)
+    }
+}
+
+struct Unwrap {
+    name: Symbol,
+    option: Arc<OptionSort>,
+}
+
+impl PrimitiveLike for Unwrap {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.option.clone(), self.option.element()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        ValueOption::load(&self.option, &values[0])
+    }
+}
+
+struct UnwrapOr {
+    name: Symbol,
+    option: Arc<OptionSort>,
+}
+
+impl PrimitiveLike for UnwrapOr {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![
+                self.option.clone(),
+                self.option.element(),
+                self.option.element(),
+            ],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        Some(ValueOption::load(&self.option, &values[0]).unwrap_or(values[1]))
+    }
+}
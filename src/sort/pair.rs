@@ -0,0 +1,293 @@
+use std::sync::Mutex;
+
+use super::*;
+
+type ValuePair = (Value, Value);
+
+/// A parametric sort of ordered pairs, declared as `(sort P (Pair A B))`.
+#[derive(Debug)]
+pub struct PairSort {
+    name: Symbol,
+    first: ArcSort,
+    second: ArcSort,
+    pairs: Mutex<IndexSet<ValuePair>>,
+}
+
+impl PairSort {
+    pub fn first(&self) -> ArcSort {
+        self.first.clone()
+    }
+
+    pub fn second(&self) -> ArcSort {
+        self.second.clone()
+    }
+}
+
+impl Presort for PairSort {
+    fn presort_name() -> Symbol {
+        "Pair".into()
+    }
+
+    fn reserved_primitives() -> Vec<Symbol> {
+        vec!["pair".into(), "fst".into(), "snd".into()]
+    }
+
+    fn make_sort(
+        typeinfo: &mut TypeInfo,
+        name: Symbol,
+        args: &[Expr],
+    ) -> Result<ArcSort, TypeError> {
+        if let [Expr::Var(a_span, a), Expr::Var(b_span, b)] = args {
+            let a = typeinfo
+                .sorts
+                .get(a)
+                .ok_or(TypeError::UndefinedSort(*a, a_span.clone()))?;
+            let b = typeinfo
+                .sorts
+                .get(b)
+                .ok_or(TypeError::UndefinedSort(*b, b_span.clone()))?;
+
+            for (sort, span) in [(a, a_span), (b, b_span)] {
+                if sort.is_eq_container_sort() {
+                    return Err(TypeError::DisallowedSort(
+                        name,
+                        "Pairs nested with other EqSort containers are not allowed".into(),
+                        span.clone(),
+                    ));
+                }
+            }
+
+            Ok(Arc::new(Self {
+                name,
+                first: a.clone(),
+                second: b.clone(),
+                pairs: Default::default(),
+            }))
+        } else {
+            panic!("Pair sort must have two sorts as arguments. Got {:?}", args)
+        }
+    }
+}
+
+impl Sort for PairSort {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn is_container_sort(&self) -> bool {
+        true
+    }
+
+    fn is_eq_container_sort(&self) -> bool {
+        self.first.is_eq_sort() || self.second.is_eq_sort()
+    }
+
+    fn inner_values(&self, value: &Value) -> Vec<(ArcSort, Value)> {
+        let (a, b) = ValuePair::load(self, value);
+        vec![(self.first.clone(), a), (self.second.clone(), b)]
+    }
+
+    fn canonicalize(&self, value: &mut Value, unionfind: &UnionFind) -> bool {
+        let (mut a, mut b) = ValuePair::load(self, value);
+        let changed = self.first.canonicalize(&mut a, unionfind)
+            | self.second.canonicalize(&mut b, unionfind);
+        *value = (a, b).store(self).unwrap();
+        changed
+    }
+
+    fn register_primitives(self: Arc<Self>, typeinfo: &mut TypeInfo) {
+        typeinfo.add_primitive(PairRebuild {
+            name: "rebuild".into(),
+            pair: self.clone(),
+        });
+        typeinfo.add_primitive(Ctor {
+            name: "pair".into(),
+            pair: self.clone(),
+        });
+        typeinfo.add_primitive(Fst {
+            name: "fst".into(),
+            pair: self.clone(),
+        });
+        typeinfo.add_primitive(Snd {
+            name: "snd".into(),
+            pair: self,
+        });
+    }
+
+    fn make_expr(&self, egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        let mut termdag = TermDag::default();
+        let extractor = Extractor::new(egraph, &mut termdag);
+        self.extract_expr(egraph, value, &extractor, &mut termdag)
+            .expect("Extraction should be successful since extractor has been fully initialized")
+    }
+
+    fn extract_expr(
+        &self,
+        _egraph: &EGraph,
+        value: Value,
+        extractor: &Extractor,
+        termdag: &mut TermDag,
+    ) -> Option<(Cost, Expr)> {
+        let (a, b) = ValuePair::load(self, &value);
+        let a = extractor.find_best(a, termdag, &self.first)?;
+        let b = extractor.find_best(b, termdag, &self.second)?;
+        Some((
+            a.0.saturating_add(b.0),
+            Expr::call_no_span(
+                "pair",
+                [termdag.term_to_expr(&a.1), termdag.term_to_expr(&b.1)],
+            ),
+        ))
+    }
+
+    fn serialized_name(&self, _value: &Value) -> Symbol {
+        "pair".into()
+    }
+}
+
+impl IntoSort for ValuePair {
+    type Sort = PairSort;
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
+        let mut pairs = sort.pairs.lock().unwrap();
+        let (i, _) = pairs.insert_full(self);
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: sort.name,
+            bits: i as u64,
+        })
+    }
+}
+
+impl FromSort for ValuePair {
+    type Sort = PairSort;
+    fn load(sort: &Self::Sort, value: &Value) -> Self {
+        let pairs = sort.pairs.lock().unwrap();
+        *pairs.get_index(value.bits as usize).unwrap()
+    }
+}
+
+struct PairRebuild {
+    name: Symbol,
+    pair: Arc<PairSort>,
+}
+
+impl PrimitiveLike for PairRebuild {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.pair.clone(), self.pair.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph = egraph.unwrap();
+        let (a, b) = ValuePair::load(&self.pair, &values[0]);
+        let new_pair = (
+            egraph.find(&self.pair.first, a),
+            egraph.find(&self.pair.second, b),
+        );
+        new_pair.store(&self.pair)
+    }
+}
+
+struct Ctor {
+    name: Symbol,
+    pair: Arc<PairSort>,
+}
+
+impl PrimitiveLike for Ctor {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.pair.first(), self.pair.second(), self.pair.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        (values[0], values[1]).store(&self.pair)
+    }
+}
+
+struct Fst {
+    name: Symbol,
+    pair: Arc<PairSort>,
+}
+
+impl PrimitiveLike for Fst {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.pair.clone(), self.pair.first()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        Some(ValuePair::load(&self.pair, &values[0]).0)
+    }
+}
+
+struct Snd {
+    name: Symbol,
+    pair: Arc<PairSort>,
+}
+
+impl PrimitiveLike for Snd {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.pair.clone(), self.pair.second()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        Some(ValuePair::load(&self.pair, &values[0]).1)
+    }
+}
@@ -0,0 +1,242 @@
+use num::traits::{CheckedAdd, CheckedMul, One, Zero};
+use std::collections::BTreeMap;
+
+type R = num::rational::Rational64;
+use crate::{ast::Literal, util::Interner};
+
+use super::*;
+
+lazy_static! {
+    static ref POLYNOMIAL_SORT_NAME: Symbol = "Polynomial".into();
+}
+
+/// A product of variables raised to positive powers, sorted by variable.
+type Monomial = Vec<(Symbol, u32)>;
+
+/// A sparse multivariate polynomial with rational coefficients.
+///
+/// Terms are kept in a normal form (no zero coefficients, sorted monomials),
+/// so two polynomials are equal as values exactly when they are equal as
+/// polynomials. Overflowing coefficients make the primitives fail.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Polynomial {
+    terms: BTreeMap<Monomial, R>,
+}
+
+fn monomial_mul(a: &Monomial, b: &Monomial) -> Option<Monomial> {
+    let mut powers: BTreeMap<Symbol, u32> = a.iter().copied().collect();
+    for (x, k) in b {
+        let power = powers.entry(*x).or_insert(0);
+        *power = power.checked_add(*k)?;
+    }
+    Some(powers.into_iter().collect())
+}
+
+impl Polynomial {
+    pub fn constant(c: R) -> Self {
+        if c.is_zero() {
+            return Polynomial::default();
+        }
+        Polynomial {
+            terms: BTreeMap::from([(vec![], c)]),
+        }
+    }
+
+    pub fn var(x: Symbol) -> Self {
+        Polynomial {
+            terms: BTreeMap::from([(vec![(x, 1)], R::one())]),
+        }
+    }
+
+    fn add_term(&mut self, monomial: Monomial, c: R) -> Option<()> {
+        let coeff = self.terms.entry(monomial.clone()).or_insert_with(R::zero);
+        *coeff = coeff.checked_add(&c)?;
+        if coeff.is_zero() {
+            self.terms.remove(&monomial);
+        }
+        Some(())
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        let mut res = self.clone();
+        for (m, c) in &other.terms {
+            res.add_term(m.clone(), *c)?;
+        }
+        Some(res)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        let mut res = Polynomial::default();
+        for (m1, c1) in &self.terms {
+            for (m2, c2) in &other.terms {
+                res.add_term(monomial_mul(m1, m2)?, c1.checked_mul(c2)?)?;
+            }
+        }
+        Some(res)
+    }
+
+    fn checked_pow(&self, mut n: u32) -> Option<Self> {
+        let (mut base, mut res) = (self.clone(), Polynomial::constant(R::one()));
+        while n > 0 {
+            if n % 2 == 1 {
+                res = res.checked_mul(&base)?;
+            }
+            n /= 2;
+            if n > 0 {
+                base = base.checked_mul(&base)?;
+            }
+        }
+        Some(res)
+    }
+
+    fn neg(&self) -> Self {
+        Polynomial {
+            terms: self.terms.iter().map(|(m, c)| (m.clone(), -c)).collect(),
+        }
+    }
+
+    /// Substitutes `value` for the variable `x`.
+    fn eval(&self, x: Symbol, value: R) -> Option<Self> {
+        let mut res = Polynomial::default();
+        for (m, c) in &self.terms {
+            let mut coeff = *c;
+            let mut rest = vec![];
+            for (y, k) in m {
+                if *y == x {
+                    let power = Polynomial::constant(value).checked_pow(*k)?;
+                    coeff = coeff.checked_mul(&power.to_constant()?)?;
+                } else {
+                    rest.push((*y, *k));
+                }
+            }
+            res.add_term(rest, coeff)?;
+        }
+        Some(res)
+    }
+
+    /// The total degree, or `None` for the zero polynomial.
+    fn degree(&self) -> Option<u32> {
+        self.terms
+            .keys()
+            .map(|m| m.iter().map(|(_, k)| k).sum())
+            .max()
+    }
+
+    /// The coefficient of `x^n`, viewing `self` as a polynomial in `x` whose
+    /// coefficients are polynomials in the remaining variables.
+    fn coefficient(&self, x: Symbol, n: u32) -> Self {
+        let mut res = Polynomial::default();
+        for (m, c) in &self.terms {
+            let power = m.iter().find(|(y, _)| *y == x).map_or(0, |(_, k)| *k);
+            if power == n {
+                let rest = m.iter().copied().filter(|(y, _)| *y != x).collect();
+                res.terms.insert(rest, *c);
+            }
+        }
+        res
+    }
+
+    fn to_constant(&self) -> Option<R> {
+        match self.terms.len() {
+            0 => Some(R::zero()),
+            1 => self.terms.get(&vec![]).copied(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PolynomialSort {
+    polynomials: Interner<Polynomial>,
+}
+
+impl Sort for PolynomialSort {
+    fn name(&self) -> Symbol {
+        *POLYNOMIAL_SORT_NAME
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn approximate_memory_usage(&self) -> usize {
+        self.polynomials.approximate_memory_usage()
+    }
+
+    #[rustfmt::skip]
+    fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
+        type Opt<T=()> = Option<T>;
+        type P = Polynomial;
+
+        add_primitives!(eg, "poly-const" = |c: R| -> P { P::constant(c) });
+        add_primitives!(eg, "poly-var" = |x: Symbol| -> P { P::var(x) });
+
+        add_primitives!(eg, "+" = |a: P, b: P| -> Opt<P> { a.checked_add(&b) });
+        add_primitives!(eg, "-" = |a: P, b: P| -> Opt<P> { a.checked_add(&b.neg()) });
+        add_primitives!(eg, "*" = |a: P, b: P| -> Opt<P> { a.checked_mul(&b) });
+        add_primitives!(eg, "neg" = |a: P| -> P { a.neg() });
+        add_primitives!(eg, "poly-pow" = |a: P, n: i64| -> Opt<P> { a.checked_pow(n.try_into().ok()?) });
+
+        add_primitives!(eg, "poly-eval" = |a: P, x: Symbol, v: R| -> Opt<P> { a.eval(x, v) });
+        add_primitives!(eg, "poly-to-rational" = |a: P| -> Opt<R> { a.to_constant() });
+        add_primitives!(eg, "poly-degree" = |a: P| -> Opt<i64> { a.degree().map(i64::from) });
+        add_primitives!(eg, "poly-coefficient" = |a: P, x: Symbol, n: i64| -> Opt<P> { Some(a.coefficient(x, n.try_into().ok()?)) });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        let p = Polynomial::load(self, &value);
+        let constant = |c: R| Expr::call_no_span("poly-const", vec![rational_expr(c)]);
+        let term = |m: &Monomial, c: R| {
+            let vars = m.iter().map(|(x, k)| {
+                let var = Expr::call_no_span(
+                    "poly-var",
+                    vec![GenericExpr::Lit(DUMMY_SPAN.clone(), Literal::String(*x))],
+                );
+                if *k == 1 {
+                    var
+                } else {
+                    let k = GenericExpr::Lit(DUMMY_SPAN.clone(), Literal::Int(*k as i64));
+                    Expr::call_no_span("poly-pow", vec![var, k])
+                }
+            });
+            let factors: Vec<Expr> = if c.is_one() && !m.is_empty() {
+                vars.collect()
+            } else {
+                std::iter::once(constant(c)).chain(vars).collect()
+            };
+            factors
+                .into_iter()
+                .reduce(|a, b| Expr::call_no_span("*", vec![a, b]))
+                .unwrap()
+        };
+        let expr = p
+            .terms
+            .iter()
+            .map(|(m, c)| term(m, *c))
+            .reduce(|a, b| Expr::call_no_span("+", vec![a, b]))
+            .unwrap_or_else(|| constant(R::zero()));
+        (1, expr)
+    }
+}
+
+impl FromSort for Polynomial {
+    type Sort = PolynomialSort;
+    fn load(sort: &Self::Sort, value: &Value) -> Self {
+        sort.polynomials.get(value.bits)
+    }
+}
+
+impl IntoSort for Polynomial {
+    type Sort = PolynomialSort;
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
+        let i = sort.polynomials.intern(self);
+        Some(Value {
+            #[cfg(debug_assertions)]
+            tag: sort.name(),
+            bits: i,
+        })
+    }
+}
@@ -1,19 +1,20 @@
 use num::integer::Roots;
+use num::rational::BigRational;
 use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, Signed, ToPrimitive, Zero};
-use std::sync::Mutex;
 
 type R = num::rational::Rational64;
-use crate::{ast::Literal, util::IndexSet};
+use crate::{ast::Literal, util::Interner};
 
 use super::*;
 
 lazy_static! {
     static ref RATIONAL_SORT_NAME: Symbol = "Rational".into();
-    static ref RATS: Mutex<IndexSet<R>> = Default::default();
 }
 
-#[derive(Debug)]
-pub struct RationalSort;
+#[derive(Debug, Default)]
+pub struct RationalSort {
+    rats: Interner<R>,
+}
 
 impl Sort for RationalSort {
     fn name(&self) -> Symbol {
@@ -24,6 +25,10 @@ impl Sort for RationalSort {
         self
     }
 
+    fn approximate_memory_usage(&self) -> usize {
+        self.rats.approximate_memory_usage()
+    }
+
     #[rustfmt::skip]
     fn register_primitives(self: Arc<Self>, eg: &mut TypeInfo) {
         type Opt<T=()> = Option<T>;
@@ -47,6 +52,12 @@ impl Sort for RationalSort {
         add_primitives!(eg, "denom" = |a: R| -> i64 { *a.denom() });
 
         add_primitives!(eg, "to-f64" = |a: R| -> f64 { a.to_f64().unwrap() });
+        // Every finite float is a dyadic rational, so the conversion is exact
+        // as long as the numerator and denominator fit in an i64.
+        add_primitives!(eg, "from-f64" = |a: f64| -> Opt<R> {
+            num::BigRational::from_float(a)
+                .and_then(|q| Some(R::new(q.numer().to_i64()?, q.denom().to_i64()?)))
+        });
 
         add_primitives!(eg, "pow" = |a: R, b: R| -> Option<R> {
             if a.is_zero() {
@@ -57,22 +68,25 @@ impl Sort for RationalSort {
                 }
             } else if b.is_zero() {
                 Some(R::one())
+            } else if !b.is_integer() {
+                None
             } else if let Some(b) = b.to_i64() {
-                if let Ok(b) = usize::try_from(b) {
-                    num::traits::checked_pow(a, b)
+                let p = num::traits::checked_pow(a, usize::try_from(b.unsigned_abs()).ok()?)?;
+                if b.is_negative() {
+                    R::one().checked_div(&p)
                 } else {
-                    // TODO handle negative powers
-                    None
+                    Some(p)
                 }
             } else {
                 None
             }
         });
         add_primitives!(eg, "log" = |a: R| -> Option<R> {
+            // The natural log of any rational other than 1 is irrational.
             if a.is_one() {
                 Some(R::zero())
             } else {
-                todo!()
+                None
             }
         });
         add_primitives!(eg, "sqrt" = |a: R| -> Option<R> {
@@ -89,11 +103,48 @@ impl Sort for RationalSort {
                 None
             }
         });
-        add_primitives!(eg, "cbrt" = |a: R| -> Option<R> {
-            if a.is_one() {
-                Some(R::one())
+        add_primitives!(eg, "cbrt" = |a: R| -> Option<R> {{
+            let c1 = a.numer().cbrt();
+            let c2 = a.denom().cbrt();
+            let is_perfect = &(c1 * c1 * c1) == a.numer() && &(c2 * c2 * c2) == a.denom();
+            if is_perfect {
+                Some(R::new(c1, c2))
+            } else {
+                None
+            }
+        }});
+
+        // The mediant of a/b and c/d (both in lowest terms) is (a + c)/(b + d),
+        // which lies between them.
+        add_primitives!(eg, "mediant" = |a: R, b: R| -> Opt<R> {
+            Some(R::new(a.numer().checked_add(b.numer())?, a.denom().checked_add(b.denom())?))
+        });
+        // `quotient` rounds towards negative infinity and `rem` has the sign
+        // of the divisor, so `(+ (* b (quotient a b)) (rem a b))` is `a`.
+        add_primitives!(eg, "quotient" = |a: R, b: R| -> Opt<R> {
+            if b.is_zero() {
+                None
+            } else {
+                from_big(&(to_big(a) / to_big(b)).floor())
+            }
+        });
+        add_primitives!(eg, "rem" = |a: R, b: R| -> Opt<R> {
+            if b.is_zero() {
+                None
             } else {
-                todo!()
+                let (a, b) = (to_big(a), to_big(b));
+                from_big(&(&a - &b * (&a / &b).floor()))
+            }
+        });
+
+        add_primitives!(eg, "best-rational-within" = |lo: R, hi: R| -> Opt<R> {
+            best_rational_within(&to_big(lo), &to_big(hi))
+        });
+        add_primitives!(eg, "approximate" = |a: R, eps: R| -> Opt<R> {
+            if eps.is_negative() {
+                None
+            } else {
+                best_rational_within(&(to_big(a) - to_big(eps)), &(to_big(a) + to_big(eps)))
             }
         });
 
@@ -107,38 +158,74 @@ impl Sort for RationalSort {
         #[cfg(debug_assertions)]
         debug_assert_eq!(value.tag, self.name());
 
-        let rat = R::load(self, &value);
-        let numer = *rat.numer();
-        let denom = *rat.denom();
-        (
-            1,
-            Expr::call_no_span(
-                "rational",
-                vec![
-                    GenericExpr::Lit(DUMMY_SPAN.clone(), Literal::Int(numer)),
-                    GenericExpr::Lit(DUMMY_SPAN.clone(), Literal::Int(denom)),
-                ],
-            ),
-        )
+        (1, rational_expr(R::load(self, &value)))
     }
 }
 
+/// The expression `(rational numer denom)` that evaluates to `rat`.
+pub(crate) fn rational_expr(rat: R) -> Expr {
+    Expr::call_no_span(
+        "rational",
+        vec![
+            GenericExpr::Lit(DUMMY_SPAN.clone(), Literal::Int(*rat.numer())),
+            GenericExpr::Lit(DUMMY_SPAN.clone(), Literal::Int(*rat.denom())),
+        ],
+    )
+}
+
 impl FromSort for R {
     type Sort = RationalSort;
-    fn load(_sort: &Self::Sort, value: &Value) -> Self {
-        let i = value.bits as usize;
-        *RATS.lock().unwrap().get_index(i).unwrap()
+    fn load(sort: &Self::Sort, value: &Value) -> Self {
+        sort.rats.get(value.bits)
     }
 }
 
 impl IntoSort for R {
     type Sort = RationalSort;
-    fn store(self, _sort: &Self::Sort) -> Option<Value> {
-        let (i, _) = RATS.lock().unwrap().insert_full(self);
+    fn store(self, sort: &Self::Sort) -> Option<Value> {
+        let i = sort.rats.intern(self);
         Some(Value {
             #[cfg(debug_assertions)]
-            tag: RationalSort.name(),
-            bits: i as u64,
+            tag: sort.name(),
+            bits: i,
         })
     }
 }
+
+fn to_big(r: R) -> BigRational {
+    BigRational::new((*r.numer()).into(), (*r.denom()).into())
+}
+
+fn from_big(q: &BigRational) -> Option<R> {
+    Some(R::new(q.numer().to_i64()?, q.denom().to_i64()?))
+}
+
+/// Returns the simplest rational (smallest denominator, then smallest
+/// magnitude) in the closed interval `[lo, hi]`, or `None` if the interval is
+/// empty or the result does not fit in a [`Rational64`](R).
+fn best_rational_within(lo: &BigRational, hi: &BigRational) -> Option<R> {
+    if lo > hi {
+        return None;
+    }
+    from_big(&simplest_between(lo, hi))
+}
+
+/// Walks the continued fraction expansions of `lo` and `hi` until they
+/// diverge. Requires `lo <= hi`.
+fn simplest_between(lo: &BigRational, hi: &BigRational) -> BigRational {
+    if hi.is_negative() {
+        -simplest_between(&-hi, &-lo)
+    } else if !lo.is_positive() {
+        BigRational::zero()
+    } else {
+        let ceil = lo.ceil();
+        if &ceil <= hi {
+            ceil
+        } else {
+            // Both bounds lie strictly inside (n, n + 1).
+            let n = lo.floor();
+            let rest = simplest_between(&(hi - &n).recip(), &(lo - &n).recip());
+            n + rest.recip()
+        }
+    }
+}
@@ -42,6 +42,11 @@ impl Presort for SetSort {
             "set-intersect".into(),
             "set-get".into(),
             "set-length".into(),
+            "set-subset?".into(),
+            "set-superset?".into(),
+            "set-symmetric-diff".into(),
+            "set-pick".into(),
+            "unstable-set-map".into(),
         ]
     }
 
@@ -84,6 +89,10 @@ impl Sort for SetSort {
         self
     }
 
+    fn approximate_memory_usage(&self) -> usize {
+        approximate_set_memory_usage(&self.sets, |set| set.len() * std::mem::size_of::<Value>())
+    }
+
     fn is_container_sort(&self) -> bool {
         true
     }
@@ -167,8 +176,43 @@ impl Sort for SetSort {
         });
         typeinfo.add_primitive(Intersect {
             name: "set-intersect".into(),
-            set: self,
+            set: self.clone(),
+        });
+        typeinfo.add_primitive(Subset {
+            name: "set-subset?".into(),
+            set: self.clone(),
+            flipped: false,
+        });
+        typeinfo.add_primitive(Subset {
+            name: "set-superset?".into(),
+            set: self.clone(),
+            flipped: true,
+        });
+        typeinfo.add_primitive(SymmetricDiff {
+            name: "set-symmetric-diff".into(),
+            set: self.clone(),
+        });
+        if let Some(order) = element_order(&self.element) {
+            typeinfo.add_primitive(Pick {
+                name: "set-pick".into(),
+                set: self.clone(),
+                order,
+            });
+        }
+        let inner_name = self.element.name();
+        let fn_sort = typeinfo.get_sort_by(|s: &Arc<FunctionSort>| {
+            (s.output.name() == inner_name)
+                && s.inputs.len() == 1
+                && (s.inputs[0].name() == inner_name)
         });
+        // Only include map function if we already declared a function sort with the correct signature
+        if let Some(fn_sort) = fn_sort {
+            typeinfo.add_primitive(Map {
+                name: "unstable-set-map".into(),
+                set: self,
+                fn_: fn_sort,
+            });
+        }
     }
 
     fn make_expr(&self, egraph: &EGraph, value: Value) -> (Cost, Expr) {
@@ -599,3 +643,144 @@ impl PrimitiveLike for Diff {
         set1.store(&self.set)
     }
 }
+
+/// `(set-subset? a b)` succeeds if every element of `a` is in `b`; with
+/// `flipped` set this is `set-superset?`.
+struct Subset {
+    name: Symbol,
+    set: Arc<SetSort>,
+    flipped: bool,
+}
+
+impl PrimitiveLike for Subset {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.set.clone(), self.set.clone(), Arc::new(UnitSort)],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let set1 = ValueSet::load(&self.set, &values[0]);
+        let set2 = ValueSet::load(&self.set, &values[1]);
+        let holds = if self.flipped {
+            set1.is_superset(&set2)
+        } else {
+            set1.is_subset(&set2)
+        };
+        holds.then(Value::unit)
+    }
+}
+
+struct SymmetricDiff {
+    name: Symbol,
+    set: Arc<SetSort>,
+}
+
+impl PrimitiveLike for SymmetricDiff {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.set.clone(), self.set.clone(), self.set.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let set1 = ValueSet::load(&self.set, &values[0]);
+        let set2 = ValueSet::load(&self.set, &values[1]);
+        let set: ValueSet = set1.symmetric_difference(&set2).copied().collect();
+        set.store(&self.set)
+    }
+}
+
+/// Returns the smallest element of a non-empty set, in the same order that
+/// `vec-sort` uses. It is only defined for element sorts with such an order.
+struct Pick {
+    name: Symbol,
+    set: Arc<SetSort>,
+    order: ElementOrder,
+}
+
+impl PrimitiveLike for Pick {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.set.clone(), self.set.element()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let set = ValueSet::load(&self.set, &values[0]);
+        set.into_iter().min_by(|a, b| (self.order)(a, b))
+    }
+}
+
+struct Map {
+    name: Symbol,
+    set: Arc<SetSort>,
+    fn_: Arc<FunctionSort>,
+}
+
+impl PrimitiveLike for Map {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.fn_.clone(), self.set.clone(), self.set.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph =
+            egraph.unwrap_or_else(|| panic!("`{}` is not supported yet in facts.", self.name));
+        let set = ValueSet::load(&self.set, &values[1]);
+        let new_set: ValueSet = set
+            .iter()
+            .map(|e| self.fn_.apply(&values[0], &[*e], egraph))
+            .collect();
+        new_set.store(&self.set)
+    }
+}
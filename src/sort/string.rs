@@ -1,6 +1,13 @@
 use std::num::NonZeroU32;
+use std::sync::Mutex;
 
-use crate::{ast::Literal, constraint::AllEqualTypeConstraint};
+use regex::Regex;
+
+use crate::{
+    ast::Literal,
+    constraint::AllEqualTypeConstraint,
+    util::{FreshGen, HashMap},
+};
 
 use super::*;
 
@@ -9,6 +16,18 @@ pub struct StringSort;
 
 lazy_static! {
     static ref STRING_SORT_NAME: Symbol = "String".into();
+    static ref REGEXES: Mutex<HashMap<Symbol, Option<Regex>>> = Default::default();
+}
+
+/// Compiles `pattern`, caching the result since rules tend to apply the same
+/// pattern over and over. Returns `None` if the pattern is not a valid regex.
+fn regex(pattern: Symbol) -> Option<Regex> {
+    REGEXES
+        .lock()
+        .unwrap()
+        .entry(pattern)
+        .or_insert_with(|| Regex::new(pattern.as_str()).ok())
+        .clone()
 }
 
 impl Sort for StringSort {
@@ -31,6 +50,7 @@ impl Sort for StringSort {
         )
     }
 
+    #[rustfmt::skip]
     fn register_primitives(self: Arc<Self>, typeinfo: &mut TypeInfo) {
         typeinfo.add_primitive(Add {
             name: "+".into(),
@@ -38,11 +58,55 @@ impl Sort for StringSort {
         });
         typeinfo.add_primitive(Replace {
             name: "replace".into(),
+            string: self.clone(),
+        });
+        typeinfo.add_primitive(FreshSymbol {
+            name: "fresh-symbol".into(),
             string: self,
         });
+
+        type Opt<T=()> = Option<T>;
+
+        add_primitives!(typeinfo, "regex-match" = |s: Symbol, pattern: Symbol| -> Opt { regex(pattern)?.is_match(s.as_str()).then_some(()) });
+        add_primitives!(typeinfo, "regex-find" = |s: Symbol, pattern: Symbol| -> Opt<Symbol> { Some(regex(pattern)?.find(s.as_str())?.as_str().into()) });
+        add_primitives!(typeinfo, "regex-replace" = |s: Symbol, pattern: Symbol, replacement: Symbol| -> Opt<Symbol> {
+            Some(regex(pattern)?.replace_all(s.as_str(), replacement.as_str()).into_owned().into())
+        });
+
+        add_primitives!(typeinfo, "to-lower" = |s: Symbol| -> Symbol { s.as_str().to_lowercase().into() });
+        add_primitives!(typeinfo, "to-upper" = |s: Symbol| -> Symbol { s.as_str().to_uppercase().into() });
+        add_primitives!(typeinfo, "trim" = |s: Symbol| -> Symbol { s.as_str().trim().into() });
+        add_primitives!(typeinfo, "starts-with" = |s: Symbol, prefix: Symbol| -> Opt { s.as_str().starts_with(prefix.as_str()).then_some(()) });
+        add_primitives!(typeinfo, "ends-with" = |s: Symbol, suffix: Symbol| -> Opt { s.as_str().ends_with(suffix.as_str()).then_some(()) });
+        add_primitives!(typeinfo, "contains" = |s: Symbol, needle: Symbol| -> Opt { s.as_str().contains(needle.as_str()).then_some(()) });
     }
 }
 
+/// Registers the primitives that produce or consume a `Vec String`. They are
+/// added when such a vector sort is declared, since that is the first point at
+/// which both sorts exist.
+pub(super) fn register_string_vec_primitives(
+    string: Arc<StringSort>,
+    vec: Arc<VecSort>,
+    typeinfo: &mut TypeInfo,
+) {
+    typeinfo.add_primitive(RegexCaptures {
+        name: "regex-captures".into(),
+        string: string.clone(),
+        vec: vec.clone(),
+    });
+    typeinfo.add_primitive(Split {
+        name: "split".into(),
+        string: string.clone(),
+        vec: vec.clone(),
+    });
+    typeinfo.add_primitive(Join {
+        name: "join".into(),
+        string,
+        vec,
+    });
+}
+
 // TODO could use a local symbol table
 
 impl IntoSort for Symbol {
@@ -125,3 +189,160 @@ impl PrimitiveLike for Replace {
         Some(Value::from(res))
     }
 }
+
+/// Returns a new string starting with the e-graph's reserved symbol each time
+/// it is applied, so every firing of a rule gets a name that is distinct from
+/// all other generated names and from the user's own strings.
+struct FreshSymbol {
+    name: Symbol,
+    string: Arc<StringSort>,
+}
+
+impl PrimitiveLike for FreshSymbol {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.string.clone(), self.string.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph = egraph.expect("`fresh-symbol` is not supported in facts.");
+        let prefix = Symbol::load(&self.string, &values[0]);
+        egraph.symbol_gen.fresh(&prefix).store(&self.string)
+    }
+}
+
+struct RegexCaptures {
+    name: Symbol,
+    string: Arc<StringSort>,
+    vec: Arc<VecSort>,
+}
+
+impl PrimitiveLike for RegexCaptures {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.string.clone(), self.string.clone(), self.vec.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    /// Returns the capture groups of the first match, starting with the whole
+    /// match as group 0. Groups that did not participate in the match are empty.
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let s = Symbol::load(&self.string, &values[0]);
+        let pattern = Symbol::load(&self.string, &values[1]);
+        let captures = regex(pattern)?.captures(s.as_str())?;
+        let groups: Vec<Value> = captures
+            .iter()
+            .map(|group| {
+                let group: Symbol = group.map_or("", |m| m.as_str()).into();
+                Value::from(group)
+            })
+            .collect();
+        groups.store(&self.vec)
+    }
+}
+
+struct Split {
+    name: Symbol,
+    string: Arc<StringSort>,
+    vec: Arc<VecSort>,
+}
+
+impl PrimitiveLike for Split {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.string.clone(), self.string.clone(), self.vec.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    /// Splits on every occurrence of the separator. An empty separator splits
+    /// the string into its characters.
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let s = Symbol::load(&self.string, &values[0]);
+        let sep = Symbol::load(&self.string, &values[1]);
+        let parts: Vec<Value> = if sep.as_str().is_empty() {
+            s.as_str()
+                .chars()
+                .map(|c| Value::from(Symbol::from(c.to_string())))
+                .collect()
+        } else {
+            s.as_str()
+                .split(sep.as_str())
+                .map(|part| Value::from(Symbol::from(part)))
+                .collect()
+        };
+        parts.store(&self.vec)
+    }
+}
+
+struct Join {
+    name: Symbol,
+    string: Arc<StringSort>,
+    vec: Arc<VecSort>,
+}
+
+impl PrimitiveLike for Join {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.vec.clone(), self.string.clone(), self.string.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let parts: Vec<String> = Vec::<Value>::load(&self.vec, &values[0])
+            .iter()
+            .map(|v| Symbol::load(&self.string, v).to_string())
+            .collect();
+        let sep = Symbol::load(&self.string, &values[1]);
+        let res: Symbol = parts.join(sep.as_str()).into();
+        Some(Value::from(res))
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{ast::Literal, constraint::SimpleTypeConstraint, util::Interner};
+
+use super::*;
+
+/// Terms as first-class values, declared with `(sort Syntax (Term))`.
+///
+/// `(quote e)` turns the e-class `e` of any datatype into the syntax of its
+/// cheapest term, and `(unquote t)` adds the term `t` back to the e-graph.
+/// Both need the e-graph, so they are only allowed in actions. Quoting runs
+/// extraction on the whole e-graph, so it is much more expensive than other
+/// primitives.
+///
+/// Terms are compared structurally and carry no source locations.
+///
+/// Like string literals, `String` values are kept in their escaped form, so
+/// `term-to-string` escapes the quotes and backslashes in the term's source
+/// and `term-parse` undoes this before parsing.
+#[derive(Debug)]
+pub struct TermSort {
+    name: Symbol,
+    terms: Interner<Expr>,
+}
+
+impl TermSort {
+    fn load(&self, value: &Value) -> Expr {
+        self.terms.get(value.bits)
+    }
+
+    fn store(&self, expr: Expr) -> Value {
+        let expr = expr.visit_exprs(&mut |e| match e {
+            GenericExpr::Lit(_, lit) => GenericExpr::Lit(DUMMY_SPAN.clone(), lit),
+            GenericExpr::Var(_, v) => GenericExpr::Var(DUMMY_SPAN.clone(), v),
+            GenericExpr::Call(_, head, args) => GenericExpr::Call(DUMMY_SPAN.clone(), head, args),
+        });
+        Value {
+            #[cfg(debug_assertions)]
+            tag: self.name,
+            bits: self.terms.intern(expr),
+        }
+    }
+
+    /// Registers `quote` and `unquote` between this sort and the datatype `eq`.
+    fn register_eq_primitives(self: &Arc<Self>, eq: ArcSort, typeinfo: &mut TypeInfo) {
+        typeinfo.add_primitive(Quote {
+            name: "quote".into(),
+            term: self.clone(),
+            eq: eq.clone(),
+        });
+        typeinfo.add_primitive(Unquote {
+            name: "unquote".into(),
+            term: self.clone(),
+            eq,
+        });
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn unescape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && matches!(chars.peek(), Some('\\' | '"')) {
+            res.extend(chars.next());
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+/// Registers `quote` and `unquote` for a newly declared datatype and every
+/// term sort declared before it.
+pub(super) fn register_term_primitives(eq: ArcSort, typeinfo: &mut TypeInfo) {
+    let terms: Vec<Arc<TermSort>> = typeinfo
+        .sorts
+        .values()
+        .filter_map(|sort| Arc::downcast(sort.clone().as_arc_any()).ok())
+        .collect();
+    for term in terms {
+        term.register_eq_primitives(eq.clone(), typeinfo);
+    }
+}
+
+impl Presort for TermSort {
+    fn presort_name() -> Symbol {
+        "Term".into()
+    }
+
+    fn reserved_primitives() -> Vec<Symbol> {
+        vec![
+            "quote".into(),
+            "unquote".into(),
+            "term-parse".into(),
+            "term-to-string".into(),
+            "term-size".into(),
+        ]
+    }
+
+    fn make_sort(
+        _typeinfo: &mut TypeInfo,
+        name: Symbol,
+        args: &[Expr],
+    ) -> Result<ArcSort, TypeError> {
+        if let Some(arg) = args.first() {
+            return Err(TypeError::DisallowedSort(
+                name,
+                "Term sorts take no arguments".into(),
+                arg.span(),
+            ));
+        }
+        Ok(Arc::new(Self {
+            name,
+            terms: Default::default(),
+        }))
+    }
+}
+
+impl Sort for TermSort {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+        self
+    }
+
+    fn approximate_memory_usage(&self) -> usize {
+        self.terms.approximate_memory_usage()
+    }
+
+    fn register_primitives(self: Arc<Self>, typeinfo: &mut TypeInfo) {
+        let string = typeinfo.get_sort_nofail::<StringSort>();
+        let eqs: Vec<ArcSort> = typeinfo
+            .sorts
+            .values()
+            .filter(|sort| sort.is_eq_sort())
+            .cloned()
+            .collect();
+        for eq in eqs {
+            self.register_eq_primitives(eq, typeinfo);
+        }
+        typeinfo.add_primitive(Parse {
+            name: "term-parse".into(),
+            term: self.clone(),
+            string: string.clone(),
+        });
+        typeinfo.add_primitive(TermToString {
+            name: "term-to-string".into(),
+            term: self.clone(),
+            string,
+        });
+        typeinfo.add_primitive(Size {
+            name: "term-size".into(),
+            term: self,
+        });
+    }
+
+    fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(value.tag, self.name());
+
+        let text = escape(&self.load(&value).to_string());
+        let text = GenericExpr::Lit(DUMMY_SPAN.clone(), Literal::String(text.into()));
+        (1, Expr::call_no_span("term-parse", vec![text]))
+    }
+}
+
+struct Quote {
+    name: Symbol,
+    term: Arc<TermSort>,
+    eq: ArcSort,
+}
+
+impl PrimitiveLike for Quote {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.eq.clone(), self.term.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph = egraph.expect("`quote` is not supported in facts.");
+        let (termdag, term) = egraph.extract_value(&self.eq, values[0]);
+        Some(self.term.store(termdag.term_to_expr(&term)))
+    }
+}
+
+struct Unquote {
+    name: Symbol,
+    term: Arc<TermSort>,
+    eq: ArcSort,
+}
+
+impl PrimitiveLike for Unquote {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.term.clone(), self.eq.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    /// Fails if the term is not well-typed or does not belong to the
+    /// expected datatype.
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph = egraph.expect("`unquote` is not supported in facts.");
+        let expr = self.term.load(&values[0]);
+        let expr = egraph
+            .type_info
+            .typecheck_expr(&mut egraph.symbol_gen, &expr, &Default::default())
+            .ok()?;
+        if expr.output_type().name() != self.eq.name() {
+            return None;
+        }
+        egraph.eval_resolved_expr(&expr).ok()
+    }
+}
+
+struct Parse {
+    name: Symbol,
+    term: Arc<TermSort>,
+    string: Arc<StringSort>,
+}
+
+impl PrimitiveLike for Parse {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.string.clone(), self.term.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let text = Symbol::load(&self.string, &values[0]);
+        let expr = crate::ast::parse_expr(None, &unescape(text.as_str())).ok()?;
+        Some(self.term.store(expr))
+    }
+}
+
+struct TermToString {
+    name: Symbol,
+    term: Arc<TermSort>,
+    string: Arc<StringSort>,
+}
+
+impl PrimitiveLike for TermToString {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.term.clone(), self.string.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let text: Symbol = escape(&self.term.load(&values[0]).to_string()).into();
+        text.store(&self.string)
+    }
+}
+
+struct Size {
+    name: Symbol,
+    term: Arc<TermSort>,
+}
+
+impl PrimitiveLike for Size {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.term.clone(), Arc::new(I64Sort)],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        (self.term.load(&values[0]).ast_size() as i64).store(&I64Sort)
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::sync::Mutex;
 
 use crate::constraint::AllEqualTypeConstraint;
@@ -41,6 +42,17 @@ impl Presort for VecSort {
             "vec-get".into(),
             "vec-set".into(),
             "vec-remove".into(),
+            "vec-sort".into(),
+            "vec-reverse".into(),
+            "vec-slice".into(),
+            "vec-index-of".into(),
+            "vec-sum".into(),
+            "vec-min".into(),
+            "vec-max".into(),
+            "vec-mean".into(),
+            "unstable-vec-map".into(),
+            "unstable-vec-filter".into(),
+            "unstable-vec-fold".into(),
         ]
     }
 
@@ -83,6 +95,12 @@ impl Sort for VecSort {
         self
     }
 
+    fn approximate_memory_usage(&self) -> usize {
+        approximate_set_memory_usage(&self.vecs, |vec| {
+            vec.capacity() * std::mem::size_of::<Value>()
+        })
+    }
+
     fn is_container_sort(&self) -> bool {
         true
     }
@@ -120,6 +138,10 @@ impl Sort for VecSort {
     }
 
     fn register_primitives(self: Arc<Self>, typeinfo: &mut TypeInfo) {
+        if let Ok(string) = self.element.clone().as_arc_any().downcast::<StringSort>() {
+            register_string_vec_primitives(string, self.clone(), typeinfo);
+        }
+        register_numeric_vec_primitives(&self, typeinfo);
         typeinfo.add_primitive(VecRebuild {
             name: "rebuild".into(),
             vec: self.clone(),
@@ -166,8 +188,75 @@ impl Sort for VecSort {
         });
         typeinfo.add_primitive(Remove {
             name: "vec-remove".into(),
-            vec: self,
-        })
+            vec: self.clone(),
+        });
+        if let Some(cmp) = element_order(&self.element) {
+            typeinfo.add_primitive(SortElements {
+                name: "vec-sort".into(),
+                vec: self.clone(),
+                cmp,
+            });
+        }
+        typeinfo.add_primitive(Reverse {
+            name: "vec-reverse".into(),
+            vec: self.clone(),
+        });
+        typeinfo.add_primitive(Slice {
+            name: "vec-slice".into(),
+            vec: self.clone(),
+        });
+        typeinfo.add_primitive(IndexOf {
+            name: "vec-index-of".into(),
+            vec: self.clone(),
+        });
+
+        let map_sorts: Vec<Arc<MapSort>> = typeinfo
+            .sorts
+            .values()
+            .filter_map(|sort| sort.clone().as_arc_any().downcast::<MapSort>().ok())
+            .collect();
+        for map in map_sorts {
+            register_map_vec_primitives(&map, &self, typeinfo);
+        }
+
+        // Only include the higher-order primitives for function sorts with a
+        // matching signature that have already been declared
+        let element_name = self.element.name();
+        let fn_sorts: Vec<Arc<FunctionSort>> = typeinfo
+            .sorts
+            .values()
+            .filter_map(|sort| sort.clone().as_arc_any().downcast::<FunctionSort>().ok())
+            .collect();
+        for fn_sort in fn_sorts {
+            match fn_sort.inputs.as_slice() {
+                [input] if input.name() == element_name => {
+                    if fn_sort.output.name() == element_name {
+                        typeinfo.add_primitive(MapElements {
+                            name: "unstable-vec-map".into(),
+                            vec: self.clone(),
+                            fn_: fn_sort.clone(),
+                        });
+                    }
+                    if fn_sort.output.name() == BoolSort.name() {
+                        typeinfo.add_primitive(Filter {
+                            name: "unstable-vec-filter".into(),
+                            vec: self.clone(),
+                            fn_: fn_sort.clone(),
+                        });
+                    }
+                }
+                [acc, input]
+                    if input.name() == element_name && acc.name() == fn_sort.output.name() =>
+                {
+                    typeinfo.add_primitive(Fold {
+                        name: "unstable-vec-fold".into(),
+                        vec: self.clone(),
+                        fn_: fn_sort.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
     }
 
     fn make_expr(&self, egraph: &EGraph, value: Value) -> (Cost, Expr) {
@@ -229,6 +318,122 @@ impl FromSort for ValueVec {
     }
 }
 
+/// Registers `vec-sum`, `vec-min`, `vec-max`, and `vec-mean` for vectors of
+/// `i64`, `f64`, or `Rational`. Overflow and empty vectors (other than for
+/// `vec-sum`) make them fail. The mean of integers is an exact `Rational`.
+fn register_numeric_vec_primitives(vec: &Arc<VecSort>, typeinfo: &mut TypeInfo) {
+    use num::traits::{CheckedAdd, CheckedDiv, Zero};
+    type R = num::rational::Rational64;
+    let element = vec.element.clone().as_arc_any();
+    let rational = typeinfo.get_sort_nofail::<RationalSort>();
+
+    if let Ok(i64) = element.clone().downcast::<I64Sort>() {
+        add_aggregate(typeinfo, "vec-sum", vec, &i64, &i64, |xs: Vec<i64>| {
+            xs.into_iter().try_fold(0, i64::checked_add)
+        });
+        add_aggregate(typeinfo, "vec-min", vec, &i64, &i64, |xs: Vec<i64>| {
+            xs.into_iter().min()
+        });
+        add_aggregate(typeinfo, "vec-max", vec, &i64, &i64, |xs: Vec<i64>| {
+            xs.into_iter().max()
+        });
+        add_aggregate(
+            typeinfo,
+            "vec-mean",
+            vec,
+            &i64,
+            &rational,
+            |xs: Vec<i64>| {
+                let len = i64::try_from(xs.len()).ok().filter(|len| *len > 0)?;
+                Some(R::new(xs.into_iter().try_fold(0, i64::checked_add)?, len))
+            },
+        );
+    } else if let Ok(f64) = element.clone().downcast::<F64Sort>() {
+        add_aggregate(typeinfo, "vec-sum", vec, &f64, &f64, |xs: Vec<f64>| {
+            Some(xs.into_iter().sum::<f64>())
+        });
+        add_aggregate(typeinfo, "vec-min", vec, &f64, &f64, |xs: Vec<f64>| {
+            xs.into_iter().reduce(f64::min)
+        });
+        add_aggregate(typeinfo, "vec-max", vec, &f64, &f64, |xs: Vec<f64>| {
+            xs.into_iter().reduce(f64::max)
+        });
+        add_aggregate(typeinfo, "vec-mean", vec, &f64, &f64, |xs: Vec<f64>| {
+            let len = xs.len() as f64;
+            (len > 0.0).then(|| xs.into_iter().sum::<f64>() / len)
+        });
+    } else if let Ok(r) = element.downcast::<RationalSort>() {
+        fn sum(xs: Vec<R>) -> Option<R> {
+            xs.into_iter().try_fold(R::zero(), |a, b| a.checked_add(&b))
+        }
+        add_aggregate(typeinfo, "vec-sum", vec, &r, &r, sum);
+        add_aggregate(typeinfo, "vec-min", vec, &r, &r, |xs: Vec<R>| {
+            xs.into_iter().min()
+        });
+        add_aggregate(typeinfo, "vec-max", vec, &r, &r, |xs: Vec<R>| {
+            xs.into_iter().max()
+        });
+        add_aggregate(typeinfo, "vec-mean", vec, &r, &r, |xs: Vec<R>| {
+            let len = i64::try_from(xs.len()).ok().filter(|len| *len > 0)?;
+            sum(xs)?.checked_div(&R::from_integer(len))
+        });
+    }
+}
+
+fn add_aggregate<T, U>(
+    typeinfo: &mut TypeInfo,
+    name: &str,
+    vec: &Arc<VecSort>,
+    element: &Arc<T::Sort>,
+    output: &Arc<U::Sort>,
+    reduce: fn(Vec<T>) -> Option<U>,
+) where
+    T: FromSort + 'static,
+    U: IntoSort + 'static,
+{
+    typeinfo.add_primitive(Aggregate::<T, U> {
+        name: name.into(),
+        vec: vec.clone(),
+        element: element.clone(),
+        output: output.clone(),
+        reduce,
+    });
+}
+
+struct Aggregate<T: FromSort, U: IntoSort> {
+    name: Symbol,
+    vec: Arc<VecSort>,
+    element: Arc<T::Sort>,
+    output: Arc<U::Sort>,
+    reduce: fn(Vec<T>) -> Option<U>,
+}
+
+impl<T: FromSort, U: IntoSort> PrimitiveLike for Aggregate<T, U> {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.vec.clone(), self.output.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let vec = ValueVec::load(&self.vec, &values[0]);
+        let xs = vec.iter().map(|x| T::load(&self.element, x)).collect();
+        (self.reduce)(xs)?.store(&self.output)
+    }
+}
+
 struct VecRebuild {
     name: Symbol,
     vec: Arc<VecSort>,
@@ -604,6 +809,296 @@ impl PrimitiveLike for Remove {
     }
 }
 
+/// Compares two values of the same sort.
+pub(crate) type ElementOrder = Arc<dyn Fn(&Value, &Value) -> Ordering + Send + Sync>;
+
+/// How `vec-sort` orders the elements of a sort: numbers (including
+/// `Rational`, `BigInt`, and `BigRat`) by value, strings alphabetically,
+/// chars by code point, and `false` before `true`. Other sorts, such as
+/// e-classes, have no order that means anything, so their vectors can't be
+/// sorted.
+pub(crate) fn element_order(element: &ArcSort) -> Option<ElementOrder> {
+    use num::{rational::Rational64, BigInt, BigRational};
+    let element = element.clone().as_arc_any();
+    if element.is::<I64Sort>() {
+        Some(Arc::new(|a, b| (a.bits as i64).cmp(&(b.bits as i64))))
+    } else if element.is::<F64Sort>() {
+        Some(Arc::new(|a, b| {
+            f64::from_bits(a.bits).total_cmp(&f64::from_bits(b.bits))
+        }))
+    } else if element.is::<StringSort>() {
+        Some(Arc::new(|a, b| {
+            let load = |v| Symbol::load(&StringSort, v);
+            load(a).as_str().cmp(load(b).as_str())
+        }))
+    } else if element.is::<BoolSort>() || element.is::<CharSort>() {
+        Some(Arc::new(|a, b| a.bits.cmp(&b.bits)))
+    } else if let Ok(rats) = element.clone().downcast::<RationalSort>() {
+        Some(Arc::new(move |a, b| {
+            Rational64::load(&rats, a).cmp(&Rational64::load(&rats, b))
+        }))
+    } else if element.is::<BigIntSort>() {
+        Some(Arc::new(|a, b| {
+            BigInt::load(&BigIntSort, a).cmp(&BigInt::load(&BigIntSort, b))
+        }))
+    } else if element.is::<BigRatSort>() {
+        Some(Arc::new(|a, b| {
+            BigRational::load(&BigRatSort, a).cmp(&BigRational::load(&BigRatSort, b))
+        }))
+    } else {
+        None
+    }
+}
+
+struct SortElements {
+    name: Symbol,
+    vec: Arc<VecSort>,
+    cmp: ElementOrder,
+}
+
+impl PrimitiveLike for SortElements {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.vec.clone(), self.vec.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let mut vec = ValueVec::load(&self.vec, &values[0]);
+        vec.sort_by(|a, b| (self.cmp)(a, b));
+        vec.store(&self.vec)
+    }
+}
+
+struct Reverse {
+    name: Symbol,
+    vec: Arc<VecSort>,
+}
+
+impl PrimitiveLike for Reverse {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.vec.clone(), self.vec.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let mut vec = ValueVec::load(&self.vec, &values[0]);
+        vec.reverse();
+        vec.store(&self.vec)
+    }
+}
+
+struct Slice {
+    name: Symbol,
+    vec: Arc<VecSort>,
+}
+
+impl PrimitiveLike for Slice {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![
+                self.vec.clone(),
+                Arc::new(I64Sort),
+                Arc::new(I64Sort),
+                self.vec.clone(),
+            ],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    /// Returns the elements in the half-open range `[start, end)`, failing if
+    /// the range is out of bounds.
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let vec = ValueVec::load(&self.vec, &values[0]);
+        let start = usize::try_from(i64::load(&I64Sort, &values[1])).ok()?;
+        let end = usize::try_from(i64::load(&I64Sort, &values[2])).ok()?;
+        vec.get(start..end)?.to_vec().store(&self.vec)
+    }
+}
+
+struct IndexOf {
+    name: Symbol,
+    vec: Arc<VecSort>,
+}
+
+impl PrimitiveLike for IndexOf {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.vec.clone(), self.vec.element(), Arc::new(I64Sort)],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        _egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let vec = ValueVec::load(&self.vec, &values[0]);
+        let index = vec.iter().position(|e| *e == values[1])?;
+        Some(Value::from(index as i64))
+    }
+}
+
+struct MapElements {
+    name: Symbol,
+    vec: Arc<VecSort>,
+    fn_: Arc<FunctionSort>,
+}
+
+impl PrimitiveLike for MapElements {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.fn_.clone(), self.vec.clone(), self.vec.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph =
+            egraph.unwrap_or_else(|| panic!("`{}` is not supported yet in facts.", self.name));
+        let vec = ValueVec::load(&self.vec, &values[1]);
+        let new_vec: ValueVec = vec
+            .iter()
+            .map(|e| self.fn_.apply(&values[0], &[*e], egraph))
+            .collect();
+        new_vec.store(&self.vec)
+    }
+}
+
+struct Filter {
+    name: Symbol,
+    vec: Arc<VecSort>,
+    fn_: Arc<FunctionSort>,
+}
+
+impl PrimitiveLike for Filter {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![self.fn_.clone(), self.vec.clone(), self.vec.clone()],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph =
+            egraph.unwrap_or_else(|| panic!("`{}` is not supported yet in facts.", self.name));
+        let vec = ValueVec::load(&self.vec, &values[1]);
+        let new_vec: ValueVec = vec
+            .into_iter()
+            .filter(|e| bool::load(&BoolSort, &self.fn_.apply(&values[0], &[*e], egraph)))
+            .collect();
+        new_vec.store(&self.vec)
+    }
+}
+
+struct Fold {
+    name: Symbol,
+    vec: Arc<VecSort>,
+    fn_: Arc<FunctionSort>,
+}
+
+impl PrimitiveLike for Fold {
+    fn name(&self) -> Symbol {
+        self.name
+    }
+
+    fn get_type_constraints(&self, span: &Span) -> Box<dyn TypeConstraint> {
+        SimpleTypeConstraint::new(
+            self.name(),
+            vec![
+                self.fn_.clone(),
+                self.fn_.output.clone(),
+                self.vec.clone(),
+                self.fn_.output.clone(),
+            ],
+            span.clone(),
+        )
+        .into_box()
+    }
+
+    /// Folds from the left: `(unstable-vec-fold f init (vec-of a b))` is
+    /// `(unstable-app f (unstable-app f init a) b)`.
+    fn apply(
+        &self,
+        values: &[Value],
+        _sorts: (&[ArcSort], &ArcSort),
+        egraph: Option<&mut EGraph>,
+    ) -> Option<Value> {
+        let egraph =
+            egraph.unwrap_or_else(|| panic!("`{}` is not supported yet in facts.", self.name));
+        let vec = ValueVec::load(&self.vec, &values[2]);
+        Some(vec.iter().fold(values[1], |acc, e| {
+            self.fn_.apply(&values[0], &[acc, *e], egraph)
+        }))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
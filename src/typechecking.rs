@@ -22,6 +22,7 @@ pub struct TypeInfo {
     pub primitives: HashMap<Symbol, Vec<Primitive>>,
     pub func_types: HashMap<Symbol, FuncType>,
     pub global_types: HashMap<Symbol, ArcSort>,
+    numeric_coercion: bool,
 }
 
 impl Default for TypeInfo {
@@ -33,22 +34,46 @@ impl Default for TypeInfo {
             primitives: Default::default(),
             func_types: Default::default(),
             global_types: Default::default(),
+            numeric_coercion: false,
         };
 
         res.add_sort(UnitSort, DUMMY_SPAN.clone()).unwrap();
         res.add_sort(StringSort, DUMMY_SPAN.clone()).unwrap();
         res.add_sort(BoolSort, DUMMY_SPAN.clone()).unwrap();
         res.add_sort(I64Sort, DUMMY_SPAN.clone()).unwrap();
-        res.add_sort(F64Sort, DUMMY_SPAN.clone()).unwrap();
-        res.add_sort(RationalSort, DUMMY_SPAN.clone()).unwrap();
+        res.add_sort(F64Sort::default(), DUMMY_SPAN.clone())
+            .unwrap();
+        res.add_sort(F32Sort, DUMMY_SPAN.clone()).unwrap();
+        res.add_sort(CharSort, DUMMY_SPAN.clone()).unwrap();
+        res.add_sort(BytesSort, DUMMY_SPAN.clone()).unwrap();
+        res.add_sort(DurationSort, DUMMY_SPAN.clone()).unwrap();
+        res.add_sort(DateTimeSort::default(), DUMMY_SPAN.clone())
+            .unwrap();
+        res.add_sort(RationalSort::default(), DUMMY_SPAN.clone())
+            .unwrap();
         res.add_sort(BigIntSort, DUMMY_SPAN.clone()).unwrap();
         res.add_sort(BigRatSort, DUMMY_SPAN.clone()).unwrap();
+        res.add_sort(IntervalSort::default(), DUMMY_SPAN.clone())
+            .unwrap();
+        res.add_sort(ComplexSort::default(), DUMMY_SPAN.clone())
+            .unwrap();
+        res.add_sort(DecimalSort::default(), DUMMY_SPAN.clone())
+            .unwrap();
+        res.add_sort(MatrixSort::default(), DUMMY_SPAN.clone())
+            .unwrap();
+        res.add_sort(PolynomialSort::default(), DUMMY_SPAN.clone())
+            .unwrap();
 
         res.add_presort::<MapSort>(DUMMY_SPAN.clone()).unwrap();
         res.add_presort::<SetSort>(DUMMY_SPAN.clone()).unwrap();
         res.add_presort::<VecSort>(DUMMY_SPAN.clone()).unwrap();
+        res.add_presort::<PairSort>(DUMMY_SPAN.clone()).unwrap();
+        res.add_presort::<OptionSort>(DUMMY_SPAN.clone()).unwrap();
         res.add_presort::<FunctionSort>(DUMMY_SPAN.clone()).unwrap();
         res.add_presort::<MultiSetSort>(DUMMY_SPAN.clone()).unwrap();
+        res.add_presort::<BitVecSort>(DUMMY_SPAN.clone()).unwrap();
+        res.add_presort::<TermSort>(DUMMY_SPAN.clone()).unwrap();
+        res.add_presort::<NewtypeSort>(DUMMY_SPAN.clone()).unwrap();
 
         res.add_primitive(ValueEq);
 
@@ -109,6 +134,15 @@ impl TypeInfo {
         }
     }
 
+    /// Adds overloads that promote numeric arguments along `i64 → Rational → f64`.
+    /// See [`register_numeric_coercions`] for details.
+    pub fn enable_numeric_coercion(&mut self) {
+        if !self.numeric_coercion {
+            self.numeric_coercion = true;
+            register_numeric_coercions(self);
+        }
+    }
+
     pub fn add_primitive(&mut self, prim: impl Into<Primitive>) {
         let prim = prim.into();
         self.primitives.entry(prim.name()).or_default().push(prim);
@@ -163,89 +197,234 @@ impl TypeInfo {
         symbol_gen: &mut SymbolGen,
         command: &NCommand,
     ) -> Result<ResolvedNCommand, TypeError> {
-        let command: ResolvedNCommand =
-            match command {
-                NCommand::Function(fdecl) => {
-                    ResolvedNCommand::Function(self.typecheck_function(symbol_gen, fdecl)?)
-                }
-                NCommand::NormRule {
-                    rule,
-                    ruleset,
-                    name,
-                } => ResolvedNCommand::NormRule {
-                    rule: self.typecheck_rule(symbol_gen, rule)?,
-                    ruleset: *ruleset,
-                    name: *name,
-                },
-                NCommand::Sort(span, sort, presort_and_args) => {
-                    // Note this is bad since typechecking should be pure and idempotent
-                    // Otherwise typechecking the same program twice will fail
-                    self.declare_sort(*sort, presort_and_args, span.clone())?;
-                    ResolvedNCommand::Sort(span.clone(), *sort, presort_and_args.clone())
-                }
-                NCommand::CoreAction(Action::Let(span, var, expr)) => {
-                    let expr = self.typecheck_expr(symbol_gen, expr, &Default::default())?;
-                    let output_type = expr.output_type();
-                    self.global_types.insert(*var, output_type.clone());
-                    let var = ResolvedVar {
-                        name: *var,
-                        sort: output_type,
-                        // not a global reference, but a global binding
-                        is_global_ref: false,
-                    };
-                    ResolvedNCommand::CoreAction(ResolvedAction::Let(span.clone(), var, expr))
-                }
-                NCommand::CoreAction(action) => ResolvedNCommand::CoreAction(
-                    self.typecheck_action(symbol_gen, action, &Default::default())?,
-                ),
-                NCommand::Check(span, facts) => {
-                    ResolvedNCommand::Check(span.clone(), self.typecheck_facts(symbol_gen, facts)?)
-                }
-                NCommand::Fail(span, cmd) => ResolvedNCommand::Fail(
-                    span.clone(),
-                    Box::new(self.typecheck_command(symbol_gen, cmd)?),
-                ),
-                NCommand::RunSchedule(schedule) => {
-                    ResolvedNCommand::RunSchedule(self.typecheck_schedule(symbol_gen, schedule)?)
-                }
-                NCommand::Pop(span, n) => ResolvedNCommand::Pop(span.clone(), *n),
-                NCommand::Push(n) => ResolvedNCommand::Push(*n),
-                NCommand::SetOption { name, value } => {
-                    let value = self.typecheck_expr(symbol_gen, value, &Default::default())?;
-                    ResolvedNCommand::SetOption { name: *name, value }
-                }
-                NCommand::AddRuleset(ruleset) => ResolvedNCommand::AddRuleset(*ruleset),
-                NCommand::UnstableCombinedRuleset(name, sub_rulesets) => {
-                    ResolvedNCommand::UnstableCombinedRuleset(*name, sub_rulesets.clone())
-                }
-                NCommand::PrintOverallStatistics => ResolvedNCommand::PrintOverallStatistics,
-                NCommand::PrintTable(span, table, size) => {
-                    ResolvedNCommand::PrintTable(span.clone(), *table, *size)
+        let command: ResolvedNCommand = match command {
+            NCommand::Function(fdecl) => {
+                ResolvedNCommand::Function(self.typecheck_function(symbol_gen, fdecl)?)
+            }
+            NCommand::NormRule {
+                rule,
+                ruleset,
+                name,
+            } => ResolvedNCommand::NormRule {
+                rule: self.typecheck_rule(symbol_gen, rule)?,
+                ruleset: *ruleset,
+                name: *name,
+            },
+            NCommand::Sort(span, sort, presort_and_args) => {
+                // Note this is bad since typechecking should be pure and idempotent
+                // Otherwise typechecking the same program twice will fail
+                self.declare_sort(*sort, presort_and_args, span.clone())?;
+                ResolvedNCommand::Sort(span.clone(), *sort, presort_and_args.clone())
+            }
+            NCommand::CoreAction(Action::Let(span, var, expr)) => {
+                let expr = self.typecheck_expr(symbol_gen, expr, &Default::default())?;
+                let output_type = expr.output_type();
+                self.global_types.insert(*var, output_type.clone());
+                let var = ResolvedVar {
+                    name: *var,
+                    sort: output_type,
+                    // not a global reference, but a global binding
+                    is_global_ref: false,
+                };
+                ResolvedNCommand::CoreAction(ResolvedAction::Let(span.clone(), var, expr))
+            }
+            NCommand::CoreAction(action) => ResolvedNCommand::CoreAction(self.typecheck_action(
+                symbol_gen,
+                action,
+                &Default::default(),
+            )?),
+            NCommand::Check(span, facts) => {
+                ResolvedNCommand::Check(span.clone(), self.typecheck_facts(symbol_gen, facts)?)
+            }
+            NCommand::Fail(span, cmd) => ResolvedNCommand::Fail(
+                span.clone(),
+                Box::new(self.typecheck_command(symbol_gen, cmd)?),
+            ),
+            NCommand::RunSchedule(schedule) => {
+                ResolvedNCommand::RunSchedule(self.typecheck_schedule(symbol_gen, schedule)?)
+            }
+            NCommand::RunPortfolio {
+                span,
+                schedules,
+                cost_of,
+            } => ResolvedNCommand::RunPortfolio {
+                span: span.clone(),
+                schedules: schedules
+                    .iter()
+                    .map(|schedule| self.typecheck_schedule(symbol_gen, schedule))
+                    .collect::<Result<_, _>>()?,
+                cost_of: self.typecheck_expr(symbol_gen, cost_of, &Default::default())?,
+            },
+            NCommand::Module { .. } | NCommand::Import(..) => {
+                panic!("Modules should have been resolved before typechecking")
+            }
+            NCommand::DefMacro { .. } => {
+                panic!("Macros should have been expanded before typechecking")
+            }
+            NCommand::Pop(span, n) => ResolvedNCommand::Pop(span.clone(), *n),
+            NCommand::Push(n) => ResolvedNCommand::Push(*n),
+            NCommand::SetOption { name, value } => {
+                let value = self.typecheck_expr(symbol_gen, value, &Default::default())?;
+                if name.as_str() == "numeric_coercion" {
+                    match value {
+                        ResolvedExpr::Lit(_, Literal::Int(0)) => assert!(
+                            !self.numeric_coercion,
+                            "numeric_coercion cannot be disabled once enabled"
+                        ),
+                        ResolvedExpr::Lit(_, Literal::Int(_)) => self.enable_numeric_coercion(),
+                        _ => panic!("numeric_coercion must be an integer"),
+                    }
                 }
-                NCommand::PrintSize(span, n) => {
-                    // Should probably also resolve the function symbol here
-                    ResolvedNCommand::PrintSize(span.clone(), *n)
+                ResolvedNCommand::SetOption { name: *name, value }
+            }
+            NCommand::AddRuleset(ruleset) => ResolvedNCommand::AddRuleset(*ruleset),
+            NCommand::AddAnalysisRuleset(ruleset) => ResolvedNCommand::AddAnalysisRuleset(*ruleset),
+            NCommand::UnstableCombinedRuleset(name, sub_rulesets) => {
+                ResolvedNCommand::UnstableCombinedRuleset(*name, sub_rulesets.clone())
+            }
+            NCommand::PrintOverallStatistics => ResolvedNCommand::PrintOverallStatistics,
+            NCommand::PrintTable(span, table, size) => {
+                ResolvedNCommand::PrintTable(span.clone(), *table, *size)
+            }
+            NCommand::PrintSize(span, n) => {
+                // Should probably also resolve the function symbol here
+                ResolvedNCommand::PrintSize(span.clone(), *n)
+            }
+            NCommand::Output { span, file, exprs } => {
+                let exprs = exprs
+                    .iter()
+                    .map(|expr| self.typecheck_expr(symbol_gen, expr, &Default::default()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                ResolvedNCommand::Output {
+                    span: span.clone(),
+                    file: file.clone(),
+                    exprs,
                 }
-                NCommand::Output { span, file, exprs } => {
-                    let exprs = exprs
-                        .iter()
-                        .map(|expr| self.typecheck_expr(symbol_gen, expr, &Default::default()))
-                        .collect::<Result<Vec<_>, _>>()?;
-                    ResolvedNCommand::Output {
-                        span: span.clone(),
-                        file: file.clone(),
-                        exprs,
-                    }
+            }
+            NCommand::Input {
+                span,
+                name,
+                file,
+                format,
+            } => {
+                self.lookup_user_func(*name)
+                    .ok_or_else(|| TypeError::UnboundFunction(*name, span.clone()))?;
+                ResolvedNCommand::Input {
+                    span: span.clone(),
+                    name: *name,
+                    file: file.clone(),
+                    format: format.clone(),
                 }
-                NCommand::Input { span, name, file } => ResolvedNCommand::Input {
+            }
+            NCommand::OutputFunction {
+                span,
+                name,
+                file,
+                format,
+            } => {
+                self.lookup_user_func(*name)
+                    .ok_or_else(|| TypeError::UnboundFunction(*name, span.clone()))?;
+                ResolvedNCommand::OutputFunction {
                     span: span.clone(),
                     name: *name,
                     file: file.clone(),
-                },
-            };
+                    format: format.clone(),
+                }
+            }
+            NCommand::Save { span, file } => ResolvedNCommand::Save {
+                span: span.clone(),
+                file: file.clone(),
+            },
+            NCommand::Load { span, file } => ResolvedNCommand::Load {
+                span: span.clone(),
+                file: file.clone(),
+            },
+            NCommand::ImportEGraph { span, file } => ResolvedNCommand::ImportEGraph {
+                span: span.clone(),
+                file: file.clone(),
+            },
+            NCommand::CollectGarbage(span) => ResolvedNCommand::CollectGarbage(span.clone()),
+            NCommand::Explain(span, a, b) => {
+                let resolved_a = self.typecheck_expr(symbol_gen, a, &Default::default())?;
+                let resolved_b = self.typecheck_expr(symbol_gen, b, &Default::default())?;
+                if resolved_a.output_type().name() != resolved_b.output_type().name() {
+                    return Err(TypeError::Mismatch {
+                        expr: b.clone(),
+                        expected: resolved_a.output_type(),
+                        actual: resolved_b.output_type(),
+                    });
+                }
+                ResolvedNCommand::Explain(span.clone(), resolved_a, resolved_b)
+            }
+            NCommand::SetCost {
+                span,
+                constructor,
+                args,
+                cost,
+            } => self.typecheck_set_cost(symbol_gen, span, *constructor, args, cost)?,
+        };
         Ok(command)
     }
 
+    fn typecheck_set_cost(
+        &self,
+        symbol_gen: &mut SymbolGen,
+        span: &Span,
+        constructor: Symbol,
+        args: &[Symbol],
+        cost: &Expr,
+    ) -> Result<ResolvedNCommand, TypeError> {
+        let func_type = self
+            .lookup_user_func(constructor)
+            .ok_or_else(|| TypeError::UnboundFunction(constructor, span.clone()))?;
+        if func_type.input.len() != args.len() {
+            let vars = args.iter().map(|arg| Expr::Var(span.clone(), *arg));
+            return Err(TypeError::Arity {
+                expr: Expr::Call(span.clone(), constructor, vars.collect()),
+                expected: func_type.input.len(),
+            });
+        }
+        let i64_sort: ArcSort = self.get_sort_nofail::<I64Sort>();
+        let mut binding = IndexMap::default();
+        for (arg, sort) in args.iter().zip(&func_type.input) {
+            binding.insert(*arg, (span.clone(), sort.clone()));
+            binding.insert(cost_var(*arg), (span.clone(), i64_sort.clone()));
+        }
+        // `(cost a)` is a variable bound to the cost of `a`
+        let cost_expr = cost.clone().visit_exprs(&mut |expr| match &expr {
+            Expr::Call(span, head, children) if head.as_str() == "cost" => match children[..] {
+                [Expr::Var(_, arg)] if args.contains(&arg) => {
+                    Expr::Var(span.clone(), cost_var(arg))
+                }
+                _ => expr,
+            },
+            _ => expr,
+        });
+        let resolved = self.typecheck_expr(symbol_gen, &cost_expr, &binding)?;
+        if resolved.output_type().name() != i64_sort.name() {
+            return Err(TypeError::Mismatch {
+                expr: cost.clone(),
+                expected: i64_sort,
+                actual: resolved.output_type(),
+            });
+        }
+        let args = args
+            .iter()
+            .zip(&func_type.input)
+            .map(|(arg, sort)| ResolvedVar {
+                name: *arg,
+                sort: sort.clone(),
+                is_global_ref: false,
+            })
+            .collect();
+        Ok(ResolvedNCommand::SetCost {
+            span: span.clone(),
+            constructor,
+            args,
+            cost: resolved,
+        })
+    }
+
     fn typecheck_function(
         &mut self,
         symbol_gen: &mut SymbolGen,
@@ -314,7 +493,24 @@ impl TypeInfo {
                 span.clone(),
                 Box::new(self.typecheck_schedule(symbol_gen, schedule)?),
             ),
-            Schedule::Run(span, RunConfig { ruleset, until }) => {
+            Schedule::Backoff(span, config, schedule) => ResolvedSchedule::Backoff(
+                span.clone(),
+                *config,
+                Box::new(self.typecheck_schedule(symbol_gen, schedule)?),
+            ),
+            Schedule::TimeLimit(span, limit, schedule) => ResolvedSchedule::TimeLimit(
+                span.clone(),
+                *limit,
+                Box::new(self.typecheck_schedule(symbol_gen, schedule)?),
+            ),
+            Schedule::Run(
+                span,
+                RunConfig {
+                    ruleset,
+                    node_limit,
+                    until,
+                },
+            ) => {
                 let until = until
                     .as_ref()
                     .map(|facts| self.typecheck_facts(symbol_gen, facts))
@@ -323,6 +519,7 @@ impl TypeInfo {
                     span.clone(),
                     ResolvedRunConfig {
                         ruleset: *ruleset,
+                        node_limit: *node_limit,
                         until,
                     },
                 )
@@ -361,7 +558,13 @@ impl TypeInfo {
         symbol_gen: &mut SymbolGen,
         rule: &Rule,
     ) -> Result<ResolvedRule, TypeError> {
-        let Rule { span, head, body } = rule;
+        let Rule {
+            span,
+            head,
+            body,
+            priority,
+            match_limit,
+        } = rule;
         let mut constraints = vec![];
 
         let (query, mapped_query) = Facts(body.clone()).to_query(self, symbol_gen);
@@ -392,6 +595,8 @@ impl TypeInfo {
             span: span.clone(),
             body,
             head: actions,
+            priority: *priority,
+            match_limit: *match_limit,
         })
     }
 
@@ -437,7 +642,7 @@ impl TypeInfo {
         Ok(annotated_actions)
     }
 
-    fn typecheck_expr(
+    pub(crate) fn typecheck_expr(
         &self,
         symbol_gen: &mut SymbolGen,
         expr: &Expr,
@@ -451,7 +656,7 @@ impl TypeInfo {
         }
     }
 
-    fn typecheck_action(
+    pub(crate) fn typecheck_action(
         &self,
         symbol_gen: &mut SymbolGen,
         action: &Action,
@@ -1,38 +1,123 @@
 //! Baseline union-find implementation without sizes or ranks, using path
 //! halving for compression.
 //!
-//! This implementation uses interior mutability for `find`.
+//! Parents are stored in atomics, so `find` can be called from several threads
+//! at once, and [`UnionFind::union_concurrent`] merges classes through a shared
+//! reference using compare-and-swap, without taking a lock.
+use crate::explain::{Explanations, Reason};
 use crate::util::HashMap;
 use crate::{Symbol, Value};
 
-use std::cell::Cell;
 use std::fmt::Debug;
 use std::mem;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 pub type Id = u64;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Default)]
 pub struct UnionFind {
-    parents: Vec<Cell<Id>>,
-    n_unions: usize,
+    parents: Vec<AtomicU64>,
+    n_unions: AtomicUsize,
     recent_ids: HashMap<Symbol, Vec<Id>>,
     staged_ids: HashMap<Symbol, Vec<Id>>,
+    /// The proof forest, when explanations are enabled.
+    explanations: Option<Box<Explanations>>,
+}
+
+impl Clone for UnionFind {
+    fn clone(&self) -> Self {
+        UnionFind {
+            parents: self
+                .parents
+                .iter()
+                .map(|parent| AtomicU64::new(parent.load(Ordering::Acquire)))
+                .collect(),
+            n_unions: AtomicUsize::new(self.n_unions()),
+            recent_ids: self.recent_ids.clone(),
+            staged_ids: self.staged_ids.clone(),
+            explanations: self.explanations.clone(),
+        }
+    }
 }
 
 impl UnionFind {
     /// The number of unions that have been performed over the lifetime of this
     /// data-structure.
     pub fn n_unions(&self) -> usize {
-        self.n_unions
+        self.n_unions.load(Ordering::Relaxed)
+    }
+
+    /// Roughly how many bytes the union-find uses.
+    pub fn approximate_memory_usage(&self) -> usize {
+        let ids: usize = self
+            .recent_ids
+            .values()
+            .chain(self.staged_ids.values())
+            .map(Vec::capacity)
+            .sum();
+        (self.parents.capacity() + ids) * std::mem::size_of::<u64>()
     }
 
     /// Create a fresh [`Id`].
     pub fn make_set(&mut self) -> Id {
         let res = self.parents.len() as u64;
-        self.parents.push(Cell::new(res));
+        self.parents.push(AtomicU64::new(res));
         res
     }
 
+    /// The number of ids made so far.
+    pub(crate) fn num_ids(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// A union-find where each id starts out with the given parent.
+    pub(crate) fn from_parents(parents: Vec<Id>) -> Self {
+        UnionFind {
+            parents: parents.into_iter().map(AtomicU64::new).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Forgets every union, leaving `num_ids` ids that are each in their own
+    /// class. The number of unions performed so far is kept, and so is
+    /// whether explanations are enabled, but not what they recorded.
+    pub(crate) fn reset(&mut self, num_ids: usize) {
+        self.parents = (0..num_ids as Id).map(AtomicU64::new).collect();
+        self.recent_ids.clear();
+        self.staged_ids.clear();
+        if let Some(explanations) = &mut self.explanations {
+            **explanations = Explanations::default();
+        }
+    }
+
+    /// Starts recording why classes are merged, in a proof forest.
+    pub(crate) fn enable_explanations(&mut self) {
+        self.explanations.get_or_insert_with(Default::default);
+    }
+
+    pub(crate) fn explanations(&self) -> Option<&Explanations> {
+        self.explanations.as_deref()
+    }
+
+    pub(crate) fn explanations_mut(&mut self) -> Option<&mut Explanations> {
+        self.explanations.as_deref_mut()
+    }
+
+    /// Sets the reason recorded for the unions that follow, returning the
+    /// previous one. Does nothing unless explanations are enabled.
+    pub(crate) fn set_reason(&mut self, reason: Reason) -> Reason {
+        match &mut self.explanations {
+            Some(explanations) => explanations.set_reason(reason),
+            None => Reason::Given,
+        }
+    }
+
+    /// Makes `parent` the parent of `id`, for restoring a saved union-find.
+    /// `parent` should be a root that is not in the class of `id`.
+    pub(crate) fn set_parent(&mut self, id: Id, parent: Id) {
+        *self.parents[id as usize].get_mut() = parent;
+    }
+
     /// The number of ids that recently stopped being canonical.
     pub fn new_ids(&self, sort_filter: impl Fn(Symbol) -> bool) -> usize {
         self.recent_ids
@@ -55,6 +140,17 @@ impl UnionFind {
         self.staged_ids.values_mut().for_each(Vec::clear);
     }
 
+    /// Marks the dirty ids as non-canonical again, so the next call to
+    /// [`clear_recent_ids`] keeps them dirty, for a rebuild that stopped
+    /// before it got to them.
+    ///
+    /// [`clear_recent_ids`]: UnionFind::clear_recent_ids
+    pub(crate) fn restage_recent_ids(&mut self) {
+        for (sort, ids) in self.recent_ids.iter_mut() {
+            self.staged_ids.entry(*sort).or_default().append(ids);
+        }
+    }
+
     /// Iterate over the ids of the given sort marked as "dirty", i.e. any
     /// [`Id`]s that ceased to be canonical between the last call to
     /// [`clear_recent_ids`] and the call prior to that.
@@ -71,15 +167,20 @@ impl UnionFind {
 
     /// Look up the canonical representative for the given [`Id`].
     pub fn find(&self, id: Id) -> Id {
-        let mut cur = self.parent(id);
+        let mut cur = id;
         loop {
-            let next = self.parent(cur.get());
-            if cur.get() == next.get() {
-                return cur.get();
+            let parent = self.parent(cur).load(Ordering::Acquire);
+            if parent == cur {
+                return cur;
             }
-            // Path halving
-            let grand = self.parent(next.get());
-            cur.set(grand.get());
+            let grand = self.parent(parent).load(Ordering::Acquire);
+            if grand == parent {
+                return parent;
+            }
+            // Path halving. `cur` is not a root, so no other thread can be
+            // linking it, and `grand` stays one of its ancestors even if a
+            // concurrent `find` already compressed this path further.
+            self.parent(cur).store(grand, Ordering::Release);
             cur = grand;
         }
     }
@@ -105,7 +206,10 @@ impl UnionFind {
     pub fn union(&mut self, id1: Id, id2: Id, sort: Symbol) -> Id {
         let (res, reparented) = self.do_union(id1, id2);
         if let Some(id) = reparented {
-            self.staged_ids.entry(sort).or_default().push(id)
+            self.staged_ids.entry(sort).or_default().push(id);
+            if let Some(explanations) = &mut self.explanations {
+                explanations.add_edge(id1, id2);
+            }
         }
         res
     }
@@ -120,29 +224,148 @@ impl UnionFind {
         self.do_union(id1, id2).0
     }
 
+    /// Merge the equivalence classes for the two ids through a shared
+    /// reference, so several threads can union at once.
+    ///
+    /// Unlike [`union`], the root with the larger id is always placed under
+    /// the root with the smaller one, which rules out cycles when two threads
+    /// link the same pair of classes in opposite directions. Returns the new
+    /// root and, if the classes were distinct, the id that stopped being
+    /// canonical. The caller must pass that id to [`mark_dirty`] once the
+    /// parallel phase is over.
+    ///
+    /// [`union`]: UnionFind::union
+    /// [`mark_dirty`]: UnionFind::mark_dirty
+    pub fn union_concurrent(&self, id1: Id, id2: Id) -> (Id, Option<Id>) {
+        let (mut id1, mut id2) = (id1, id2);
+        loop {
+            id1 = self.find(id1);
+            id2 = self.find(id2);
+            if id1 == id2 {
+                return (id1, None);
+            }
+            let (root, child) = (id1.min(id2), id1.max(id2));
+            // fails if another thread linked `child` since we found it
+            if self
+                .parent(child)
+                .compare_exchange(child, root, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                self.n_unions.fetch_add(1, Ordering::Relaxed);
+                return (root, Some(child));
+            }
+        }
+    }
+
+    /// Record that `id`, of the given sort, stopped being canonical in a call
+    /// to [`union_concurrent`].
+    ///
+    /// [`union_concurrent`]: UnionFind::union_concurrent
+    pub fn mark_dirty(&mut self, id: Id, sort: Symbol) {
+        self.staged_ids.entry(sort).or_default().push(id)
+    }
+
     fn do_union(&mut self, id1: Id, id2: Id) -> (Id, Option<Id>) {
         let id1 = self.find(id1);
         let id2 = self.find(id2);
         if id1 != id2 {
-            self.parent(id2).set(id1);
-            self.n_unions += 1;
+            *self.parents[id2 as usize].get_mut() = id1;
+            *self.n_unions.get_mut() += 1;
             (id1, Some(id2))
         } else {
             (id1, None)
         }
     }
 
-    fn parent(&self, id: Id) -> &Cell<Id> {
+    fn parent(&self, id: Id) -> &AtomicU64 {
         &self.parents[id as usize]
     }
 }
 
+/// Access to the union-find while rebuilding a single table.
+pub(crate) enum UnionRef<'a> {
+    /// Tables are rebuilt one at a time, unioning with [`UnionFind::union`].
+    Exclusive(&'a mut UnionFind),
+    /// Tables are rebuilt in parallel, unioning with
+    /// [`UnionFind::union_concurrent`]. The ids that stopped being canonical
+    /// are collected here, for the caller to mark dirty afterwards.
+    Shared(&'a UnionFind, Vec<(Id, Symbol)>),
+}
+
+impl UnionRef<'_> {
+    pub(crate) fn get(&self) -> &UnionFind {
+        match self {
+            UnionRef::Exclusive(uf) => uf,
+            UnionRef::Shared(uf, _) => uf,
+        }
+    }
+
+    /// A count that goes up by one for each union made through this reference.
+    pub(crate) fn n_unions(&self) -> usize {
+        match self {
+            UnionRef::Exclusive(uf) => uf.n_unions(),
+            UnionRef::Shared(_, dirty) => dirty.len(),
+        }
+    }
+
+    pub(crate) fn union_values(&mut self, val1: Value, val2: Value, sort: Symbol) -> Value {
+        match self {
+            UnionRef::Exclusive(uf) => uf.union_values(val1, val2, sort),
+            UnionRef::Shared(uf, dirty) => {
+                #[cfg(debug_assertions)]
+                debug_assert_eq!(val1.tag, val2.tag);
+
+                let (bits, reparented) = uf.union_concurrent(val1.bits, val2.bits);
+                dirty.extend(reparented.map(|id| (id, sort)));
+                Value {
+                    #[cfg(debug_assertions)]
+                    tag: val1.tag,
+                    bits,
+                }
+            }
+        }
+    }
+
+    /// Unions the outputs of two rows of `function` whose inputs are equal.
+    pub(crate) fn union_congruent(
+        &mut self,
+        val1: Value,
+        val2: Value,
+        sort: Symbol,
+        function: Symbol,
+    ) -> Value {
+        match self {
+            UnionRef::Exclusive(uf) if uf.explanations.is_some() => {
+                let previous = uf.set_reason(Reason::Congruence(function));
+                let value = uf.union_values(val1, val2, sort);
+                uf.set_reason(previous);
+                value
+            }
+            _ => self.union_values(val1, val2, sort),
+        }
+    }
+
+    pub(crate) fn into_dirty(self) -> Vec<(Id, Symbol)> {
+        match self {
+            UnionRef::Exclusive(_) => vec![],
+            UnionRef::Shared(_, dirty) => dirty,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn ids(us: impl IntoIterator<Item = Id>) -> Vec<Cell<Id>> {
-        us.into_iter().map(Cell::new).collect()
+    fn ids(us: impl IntoIterator<Item = Id>) -> Vec<Id> {
+        us.into_iter().collect()
+    }
+
+    fn parents(uf: &UnionFind) -> Vec<Id> {
+        uf.parents
+            .iter()
+            .map(|p| p.load(Ordering::Relaxed))
+            .collect()
     }
 
     #[test]
@@ -155,7 +378,7 @@ mod tests {
         }
 
         // test the initial condition of everyone in their own set
-        assert_eq!(uf.parents, ids(0..n));
+        assert_eq!(parents(&uf), ids(0..n));
 
         // build up one set
         uf.union_raw(0, 1);
@@ -174,6 +397,39 @@ mod tests {
 
         // indexes:         0, 1, 2, 3, 4, 5, 6, 7, 8, 9
         let expected = vec![0, 0, 0, 0, 4, 5, 6, 6, 6, 6];
-        assert_eq!(uf.parents, ids(expected));
+        assert_eq!(parents(&uf), ids(expected));
+    }
+
+    #[test]
+    fn union_concurrent() {
+        let n = 1000;
+
+        let mut uf = UnionFind::default();
+        for _ in 0..n {
+            uf.make_set();
+        }
+
+        // four threads each union overlapping chains, in both directions
+        std::thread::scope(|scope| {
+            for t in 0..4 {
+                let uf = &uf;
+                scope.spawn(move || {
+                    for i in 0..n - 1 {
+                        let (a, b) = if (i + t) % 2 == 0 {
+                            (i, i + 1)
+                        } else {
+                            (i + 1, i)
+                        };
+                        uf.union_concurrent(a, b);
+                        assert_eq!(uf.find(a), uf.find(b));
+                    }
+                });
+            }
+        });
+
+        assert_eq!(uf.n_unions(), n as usize - 1);
+        for i in 0..n {
+            assert_eq!(uf.find(i), 0);
+        }
     }
 }
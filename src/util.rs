@@ -1,6 +1,8 @@
 #![allow(unused)]
 
 use std::fmt::Display;
+use std::hash::Hash;
+use std::sync::RwLock;
 
 use crate::core::SpecializedPrimitive;
 #[allow(unused_imports)]
@@ -29,6 +31,66 @@ pub(crate) type HEntry<'a, A, B> = Entry<'a, A, B>;
 pub type IndexMap<K, V> = indexmap::IndexMap<K, V, BuildHasher>;
 pub type IndexSet<K> = indexmap::IndexSet<K, BuildHasher>;
 
+const INTERNER_SHARDS: usize = 16;
+
+/// A thread-safe interner that assigns each distinct value a stable index.
+///
+/// Values are spread by hash over independently locked shards, so threads
+/// interning or loading unrelated values rarely contend. The shard is kept in
+/// the low bits of the index, and lookups of already-interned values only take
+/// a read lock.
+pub(crate) struct Interner<T> {
+    shards: Vec<RwLock<IndexSet<T>>>,
+}
+
+impl<T> Default for Interner<T> {
+    fn default() -> Self {
+        Self {
+            shards: (0..INTERNER_SHARDS).map(|_| Default::default()).collect(),
+        }
+    }
+}
+
+impl<T> Debug for Interner<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let len: usize = self.shards.iter().map(|s| s.read().unwrap().len()).sum();
+        f.debug_struct("Interner").field("len", &len).finish()
+    }
+}
+
+impl<T: Hash + Eq + Clone> Interner<T> {
+    pub(crate) fn intern(&self, value: T) -> u64 {
+        // The shard tables hash with the same function, so pick the shard from
+        // bits that they don't use for bucket selection or tags.
+        let hash = std::hash::BuildHasher::hash_one(&BuildHasher::default(), &value);
+        let shard = (hash >> 32) as usize % INTERNER_SHARDS;
+        let lock = &self.shards[shard];
+        // Bind the lookup first so the read guard is dropped before writing
+        let found = lock.read().unwrap().get_index_of(&value);
+        let i = match found {
+            Some(i) => i,
+            None => lock.write().unwrap().insert_full(value).0,
+        };
+        (i * INTERNER_SHARDS + shard) as u64
+    }
+
+    pub(crate) fn get(&self, index: u64) -> T {
+        let index = index as usize;
+        let shard = self.shards[index % INTERNER_SHARDS].read().unwrap();
+        shard.get_index(index / INTERNER_SHARDS).unwrap().clone()
+    }
+
+    /// Roughly how many bytes the interned values use, not counting memory
+    /// they own on the heap.
+    pub(crate) fn approximate_memory_usage(&self) -> usize {
+        let entry_size = std::mem::size_of::<T>() + 2 * std::mem::size_of::<usize>();
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap().capacity() * entry_size)
+            .sum()
+    }
+}
+
 pub(crate) fn concat_vecs<T>(to: &mut Vec<T>, mut from: Vec<T>) {
     if to.len() < from.len() {
         std::mem::swap(to, &mut from)
@@ -96,6 +158,11 @@ impl SymbolGen {
     pub fn has_been_used(&self) -> bool {
         self.gen > 0
     }
+
+    /// Whether `name` may have been generated by this [`SymbolGen`].
+    pub(crate) fn is_fresh(&self, name: Symbol) -> bool {
+        name.as_str().starts_with(&self.reserved_string)
+    }
 }
 
 /// This trait lets us statically dispatch between `fresh` methods for generic structs.
@@ -139,3 +206,25 @@ impl SymbolLike for Symbol {
         *self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interner_concurrent() {
+        let interner = Interner::<i64>::default();
+        let indices: Vec<Vec<u64>> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| (0..1000).map(|x| interner.intern(x)).collect()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        // every thread sees the same index for the same value
+        assert!(indices.iter().all(|is| is == &indices[0]));
+        for (x, i) in indices[0].iter().enumerate() {
+            assert_eq!(interner.get(*i), x as i64);
+        }
+        assert_eq!(HashSet::from_iter(indices[0].iter()).len(), 1000);
+    }
+}
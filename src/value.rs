@@ -6,7 +6,7 @@ use lazy_static::lazy_static;
 use crate::ast::Symbol;
 
 #[cfg(debug_assertions)]
-use crate::{BoolSort, F64Sort, I64Sort, Sort, StringSort};
+use crate::{BoolSort, F32Sort, F64Sort, I64Sort, Sort, StringSort};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 // FIXME this shouldn't be pub
@@ -51,16 +51,28 @@ impl From<i64> for Value {
     }
 }
 
+/// Keeps the bits of the float as they are. Values of an e-graph's `f64` sort
+/// are stored through [`F64Sort`](crate::F64Sort) instead, which follows its NaN policy.
 impl From<OrderedFloat<f64>> for Value {
     fn from(f: OrderedFloat<f64>) -> Self {
         Self {
             #[cfg(debug_assertions)]
-            tag: F64Sort.name(),
+            tag: F64Sort::default().name(),
             bits: f.into_inner().to_bits(),
         }
     }
 }
 
+impl From<OrderedFloat<f32>> for Value {
+    fn from(f: OrderedFloat<f32>) -> Self {
+        Self {
+            #[cfg(debug_assertions)]
+            tag: F32Sort.name(),
+            bits: f.into_inner().to_bits().into(),
+        }
+    }
+}
+
 impl From<Symbol> for Value {
     fn from(s: Symbol) -> Self {
         Self {
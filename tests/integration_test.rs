@@ -1,4 +1,7 @@
-use egglog::{ast::Expr, EGraph, ExtractReport, Function, SerializeConfig, Term, Value};
+use egglog::{
+    ast::Expr, EGraph, Epoch, Error, ExtractReport, Function, RunMode, SerializeConfig, StopReason,
+    Term, TermDag, Value,
+};
 use symbol_table::GlobalSymbol;
 
 #[test]
@@ -443,3 +446,1444 @@ fn test_serialize_subsume_status() {
     assert!(serialized.nodes[&a_id].subsumed);
     assert!(!serialized.nodes[&b_id].subsumed);
 }
+
+/// A sort defined outside the crate, registered through the public API.
+mod meters {
+    use egglog::{
+        add_primitives,
+        ast::{Expr, Literal, Symbol},
+        sort::{FromSort, IntoSort, Sort},
+        Cost, EGraph, SimplePrimitive, Value,
+    };
+    use std::{any::Any, sync::Arc};
+
+    #[derive(Debug)]
+    pub struct MetersSort;
+
+    pub struct Meters(pub i64);
+
+    impl Sort for MetersSort {
+        fn name(&self) -> Symbol {
+            "Meters".into()
+        }
+
+        fn as_arc_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync + 'static> {
+            self
+        }
+
+        fn make_expr(&self, _egraph: &EGraph, value: Value) -> (Cost, Expr) {
+            let n = Meters::load(self, &value).0;
+            (
+                1,
+                Expr::call_no_span("meters", [Expr::lit_no_span(Literal::Int(n))]),
+            )
+        }
+    }
+
+    impl FromSort for Meters {
+        type Sort = MetersSort;
+        fn load(_sort: &Self::Sort, value: &Value) -> Self {
+            Meters(value.bits as i64)
+        }
+    }
+
+    impl IntoSort for Meters {
+        type Sort = MetersSort;
+        fn store(self, _sort: &Self::Sort) -> Option<Value> {
+            Some(Value {
+                #[cfg(debug_assertions)]
+                tag: MetersSort.name(),
+                bits: self.0 as u64,
+            })
+        }
+    }
+
+    pub fn register(egraph: &mut EGraph) {
+        egraph.add_arcsort(Arc::new(MetersSort)).unwrap();
+        add_primitives!(egraph, "meters" = |n: i64| -> Meters { Meters(n) });
+        add_primitives!(
+            egraph,
+            "+" = |a: Meters, b: Meters| -> Option<Meters> { Some(Meters(a.0.checked_add(b.0)?)) }
+        );
+        add_primitives!(egraph, "to-i64" = |a: Meters| -> i64 { a.0 });
+        let meters = egraph.get_sort_nofail::<MetersSort>();
+        egraph.add_primitive(SimplePrimitive::new("meters-zero", vec![], meters, |_| {
+            Meters(0).store(&MetersSort)
+        }));
+    }
+}
+
+#[test]
+fn test_user_defined_sort() {
+    let mut egraph = EGraph::default();
+    meters::register(&mut egraph);
+    egraph
+        .parse_and_run_program(
+            None,
+            r#"
+            (datatype Shape (Line Meters))
+            (let l (Line (+ (meters 2) (meters 3))))
+            (check (= l (Line (meters 5))))
+            (check (= (to-i64 (meters 7)) 7))
+            (check (= (meters-zero) (meters 0)))
+            (extract (+ (meters 1) (meters 1)))
+            "#,
+        )
+        .unwrap();
+    // mixing with i64 is still a type error
+    assert!(egraph
+        .parse_and_run_program(None, "(check (= (meters 1) (+ 1 (meters 1))))")
+        .is_err());
+}
+
+#[test]
+fn test_nan_policy_is_per_egraph() {
+    let mut canonical = EGraph::default();
+    canonical
+        .parse_and_run_program(
+            None,
+            "(set-option nan_policy \"canonicalize\")
+             (check (= (neg NaN) NaN))",
+        )
+        .unwrap();
+    // a NaN with its sign flipped is a different value under the default policy
+    let mut preserving = EGraph::default();
+    preserving
+        .parse_and_run_program(None, "(fail (check (= (neg NaN) NaN)))")
+        .unwrap();
+    canonical
+        .parse_and_run_program(None, "(check (= (neg NaN) NaN))")
+        .unwrap();
+}
+
+#[test]
+fn test_nan_policy_applies_to_literals_and_pop() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(None, "(set-option nan_policy \"canonicalize\")")
+        .unwrap();
+    // a NaN literal with its sign bit set is stored as the canonical NaN
+    let negative_nan = Expr::lit_no_span(ordered_float::OrderedFloat(-f64::NAN));
+    let (_, value) = egraph.eval_expr(&negative_nan).unwrap();
+    assert_eq!(value.bits, f64::NAN.to_bits());
+
+    // popping restores the policy of the pushed e-graph
+    egraph
+        .parse_and_run_program(
+            None,
+            "(push)
+             (set-option nan_policy \"preserve\")
+             (fail (check (= (neg NaN) NaN)))
+             (pop)
+             (check (= (neg NaN) NaN))",
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_newtype_is_distinct() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            r#"
+            (sort Addr i64 :newtype)
+            (sort Offset i64 :newtype)
+            (let base (Addr 4096))
+            (let off (Offset 16))
+            "#,
+        )
+        .unwrap();
+    for program in [
+        "(check (= base 4096))",
+        "(check (= (+ base off) base))",
+        "(check (= (+ base 1) base))",
+        "(check (= (Addr 1) (Offset 1)))",
+    ] {
+        assert!(
+            egraph.parse_and_run_program(None, program).is_err(),
+            "{program} should not typecheck"
+        );
+    }
+}
+
+#[test]
+fn test_parallel_rebuild_matches_serial() {
+    let program = r#"
+        (datatype Math (Num i64) (Add Math Math))
+        (function cost (Math) i64 :merge (min old new))
+        (relation gen (i64))
+        (gen 0)
+        (rule ((gen n) (< n 50))
+              ((gen (+ n 1))
+               (set (cost (Add (Num n) (Num (+ n 1)))) n)
+               (Add (Add (Num n) (Num 1)) (Num (+ n 1)))))
+        (run 51)
+        (rule ((gen n) (= (% n 3) 0)) ((union (Num n) (Num 0))))
+        (run 1)
+        (print-size)
+    "#;
+    let run = |threads: usize| {
+        let mut egraph = EGraph::default();
+        egraph
+            .parse_and_run_program(None, &format!("(set-option threads {threads}) {program}"))
+            .unwrap()
+    };
+    assert_eq!(run(1), run(4));
+}
+
+#[test]
+fn test_parallel_search_matches_serial() {
+    let program = r#"
+        (datatype Math (Num i64) (Var String) (Add Math Math) (Mul Math Math))
+        (rewrite (Add a b) (Add b a))
+        (rewrite (Mul a b) (Mul b a))
+        (rewrite (Add a (Add b c)) (Add (Add a b) c))
+        (rewrite (Mul a (Add b c)) (Add (Mul a b) (Mul a c)))
+        (rewrite (Add (Num a) (Num b)) (Num (+ a b)))
+        (let e (Mul (Var "x") (Add (Num 1) (Add (Var "y") (Num 2)))))
+        (run 4)
+        (print-size)
+        (extract e)
+    "#;
+    let run = |threads: usize| {
+        let mut egraph = EGraph::default();
+        egraph
+            .parse_and_run_program(None, &format!("(set-option threads {threads}) {program}"))
+            .unwrap()
+    };
+    assert_eq!(run(1), run(4));
+}
+
+#[test]
+fn test_parallel_search_splits_large_rules() {
+    // enough `edge`s that the last rule is searched in chunks on the thread pool
+    let program = r#"
+        (relation num (i64))
+        (relation stride (i64))
+        (relation edge (i64 i64))
+        (relation two-step (i64 i64))
+        (num 0)
+        (stride 1)
+        (rule ((num n) (stride k) (< (+ n k) 8192)) ((num (+ n k))))
+        (rule ((stride k) (< k 4096)) ((stride (* k 2))))
+        (run 14)
+        (rule ((num n)) ((edge n (+ n 1)) (edge n (% (* n 7) 8192))))
+        (run 1)
+        (rule ((edge x y) (edge y z)) ((two-step x z)))
+        (run 1)
+        (print-size num)
+        (print-size two-step)
+        (check (two-step 0 2) (two-step 1 49) (two-step 8000 8002))
+    "#;
+    let run = |threads: usize| {
+        let mut egraph = EGraph::default();
+        egraph
+            .parse_and_run_program(None, &format!("(set-option threads {threads}) {program}"))
+            .unwrap()
+    };
+    assert_eq!(run(1), run(4));
+}
+
+#[test]
+fn test_deterministic_mode_matches_serial() {
+    let program = r#"
+        (datatype Math (Num i64) (Var String) (Add Math Math) (Mul Math Math))
+        (rewrite (Add a b) (Add b a))
+        (rewrite (Mul a b) (Mul b a))
+        (rewrite (Add a (Add b c)) (Add (Add a b) c))
+        (rewrite (Mul a (Add b c)) (Add (Mul a b) (Mul a c)))
+        (rewrite (Add (Num a) (Num b)) (Num (+ a b)))
+        (rewrite (Mul (Num 0) a) (Num 0))
+        (let e (Mul (Var "x") (Add (Num 1) (Add (Var "y") (Num 2)))))
+        (union (Var "y") (Num 0))
+        (run 4)
+        (print-size)
+        (extract e 4)
+        (print-function Add 20)
+    "#;
+    let run = |threads: usize| {
+        let mut egraph = EGraph::default();
+        egraph.deterministic = true;
+        let outputs = egraph
+            .parse_and_run_program(None, &format!("(set-option threads {threads}) {program}"))
+            .unwrap();
+        let report = egraph.get_overall_run_report().clone();
+        (outputs, report.num_matches_per_rule)
+    };
+    assert_eq!(run(1), run(4));
+}
+
+#[test]
+fn test_thread_pool_configuration() {
+    let mut egraph = EGraph::default();
+    assert_eq!(egraph.num_threads(), 1);
+    egraph.set_num_threads(3);
+    assert_eq!(egraph.num_threads(), 3);
+    egraph.set_num_threads(0);
+    assert_eq!(egraph.num_threads(), 1);
+
+    // an application can share one pool between several e-graphs
+    let pool = std::sync::Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap(),
+    );
+    let mut other = EGraph::default();
+    egraph.set_thread_pool(pool.clone());
+    other.set_thread_pool(pool);
+    for egraph in [&mut egraph, &mut other] {
+        assert_eq!(egraph.num_threads(), 2);
+        egraph
+            .parse_and_run_program(
+                None,
+                "(relation edge (i64 i64))
+                 (relation path (i64 i64))
+                 (rule ((edge x y)) ((path x y)))
+                 (rule ((path x y) (edge y z)) ((path x z)))
+                 (edge 1 2) (edge 2 3) (edge 3 1)
+                 (run 10)
+                 (check (path 1 1))",
+            )
+            .unwrap();
+    }
+
+    egraph
+        .parse_and_run_program(None, "(set-option threads 4)")
+        .unwrap();
+    assert_eq!(egraph.num_threads(), 4);
+}
+
+#[test]
+fn test_query_snapshots_during_run() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(relation edge (i64 i64))
+             (relation path (i64 i64))
+             (rule ((edge x y)) ((path x y)))
+             (rule ((path x y) (edge y z)) ((path x z)))
+             (edge 1 2) (edge 2 3) (edge 3 4) (edge 4 5)",
+        )
+        .unwrap();
+    let snapshots = egraph.share_snapshots();
+    // the shared copy reflects the e-graph as of the last publish
+    assert!(snapshots.query("(check (path 1 2))").is_err());
+
+    std::thread::scope(|scope| {
+        let reader = scope.spawn(|| {
+            // keeps querying copies until the run reaches the end of the path
+            while snapshots.query("(check (path 1 5))").is_err() {
+                std::thread::yield_now();
+            }
+            snapshots.query("(print-size path)").unwrap()
+        });
+        egraph.parse_and_run_program(None, "(run 10)").unwrap();
+        assert_eq!(reader.join().unwrap(), vec!["10".to_string()]);
+    });
+
+    // changes made by queries stay in their copy
+    snapshots.query("(edge 5 6)").unwrap();
+    assert!(egraph
+        .parse_and_run_program(None, "(check (edge 5 6))")
+        .is_err());
+}
+
+#[test]
+fn test_batch_updates() {
+    let program = r#"
+        (datatype Math (Num i64) (Add Math Math))
+        (function best (Math) i64 :merge (min old new))
+        (function last (i64) i64 :merge new)
+        (relation start (i64))
+        (start 0)
+        (rule ((start n)) ((set (last n) 5) (union (Num 1) (Add (Num 1) (Num 0)))))
+        (rule ((start n)) ((set (last n) 3) (union (Num 1) (Add (Num 1) (Num 0)))))
+        (rule ((start n)) ((set (best (Num n)) 7) (set (best (Num n)) 2)))
+        (run 1)
+        (check (= (best (Num 0)) 2))
+        (check (= (Num 1) (Add (Num 1) (Num 0))))
+        (extract (last 0))
+    "#;
+    let run = |batch_updates: bool| {
+        let mut egraph = EGraph::default();
+        egraph.batch_updates = batch_updates;
+        egraph.parse_and_run_program(None, program).unwrap()
+    };
+    // rules set `last` in declaration order, while a batch applies the
+    // values in increasing order
+    assert_eq!(run(false), vec!["3"]);
+    assert_eq!(run(true), vec!["5"]);
+}
+
+#[test]
+fn test_seminaive_only_joins_new_tuples() {
+    // transitive closure of a 30-node chain
+    let mut program = String::from(
+        "(relation edge (i64 i64))
+         (relation path (i64 i64))
+         (rule ((edge x y)) ((path x y)))
+         (rule ((path x y) (edge y z)) ((path x z)))",
+    );
+    for i in 0..30 {
+        program += &format!("(edge {i} {})", i + 1);
+    }
+    program += "(run 100) (print-size path)";
+
+    let run = |seminaive: bool| {
+        let mut egraph = EGraph::default();
+        egraph.seminaive = seminaive;
+        let outputs = egraph.parse_and_run_program(None, &program).unwrap();
+        let (_, matches) = egraph
+            .get_overall_run_report()
+            .num_matches_per_rule
+            .iter()
+            .find(|(rule, _)| rule.as_str().contains("(edge y z)"))
+            .unwrap();
+        (outputs, *matches)
+    };
+    let (naive_outputs, naive_matches) = run(false);
+    let (outputs, matches) = run(true);
+    assert_eq!(outputs, naive_outputs);
+    // each new path is joined with each edge once, rather than once per
+    // iteration
+    assert_eq!(matches, 30 * 29 / 2);
+    assert!(naive_matches > 5 * matches);
+}
+
+#[test]
+fn test_rewrite_priority_desugars_to_rule_priority() {
+    let mut egraph = EGraph::default();
+    egraph.run_mode = RunMode::ShowDesugaredEgglog;
+    let desugared = egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Neg Math))
+             (rewrite (Neg (Num n)) (Num (- 0 n)) :priority -1)
+             (birewrite (Neg (Neg (Num n))) (Num n) :priority 5)",
+        )
+        .unwrap();
+    let rules: Vec<_> = desugared
+        .iter()
+        .filter(|c| c.starts_with("(rule"))
+        .collect();
+    assert_eq!(rules.len(), 3);
+    assert!(rules[0].contains(":priority -1"));
+    assert!(rules[1].contains(":priority 5"));
+    assert!(rules[2].contains(":priority 5"));
+}
+
+#[test]
+fn test_match_limit_recognizes_applied_matches_after_unions() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static APPLIED: AtomicUsize = AtomicUsize::new(0);
+
+    let mut egraph = EGraph::default();
+    egglog::add_primitives!(
+        &mut egraph,
+        "count-application" = |x: i64| -> i64 {
+            {
+                APPLIED.fetch_add(1, Ordering::SeqCst);
+                x
+            }
+        }
+    );
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Node (Leaf i64))
+             (relation marked (Node))
+             (relation seen (i64))
+             (ruleset count)
+             (rule ((marked x)) ((seen (count-application 0))) :ruleset count :match-limit 1)
+             (marked (Leaf 1))
+             (marked (Leaf 2))
+             (run count 1)",
+        )
+        .unwrap();
+    assert_eq!(APPLIED.load(Ordering::SeqCst), 1);
+
+    // the applied match is still recognized once its e-class has a new
+    // leader, so only the other one is applied
+    egraph
+        .parse_and_run_program(
+            None,
+            "(union (Leaf 3) (Leaf 1))
+             (union (Leaf 1) (Leaf 4))
+             (run count 1)
+             (run count 1)",
+        )
+        .unwrap();
+    assert_eq!(APPLIED.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_run_with_deadline() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64))
+             (rule ((Num n)) ((Num (+ n 1))))
+             (Num 0)",
+        )
+        .unwrap();
+    // the rules never saturate, so only the deadline stops the run
+    let program = egglog::ast::parse_program(None, "(run 1000000000) (check (Num 1))").unwrap();
+    let start = instant::Instant::now();
+    let deadline = start + instant::Duration::from_millis(100);
+    egraph.run_with_deadline(program, deadline).unwrap();
+    assert!(start.elapsed() < instant::Duration::from_secs(60));
+
+    let report = egraph.get_run_report().as_ref().unwrap();
+    assert_eq!(report.stop_reason, Some(StopReason::TimeLimit));
+
+    // the deadline only applies to the program it was given
+    egraph.parse_and_run_program(None, "(run 3)").unwrap();
+    let report = egraph.get_run_report().as_ref().unwrap();
+    assert_eq!(report.stop_reason, None);
+}
+
+#[test]
+fn test_max_memory() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(relation R (i64))
+             (rule ((R n)) ((R (* 2 n)) (R (+ (* 2 n) 1))))
+             (R 1)",
+        )
+        .unwrap();
+    // `R` doubles in size every iteration
+    let limit = egraph.approximate_memory_usage() + (1 << 20);
+    egraph.max_memory = Some(limit);
+    egraph.parse_and_run_program(None, "(run 60)").unwrap();
+
+    let report = egraph.get_run_report().as_ref().unwrap();
+    assert_eq!(report.stop_reason, Some(StopReason::MemoryLimit));
+    let memory = egraph.approximate_memory_usage();
+    assert!(memory >= limit);
+    assert!(memory < 16 * limit);
+}
+
+#[test]
+fn test_push_pop_backtracking() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Add Math Math))
+             (sort MathVec (Vec Math))
+             (function terms () MathVec :merge new)
+             (let a (Add (Num 1) (Num 2)))
+             (set (terms) (vec-of a))
+             (ruleset comm)
+             (rewrite (Add x y) (Add y x) :ruleset comm)",
+        )
+        .unwrap();
+    let num_tuples = egraph.num_tuples();
+
+    // speculatively rewrite, then backtrack
+    egraph.push();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(run comm 1)
+             (check (= a (Add (Num 2) (Num 1))))
+             (union (Num 1) (Num 2))
+             (set (terms) (vec-push (terms) (Num 3)))",
+        )
+        .unwrap();
+    egraph.pop().unwrap();
+
+    assert_eq!(egraph.num_tuples(), num_tuples);
+    egraph
+        .parse_and_run_program(
+            None,
+            "(check (!= (Num 1) (Num 2)))
+             (fail (check (= a (Add (Num 2) (Num 1)))))
+             (check (= (vec-length (terms)) 1))",
+        )
+        .unwrap();
+
+    // popping past the first state is an error
+    assert!(matches!(egraph.pop(), Err(Error::Pop(_))));
+}
+
+#[test]
+fn test_save_and_load() {
+    let path = std::env::temp_dir().join("egglog-test-save-and-load.egraph");
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Var String) (Add Math Math))
+             (sort MathVec (Vec Math))
+             (function terms () MathVec :merge new)
+             (function name (Math) String :merge new)
+             (relation big (Math))
+             (let a (Add (Num 1) (Var \"x\")))
+             (let n 7)
+             (set (terms) (vec-of a (Num n)))
+             (set (name a) \"a\")
+             (big (Num 100))
+             (union (Num 2) (Var \"y\"))
+             (delete (big (Num 100)))
+             (Num 3)
+             (subsume (Num 3))
+             (ruleset comm)
+             (rewrite (Add x y) (Add y x) :ruleset comm)
+             (rule ((= e (Num m)) (< m n)) ((big e)))",
+        )
+        .unwrap();
+    egraph.save(&path).unwrap();
+
+    let mut loaded = EGraph::default();
+    loaded.load(&path).unwrap();
+    assert_eq!(loaded.num_tuples(), egraph.num_tuples());
+    loaded
+        .parse_and_run_program(
+            None,
+            "(check (= (Num 2) (Var \"y\")))
+             (check (= (vec-get (terms) 0) a))
+             (check (= (vec-get (terms) 1) (Num 7)))
+             (check (= (name a) \"a\"))
+             (fail (check (big (Num 100))))
+             (run comm 1)
+             (check (= a (Add (Var \"x\") (Num 1))))
+             (run 1)
+             (check (big (Num 2)))
+             (fail (check (big (Num 7))))",
+        )
+        .unwrap();
+
+    // checkpoints must be loaded into an empty e-graph
+    assert!(matches!(
+        egraph.load(&path),
+        Err(Error::CheckpointError(..))
+    ));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_save_and_load_commands() {
+    let mut egraph = EGraph::default();
+    egraph.fact_directory = Some(std::env::temp_dir());
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Add Math Math))
+             (Add (Num 1) (Num 2))
+             (union (Num 1) (Num 2))
+             (save \"egglog-test-save-commands.egraph\")",
+        )
+        .unwrap();
+
+    let mut loaded = EGraph::default();
+    loaded.fact_directory = Some(std::env::temp_dir());
+    loaded
+        .parse_and_run_program(
+            None,
+            "(load \"egglog-test-save-commands.egraph\")
+             (check (= (Add (Num 1) (Num 1)) (Add (Num 2) (Num 2))))",
+        )
+        .unwrap();
+    std::fs::remove_file(std::env::temp_dir().join("egglog-test-save-commands.egraph")).unwrap();
+}
+
+#[test]
+fn test_output_and_input_function() {
+    let mut egraph = EGraph::default();
+    egraph.fact_directory = Some(std::env::temp_dir());
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Add Math Math))
+             (function name (Math i64) String :merge new)
+             (set (name (Num 1) 2) \"a, b\")
+             (set (name (Add (Num 1) (Num 2)) 3) \"c\")
+             (output name \"egglog-test-output-function.csv\" :delimiter \",\" :header)
+             (relation edge (i64 i64))
+             (edge 1 2)
+             (edge 2 3)
+             (output edge \"egglog-test-output-relation.tsv\" :columns (1 0))",
+        )
+        .unwrap();
+    let path = std::env::temp_dir().join("egglog-test-output-function.csv");
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        contents,
+        "Math,i64,String\n\
+         (Num 1),2,\"a, b\"\n\
+         (Add (Num 1) (Num 2)),3,c\n"
+    );
+
+    egraph
+        .parse_and_run_program(
+            None,
+            "(function label (String i64) String :merge new)
+             (input label \"egglog-test-output-function.csv\" :delimiter \",\" :header)
+             (check (= (label \"(Num 1)\" 2) \"a, b\"))
+             (relation reversed (i64 i64))
+             (input reversed \"egglog-test-output-relation.tsv\")
+             (check (reversed 2 1) (reversed 3 2))",
+        )
+        .unwrap();
+
+    // fields that do not parse as the sort of their column are an error
+    assert!(matches!(
+        egraph.parse_and_run_program(
+            None,
+            "(relation nums (i64 i64))
+             (input nums \"egglog-test-output-function.csv\" :delimiter \",\" :header)",
+        ),
+        Err(Error::IoError(..))
+    ));
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(std::env::temp_dir().join("egglog-test-output-relation.tsv")).unwrap();
+}
+
+#[test]
+fn test_import_egraph() {
+    let mut lemmas = EGraph::default();
+    lemmas.fact_directory = Some(std::env::temp_dir());
+    lemmas
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Var String) (Add Math Math))
+             (function name (Math) String :merge new)
+             (ruleset comm)
+             (rewrite (Add x y) (Add y x) :ruleset comm)
+             (let zero (Num 0))
+             (union (Add (Var \"x\") zero) (Var \"x\"))
+             (set (name (Var \"x\")) \"x\")
+             (save \"egglog-test-import-egraph.egraph\")",
+        )
+        .unwrap();
+
+    let mut egraph = EGraph::default();
+    egraph.fact_directory = Some(std::env::temp_dir());
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Var String) (Add Math Math))
+             (ruleset comm)
+             (let e (Add (Var \"x\") (Num 0)))
+             (let v (Var \"v\"))
+             (import-egraph \"egglog-test-import-egraph.egraph\")
+             (check (= e (Var \"x\")))
+             (check (= zero (Num 0)))
+             (check (= (name e) \"x\"))
+             (fail (check (= v (Var \"x\"))))
+             (run comm 1)
+             (check (= (Add (Num 0) (Var \"x\")) e))",
+        )
+        .unwrap();
+
+    // functions in both e-graphs must have the same schema
+    let mut other = EGraph::default();
+    other.fact_directory = Some(std::env::temp_dir());
+    other
+        .parse_and_run_program(None, "(function name (i64) String :merge new)")
+        .unwrap();
+    assert!(matches!(
+        other.parse_and_run_program(None, "(import-egraph \"egglog-test-import-egraph.egraph\")"),
+        Err(Error::CheckpointError(..))
+    ));
+    std::fs::remove_file(std::env::temp_dir().join("egglog-test-import-egraph.egraph")).unwrap();
+}
+
+#[test]
+fn test_rows_at_epoch() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Add Math Math))
+             (function level (i64) i64 :merge new)
+             (set (level 0) 0)
+             (Add (Num 1) (Num 2))",
+        )
+        .unwrap();
+    let rows = |egraph: &EGraph, name: &str, epoch: &Epoch| {
+        egraph
+            .rows_at(name.into(), epoch)
+            .unwrap()
+            .map(|(inputs, output)| (inputs.to_vec(), output))
+            .collect::<Vec<_>>()
+    };
+    let epoch = egraph.pin_epoch();
+    let adds = rows(&egraph, "Add", &epoch);
+    assert_eq!(adds.len(), 1);
+
+    // enough replacements of the same row to rehash the table many times
+    for i in 1..100 {
+        egraph
+            .parse_and_run_program(None, &format!("(set (level 0) {i})"))
+            .unwrap();
+    }
+    egraph
+        .parse_and_run_program(
+            None,
+            "(delete (Add (Num 1) (Num 2)))
+             (delete (Num 1))
+             (Num 3)",
+        )
+        .unwrap();
+    assert_eq!(
+        rows(&egraph, "level", &epoch),
+        vec![(vec![Value::from(0)], Value::from(0))]
+    );
+    assert_eq!(rows(&egraph, "Add", &epoch), adds);
+    assert_eq!(rows(&egraph, "Num", &epoch).len(), 2);
+
+    let later = egraph.pin_epoch();
+    assert_eq!(
+        rows(&egraph, "level", &later),
+        vec![(vec![Value::from(0)], Value::from(99))]
+    );
+    assert!(rows(&egraph, "Add", &later).is_empty());
+    assert_eq!(rows(&egraph, "Num", &later).len(), 2);
+    egraph.release_epoch(epoch);
+
+    assert!(matches!(
+        egraph.rows_at("missing".into(), &later),
+        Err(Error::TypeError(..))
+    ));
+}
+
+#[test]
+fn test_intersect() {
+    let program = "(datatype Math (Num i64) (Var String) (Add Math Math))
+                   (function size (Math) i64 :merge (min old new))
+                   (relation small (Math))
+                   (let a (Add (Var \"x\") (Num 0)))
+                   (let b (Add (Num 0) (Var \"x\")))";
+    let mut left = EGraph::default();
+    left.parse_and_run_program(None, program).unwrap();
+    left.parse_and_run_program(
+        None,
+        "(union a b)
+         (union (Var \"x\") (Var \"y\"))
+         (set (size a) 3)
+         (set (size (Num 0)) 1)
+         (small (Num 0))
+         (relation only-left (Math))",
+    )
+    .unwrap();
+    let mut right = EGraph::default();
+    right.parse_and_run_program(None, program).unwrap();
+    right
+        .parse_and_run_program(
+            None,
+            "(union (Var \"x\") (Var \"y\"))
+             (union a b)
+             (union (Num 0) (Num 1))
+             (set (size a) 2)
+             (set (size (Num 0)) 1)
+             (small (Num 0))",
+        )
+        .unwrap();
+
+    let mut both = left.intersect(&right).unwrap();
+    both.parse_and_run_program(
+        None,
+        "(check (= a b))
+         (check (= (Add (Var \"y\") (Num 0)) a))
+         (fail (check (= (Num 0) (Num 1))))
+         (check (= (size (Num 0)) 1))
+         (fail (check (size a)))
+         (check (small (Num 0)))",
+    )
+    .unwrap();
+    assert!(both
+        .functions
+        .contains_key(&GlobalSymbol::from("only-left")));
+}
+
+#[test]
+fn test_absorb() {
+    let program = "(datatype Math (Num i64) (Var String) (Add Math Math))
+                   (sort MathVec (Vec Math))
+                   (function terms () MathVec :merge new)
+                   (let x (Add (Var \"x\") (Num 0)))";
+    let mut egraph = EGraph::default();
+    egraph.parse_and_run_program(None, program).unwrap();
+    egraph
+        .parse_and_run_program(None, "(union x (Var \"x\"))")
+        .unwrap();
+
+    let mut other = EGraph::default();
+    other.parse_and_run_program(None, program).unwrap();
+    other
+        .parse_and_run_program(
+            None,
+            "(function name (Math) String :merge new)
+             (set (name (Var \"x\")) \"a new string\")
+             (set (terms) (vec-of x (Num 1)))
+             (rewrite (Add a b) (Add b a))
+             (union (Num 0) (Var \"zero\"))",
+        )
+        .unwrap();
+
+    egraph.absorb(other).unwrap();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(check (= x (Var \"x\")))
+             (check (= (Num 0) (Var \"zero\")))
+             (check (= (name x) \"a new string\"))
+             (check (= (vec-get (terms) 0) (Var \"x\")))
+             (check (= (vec-get (terms) 1) (Num 1)))
+             (run 1)
+             (check (= x (Add (Var \"zero\") (Var \"x\"))))",
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_diff() {
+    let declarations = "(datatype Math (Num i64) (Var String) (Add Math Math))
+                        (function size (Math) i64 :merge (min old new))";
+    let terms = "(let x (Add (Var \"x\") (Num 0))) (Num 1)";
+    let mut before = EGraph::default();
+    before
+        .parse_and_run_program(None, &format!("{declarations} {terms}"))
+        .unwrap();
+    // a separate run, with other ids
+    let mut after = EGraph::default();
+    after
+        .parse_and_run_program(
+            None,
+            &format!(
+                "{declarations}
+                 (Var \"unrelated\")
+                 {terms}
+                 (rule ((= e (Add a (Num 0)))) ((union e a)))
+                 (run 1)
+                 (set (size (Num 1)) 1)"
+            ),
+        )
+        .unwrap();
+
+    let diff = before.diff(&after);
+    assert_eq!(
+        diff.to_string(),
+        "merged (Var \"x\") = (Add (Var \"x\") (Num 0))
+new (Var \"unrelated\") -> (Var \"unrelated\")
+new (size (Num 1)) -> 1
+"
+    );
+    assert!(before.diff(&before).to_string().is_empty());
+}
+
+#[test]
+fn test_explanations_are_minimized() {
+    let mut egraph = EGraph::default();
+    egraph.enable_explanations();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Add Math Math))
+             (Add (Num 1) (Num 3))
+             (Add (Num 2) (Num 3))
+             (Add (Num 1) (Num 1))
+             (union (Num 1) (Num 2))
+             (run 1)",
+        )
+        .unwrap();
+    let num = |n: i64| Expr::call_no_span("Num", [Expr::lit_no_span(n)]);
+    let add = |a, b| Expr::call_no_span("Add", [a, b]);
+
+    let (termdag, explanation) = egraph
+        .explain(&add(num(1), num(3)), &add(num(2), num(3)))
+        .unwrap();
+    assert_eq!(
+        explanation.to_string(&termdag),
+        "(Add (Num 1) (Num 3))
+= (Add (Num 2) (Num 3)) by congruence
+    (Num 1)
+    = (Num 2) given"
+    );
+    assert_eq!(explanation.size(), 2);
+
+    // both sides are explained through (Add (Num 1) (Num 1)), and the
+    // detour through it is dropped
+    let (_, explanation) = egraph
+        .explain(&add(num(2), num(2)), &add(num(2), num(2)))
+        .unwrap();
+    assert!(explanation.steps.is_empty());
+}
+
+#[test]
+fn test_export_alethe() {
+    let mut egraph = EGraph::default();
+    egraph.enable_explanations();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Var String) (Add Math Math))
+             (rule ((= e (Add a b))) ((union e (Add b a))) :name \"commute\")
+             (union (Num 0) (Var \"zero\"))
+             (Add (Num 0) (Var \"x\"))
+             (run 1)",
+        )
+        .unwrap();
+    let var = |name: &str| Expr::call_no_span("Var", [Expr::lit_no_span(GlobalSymbol::from(name))]);
+    let zero = Expr::call_no_span("Num", [Expr::lit_no_span(0)]);
+    let (termdag, explanation) = egraph
+        .explain(
+            &Expr::call_no_span("Add", [var("zero"), var("x")]),
+            &Expr::call_no_span("Add", [var("x"), zero]),
+        )
+        .unwrap();
+    let alethe = egraph.export_alethe(&termdag, &explanation);
+    assert_eq!(
+        alethe.proof,
+        "(assume goal (not (= (Add (Var \"zero\") (Var \"x\")) (Add (Var \"x\") (Num 0)))))
+; given
+(assume h1 (= (Var \"zero\") (Num 0)))
+(step t1 (cl (= (Add (Var \"zero\") (Var \"x\")) (Add (Num 0) (Var \"x\")))) :rule cong :premises (h1))
+; by rule (rule ((= e (Add a b))) ((union e (Add b a))) )
+(assume h2 (= (Add (Num 0) (Var \"x\")) (Add (Var \"x\") (Num 0))))
+(step t2 (cl (= (Add (Var \"zero\") (Var \"x\")) (Add (Var \"x\") (Num 0)))) :rule trans :premises (t1 h2))
+(step t3 (cl) :rule resolution :premises (goal t2))
+"
+    );
+    assert!(alethe
+        .problem
+        .contains("(assert (= (Add (Num 0) (Var \"x\")) (Add (Var \"x\") (Num 0))))"));
+}
+
+#[test]
+fn test_parallel_extraction() {
+    let program = "
+        (datatype Math (Num i64) (Add Math Math) (Mul Math Math))
+        (rewrite (Add a b) (Add b a))
+        (rewrite (Mul a (Num 2)) (Add a a))
+        (rewrite (Add (Num a) (Num b)) (Num (+ a b)))
+        (let x (Mul (Add (Num 1) (Num 2)) (Num 2)))
+        (let y (Add (Mul (Num 3) (Num 4)) (Num 5)))
+        (run 5)";
+    let mut serial = EGraph::default();
+    serial.parse_and_run_program(None, program).unwrap();
+    let mut parallel = EGraph::default();
+    parallel.set_num_threads(4);
+    parallel.parse_and_run_program(None, program).unwrap();
+
+    let extract = |egraph: &mut EGraph| {
+        let roots = ["x", "y"].map(|var| egraph.eval_expr(&Expr::var_no_span(var)).unwrap());
+        let mut termdag = TermDag::default();
+        egraph
+            .extract_many(&roots, &mut termdag)
+            .into_iter()
+            .map(|(cost, term)| (cost, termdag.to_string(&term)))
+            .collect::<Vec<_>>()
+    };
+    let extracted = extract(&mut parallel);
+    assert_eq!(extracted, extract(&mut serial));
+    assert_eq!(extracted[0], (2, "(Num 6)".to_string()));
+}
+
+#[test]
+fn test_dag_extraction_shares_subterms() {
+    // Each child alone is cheapest without `s`, but sharing `s` between
+    // them makes the whole term cheaper as a DAG.
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            r#"
+            (datatype T (Pair T T) (F T) (G T))
+            (function s () T :cost 3)
+            (function x () T :cost 3)
+            (function y () T :cost 3)
+            (union (F (s)) (x))
+            (union (G (s)) (y))
+            (let root (Pair (x) (y)))
+            (extract root)
+            "#,
+        )
+        .unwrap();
+    let Some(ExtractReport::Best {
+        termdag,
+        cost,
+        term,
+    }) = egraph.get_extract_report()
+    else {
+        panic!("expected a best term");
+    };
+    assert_eq!(
+        (*cost, termdag.to_string(term)),
+        (7, "(Pair (x) (y))".into())
+    );
+
+    egraph
+        .parse_and_run_program(None, "(extract root :mode dag)")
+        .unwrap();
+    let Some(ExtractReport::Best {
+        termdag,
+        cost,
+        term,
+    }) = egraph.get_extract_report()
+    else {
+        panic!("expected a best term");
+    };
+    assert_eq!(
+        (*cost, termdag.to_string(term)),
+        (6, "(Pair (F (s)) (G (s)))".into())
+    );
+}
+
+#[test]
+fn test_set_cost() {
+    let path = std::env::temp_dir().join("egglog-test-set-cost.egraph");
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Add Math Math) (Mul Math Math))
+             (set-cost (Mul a b) (* 10 (+ (cost a) (cost b))))
+             (let x (Mul (Num 2) (Num 3)))
+             (union x (Add (Add (Num 1) (Num 2)) (Num 3)))
+             (extract x)",
+        )
+        .unwrap();
+    let Some(ExtractReport::Best {
+        termdag,
+        cost,
+        term,
+    }) = egraph.get_extract_report()
+    else {
+        panic!("expected a best term");
+    };
+    assert_eq!(
+        (*cost, termdag.to_string(term)),
+        (8, "(Add (Add (Num 1) (Num 2)) (Num 3))".into())
+    );
+
+    // the cost is declared again when the e-graph is loaded
+    egraph.save(&path).unwrap();
+    let mut loaded = EGraph::default();
+    loaded.load(&path).unwrap();
+    loaded.parse_and_run_program(None, "(extract x)").unwrap();
+    let Some(ExtractReport::Best { cost, .. }) = loaded.get_extract_report() else {
+        panic!("expected a best term");
+    };
+    assert_eq!(*cost, 8);
+    std::fs::remove_file(&path).unwrap();
+
+    for program in [
+        "(set-cost (Sub a b) 1)",
+        "(set-cost (Mul a) 1)",
+        "(set-cost (Mul a b) \"expensive\")",
+        "(set-cost (Mul a b) (+ a 1))",
+    ] {
+        assert!(matches!(
+            egraph.parse_and_run_program(None, program),
+            Err(Error::TypeError(..))
+        ));
+    }
+}
+
+#[test]
+fn test_extract_top_n_variants() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Var String) (Add Math Math))
+             (let x (Add (Var \"x\") (Num 0)))
+             ; x is also (Add x (Num 0)), so it has infinitely many terms
+             (union x (Var \"x\"))
+             (let y (Add x (Num 1)))
+             (extract y 4)",
+        )
+        .unwrap();
+    let Some(ExtractReport::Variants {
+        termdag,
+        costs,
+        terms,
+    }) = egraph.get_extract_report()
+    else {
+        panic!("expected variants");
+    };
+    let terms: Vec<String> = terms.iter().map(|term| termdag.to_string(term)).collect();
+    assert_eq!(costs, &[5, 8, 11, 14]);
+    assert_eq!(
+        terms,
+        [
+            "(Add (Var \"x\") (Num 1))",
+            "(Add (Add (Var \"x\") (Num 0)) (Num 1))",
+            "(Add (Add (Add (Var \"x\") (Num 0)) (Num 0)) (Num 1))",
+            "(Add (Add (Add (Add (Var \"x\") (Num 0)) (Num 0)) (Num 0)) (Num 1))",
+        ]
+    );
+}
+
+#[test]
+fn test_incremental_extraction() {
+    let mut egraph = EGraph::default();
+    let mut extract = |program: &str| {
+        egraph
+            .parse_and_run_program(None, &format!("{program} (extract x)"))
+            .unwrap();
+        let Some(ExtractReport::Best {
+            termdag,
+            cost,
+            term,
+        }) = egraph.get_extract_report()
+        else {
+            panic!("expected a term");
+        };
+        let (cost, term) = (*cost, termdag.to_string(term));
+        // the cached costs agree with extracting from scratch
+        let (sort, value) = egraph.eval_expr(&Expr::var_no_span("x")).unwrap();
+        let (fresh, _) = egraph.extract(value, &mut TermDag::default(), &sort);
+        assert_eq!(cost, fresh);
+        (cost, term)
+    };
+    assert_eq!(
+        extract(
+            "(datatype Math (Num i64) (Neg Math) (Add Math Math))
+             (let y (Add (Num 1) (Num 2)))
+             (let x (Neg (Neg y)))"
+        ),
+        (7, "(Neg (Neg (Add (Num 1) (Num 2))))".into())
+    );
+    assert_eq!(
+        extract("(union y (Num 3))"),
+        (4, "(Neg (Neg (Num 3)))".into())
+    );
+    assert_eq!(
+        extract("(union (Neg y) (Num -3))"),
+        (3, "(Neg (Num -3))".into())
+    );
+    // deleting an e-node makes its e-class more expensive
+    assert_eq!(
+        extract("(delete (Num -3))"),
+        (4, "(Neg (Neg (Num 3)))".into())
+    );
+    assert_eq!(
+        extract("(rule ((= e (Neg (Neg a)))) ((union e a))) (run 1)"),
+        (2, "(Num 3)".into())
+    );
+}
+
+#[test]
+fn test_extract_depth_and_size() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math
+               (Var String)
+               (Neg Math :cost 10)
+               (Add Math Math)
+               (Mul Math Math Math :cost 20))
+             (let x (Var \"x\"))
+             (let e (Neg (Neg (Neg x))))
+             (union e (Add (Add x x) (Add x x)))
+             (union e (Mul x x x))",
+        )
+        .unwrap();
+    let mut extract = |mode| {
+        egraph
+            .parse_and_run_program(None, &format!("(extract e :mode {mode})"))
+            .unwrap();
+        let Some(ExtractReport::Best {
+            termdag,
+            cost,
+            term,
+        }) = egraph.get_extract_report()
+        else {
+            panic!("expected a term");
+        };
+        (*cost, termdag.to_string(term))
+    };
+    assert_eq!(
+        extract("tree"),
+        (
+            11,
+            "(Add (Add (Var \"x\") (Var \"x\")) (Add (Var \"x\") (Var \"x\")))".into()
+        )
+    );
+    assert_eq!(extract("size"), (5, "(Neg (Neg (Neg (Var \"x\"))))".into()));
+    assert_eq!(
+        extract("depth"),
+        (3, "(Mul (Var \"x\") (Var \"x\") (Var \"x\"))".into())
+    );
+}
+
+#[test]
+fn test_containers_are_per_egraph() {
+    let mut a = EGraph::default();
+    a.parse_and_run_program(
+        None,
+        "(sort IntVec (Vec i64))
+         (sort IntSet (Set i64))
+         (let u (vec-of 1 2))
+         (let v (set-of 1 2))
+         (let w (vec-of 3))",
+    )
+    .unwrap();
+    let mut b = EGraph::default();
+    b.parse_and_run_program(None, "(sort IntVec (Vec i64)) (let w (vec-of 3))")
+        .unwrap();
+
+    // each e-graph numbers its own containers from zero
+    let w = Expr::var_no_span("w");
+    assert_eq!(a.eval_expr(&w).unwrap().1.bits, 1);
+    assert_eq!(b.eval_expr(&w).unwrap().1.bits, 0);
+    b.parse_and_run_program(None, "(fail (check (= w (vec-of 1 2))))")
+        .unwrap();
+    drop(a);
+    b.parse_and_run_program(None, "(check (= w (vec-of 3)))")
+        .unwrap();
+}
+
+#[test]
+fn test_rebuild_profile() {
+    let program = "(datatype Math (Num i64) (Neg Math))
+                   (let a (Neg (Neg (Neg (Num 1)))))
+                   (let b (Neg (Neg (Neg (Num 2)))))
+                   (rule ((= x (Num 1))) ((union x (Num 2))))
+                   (run 2)";
+    let mut egraph = EGraph::default();
+    egraph.parse_and_run_program(None, program).unwrap();
+    let report = egraph.get_run_report().clone().unwrap();
+    // the second iteration rebuilds until the unions stop, one level of
+    // `Neg` per pass
+    assert!(report.rebuild_profile.passes >= 5);
+    assert!(report.to_string().contains("Rebuild: canonicalize"));
+
+    let mut egraph = EGraph::default();
+    egraph.rebuild_passes = Some(1);
+    egraph.parse_and_run_program(None, program).unwrap();
+    let report = egraph.get_run_report().clone().unwrap();
+    assert_eq!(report.rebuild_profile.passes, 2);
+    egraph
+        .parse_and_run_program(None, "(check (= a b))")
+        .unwrap();
+}
+
+#[test]
+fn test_insert_facts() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Add Math Math))
+             (function size (Math) i64 :merge (max old new))",
+        )
+        .unwrap();
+    let num = |n: i64| ("Num".into(), vec![Value::from(n)], None);
+    egraph.insert_facts([num(1), num(2), num(1)]).unwrap();
+    assert_eq!(egraph.num_tuples(), 2);
+
+    let (_, one) = egraph
+        .eval_expr(&egglog::ast::parse_expr(None, "(Num 1)").unwrap())
+        .unwrap();
+    let (_, two) = egraph
+        .eval_expr(&egglog::ast::parse_expr(None, "(Num 2)").unwrap())
+        .unwrap();
+    egraph
+        .insert_facts([
+            ("Add".into(), vec![one, two], None),
+            ("size".into(), vec![one], Some(Value::from(3))),
+            ("size".into(), vec![one], Some(Value::from(1))),
+        ])
+        .unwrap();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(check (Add (Num 1) (Num 2)))
+             (check (= (size (Num 1)) 3))",
+        )
+        .unwrap();
+
+    assert!(matches!(
+        egraph.insert_facts([("Add".into(), vec![one], None)]),
+        Err(Error::FactArityError(..))
+    ));
+    assert!(matches!(
+        egraph.insert_facts([("Sub".into(), vec![one, two], None)]),
+        Err(Error::TypeError(_))
+    ));
+}
+
+#[test]
+fn test_add_expr() {
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(
+            None,
+            "(datatype Math (Num i64) (Add Math Math))
+             (let x (Num 1))",
+        )
+        .unwrap();
+    let functions = egraph.functions.len();
+    let parse = |s| egglog::ast::parse_expr(None, s).unwrap();
+
+    let (sort, value) = egraph.add_expr(&parse("(Add x (Num 2))")).unwrap();
+    let (termdag, term) = egraph.extract_value(&sort, value);
+    assert_eq!(termdag.to_string(&term), "(Add (Num 1) (Num 2))");
+    egraph
+        .parse_and_run_program(None, "(check (Add (Num 1) (Num 2)))")
+        .unwrap();
+
+    let added = egraph
+        .add_exprs(&[parse("(Num 2)"), parse("(+ 1 2)")])
+        .unwrap();
+    assert_eq!(added[1].1, Value::from(3));
+    // nothing is added when one of the terms does not typecheck
+    assert!(egraph
+        .add_exprs(&[parse("(Num 5)"), parse("(Add 1 2)")])
+        .is_err());
+    egraph
+        .parse_and_run_program(None, "(fail (check (Num 5)))")
+        .unwrap();
+    assert_eq!(egraph.functions.len(), functions);
+}
+
+#[test]
+fn test_include_relative_and_cycle() {
+    let dir = std::env::temp_dir().join("egglog-test-include");
+    std::fs::create_dir_all(dir.join("inner")).unwrap();
+    std::fs::write(
+        dir.join("main.egg"),
+        "(relation edge (i64 i64))\n(include \"inner/lemmas.egg\")",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("inner/lemmas.egg"),
+        "(edge 1 2)\n(include \"../main.egg\")",
+    )
+    .unwrap();
+
+    let mut egraph = EGraph::default();
+    let main = dir.join("main.egg");
+    let program = std::fs::read_to_string(&main).unwrap();
+    let err = egraph
+        .parse_and_run_program(main.to_str().map(String::from), &program)
+        .unwrap_err();
+    assert!(matches!(err, Error::IncludeCycle(..)));
+    // the error goes through both includes, back to the including file
+    let message = err.to_string();
+    assert!(message.contains("lemmas.egg: (include \"../main.egg\")"));
+    assert!(message.contains("Included from: In 2:1-28 of"));
+
+    // the paths are relative to the including file, not the working directory
+    std::fs::write(dir.join("inner/lemmas.egg"), "(edge 1 2)").unwrap();
+    let mut egraph = EGraph::default();
+    egraph
+        .parse_and_run_program(main.to_str().map(String::from), &program)
+        .unwrap();
+    egraph
+        .parse_and_run_program(None, "(check (edge 1 2))")
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}